@@ -0,0 +1,256 @@
+use crate::clock::{Clock, Recheck};
+use crate::exit::ExitReason;
+use crate::id::TaskId;
+use futures::future::{BoxFuture, FutureExt};
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// The thresholds past which a task raises an [`Alert`], see
+/// [`crate::Builder::on_alert`]. No threshold is set by default.
+///
+/// ```
+/// use std::time::Duration;
+/// use watch::Thresholds;
+///
+/// let thresholds = Thresholds::new()
+///     .restarts(10, Duration::from_secs(60))
+///     .downtime(Duration::from_secs(30))
+///     .consecutive_failures(5);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Thresholds {
+    restarts: Option<(usize, Duration)>,
+    downtime: Option<Duration>,
+    consecutive_failures: Option<u32>,
+}
+
+impl Thresholds {
+    /// Creates [`Thresholds`] that never raise an alert.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Alerts once a task was restarted more than `max` times `within` a
+    /// sliding window. A task alerts again once it got back below.
+    pub fn restarts(mut self, max: usize, within: Duration) -> Self {
+        self.restarts = Some((max, within));
+        self
+    }
+
+    /// Alerts once a task has been down for `downtime`: waiting to be
+    /// respawned, or starting but not ready yet. A task alerts again once it
+    /// was up in between.
+    pub fn downtime(mut self, downtime: Duration) -> Self {
+        self.downtime = Some(downtime);
+        self
+    }
+
+    /// Alerts once `count` instances of a task failed in a row. A task
+    /// alerts again once an instance completed in between.
+    pub fn consecutive_failures(mut self, count: u32) -> Self {
+        self.consecutive_failures = Some(count);
+        self
+    }
+}
+
+/// A task crossed one of its [`Thresholds`], as passed to the hook of
+/// [`crate::Builder::on_alert`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alert {
+    task: TaskId,
+    name: Option<String>,
+    kind: AlertKind,
+}
+
+impl Alert {
+    /// Returns the task that crossed the threshold.
+    pub fn task(&self) -> TaskId {
+        self.task
+    }
+
+    /// Returns the name of the task, see [`crate::Task::name`].
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns which threshold was crossed.
+    pub fn kind(&self) -> AlertKind {
+        self.kind
+    }
+}
+
+/// Which of its [`Thresholds`] a task crossed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertKind {
+    /// The task was restarted `count` times `within` the window.
+    Restarts { count: usize, within: Duration },
+    /// The task has been down for that long.
+    Downtime(Duration),
+    /// That many instances of the task failed in a row.
+    ConsecutiveFailures(u32),
+}
+
+/// What is called with every [`Alert`].
+pub(crate) type Hook = Box<dyn Fn(Alert) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// Raises an [`Alert`] through the hook as tasks cross their [`Thresholds`],
+/// and runs the futures of the hook.
+pub(crate) struct Alerter {
+    thresholds: Thresholds,
+    hook: Hook,
+    tasks: Vec<TaskAlerts>,
+    /// The futures returned by the hook that are not over yet.
+    running: FuturesUnordered<BoxFuture<'static, ()>>,
+    /// When a task will have been down for too long, to check again.
+    recheck: Recheck,
+}
+
+/// What the [`Alerter`] remembers about a task.
+#[derive(Default)]
+struct TaskAlerts {
+    /// When the task was restarted within the window, oldest first.
+    restarts: VecDeque<Instant>,
+    restarts_alerted: bool,
+    /// Since when the task has been down, if it is.
+    down_since: Option<Instant>,
+    downtime_alerted: bool,
+    failures: u32,
+}
+
+impl Alerter {
+    pub(crate) fn new<F, T>(thresholds: Thresholds, hook: F) -> Self
+    where
+        F: Fn(Alert) -> T + Send + Sync + 'static,
+        T: Future<Output = ()> + Send + 'static,
+    {
+        Self {
+            thresholds,
+            hook: Box::new(move |alert| hook(alert).boxed()),
+            tasks: Vec::new(),
+            running: FuturesUnordered::new(),
+            recheck: Recheck::default(),
+        }
+    }
+
+    fn task(&mut self, id: usize) -> &mut TaskAlerts {
+        // Children started at runtime come after the tasks known so far.
+        if id >= self.tasks.len() {
+            self.tasks.resize_with(id + 1, TaskAlerts::default);
+        }
+        &mut self.tasks[id]
+    }
+
+    /// Forgets about the task `id`, whose identifier goes to a new task.
+    pub(crate) fn forget(&mut self, id: usize) {
+        if let Some(task) = self.tasks.get_mut(id) {
+            *task = TaskAlerts::default();
+        }
+    }
+
+    fn alert(&mut self, task: usize, name: Option<&str>, kind: AlertKind) {
+        self.running.push((self.hook)(Alert {
+            task: TaskId::from(task),
+            name: name.map(str::to_string),
+            kind,
+        }));
+    }
+
+    /// Counts an instance of the task `id` that returned.
+    pub(crate) fn exited(&mut self, id: usize, name: Option<&str>, reason: &ExitReason) {
+        let threshold = self.thresholds.consecutive_failures;
+        let task = self.task(id);
+        match reason {
+            ExitReason::Failed(_) | ExitReason::TimedOut | ExitReason::Panicked(_) => {
+                task.failures += 1
+            }
+            ExitReason::Completed => task.failures = 0,
+            ExitReason::Cancelled => return,
+        }
+        let failures = task.failures;
+        if threshold == Some(failures) {
+            self.alert(id, name, AlertKind::ConsecutiveFailures(failures));
+        }
+    }
+
+    /// Counts a restart of the task `id` happening `now`.
+    pub(crate) fn restarted(&mut self, id: usize, name: Option<&str>, now: Instant) {
+        let (max, within) = match self.thresholds.restarts {
+            Some(restarts) => restarts,
+            None => return,
+        };
+        let task = self.task(id);
+        while matches!(task.restarts.front(), Some(at) if now - *at >= within) {
+            task.restarts.pop_front();
+        }
+        task.restarts.push_back(now);
+
+        let count = task.restarts.len();
+        if count <= max {
+            task.restarts_alerted = false;
+        } else if !task.restarts_alerted {
+            task.restarts_alerted = true;
+            self.alert(id, name, AlertKind::Restarts { count, within });
+        }
+    }
+
+    /// Tells whether the task `id` is down `now`, since when if it is.
+    pub(crate) fn observe(
+        &mut self,
+        id: usize,
+        name: Option<&str>,
+        down_since: Option<Instant>,
+        now: Instant,
+    ) {
+        let threshold = match self.thresholds.downtime {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        let task = self.task(id);
+        let down_since = match (down_since, task.down_since) {
+            (None, _) => {
+                task.down_since = None;
+                task.downtime_alerted = false;
+                return;
+            }
+            (Some(_), Some(since)) => since,
+            (Some(since), None) => *task.down_since.insert(since),
+        };
+
+        let downtime = now.saturating_duration_since(down_since);
+        if downtime >= threshold && !task.downtime_alerted {
+            task.downtime_alerted = true;
+            self.alert(id, name, AlertKind::Downtime(downtime));
+        }
+    }
+
+    /// Runs the futures of the hook, and makes sure the watcher is polled
+    /// again once a task will have been down for too long, as long as the
+    /// watcher is `watching` its tasks rather than shutting down.
+    pub(crate) fn poll(&mut self, cx: &mut Context<'_>, clock: &dyn Clock, watching: bool) {
+        while let Poll::Ready(Some(())) = self.running.poll_next_unpin(cx) {}
+
+        let recheck_at = match self.thresholds.downtime {
+            Some(threshold) if watching => self
+                .tasks
+                .iter()
+                .filter(|task| !task.downtime_alerted)
+                .filter_map(|task| task.down_since)
+                .min()
+                .map(|since| since + threshold),
+            _ => None,
+        };
+        self.recheck.poll(cx, clock, recheck_at);
+    }
+}
+
+impl fmt::Debug for Alerter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Alerter")
+            .field("thresholds", &self.thresholds)
+            .finish()
+    }
+}
@@ -0,0 +1,249 @@
+use crate::error::{Escalation, WatchError};
+use crate::exit::ExitReason;
+use crate::id::TaskId;
+use crate::panic::Panic;
+use crate::policy::{RestartContext, RestartDecision, RestartPolicy, RestartTrigger};
+use std::fmt;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::{Instant, Sleep};
+
+/// A watcher over exactly `N` tasks, known at compile time, keeping their
+/// instances and bookkeeping in fixed-size arrays instead of on the heap. For
+/// embedded and latency-critical users who do not need everything a
+/// [`crate::Watch`] does.
+///
+/// Task `index` is spawned by calling `factory` with its index, so every
+/// instance is of the same type and stored inline, without boxing. Each task
+/// goes through its own copy of the policy, and outputs are ignored, as with
+/// [`crate::Task::new`]. Restart delays are slept with Tokio's timer, inline
+/// too. There are no handles, events, layers nor budgets: the watcher
+/// resolves once every task retired, or with [`WatchError::Escalated`] once a
+/// policy escalated, dropping the instances left.
+///
+/// Instances and factories panicking are caught, so that the other tasks
+/// keep running: the task goes through its policy as if the instance exited,
+/// with [`ExitReason::Panicked`], and instances returning with
+/// [`ExitReason::Completed`]. Backtraces are not captured.
+///
+/// An empty array does not compile:
+///
+/// ```compile_fail
+/// use watch::{RestartDecision, WatchArray};
+///
+/// let watch: WatchArray<_, _, _, 0> =
+///     WatchArray::new(|_| async {}, |_: &_| RestartDecision::Retire);
+/// ```
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use watch::{RestartContext, RestartDecision, WatchArray};
+///
+/// async fn worker(index: usize) {}
+///
+/// let watch: WatchArray<_, _, _, 4> = WatchArray::new(worker, |context: &RestartContext| {
+///     if context.instance() < 3 {
+///         RestartDecision::RestartAfter(std::time::Duration::from_millis(1))
+///     } else {
+///         RestartDecision::Retire
+///     }
+/// });
+/// assert_eq!(watch.await.unwrap(), [3; 4]);
+/// # }
+/// ```
+pub struct WatchArray<F, T, P, const N: usize> {
+    factory: F,
+    policies: [P; N],
+    slots: [Slot<T>; N],
+    /// How many instances of each task were spawned so far.
+    instances: [u64; N],
+    /// How many times in a row each task was restarted, see
+    /// [`RestartContext::attempt`].
+    attempts: [u32; N],
+}
+
+/// A task of a [`WatchArray`].
+enum Slot<T> {
+    /// The task was not spawned yet, or is spawned right away.
+    Idle,
+    Running {
+        instance: T,
+        since: Instant,
+    },
+    Delayed(Sleep),
+    Awaiting(RestartTrigger),
+    Retired,
+}
+
+impl<F, T, P, const N: usize> WatchArray<F, T, P, N>
+where
+    F: FnMut(usize) -> T,
+    T: Future,
+    P: RestartPolicy + Clone,
+{
+    /// Fails to compile for empty arrays.
+    const NOT_EMPTY: () = assert!(N > 0, "a WatchArray needs at least one task");
+
+    /// Creates a watcher over `N` tasks spawned by `factory`, each going
+    /// through its own copy of `policy`.
+    #[allow(clippy::let_unit_value)]
+    pub fn new(factory: F, policy: P) -> Self {
+        let () = Self::NOT_EMPTY;
+        Self {
+            factory,
+            policies: std::array::from_fn(|_| policy.clone()),
+            slots: std::array::from_fn(|_| Slot::Idle),
+            instances: [0; N],
+            attempts: [0; N],
+        }
+    }
+}
+
+impl<F, T, P, const N: usize> WatchArray<F, T, P, N>
+where
+    F: FnMut(usize) -> T,
+    T: Future,
+    P: RestartPolicy,
+{
+    /// Applies the policy of the task `index`, whose instance exited for
+    /// `reason` after running for `uptime`. Returns an error if the watcher
+    /// must stop.
+    fn exited(
+        &mut self,
+        index: usize,
+        uptime: Duration,
+        reason: ExitReason,
+    ) -> Result<Slot<T>, WatchError> {
+        let policy = &mut self.policies[index];
+        if matches!(policy.healthy_after(), Some(after) if uptime >= after) {
+            self.attempts[index] = 0;
+        }
+        let attempt = self.attempts[index];
+        let instance = self.instances[index];
+        let context = RestartContext::new(uptime, reason.clone(), instance, attempt);
+        match policy.decide(&context) {
+            RestartDecision::RestartAfter(delay) => {
+                self.attempts[index] = attempt.saturating_add(1);
+                Ok(match delay {
+                    Duration::ZERO => Slot::Idle,
+                    delay => Slot::Delayed(tokio::time::sleep(delay)),
+                })
+            }
+            RestartDecision::AwaitTrigger(trigger) => Ok(Slot::Awaiting(trigger)),
+            RestartDecision::Retire => Ok(Slot::Retired),
+            RestartDecision::Escalate => Err(WatchError::Escalated(Escalation::new(
+                TaskId::from(index),
+                None,
+                reason,
+                None,
+            ))),
+        }
+    }
+}
+
+impl<F, T, P, const N: usize> Future for WatchArray<F, T, P, N>
+where
+    F: FnMut(usize) -> T,
+    T: Future,
+    P: RestartPolicy,
+{
+    /// How many instances of each task were spawned.
+    type Output = Result<[u64; N], WatchError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: slots are pinned along with the watcher, and are never
+        // moved out of: they are only polled in place, or overwritten, which
+        // drops the previous instance or sleep in place first.
+        let this = unsafe { self.get_unchecked_mut() };
+        for index in 0..N {
+            loop {
+                let (uptime, reason) = match &mut this.slots[index] {
+                    Slot::Idle => {
+                        this.instances[index] += 1;
+                        let factory = &mut this.factory;
+                        match panic::catch_unwind(AssertUnwindSafe(|| factory(index))) {
+                            Ok(instance) => {
+                                this.slots[index] = Slot::Running {
+                                    instance,
+                                    since: Instant::now(),
+                                };
+                                continue;
+                            }
+                            Err(payload) => (Duration::ZERO, panicked(payload)),
+                        }
+                    }
+                    Slot::Running { instance, since } => {
+                        // SAFETY: see above.
+                        let instance = unsafe { Pin::new_unchecked(instance) };
+                        // The instance is dropped right after panicking,
+                        // without being polled again.
+                        match panic::catch_unwind(AssertUnwindSafe(|| instance.poll(cx))) {
+                            Ok(Poll::Ready(_)) => (since.elapsed(), ExitReason::Completed),
+                            Ok(Poll::Pending) => break,
+                            Err(payload) => (since.elapsed(), panicked(payload)),
+                        }
+                    }
+                    Slot::Delayed(sleep) => {
+                        // SAFETY: see above.
+                        let sleep = unsafe { Pin::new_unchecked(sleep) };
+                        match sleep.poll(cx) {
+                            Poll::Ready(()) => {
+                                this.slots[index] = Slot::Idle;
+                                continue;
+                            }
+                            Poll::Pending => break,
+                        }
+                    }
+                    Slot::Awaiting(trigger) => match trigger.poll_fired(cx) {
+                        Poll::Ready(()) => {
+                            this.slots[index] = Slot::Idle;
+                            continue;
+                        }
+                        Poll::Pending => break,
+                    },
+                    Slot::Retired => break,
+                };
+
+                match this.exited(index, uptime, reason) {
+                    // Respawned on the next poll, so that instances returning
+                    // right away do not keep the watcher busy.
+                    Ok(Slot::Idle) => {
+                        this.slots[index] = Slot::Idle;
+                        cx.waker().wake_by_ref();
+                        break;
+                    }
+                    Ok(slot) => this.slots[index] = slot,
+                    Err(error) => {
+                        for slot in &mut this.slots {
+                            *slot = Slot::Retired;
+                        }
+                        return Poll::Ready(Err(error));
+                    }
+                }
+            }
+        }
+
+        if this.slots.iter().all(|slot| matches!(slot, Slot::Retired)) {
+            Poll::Ready(Ok(this.instances))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Turns the payload of a panic into an exit reason.
+fn panicked(payload: Box<dyn std::any::Any + Send>) -> ExitReason {
+    ExitReason::Panicked(Panic::new(payload, None))
+}
+
+impl<F, T, P, const N: usize> fmt::Debug for WatchArray<F, T, P, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WatchArray")
+            .field("instances", &self.instances)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,80 @@
+use crate::policy::{RestartContext, RestartDecision, RestartPolicy};
+use std::time::Duration;
+
+/// Exponential delay applied between an exit and the respawn of a task.
+///
+/// The first respawn waits `initial`, and every consecutive one multiplies the
+/// previous delay by `factor`, never waiting for longer than `max`.
+///
+/// Every task keeps its own attempt counter, see [`RestartContext::attempt`].
+/// A task that stays up for at least [`Backoff::reset_after`] is considered
+/// healthy and its counter goes back to zero, so a crash after days of uptime
+/// starts from `initial` again instead of waiting for `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    factor: u32,
+    reset_after: Option<Duration>,
+    max_attempts: Option<u32>,
+}
+
+impl Backoff {
+    /// Creates a [`Backoff`] that starts at `initial`, doubles on every
+    /// consecutive respawn and is capped at `max`.
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            factor: 2,
+            reset_after: None,
+            max_attempts: None,
+        }
+    }
+
+    /// Sets the multiplier applied between two consecutive delays.
+    pub fn factor(mut self, factor: u32) -> Self {
+        self.factor = factor;
+        self
+    }
+
+    /// Sets how long a task must stay up to be considered healthy. When a
+    /// healthy task exits, its attempt counter is reset and the next respawn
+    /// waits `initial` again.
+    ///
+    /// By default the counter is never reset.
+    pub fn reset_after(mut self, uptime: Duration) -> Self {
+        self.reset_after = Some(uptime);
+        self
+    }
+
+    /// Retires the task once it was respawned `max` times in a row, that is
+    /// without staying up for [`Backoff::reset_after`] in between.
+    ///
+    /// By default tasks are respawned forever.
+    pub fn max_attempts(mut self, max: u32) -> Self {
+        self.max_attempts = Some(max);
+        self
+    }
+
+    /// Returns the delay to wait before the respawn number `attempt`, starting
+    /// at zero.
+    fn delay(&self, attempt: u32) -> Duration {
+        self.initial
+            .saturating_mul(self.factor.saturating_pow(attempt))
+            .min(self.max)
+    }
+}
+
+impl RestartPolicy for Backoff {
+    fn decide(&mut self, context: &RestartContext) -> RestartDecision {
+        if matches!(self.max_attempts, Some(max) if context.attempt() >= max) {
+            return RestartDecision::Retire;
+        }
+        RestartDecision::RestartAfter(self.delay(context.attempt()))
+    }
+
+    fn healthy_after(&self) -> Option<Duration> {
+        self.reset_after
+    }
+}
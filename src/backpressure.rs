@@ -0,0 +1,202 @@
+use crate::handle::Command;
+use futures::stream::Stream;
+use futures::task::AtomicWaker;
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// What happens to the commands sent through the [`crate::WatchHandle`]'s of
+/// a watcher when they come faster than it applies them, such as during a
+/// burst of admin commands, see [`crate::Builder::backpressure`].
+///
+/// Commands wait in a queue until the watcher is polled again. Bounds only
+/// apply to commands about a task that are safe to lose, such as
+/// restarts, pauses or removals: the others, such as adding tasks, shutting
+/// down or the holds of a [`crate::RestartGate`], are always queued.
+///
+/// ```
+/// use futures::{future, stream, SinkExt};
+/// use watch::{Backpressure, Builder, TaskId, WatchCommand};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let (watch, mut handle) = Builder::new()
+///     .task(future::pending::<()>)
+///     .backpressure(Backpressure::Block(16))
+///     .build();
+/// # let watch = tokio::spawn(watch);
+/// // Waits for room whenever 16 commands are queued.
+/// let restarts = (0..100).map(|_| Ok(WatchCommand::Restart(TaskId::from(0))));
+/// handle.send_all(&mut stream::iter(restarts)).await.unwrap();
+/// # handle.shutdown();
+/// # watch.await.unwrap().unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backpressure {
+    /// Every command is queued, however many are. This is the default.
+    #[default]
+    Unbounded,
+    /// Once that many commands are queued, the [`futures::Sink`] of the
+    /// handles waits for room before taking more. The methods of the
+    /// handles, which can't wait, queue their commands all the same.
+    Block(usize),
+    /// Once that many commands are queued, the oldest one about a task is
+    /// dropped to make room, which the watcher reports as an
+    /// [`crate::Event::CommandDropped`].
+    DropOldest(usize),
+}
+
+/// Returns the ends of the queue of commands of a watcher.
+pub(crate) fn channel(backpressure: Backpressure) -> (Sender, Receiver) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(Queue::default()),
+        backpressure,
+        receiver: AtomicWaker::new(),
+    });
+    let sender = Sender {
+        shared: Arc::clone(&shared),
+    };
+    (sender, Receiver { shared })
+}
+
+#[derive(Default)]
+struct Queue {
+    commands: VecDeque<Command>,
+    /// The commands dropped to make room, for the watcher to report.
+    dropped: Vec<Command>,
+    /// The senders waiting for room, see [`Backpressure::Block`].
+    blocked: Vec<Waker>,
+    closed: bool,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    backpressure: Backpressure,
+    receiver: AtomicWaker,
+}
+
+/// Queues the commands of the handles of a watcher.
+#[derive(Clone)]
+pub(crate) struct Sender {
+    shared: Arc<Shared>,
+}
+
+impl Sender {
+    /// Queues `command`, unless the watcher stopped, in which case it is
+    /// given back.
+    pub(crate) fn send(&self, command: Command) -> Result<(), Command> {
+        let mut queue = match self.shared.queue.lock() {
+            Ok(queue) => queue,
+            Err(_) => return Err(command),
+        };
+        if queue.closed {
+            return Err(command);
+        }
+        if let Backpressure::DropOldest(capacity) = self.shared.backpressure {
+            if command.droppable().is_some() && queue.commands.len() >= capacity {
+                let oldest = queue
+                    .commands
+                    .iter()
+                    .position(|queued| queued.droppable().is_some());
+                if let Some(oldest) = oldest.and_then(|oldest| queue.commands.remove(oldest)) {
+                    queue.dropped.push(oldest);
+                }
+            }
+        }
+        queue.commands.push_back(command);
+        drop(queue);
+        self.shared.receiver.wake();
+        Ok(())
+    }
+
+    /// Returns whether there is room for more commands, see
+    /// [`Backpressure::Block`], registering to be woken up once there is if
+    /// there is not.
+    pub(crate) fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let capacity = match self.shared.backpressure {
+            Backpressure::Block(capacity) => capacity.max(1),
+            Backpressure::Unbounded | Backpressure::DropOldest(_) => return Poll::Ready(()),
+        };
+        let mut queue = match self.shared.queue.lock() {
+            Ok(queue) => queue,
+            Err(_) => return Poll::Ready(()),
+        };
+        if queue.closed || queue.commands.len() < capacity {
+            return Poll::Ready(());
+        }
+        queue.blocked.push(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl fmt::Debug for Sender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender")
+            .field("backpressure", &self.shared.backpressure)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Takes the commands of the handles of a watcher, in order.
+pub(crate) struct Receiver {
+    shared: Arc<Shared>,
+}
+
+impl Receiver {
+    /// Takes the commands dropped to make room since the last call.
+    pub(crate) fn dropped(&mut self) -> Vec<Command> {
+        match self.shared.queue.lock() {
+            Ok(mut queue) => std::mem::take(&mut queue.dropped),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Stops taking commands, dropping the ones queued so that whoever
+    /// waits for them to be applied is let go.
+    pub(crate) fn close(&mut self) {
+        let blocked = match self.shared.queue.lock() {
+            Ok(mut queue) => {
+                queue.closed = true;
+                queue.commands.clear();
+                queue.dropped.clear();
+                std::mem::take(&mut queue.blocked)
+            }
+            Err(_) => return,
+        };
+        blocked.into_iter().for_each(Waker::wake);
+    }
+}
+
+impl Stream for Receiver {
+    type Item = Command;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // Registered first, so that commands queued meanwhile wake it up.
+        self.shared.receiver.register(cx.waker());
+        let (command, blocked) = match self.shared.queue.lock() {
+            Ok(mut queue) => {
+                let command = queue.commands.pop_front();
+                let blocked = match command {
+                    Some(_) => std::mem::take(&mut queue.blocked),
+                    None => Vec::new(),
+                };
+                (command, blocked)
+            }
+            Err(_) => return Poll::Ready(None),
+        };
+        blocked.into_iter().for_each(Waker::wake);
+        match command {
+            Some(command) => Poll::Ready(Some(command)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
@@ -0,0 +1,107 @@
+use crate::labels::{Labels, Selector};
+use crate::policy::RestartDecision;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Bounds how many times a group of tasks may restart, collectively, within a
+/// window of time, on top of the [`crate::RestartPolicy`] of every task.
+///
+/// Budgets are given to the tasks whose labels match a [`crate::Selector`],
+/// see [`crate::Builder::budget`]. Every restart decided by the policy of one
+/// of those tasks spends the budget. Once it is spent, restarts are replaced
+/// by [`RestartBudget::exhausted`], which escalates by default, stopping the
+/// watcher with [`crate::WatchError::BudgetExhausted`]:
+///
+/// ```no_run
+/// # async fn warm() {}
+/// # async fn run() {
+/// use std::time::Duration;
+/// use watch::{Builder, RestartBudget, Task};
+///
+/// // The cache warmers collectively may restart 10 times per minute.
+/// Builder::new()
+///     .task(Task::new(warm).label("role", "warmer"))
+///     .task(Task::new(warm).label("role", "warmer"))
+///     .budget("role=warmer", RestartBudget::new(10, Duration::from_secs(60)))
+///     .run()
+///     .await
+///     .unwrap_err();
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestartBudget {
+    max: usize,
+    window: Duration,
+    exhausted: RestartDecision,
+}
+
+impl RestartBudget {
+    /// Creates a budget of `max` restarts within any `window`.
+    pub fn new(max: usize, window: Duration) -> Self {
+        Self {
+            max,
+            window,
+            exhausted: RestartDecision::Escalate,
+        }
+    }
+
+    /// Sets what happens instead of a restart once the budget is spent. Use
+    /// [`RestartDecision::Retire`] to give up on the task alone, or
+    /// [`RestartDecision::RestartAfter`] to hold it back longer.
+    ///
+    /// By default the watcher escalates.
+    pub fn exhausted(mut self, decision: RestartDecision) -> Self {
+        self.exhausted = decision;
+        self
+    }
+}
+
+/// A [`RestartBudget`] along with the tasks it is given to and the restarts
+/// that spent it.
+#[derive(Debug)]
+pub(crate) struct Budget {
+    /// Which tasks spend the budget, including children started later.
+    pub(crate) selector: Selector,
+    budget: RestartBudget,
+    /// When the restarts within the last window happened, oldest first.
+    restarts: VecDeque<Instant>,
+}
+
+impl Budget {
+    pub(crate) fn new(selector: Selector, budget: RestartBudget) -> Self {
+        Self {
+            selector,
+            budget,
+            restarts: VecDeque::new(),
+        }
+    }
+
+    /// Forgets the restarts that are out of the window, then returns the
+    /// decision replacing a restart if the budget is spent.
+    pub(crate) fn check(&mut self, now: Instant) -> Option<RestartDecision> {
+        while matches!(self.restarts.front(), Some(at) if now - *at >= self.budget.window) {
+            self.restarts.pop_front();
+        }
+
+        if self.restarts.len() >= self.budget.max {
+            Some(self.budget.exhausted.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Spends the budget for a restart happening `now`.
+    pub(crate) fn spend(&mut self, now: Instant) {
+        self.restarts.push_back(now);
+    }
+}
+
+/// Returns the indices of the `budgets` given to a task with `labels`.
+pub(crate) fn matching(budgets: &[Budget], labels: &Labels) -> Vec<usize> {
+    budgets
+        .iter()
+        .enumerate()
+        .filter(|(_, budget)| budget.selector.matches(labels))
+        .map(|(index, _)| index)
+        .collect()
+}
@@ -0,0 +1,1570 @@
+use crate::alert::{Alert, Alerter, Thresholds};
+use crate::backoff::Backoff;
+use crate::backpressure::Backpressure;
+use crate::budget::{self, Budget, RestartBudget};
+use crate::cancellation::Cancellation;
+use crate::clock::{Clock, TokioClock};
+use crate::context::{Shared, TaskContext};
+use crate::debounce::Debounce;
+use crate::error::WatchError;
+use crate::event::Executor;
+use crate::handle::WatchHandle;
+use crate::health::{Health, HealthMonitor};
+use crate::id::TaskId;
+use crate::keyed::{KeyedShards, Shard};
+use crate::labels::Selector;
+use crate::layer::{Instance, Layer};
+use crate::map::WatchMap;
+use crate::observer::WatchObserver;
+use crate::pacing::RespawnPacing;
+use crate::panic::PanicBehavior;
+use crate::policy::{Immediate, MaxAttempts, PolicyFactory, RestartPolicy};
+use crate::quorum::{Group, Quorum};
+use crate::set::TaskSet;
+use crate::shutdown::{Shutdown, ShutdownSignal};
+use crate::strategy::{RestartOrder, SupervisionStrategy};
+use crate::summary::Summary;
+use crate::task::Task;
+use crate::template::Template;
+use crate::watcher::{Config, Slot, Watch};
+use crate::window::RestartWindow;
+use futures::future::{self, BoxFuture, Either};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use std::future::{Future, IntoFuture};
+use std::hash::Hash;
+use std::mem;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Configures the set of tasks to watch and how they are respawned.
+///
+/// Policies given to a [`Task`] take precedence over the default policy of the
+/// [`Builder`], which itself defaults to respawning tasks immediately. The
+/// default policy applies to every task without one, no matter whether it was
+/// added before or after the default was set.
+///
+/// ```no_run
+/// # async fn serve() {}
+/// # async fn flush() {}
+/// # async fn run() {
+/// use std::time::Duration;
+/// use watch::{Backoff, Builder, Task};
+///
+/// Builder::new()
+///     .task(serve)
+///     .task(Task::new(flush).policy(Backoff::new(
+///         Duration::from_secs(1),
+///         Duration::from_secs(60),
+///     )))
+///     .backoff(
+///         Backoff::new(Duration::from_millis(100), Duration::from_secs(30))
+///             .reset_after(Duration::from_secs(60)),
+///     )
+///     .run()
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Default)]
+pub struct Builder {
+    tasks: Vec<Task>,
+    /// The tasks added as they are yielded, see [`Builder::task_stream`].
+    incoming: Option<BoxStream<'static, Task>>,
+    policy: Option<PolicyFactory>,
+    /// How many times in a row tasks using the default policy are respawned
+    /// at most, see [`Builder::with_max_attempts`].
+    max_attempts: Option<u32>,
+    shutdown: Shutdown,
+    grace_period: Duration,
+    max_concurrent_starts: Option<usize>,
+    max_running: Option<usize>,
+    shards: Option<usize>,
+    observers: Vec<Box<dyn WatchObserver>>,
+    backpressure: Backpressure,
+    executor: Option<Executor>,
+    event_capacity: Option<usize>,
+    budgets: Vec<(Selector, RestartBudget)>,
+    quorums: Vec<(Selector, Quorum)>,
+    health: Option<HealthMonitor>,
+    alerter: Option<Alerter>,
+    #[cfg(feature = "history")]
+    history: Vec<Box<dyn crate::HistorySink>>,
+    #[cfg(feature = "history")]
+    history_errors: Option<crate::history::ErrorHook>,
+    #[cfg(feature = "instrument")]
+    on_poll: Option<crate::instrument::PollHook>,
+    strategy: Option<Box<dyn SupervisionStrategy>>,
+    restart_order: RestartOrder,
+    pacing: RespawnPacing,
+    template: Option<Template>,
+    keyed: Option<KeyedShards>,
+    startup_deadline: Option<Duration>,
+    max_immediate_exits: Option<u32>,
+    held: bool,
+    fail_fast: bool,
+    failure_window: Option<Duration>,
+    restart_window: Option<RestartWindow>,
+    debounce: Option<Debounce>,
+    clock: Option<Arc<dyn Clock>>,
+    context: Option<Shared>,
+    layers: Vec<Arc<dyn Layer>>,
+    backtraces: bool,
+    panic_behavior: PanicBehavior,
+    cancellation: Cancellation,
+    #[cfg(feature = "join-set")]
+    join_set: bool,
+    #[cfg(all(unix, feature = "signals"))]
+    signals: Vec<(crate::SignalKind, crate::signals::Hook)>,
+}
+
+impl Builder {
+    /// Creates a [`Builder`] with no tasks, that respawns tasks as soon as they
+    /// exit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a task to the set. Closures are accepted and turned into a
+    /// [`Task`] with no configuration of its own, see [`Task::new`].
+    pub fn task<T>(mut self, task: T) -> Self
+    where
+        T: Into<Task>,
+    {
+        self.tasks.push(task.into());
+        self
+    }
+
+    /// Sets the application context of the watcher, handed to the factories
+    /// of tasks created with [`Task::shared`] and available to any instance
+    /// through [`crate::TaskContext::shared`]. This replaces any previous
+    /// context.
+    pub fn context<C>(mut self, context: Arc<C>) -> Self
+    where
+        C: Send + Sync + 'static,
+    {
+        self.context = Some(context);
+        self
+    }
+
+    /// Wraps every instance of every task with `layer`, including the tasks
+    /// added at runtime, see [`Layer`]. Layers added later wrap the ones
+    /// added before.
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer + 'static,
+    {
+        self.layers.push(Arc::new(layer));
+        self
+    }
+
+    /// Spawns instances onto the Tokio runtime, in a
+    /// [`tokio::task::JoinSet`] owned by the [`Watch`], instead of polling
+    /// them along with the [`Watch`] itself, with the `join-set` feature.
+    /// Instances are then scheduled by the runtime, possibly in parallel on
+    /// several threads, and are aborted when the [`Watch`] is dropped.
+    /// Supervision works the same either way.
+    ///
+    /// The [`Watch`] must be polled within a Tokio runtime.
+    ///
+    /// ```
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use watch::{Builder, RestartDecision};
+    ///
+    /// let summary = Builder::new()
+    ///     .task(|| async {})
+    ///     .join_set()
+    ///     .policy(|_: &_| RestartDecision::Retire)
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// assert_eq!(summary.spawned(), 1);
+    /// # }
+    /// ```
+    #[cfg(feature = "join-set")]
+    pub fn join_set(mut self) -> Self {
+        self.join_set = true;
+        self
+    }
+
+    /// Decides how the watcher stops instances of tasks without a
+    /// [`Task::cancellation`] of their own, [`Cancellation::Abort`] by
+    /// default.
+    pub fn cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Decides what happens when an instance of a task without a
+    /// [`Task::panic_behavior`] of its own panics, [`PanicBehavior::Restart`]
+    /// by default.
+    pub fn panic_behavior(mut self, behavior: PanicBehavior) -> Self {
+        self.panic_behavior = behavior;
+        self
+    }
+
+    /// Captures a backtrace whenever an instance panics, to be found in the
+    /// [`crate::Panic`] of its [`crate::ExitReason::Panicked`]. This chains a
+    /// hook to the panic hook of the process, which must not be replaced
+    /// afterwards for backtraces to keep being captured.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use futures::StreamExt;
+    /// use watch::{Builder, ExitReason, RestartDecision};
+    ///
+    /// let (watch, handle) = Builder::new()
+    ///     .task(|| async { panic!("lost the connection") })
+    ///     .capture_backtraces()
+    ///     .policy(|_: &_| RestartDecision::Retire)
+    ///     .build();
+    /// let mut monitor = handle.monitor(0);
+    /// watch.await.unwrap();
+    ///
+    /// match monitor.next().await.unwrap().reason() {
+    ///     ExitReason::Panicked(panic) => {
+    ///         assert_eq!(panic.message(), "lost the connection");
+    ///         assert!(panic.backtrace().is_some());
+    ///     }
+    ///     _ => unreachable!(),
+    /// }
+    /// # }
+    /// ```
+    pub fn capture_backtraces(mut self) -> Self {
+        self.backtraces = true;
+        self
+    }
+
+    /// Sets the template of the children started at runtime with
+    /// [`WatchHandle::start_child`], which calls `template` with the given
+    /// arguments every time the child needs to be (re)spawned. Children go
+    /// through the default policy of the [`Builder`]. This is the shape of a
+    /// worker per connection or per tenant.
+    ///
+    /// A watcher with a template keeps going even without tasks, waiting for
+    /// children, until it is drained or shut down.
+    pub fn template<F, A, T>(mut self, template: F) -> Self
+    where
+        F: Fn(A) -> T + Send + Sync + 'static,
+        A: Clone + Send + Sync + 'static,
+        T: Future + Send + 'static,
+    {
+        self.template = Some(Template::new(template));
+        self
+    }
+
+    /// Spreads a key space over `count` workers, one task per [`Shard`],
+    /// added after the other tasks and named `shard-0`, `shard-1` and so on.
+    /// `worker` is called with the shard every time its task needs to be
+    /// (re)spawned, and the shard tells which keys are its own, see
+    /// [`Shard::owns`].
+    ///
+    /// The shard count changes at runtime with [`WatchHandle::reshard`],
+    /// which only starts or removes the shards at the end: the others keep
+    /// running. A watcher with keyed shards keeps going even without tasks,
+    /// until it is drained or shut down.
+    pub fn keyed_shards<F, T>(mut self, count: usize, worker: F) -> Self
+    where
+        F: Fn(Shard) -> T + Send + Sync + 'static,
+        T: Future + Send + 'static,
+    {
+        self.keyed = Some(KeyedShards::new(count, worker));
+        self
+    }
+
+    /// Adds every task yielded by `tasks` to the set. See [`Builder::task`].
+    pub fn tasks<I>(self, tasks: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Task>,
+    {
+        tasks.into_iter().fold(self, Builder::task)
+    }
+
+    /// Adds every task yielded by `tasks` to the set, each through
+    /// `configure` along with what it was made from, for sets whose size is
+    /// only known at runtime but whose tasks still need names, policies and
+    /// so on of their own. See [`Builder::tasks`].
+    ///
+    /// ```no_run
+    /// # async fn serve(shard: u32) {}
+    /// # async fn run() {
+    /// use std::time::Duration;
+    /// use watch::{Backoff, Builder, Task};
+    ///
+    /// #[derive(Clone)]
+    /// struct Shard {
+    ///     id: u32,
+    ///     critical: bool,
+    /// }
+    ///
+    /// impl From<Shard> for Task {
+    ///     fn from(shard: Shard) -> Self {
+    ///         Task::new(move || serve(shard.id))
+    ///     }
+    /// }
+    ///
+    /// let shards = (0..16).map(|id| Shard { id, critical: id == 0 });
+    /// let second = Duration::from_secs(1);
+    /// Builder::new()
+    ///     .tasks_with(shards, |shard, task| {
+    ///         let task = task.name(format!("shard-{}", shard.id));
+    ///         match shard.critical {
+    ///             true => task.critical().policy(Backoff::new(second, second)),
+    ///             false => task,
+    ///         }
+    ///     })
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn tasks_with<I, F>(self, tasks: I, mut configure: F) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Clone + Into<Task>,
+        F: FnMut(I::Item, Task) -> Task,
+    {
+        tasks.into_iter().fold(self, |builder, item| {
+            let task = item.clone().into();
+            builder.task(configure(item, task))
+        })
+    }
+
+    /// Adds every task yielded by `tasks` as it is, after the tasks added to
+    /// the [`Builder`], for sets discovered at runtime such as from a service
+    /// registry. The stream is pinned internally, so streams that are not
+    /// [`Unpin`], such as the ones of `async_stream::stream!` or
+    /// [`futures::stream::unfold`], work as they are. See [`Builder::task`].
+    ///
+    /// The watcher keeps going while the stream does, even without tasks, and
+    /// stops polling it once drained or shut down. Several streams are polled
+    /// in turn.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use futures::stream;
+    /// use watch::{Builder, RestartDecision, Task};
+    ///
+    /// # async fn discover(shard: u32) -> u32 { shard }
+    /// # async fn serve(shard: u32) {}
+    /// let shards = stream::unfold(0, |shard| async move {
+    ///     if shard == 3 {
+    ///         return None;
+    ///     }
+    ///     let shard = discover(shard).await;
+    ///     Some((Task::new(move || serve(shard)), shard + 1))
+    /// });
+    ///
+    /// let summary = Builder::new()
+    ///     .task_stream(shards)
+    ///     .policy(|_: &_| RestartDecision::Retire)
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// assert_eq!(summary.spawned(), 3);
+    /// # }
+    /// ```
+    pub fn task_stream<S>(mut self, tasks: S) -> Self
+    where
+        S: Stream + Send + 'static,
+        S::Item: Into<Task>,
+    {
+        self.incoming(tasks.map(Into::into).boxed());
+        self
+    }
+
+    /// Adds the tasks yielded by `tasks` as they are, along with the ones of
+    /// the other streams, if any.
+    fn incoming(&mut self, tasks: BoxStream<'static, Task>) {
+        self.incoming = Some(match self.incoming.take() {
+            Some(incoming) => stream::select(incoming, tasks).boxed(),
+            None => tasks,
+        });
+    }
+
+    /// Adds the tasks of `other` to the set, so that sets of tasks built
+    /// independently, such as by libraries, are supervised by a single
+    /// [`Watch`], with a single [`WatchHandle`] and stream of events.
+    ///
+    /// The tasks of `other` come after the tasks added so far, and keep what
+    /// `other` configured for them: its default policy for the ones without a
+    /// policy of their own, its layers, and the dependencies between them, see
+    /// [`Task::depends_on`]. So do the tasks its streams yield, see
+    /// [`Builder::task_stream`], added along with the ones of `self`. Its
+    /// budgets, quorums, observers, history sinks and signal hooks are added
+    /// to the ones of `self`. Its template, context and health and alert
+    /// hooks are only kept if `self` has none. Anything else, such as the
+    /// grace period or the clock, is the one of `self`.
+    ///
+    /// To merge a set into a watcher that already started, see
+    /// [`WatchHandle::merge`].
+    ///
+    /// ```
+    /// # async fn index() {}
+    /// # async fn serve() {}
+    /// use std::time::Duration;
+    /// use watch::{Backoff, Builder};
+    ///
+    /// // Exported by a library.
+    /// fn indexer() -> Builder {
+    ///     let second = Duration::from_secs(1);
+    ///     Builder::new()
+    ///         .task(index)
+    ///         .backoff(Backoff::new(second, 60 * second))
+    /// }
+    ///
+    /// let (watch, handle) = Builder::new().task(serve).merge(indexer()).build();
+    /// # drop(watch);
+    /// assert_eq!(handle.tasks().len(), 2);
+    /// ```
+    pub fn merge(mut self, mut other: Builder) -> Self {
+        let offset = self.tasks.len();
+        let configure = other.configure();
+        for task in mem::take(&mut other.tasks) {
+            let mut task = configure(task);
+            for dependency in &mut task.dependencies {
+                *dependency += offset;
+            }
+            self.tasks.push(task);
+        }
+        if let Some(incoming) = other.incoming.take() {
+            self.incoming(incoming.map(configure).boxed());
+        }
+        self.budgets.extend(other.budgets);
+        self.quorums.extend(other.quorums);
+        self.observers.extend(other.observers);
+        #[cfg(feature = "history")]
+        {
+            self.history.extend(other.history);
+            self.history_errors = self.history_errors.or(other.history_errors);
+        }
+        #[cfg(all(unix, feature = "signals"))]
+        self.signals.extend(other.signals);
+        self.template = self.template.or(other.template);
+        self.keyed = self.keyed.or(other.keyed);
+        self.context = self.context.or(other.context);
+        self.health = self.health.or(other.health);
+        self.alerter = self.alerter.or(other.alerter);
+        self
+    }
+
+    /// Returns what gives the tasks of the set its default policy, for the
+    /// ones without a policy of their own, and its layers, for them to keep
+    /// once merged into another set.
+    fn configure(&self) -> impl Fn(Task) -> Task + Clone + Send + Sync + 'static {
+        let policy = self.default_policy();
+        let layers = self.layers.clone();
+        move |mut task| {
+            if task.policy.is_none() {
+                task.policy = policy.as_ref().map(|policy| policy());
+            }
+            for layer in &layers {
+                let layer = Arc::clone(layer);
+                task = task.layer(move |instance: Instance, context: &TaskContext| {
+                    layer.layer(instance, context)
+                });
+            }
+            task
+        }
+    }
+
+    /// Returns the tasks of the set, and the stream of the ones added as they
+    /// are yielded, configured as by [`Builder::merge`]. Anything else is
+    /// dropped.
+    pub(crate) fn into_tasks(mut self) -> (Vec<Task>, Option<BoxStream<'static, Task>>) {
+        let configure = self.configure();
+        let incoming = self
+            .incoming
+            .take()
+            .map(|incoming| incoming.map(configure.clone()).boxed());
+        let tasks = mem::take(&mut self.tasks)
+            .into_iter()
+            .map(configure)
+            .collect();
+        (tasks, incoming)
+    }
+
+    /// Mounts the tasks of `set` under `prefix`, such as the tasks a library
+    /// exports. See [`TaskSet`].
+    ///
+    /// The tasks come after the tasks added so far, and their names are
+    /// prefixed with `prefix` and a dot, so that tasks of different sets can
+    /// be told apart: the task `flush` of a set mounted under `storage` is
+    /// named `storage.flush`. Tasks without a name are named `prefix`. As with
+    /// [`Builder::merge`], the dependencies between the tasks of the set are
+    /// kept, and their default policy is the one of the set if it has one.
+    ///
+    /// ```
+    /// # async fn flush() {}
+    /// use watch::{Builder, Task, TaskSet};
+    ///
+    /// let storage = TaskSet::new().task(Task::new(flush).name("flush"));
+    /// let (watch, handle) = Builder::new().mount("storage", storage).build();
+    /// # drop(watch);
+    /// assert_eq!(handle.tasks()[0].name(), Some("storage.flush"));
+    /// ```
+    pub fn mount<P>(self, prefix: P, set: TaskSet) -> Self
+    where
+        P: Into<String>,
+    {
+        let prefix = prefix.into();
+        let mut other = Builder::new();
+        other.policy = set.policy;
+        other.tasks = set.tasks;
+        for task in &mut other.tasks {
+            task.metadata.name = Some(match task.metadata.name.take() {
+                Some(name) => format!("{}.{}", prefix, name),
+                None => prefix.clone(),
+            });
+        }
+        self.merge(other)
+    }
+
+    /// Decides how tasks without a policy of their own are respawned. Every
+    /// such task gets a clone of `policy`.
+    pub fn policy<P>(self, policy: P) -> Self
+    where
+        P: RestartPolicy + Clone + Sync + 'static,
+    {
+        self.policy_with(move || policy.clone())
+    }
+
+    /// Decides how tasks without a policy of their own are respawned.
+    /// `policy` is called once per task to create its own [`RestartPolicy`].
+    pub fn policy_with<F, P>(mut self, policy: F) -> Self
+    where
+        F: Fn() -> P + Send + Sync + 'static,
+        P: RestartPolicy + 'static,
+    {
+        self.policy = Some(Arc::new(move || Box::new(policy())));
+        self
+    }
+
+    /// Retires tasks without a policy of their own once they were respawned
+    /// `max_attempts` times in a row, see [`crate::RestartContext::attempt`].
+    ///
+    /// Applies on top of the default policy, such as set with
+    /// [`Builder::policy`], whether it is set before or after. Without a
+    /// default policy, tasks are not respawned right away as they otherwise
+    /// are, but back off: they wait from 100ms up to 30s between attempts, and
+    /// start over after staying up for a minute, as with
+    /// `Backoff::new(100ms, 30s).reset_after(60s)`, see
+    /// [`Backoff::max_attempts`].
+    ///
+    /// ```no_run
+    /// # async fn connect() {}
+    /// # async fn run() {
+    /// use std::time::Duration;
+    /// use watch::Builder;
+    ///
+    /// Builder::new()
+    ///     .task(connect)
+    ///     .with_timeout(Duration::from_secs(30))
+    ///     .with_max_attempts(5)
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Returns what creates the policy of the tasks without one of their own,
+    /// if not the watcher's, see [`Builder::with_max_attempts`].
+    fn default_policy(&self) -> Option<PolicyFactory> {
+        let max_attempts = match self.max_attempts {
+            Some(max_attempts) => max_attempts,
+            None => return self.policy.clone(),
+        };
+        Some(match self.policy.clone() {
+            Some(policy) => Arc::new(move || Box::new(MaxAttempts::new(policy(), max_attempts))),
+            None => Arc::new(move || {
+                Box::new(
+                    Backoff::new(Duration::from_millis(100), Duration::from_secs(30))
+                        .reset_after(Duration::from_secs(60))
+                        .max_attempts(max_attempts),
+                )
+            }),
+        })
+    }
+
+    /// Times out instances of every task running for longer than `timeout`,
+    /// which then go through their policy. Shorthand for [`Builder::layer`]
+    /// with a [`crate::TimeoutLayer`], see [`Builder::with_max_attempts`].
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.layer(crate::TimeoutLayer::new(timeout))
+    }
+
+    /// Waits according to `backoff` before respawning a task that exited.
+    /// Shorthand for [`Builder::policy`].
+    pub fn backoff(self, backoff: Backoff) -> Self {
+        self.policy(backoff)
+    }
+
+    /// Takes the delays to wait before respawning a task from an
+    /// implementation of the [`backoff`] crate. `backoff` is called once per
+    /// task, so every task backs off independently. Shorthand for
+    /// [`Builder::policy_with`] and [`crate::FromBackoff`].
+    ///
+    /// When the [`backoff::backoff::Backoff`] gives up by returning [`None`],
+    /// the task is not respawned anymore. With the default
+    /// [`backoff::ExponentialBackoff`], this happens after 15 minutes of
+    /// failures, see [`crate::FromBackoff`] to reset it after healthy runs.
+    ///
+    /// ```no_run
+    /// # async fn serve() {}
+    /// # async fn run() {
+    /// use backoff::ExponentialBackoff;
+    /// use std::time::Duration;
+    /// use watch::Builder;
+    ///
+    /// let tuned = ExponentialBackoff {
+    ///     initial_interval: Duration::from_millis(50),
+    ///     ..ExponentialBackoff::default()
+    /// };
+    ///
+    /// Builder::new()
+    ///     .task(serve)
+    ///     .backoff_with(move || tuned.clone())
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "backoff")]
+    pub fn backoff_with<F, B>(self, backoff: F) -> Self
+    where
+        F: Fn() -> B + Send + Sync + 'static,
+        B: backoff::backoff::Backoff + Send + 'static,
+    {
+        self.policy_with(move || crate::FromBackoff::new(backoff()))
+    }
+
+    /// Sets for how long running instances may keep running once the watcher
+    /// began shutting down, so they can tear down cleanly after their
+    /// [`ShutdownSignal`] resolved. Instances still running afterwards are
+    /// dropped.
+    ///
+    /// By default instances are dropped as soon as the shutdown begins.
+    pub fn grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Requires every task to come up, that is to have an instance ready, see
+    /// [`Task::signals_readiness`], within `deadline` of the watcher starting.
+    /// Otherwise the [`Watch`] stops with
+    /// [`crate::WatchError::StartupTimeout`], listing the tasks that never
+    /// came up. Removed tasks and children started at runtime do not count.
+    ///
+    /// By default there is no deadline.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+    /// # async fn main() {
+    /// use std::time::Duration;
+    /// use watch::{Builder, Task, TaskId, WatchError};
+    ///
+    /// let error = Builder::new()
+    ///     .task(|| futures::future::pending::<()>())
+    ///     .task(Task::new(|| futures::future::pending::<()>()).signals_readiness())
+    ///     .startup_deadline(Duration::from_secs(10))
+    ///     .run()
+    ///     .await
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(error, WatchError::StartupTimeout { tasks: vec![TaskId::from(1)] });
+    /// # }
+    /// ```
+    pub fn startup_deadline(mut self, deadline: Duration) -> Self {
+        self.startup_deadline = Some(deadline);
+        self
+    }
+
+    /// Fails startup once the instances of a task completed on their first
+    /// poll `max` times in a row, before any of them was up, that is before
+    /// one of them was ever pending. The [`Watch`] then stops with
+    /// [`crate::WatchError::StartupFailed`] rather than restarting a task that
+    /// can't even start, unless the task is [`Task::best_effort`], in which
+    /// case it is retired. A `max` of zero is taken as one: startup fails on
+    /// the first immediate exit.
+    ///
+    /// Only the tasks spawned as the watcher starts are watched so, until one
+    /// of their instances is up: tasks added through a [`WatchHandle`] or
+    /// triggered later, see [`Task::deferred`], never are. Tasks whose
+    /// instances complete right away by design, such as periodic jobs doing
+    /// their work in a single poll, fail startup as well.
+    ///
+    /// Every such exit is reported with [`crate::Event::StartupImmediateExit`]
+    /// either way. By default, tasks are restarted as their policy says.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use watch::{Builder, TaskId, WatchError};
+    ///
+    /// // Returns right away, without the configuration it needs.
+    /// async fn serve() {}
+    ///
+    /// let error = Builder::new()
+    ///     .task(serve)
+    ///     .max_immediate_exits(3)
+    ///     .run()
+    ///     .await
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(error, WatchError::StartupFailed { task: TaskId::from(0), exits: 3 });
+    /// # }
+    /// ```
+    pub fn max_immediate_exits(mut self, max: u32) -> Self {
+        self.max_immediate_exits = Some(max.max(1));
+        self
+    }
+
+    /// Registers every task without spawning any until
+    /// [`WatchHandle::start`], so that the application can finish wiring
+    /// whatever depends on the watcher, such as metrics or admin endpoints,
+    /// before any work begins. The startup deadline, if any, only runs from
+    /// then on, see [`Builder::startup_deadline`].
+    ///
+    /// Until then, the [`Watch`] keeps going even though nothing runs, and
+    /// tasks added at runtime wait along with the others. Draining or shutting
+    /// it down stops it without spawning anything.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use watch::{Builder, TaskState};
+    ///
+    /// let (mut watch, handle) = Builder::new()
+    ///     .task(futures::future::pending::<()>)
+    ///     .start_paused()
+    ///     .build();
+    ///
+    /// watch.tick();
+    /// assert_eq!(handle.task_info(0).unwrap().state(), TaskState::Stopped);
+    ///
+    /// handle.start();
+    /// watch.tick();
+    /// assert_eq!(handle.task_info(0).unwrap().state(), TaskState::Running);
+    /// # }
+    /// ```
+    pub fn start_paused(mut self) -> Self {
+        self.held = true;
+        self
+    }
+
+    /// Brings the whole watcher down as soon as a task fails for good: its
+    /// instance failed with [`crate::FailureKind::Permanent`], or its policy
+    /// retired it after a failure. Every other instance is dropped, and the
+    /// [`Watch`] returns [`crate::WatchError::Escalated`] with the failure,
+    /// as if the policy had escalated. This is a supervised analogue of
+    /// [`futures::future::try_join_all`], for programs where running
+    /// partially is worse than exiting.
+    ///
+    /// Tasks marked with [`Task::best_effort`] are retired all the same, see
+    /// also [`Task::critical`].
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use watch::{Builder, ExitReason, FailureKind, Task, WatchError};
+    ///
+    /// let error = Builder::new()
+    ///     .task(|| futures::future::pending::<()>())
+    ///     .task(Task::fallible(
+    ///         || async { Err::<(), _>("bad config") },
+    ///         |_| FailureKind::Permanent,
+    ///     ))
+    ///     .fail_fast()
+    ///     .run()
+    ///     .await
+    ///     .unwrap_err();
+    ///
+    /// match error {
+    ///     WatchError::Escalated(escalation) => {
+    ///         assert_eq!(escalation.task(), 1);
+    ///         assert_eq!(escalation.reason(), ExitReason::Failed(FailureKind::Permanent));
+    ///     }
+    ///     _ => unreachable!(),
+    /// }
+    /// # }
+    /// ```
+    pub fn fail_fast(mut self) -> Self {
+        self.fail_fast = true;
+        self
+    }
+
+    /// Sets the window over which failure rates are weighted, see
+    /// [`crate::TaskInfo::failure_rate`]. Failures older than the window
+    /// barely count anymore.
+    ///
+    /// The window is at least a millisecond. By default it is a minute.
+    pub fn failure_window(mut self, window: Duration) -> Self {
+        self.failure_window = Some(window);
+        self
+    }
+
+    /// Only lets policies restart tasks during `window`, such as at night
+    /// outside of business hours. Tasks whose restart delay is over outside of
+    /// the window stay delayed until it opens. See also
+    /// [`WatchHandle::freeze`].
+    ///
+    /// Tasks marked with [`Task::critical`] are restarted whenever their
+    /// policy says, and so are restarts through [`WatchHandle::restart`]. By
+    /// default restarts are always allowed.
+    ///
+    /// ```no_run
+    /// # async fn reindex() {}
+    /// # async fn run() {
+    /// use std::time::Duration;
+    /// use watch::{Builder, RestartWindow};
+    ///
+    /// let hour = Duration::from_secs(60 * 60);
+    /// Builder::new()
+    ///     .task(reindex)
+    ///     .restart_window(RestartWindow::daily(2 * hour, 4 * hour))
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn restart_window(mut self, window: RestartWindow) -> Self {
+        self.restart_window = Some(window);
+        self
+    }
+
+    /// Coalesces the restarts of flapping tasks, whose instances keep exiting
+    /// right after being spawned: they cool down as set by `debounce`, with a
+    /// single [`crate::Event::Flapping`] instead of a burst of restarts. See
+    /// [`Debounce`].
+    ///
+    /// The cool-down only ever lengthens the delays decided by policies, and
+    /// restarts through [`WatchHandle::restart`] are not held back. By default
+    /// restarts are not debounced.
+    pub fn debounce(mut self, debounce: Debounce) -> Self {
+        self.debounce = Some(debounce);
+        self
+    }
+
+    /// Makes every delay, deadline and timestamp of the watcher go through
+    /// `clock`. See [`Clock`]. By default the watcher uses the [`TokioClock`].
+    pub fn clock<C>(mut self, clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Caps how many instances may be starting at once, that is spawned but
+    /// not ready yet, see [`Task::signals_readiness`]. Tasks that would exceed
+    /// the cap wait in a queue until an instance gets ready or returns. This
+    /// bounds the load when starting is expensive (TLS handshakes, schema
+    /// loading) and many tasks die at once.
+    ///
+    /// The cap is at least one. By default there is no cap.
+    pub fn max_concurrent_starts(mut self, max: usize) -> Self {
+        self.max_concurrent_starts = Some(max);
+        self
+    }
+
+    /// Caps how many tasks may have an instance running at once, ready or
+    /// not. Tasks that would exceed the cap wait in the same queue as with
+    /// [`Builder::max_concurrent_starts`], by [`Task::priority`], until an
+    /// instance returns. This makes the watcher a supervised pool of workers,
+    /// with the cap as its concurrency.
+    ///
+    /// Restarts through [`WatchHandle::restart`] spawn tasks right away. The
+    /// cap is at least one. By default there is no cap.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use watch::{Builder, RestartDecision};
+    ///
+    /// let summary = Builder::new()
+    ///     .tasks((0..8).map(|_| || async {}))
+    ///     .max_running(2)
+    ///     .policy(|_: &_| RestartDecision::Retire)
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// assert_eq!(summary.spawned(), 8);
+    /// # }
+    /// ```
+    pub fn max_running(mut self, max: usize) -> Self {
+        self.max_running = Some(max);
+        self
+    }
+
+    /// Paces the instances the watcher spawns, see [`RespawnPacing`], such as
+    /// to yield to the other futures of the runtime between factories doing
+    /// heavy synchronous work. By default, instances are spawned as soon as
+    /// they can be.
+    pub fn respawn_pacing(mut self, pacing: RespawnPacing) -> Self {
+        self.pacing = pacing;
+        self
+    }
+
+    /// Spreads running instances over `shards` sets by task, instead of a
+    /// single one, for watchers of tens of thousands of tasks. Each set only
+    /// keeps track of the instances of its tasks, so a completion or a wake
+    /// touches a fraction of the instances, and sets are polled in turn so
+    /// that busy ones do not hold the others back. See also
+    /// [`Builder::join_set`], with the `join-set` feature, to drive instances
+    /// from the runtime instead.
+    ///
+    /// There is at least one set. By default there is one.
+    pub fn shards(mut self, shards: usize) -> Self {
+        self.shards = Some(shards);
+        self
+    }
+
+    /// Decides which other tasks are restarted along with a task that is, see
+    /// [`SupervisionStrategy`]. By default only the task itself is.
+    pub fn strategy<S>(mut self, strategy: S) -> Self
+    where
+        S: SupervisionStrategy + 'static,
+    {
+        self.strategy = Some(Box::new(strategy));
+        self
+    }
+
+    /// Sets in which order the tasks the [`SupervisionStrategy`] restarts
+    /// together start again. By default, they do by registration order,
+    /// without waiting for one another to be ready.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use watch::{Builder, OneForAll, RestartOrder, Task, TaskContext};
+    ///
+    /// let (watch, handle) = Builder::new()
+    ///     .task(Task::with_context(|context: TaskContext| async move {
+    ///         context.ready();
+    ///         futures::future::pending::<()>().await
+    ///     })
+    ///     .signals_readiness())
+    ///     .task(futures::future::pending::<()>)
+    ///     .strategy(OneForAll)
+    ///     .restart_order(RestartOrder::Ready)
+    ///     .build();
+    /// # let watch = tokio::spawn(watch);
+    /// # handle.shutdown();
+    /// # watch.await.unwrap().unwrap();
+    /// # }
+    /// ```
+    pub fn restart_order(mut self, order: RestartOrder) -> Self {
+        self.restart_order = order;
+        self
+    }
+
+    /// Makes the tasks whose labels match `selector` share `budget`, bounding
+    /// how many times they may restart collectively. A task may share several
+    /// budgets, and restarting spends all of them. See [`RestartBudget`].
+    pub fn budget<S>(mut self, selector: S, budget: RestartBudget) -> Self
+    where
+        S: Into<Selector>,
+    {
+        self.budgets.push((selector.into(), budget));
+        self
+    }
+
+    /// Requires the tasks whose labels match `selector` to meet `quorum`,
+    /// see [`Quorum`]. Quorums are numbered in the order they were added,
+    /// and children started at runtime count towards the quorums they match.
+    pub fn quorum<S>(mut self, selector: S, quorum: Quorum) -> Self
+    where
+        S: Into<Selector>,
+    {
+        self.quorums.push((selector.into(), quorum));
+        self
+    }
+
+    /// Calls `on_change` every time the watcher becomes [`Health::Degraded`]
+    /// or [`Health::Healthy`] again, so applications can flip feature flags
+    /// or shed load automatically. Health is not tracked while the watcher
+    /// drains or shuts down.
+    ///
+    /// ```no_run
+    /// # async fn serve() {}
+    /// # async fn run() {
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    /// use watch::{Builder, Health};
+    ///
+    /// let shedding = Arc::new(AtomicBool::new(false));
+    /// let flag = Arc::clone(&shedding);
+    ///
+    /// Builder::new()
+    ///     .task(serve)
+    ///     .on_health_change(move |health| {
+    ///         flag.store(health == Health::Degraded, Ordering::Relaxed)
+    ///     })
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn on_health_change<F>(mut self, on_change: F) -> Self
+    where
+        F: FnMut(Health) + Send + 'static,
+    {
+        self.health = Some(HealthMonitor::new(Box::new(on_change)));
+        self
+    }
+
+    /// Calls `hook` with an [`Alert`] every time a task crosses one of
+    /// `thresholds`, and runs the future it returns: a built-in place to
+    /// page someone. The futures are run by the [`Watch`] itself, and dropped
+    /// once it stopped.
+    ///
+    /// ```no_run
+    /// # async fn serve() {}
+    /// # async fn page(message: String) {}
+    /// # async fn run() {
+    /// use std::time::Duration;
+    /// use watch::{Builder, Thresholds};
+    ///
+    /// Builder::new()
+    ///     .task(serve)
+    ///     .on_alert(
+    ///         Thresholds::new().downtime(Duration::from_secs(30)),
+    ///         |alert| page(format!("{:?} is down: {:?}", alert.name(), alert.kind())),
+    ///     )
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn on_alert<F, T>(mut self, thresholds: Thresholds, hook: F) -> Self
+    where
+        F: Fn(Alert) -> T + Send + Sync + 'static,
+        T: Future<Output = ()> + Send + 'static,
+    {
+        self.alerter = Some(Alerter::new(thresholds, hook));
+        self
+    }
+
+    /// Appends every [`crate::Event`] to `sink`, along with when it happened,
+    /// so that the restart history survives the process, with the `history`
+    /// feature. See [`crate::HistorySink`]. Several sinks may be registered.
+    ///
+    /// Records that could not be appended are not appended again: the error
+    /// is logged with the `log` feature, and handed to the hook set with
+    /// [`Builder::on_history_error`], if any.
+    #[cfg(feature = "history")]
+    pub fn history<H>(mut self, sink: H) -> Self
+    where
+        H: crate::HistorySink + 'static,
+    {
+        self.history.push(Box::new(sink));
+        self
+    }
+
+    /// Calls `hook` with the error of every [`crate::HistorySink::append`]
+    /// that failed, with the `history` feature, so that a history going
+    /// missing does not go unnoticed. It is called wherever the sinks are,
+    /// see [`Builder::observer_executor`].
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use std::io;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use watch::{Builder, HistorySink, Record, RestartDecision};
+    ///
+    /// struct Full;
+    ///
+    /// impl HistorySink for Full {
+    ///     fn append(&mut self, _: &Record) -> io::Result<()> {
+    ///         Err(io::ErrorKind::StorageFull.into())
+    ///     }
+    /// }
+    ///
+    /// let errors = Arc::new(AtomicUsize::new(0));
+    /// let failed = Arc::clone(&errors);
+    /// Builder::new()
+    ///     .task(|| async {})
+    ///     .policy(|_: &_| RestartDecision::Retire)
+    ///     .history(Full)
+    ///     .on_history_error(move |_| {
+    ///         failed.fetch_add(1, Ordering::Relaxed);
+    ///     })
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    /// assert!(errors.load(Ordering::Relaxed) > 0);
+    /// # }
+    /// ```
+    #[cfg(feature = "history")]
+    pub fn on_history_error<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&std::io::Error) + Send + Sync + 'static,
+    {
+        self.history_errors = Some(Arc::new(hook));
+        self
+    }
+
+    /// Calls `hook` after each poll of an instance, with the task it belongs
+    /// to, how long the poll took and what it returned, with the `instrument`
+    /// feature. This is the ground for custom profilers, such as one
+    /// reporting polls that block the thread for too long.
+    ///
+    /// Poll durations are measured with the system clock whatever the
+    /// [`Builder::clock`]. The hook runs on the thread polling the instance,
+    /// within the poll of the watcher or on the runtime with
+    /// [`Builder::join_set`], so it should return quickly. Registering another
+    /// hook replaces it.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// use std::sync::Arc;
+    /// use watch::{Builder, RestartDecision};
+    ///
+    /// let polls = Arc::new(AtomicU64::new(0));
+    /// let counter = Arc::clone(&polls);
+    /// Builder::new()
+    ///     .task(|| async {})
+    ///     .policy(|_: &_| RestartDecision::Retire)
+    ///     .on_poll(move |_, _, _| {
+    ///         counter.fetch_add(1, Ordering::Relaxed);
+    ///     })
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(polls.load(Ordering::Relaxed), 1);
+    /// # }
+    /// ```
+    #[cfg(feature = "instrument")]
+    pub fn on_poll<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(crate::TaskId, Duration, std::task::Poll<&crate::ExitReason>) + Send + Sync + 'static,
+    {
+        self.on_poll = Some(Arc::new(hook));
+        self
+    }
+
+    /// Registers `observer` to be called as things happen to the tasks. See
+    /// [`WatchObserver`].
+    pub fn observer<O>(mut self, observer: O) -> Self
+    where
+        O: WatchObserver + 'static,
+    {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Notifies the observers, see [`Builder::observer`], and history sinks
+    /// from a future handed to `spawn`, rather than from the polls of the
+    /// [`Watch`], so that heavyweight ones cannot slow restarts down. `spawn`
+    /// is given the future the first time something happens, and typically
+    /// spawns it onto a runtime; the future returns once the watcher stopped
+    /// and every event was dispatched.
+    ///
+    /// Observers then see events in order, but after the fact: by then, the
+    /// watcher may have moved on.
+    ///
+    /// Only the observers and history sinks are offloaded: instances, timers
+    /// and the subscribers to [`WatchHandle::events`], which are sent events
+    /// without waiting for them, are still driven by the polls of the
+    /// [`Watch`].
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use watch::{Builder, RestartDecision, WatchObserver};
+    ///
+    /// struct Slow;
+    ///
+    /// impl WatchObserver for Slow {}
+    ///
+    /// let summary = Builder::new()
+    ///     .task(|| async {})
+    ///     .policy(|_: &_| RestartDecision::Retire)
+    ///     .observer(Slow)
+    ///     .observer_executor(|future| {
+    ///         tokio::spawn(future);
+    ///     })
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// assert_eq!(summary.spawned(), 1);
+    /// # }
+    /// ```
+    pub fn observer_executor<E>(mut self, spawn: E) -> Self
+    where
+        E: Fn(BoxFuture<'static, ()>) + Send + Sync + 'static,
+    {
+        self.executor = Some(Arc::new(spawn));
+        self
+    }
+
+    /// Sets what happens to the commands sent through the handles when they
+    /// come faster than the watcher applies them, see [`Backpressure`]. By
+    /// default, every command is queued.
+    pub fn backpressure(mut self, backpressure: Backpressure) -> Self {
+        self.backpressure = backpressure;
+        self
+    }
+
+    /// Sets how many [`crate::Event`]'s are kept for subscribers lagging
+    /// behind, see [`crate::Events`]. Subscribers further behind miss the
+    /// oldest events.
+    ///
+    /// The capacity is at least one. By default it is 1024.
+    pub fn event_capacity(mut self, capacity: usize) -> Self {
+        self.event_capacity = Some(capacity);
+        self
+    }
+
+    /// Calls `hook` with a [`WatchHandle`] every time the process receives
+    /// the signal `kind`, on Unix with the `signals` feature. This wires
+    /// operational hooks such as dumping a [`crate::Report`] or restarting a
+    /// group of tasks without any plumbing. Several hooks may be registered
+    /// for the same signal.
+    ///
+    /// Signals are only listened to once the [`Watch`] is first polled, and
+    /// while it is, within a Tokio runtime with IO enabled. Signals that
+    /// cannot be listened to, such as `SIGKILL`, are ignored.
+    ///
+    /// ```no_run
+    /// # async fn query() {}
+    /// # async fn run() {
+    /// use watch::{Builder, SignalKind, Task};
+    ///
+    /// Builder::new()
+    ///     .task(Task::new(query).label("group", "db"))
+    ///     .on_signal(SignalKind::user_defined1(), |handle| {
+    ///         eprintln!("{}", handle.report())
+    ///     })
+    ///     .on_signal(SignalKind::user_defined2(), |handle| {
+    ///         handle.restart_matching("group=db");
+    ///     })
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(all(unix, feature = "signals"))]
+    pub fn on_signal<F>(mut self, kind: crate::SignalKind, hook: F) -> Self
+    where
+        F: Fn(&WatchHandle) + Send + Sync + 'static,
+    {
+        self.signals.push((kind, Box::new(hook)));
+        self
+    }
+
+    /// Returns a [`ShutdownSignal`] resolving once the watcher built by this
+    /// [`Builder`] begins shutting down. Use it to let tasks tear down
+    /// cleanly.
+    pub fn shutdown_signal(&self) -> ShutdownSignal {
+        self.shutdown.signal()
+    }
+
+    /// Spawns and watches all the tasks. Shorthand for [`Builder::build`],
+    /// for when the [`WatchHandle`] is not needed.
+    pub fn run(self) -> Watch {
+        self.build().0
+    }
+
+    /// Runs all the tasks as the main of a service, with the `service`
+    /// feature: once the process is asked to terminate, the watcher is
+    /// drained, and shut down if it still runs after `deadline`. This is the
+    /// lifecycle orchestrators such as Kubernetes expect.
+    ///
+    /// On Unix, the process is asked to terminate by `SIGTERM` or `SIGINT`. On
+    /// Windows, it is by Ctrl+C, Ctrl+Break, and the console being closed,
+    /// the user logging off or the system shutting down. Windows only gives
+    /// a few seconds to handle the latter three, so keep `deadline` short. The
+    /// [`crate::ServiceExit`] tells whether the service stopped cleanly, and
+    /// the status to exit the process with.
+    ///
+    /// Signals are only listened to while the returned future is polled,
+    /// within a Tokio runtime with IO enabled.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use std::time::Duration;
+    /// use watch::{Builder, RestartDecision};
+    ///
+    /// let exit = Builder::new()
+    ///     .task(|| async {})
+    ///     .policy(|_: &_| RestartDecision::Retire)
+    ///     .serve(Duration::from_secs(30))
+    ///     .await;
+    ///
+    /// assert!(exit.is_clean());
+    /// assert_eq!(exit.code(), 0);
+    /// # }
+    /// ```
+    #[cfg(feature = "service")]
+    pub async fn serve(mut self, deadline: Duration) -> crate::ServiceExit {
+        let clock = self.clock.get_or_insert_with(|| Arc::new(TokioClock));
+        let clock = Arc::clone(clock);
+        let (watch, handle) = self.build();
+        crate::service::serve(watch, handle, deadline, clock).await
+    }
+
+    /// Runs all the tasks for `duration`, after which the watcher is shut
+    /// down, for soak tests and batch jobs where running forever is wrong.
+    /// Instances get the [`Builder::grace_period`] to return. Time goes by
+    /// the [`Builder::clock`].
+    ///
+    /// # Errors
+    ///
+    /// Returns whatever the [`Watch`] returned if it stopped by itself first,
+    /// see [`Builder::build`].
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use std::time::Duration;
+    /// use watch::{Builder, RestartDecision};
+    ///
+    /// tokio::time::pause();
+    /// let summary = Builder::new()
+    ///     .task(|| tokio::time::sleep(Duration::from_secs(10)))
+    ///     .run_for(Duration::from_secs(35))
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// assert_eq!(summary.restarts(), 3);
+    /// # }
+    /// ```
+    pub async fn run_for(mut self, duration: Duration) -> Result<Summary, WatchError> {
+        let clock = self.clock.get_or_insert_with(|| Arc::new(TokioClock));
+        let over = clock.sleep(duration);
+        let (watch, handle) = self.build();
+        bounded(watch, handle, over).await
+    }
+
+    /// Runs all the tasks until they were restarted `restarts` times in
+    /// total, after which the watcher is shut down. Restarts through the
+    /// [`WatchHandle`] count. No more restarts are scheduled once the count
+    /// is reached, so that [`crate::Summary::restarts`] tells `restarts`
+    /// unless a handle restarted tasks meanwhile. See [`Builder::run_for`].
+    ///
+    /// # Errors
+    ///
+    /// Returns whatever the [`Watch`] returned if it stopped by itself first,
+    /// see [`Builder::build`].
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use std::time::Duration;
+    /// use watch::Builder;
+    ///
+    /// tokio::time::pause();
+    /// let summary = Builder::new()
+    ///     .task(|| tokio::time::sleep(Duration::from_secs(10)))
+    ///     .run_restarts(3)
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// assert_eq!(summary.restarts(), 3);
+    /// # }
+    /// ```
+    pub async fn run_restarts(self, restarts: u64) -> Result<Summary, WatchError> {
+        // Counted by the watcher itself, as subscribers may miss events.
+        let (mut watch, _handle) = self.build();
+        watch.shut_down_after(restarts);
+        watch.await
+    }
+
+    /// Spawns and watches all the tasks, returning a [`WatchHandle`] to
+    /// control them.
+    ///
+    /// # Return
+    ///
+    /// Returns a [`Watch`] future that only returns once every task was
+    /// retired by its [`RestartPolicy`] or removed, which never happens with
+    /// the default policy or a [`Backoff`], or once it was drained or shut
+    /// down through the [`WatchHandle`]. It then returns a
+    /// [`crate::Summary`]. Every time it is polled, it internally polls the
+    /// [`std::future::Future`]'s it is watching.
+    ///
+    /// # Errors
+    ///
+    /// The [`Watch`] returns [`crate::WatchError::EmptySet`] if no task was
+    /// added and there is no [`Builder::template`], and
+    /// [`crate::WatchError::Escalated`] once a policy escalated, along with
+    /// the [`crate::Escalation`] telling which task and why, or
+    /// [`crate::WatchError::BudgetExhausted`] if a spent
+    /// [`crate::RestartBudget`] escalated. It returns
+    /// [`crate::WatchError::StartupTimeout`] if tasks missed the
+    /// [`Builder::startup_deadline`], [`crate::WatchError::StartupFailed`] if a
+    /// task kept exiting right away, see [`Builder::max_immediate_exits`], and
+    /// [`crate::WatchError::MissingContext`] if a task borrows a context the
+    /// watcher does not have.
+    pub fn build(self) -> (Watch, WatchHandle) {
+        self.watch(false)
+    }
+
+    /// Spawns and watches all the tasks, returning a [`WatchMap`] to add and
+    /// control more tasks by key. See [`Builder::build`].
+    ///
+    /// The watcher keeps going even without tasks, waiting for tasks to be
+    /// inserted, until it is drained or shut down.
+    pub fn build_map<K>(self) -> (Watch, WatchMap<K>)
+    where
+        K: Hash + Eq,
+    {
+        let (watch, handle) = self.watch(true);
+        (watch, WatchMap::new(handle))
+    }
+
+    /// Builds the [`Watch`], which keeps going without tasks when `open`, as
+    /// tasks may be added at runtime.
+    fn watch(mut self, open: bool) -> (Watch, WatchHandle) {
+        if let Some(keyed) = &self.keyed {
+            let first = self.tasks.len();
+            let shards = keyed.tasks();
+            keyed.added((first..first + shards.len()).map(TaskId::from).collect());
+            self.tasks.extend(shards);
+        }
+        let default_policy = self.default_policy();
+        let budgets: Vec<Budget> = self
+            .budgets
+            .into_iter()
+            .map(|(selector, budget)| Budget::new(selector, budget))
+            .collect();
+
+        let slots: Vec<Slot> = self
+            .tasks
+            .into_iter()
+            .map(|mut task| {
+                let policy = task.policy.take().unwrap_or_else(|| match &default_policy {
+                    Some(policy) => policy(),
+                    None => Box::new(Immediate),
+                });
+                let matching = budget::matching(&budgets, &task.metadata.labels);
+                Slot::new(task, policy, matching)
+            })
+            .collect();
+
+        let quorums = self
+            .quorums
+            .into_iter()
+            .map(|(selector, quorum)| {
+                let mut group = Group::new(selector, quorum);
+                group.members = slots
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, slot)| group.selector.matches(slot.labels()))
+                    .map(|(id, _)| id)
+                    .collect();
+                group
+            })
+            .collect();
+
+        let config = Config {
+            quorums,
+            health: self.health,
+            alerter: self.alerter,
+            shutdown: self.shutdown,
+            grace_period: self.grace_period,
+            max_starting: self.max_concurrent_starts.unwrap_or(usize::MAX),
+            max_running: self.max_running.unwrap_or(usize::MAX),
+            shards: self.shards.unwrap_or(1),
+            observers: self.observers,
+            backpressure: self.backpressure,
+            incoming: self.incoming,
+            executor: self.executor,
+            event_capacity: self.event_capacity.unwrap_or(1024),
+            budgets,
+            strategy: self.strategy,
+            restart_order: self.restart_order,
+            pacing: self.pacing,
+            open: open || self.template.is_some() || self.keyed.is_some(),
+            template: self.template,
+            keyed: self.keyed,
+            policy: default_policy,
+            startup_deadline: self.startup_deadline,
+            max_immediate_exits: self.max_immediate_exits,
+            held: self.held,
+            fail_fast: self.fail_fast,
+            failure_window: self
+                .failure_window
+                .unwrap_or(Duration::from_secs(60))
+                .max(Duration::from_millis(1)),
+            restart_window: self.restart_window,
+            debounce: self.debounce,
+            clock: self.clock.unwrap_or_else(|| Arc::new(TokioClock)),
+            context: self.context,
+            layers: self.layers,
+            backtraces: self.backtraces,
+            panic_behavior: self.panic_behavior,
+            cancellation: self.cancellation,
+            #[cfg(feature = "join-set")]
+            join_set: self.join_set,
+            #[cfg(all(unix, feature = "signals"))]
+            signals: self.signals,
+            #[cfg(feature = "history")]
+            history: self.history,
+            #[cfg(feature = "history")]
+            history_errors: self.history_errors,
+            #[cfg(feature = "instrument")]
+            on_poll: self.on_poll,
+        };
+        Watch::new(slots, config)
+    }
+}
+
+/// Awaiting a [`Builder`] spawns and watches all the tasks, as with
+/// [`Builder::run`]. Use [`Builder::build`] to get a [`WatchHandle`] along
+/// with the [`Watch`].
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use watch::{Builder, RestartDecision};
+///
+/// let summary = Builder::new()
+///     .task(|| async {})
+///     .task(|| async {})
+///     .policy(|_: &_| RestartDecision::Retire)
+///     .await
+///     .unwrap();
+///
+/// assert_eq!(summary.spawned(), 2);
+/// # }
+/// ```
+impl IntoFuture for Builder {
+    type Output = Result<Summary, WatchError>;
+    type IntoFuture = Watch;
+
+    fn into_future(self) -> Self::IntoFuture {
+        self.run()
+    }
+}
+
+/// Runs `watch` until `over` resolves, then shuts it down.
+async fn bounded(
+    watch: Watch,
+    handle: WatchHandle,
+    over: BoxFuture<'static, ()>,
+) -> Result<Summary, WatchError> {
+    match future::select(watch, over).await {
+        Either::Left((result, _)) => result,
+        Either::Right(((), watch)) => {
+            handle.shutdown();
+            watch.await
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+/// How the watcher stops an instance before it returned by itself, such as
+/// to restart it, to pause or remove its task, or through
+/// [`crate::WatchHandle::cancel_current`]. Set for every task with
+/// [`crate::Builder::cancellation`] or for a single one with
+/// [`crate::Task::cancellation`].
+///
+/// Either way, the instance exits with [`crate::ExitReason::Cancelled`] right
+/// away as far as the watcher is concerned. Shutdowns go through the
+/// [`crate::Builder::grace_period`] instead, at the start of which instances
+/// cancelled cooperatively see their signal resolve too.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use std::time::Duration;
+/// use watch::{Builder, Cancellation, RestartDecision, Task, TaskContext};
+///
+/// let (watch, handle) = Builder::new()
+///     .task(
+///         Task::with_context(|context: TaskContext| async move {
+///             // Finish the current batch before stopping.
+///             context.cancelled().await;
+///         })
+///         .cancellation(Cancellation::Cooperative(Duration::from_secs(5))),
+///     )
+///     .policy(|_: &_| RestartDecision::Retire)
+///     .build();
+///
+/// handle.cancel_current(0);
+/// let summary = watch.await.unwrap();
+/// assert_eq!(summary.spawned(), 1);
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Cancellation {
+    /// The instance is dropped right away. This is the default.
+    #[default]
+    Abort,
+    /// The [`crate::TaskContext::cancelled`] signal of the instance resolves,
+    /// and the instance keeps running in the background for up to the given
+    /// period to wind down, after which it is dropped. Its replacement, if
+    /// any, is spawned without waiting, so both may run at the same time.
+    Cooperative(Duration),
+}
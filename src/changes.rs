@@ -0,0 +1,156 @@
+use crate::clock::Clock;
+use crate::event::Subscribers;
+use crate::id::TaskId;
+use crate::info::{Snapshot, TaskInfo, TaskState};
+use futures::future::{BoxFuture, FutureExt};
+use futures::stream::Stream;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// A difference between two snapshots of a task, as received through
+/// [`Changes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// The task went from `from` to `to`, such as from [`TaskState::Running`]
+    /// to [`TaskState::Delayed`] as it entered backoff, or back as it
+    /// recovered. Tasks added since the previous snapshot come from
+    /// [`TaskState::Stopped`].
+    State {
+        task: TaskId,
+        from: TaskState,
+        to: TaskState,
+    },
+    /// The task was spawned `instances` times, crossing the threshold set
+    /// with [`Changes::instances_above`].
+    InstancesAbove { task: TaskId, instances: u64 },
+}
+
+/// The differences between successive snapshots of the tasks of a watcher,
+/// taken at an interval, as returned by [`crate::WatchHandle::changes`].
+/// Ends once the watcher stopped, with the last differences.
+///
+/// Each item holds every [`Change`] since the previous one, in the order of
+/// tasks, and snapshots without changes are skipped. What happens between
+/// two snapshots is coalesced: a task restarting within the interval does
+/// not change, which keeps dashboards cheap to feed, unlike
+/// [`crate::WatchHandle::events`].
+///
+/// ```no_run
+/// # async fn serve() {}
+/// # async fn run() {
+/// use futures::StreamExt;
+/// use std::time::Duration;
+/// use watch::{Builder, Change, TaskState};
+///
+/// let (watch, handle) = Builder::new().task(serve).build();
+/// let mut changes = handle.changes(Duration::from_secs(5)).instances_above(100);
+///
+/// tokio::spawn(watch);
+/// while let Some(changes) = changes.next().await {
+///     for change in changes {
+///         if let Change::State { task, to: TaskState::Delayed, .. } = change {
+///             println!("task {} is backing off", task);
+///         }
+///     }
+/// }
+/// # }
+/// ```
+pub struct Changes {
+    snapshot: Snapshot,
+    subscribers: Subscribers,
+    clock: Arc<dyn Clock>,
+    interval: Duration,
+    sleep: BoxFuture<'static, ()>,
+    previous: Vec<TaskInfo>,
+    threshold: Option<u64>,
+    done: bool,
+}
+
+impl Changes {
+    pub(crate) fn new(
+        snapshot: Snapshot,
+        subscribers: Subscribers,
+        clock: Arc<dyn Clock>,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            previous: snapshot.tasks(),
+            sleep: clock.sleep(interval),
+            snapshot,
+            subscribers,
+            clock,
+            interval,
+            threshold: None,
+            done: false,
+        }
+    }
+
+    /// Also reports tasks once they were spawned more than `threshold`
+    /// times, as [`Change::InstancesAbove`].
+    pub fn instances_above(mut self, threshold: u64) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+
+    /// Returns the changes from `previous` to `tasks`.
+    fn diff(&self, tasks: &[TaskInfo]) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for task in tasks {
+            let previous = self.previous.get(task.id().index());
+            // Identifiers handed out again belong to new tasks.
+            let previous = previous.filter(|previous| previous.instances() <= task.instances());
+            let from = previous.map_or(TaskState::Stopped, TaskInfo::state);
+            if from != task.state() {
+                changes.push(Change::State {
+                    task: task.id(),
+                    from,
+                    to: task.state(),
+                });
+            }
+            let before = previous.map_or(0, TaskInfo::instances);
+            if matches!(self.threshold, Some(threshold) if before <= threshold && threshold < task.instances())
+            {
+                changes.push(Change::InstancesAbove {
+                    task: task.id(),
+                    instances: task.instances(),
+                });
+            }
+        }
+        changes
+    }
+}
+
+impl fmt::Debug for Changes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Changes")
+            .field("interval", &self.interval)
+            .field("threshold", &self.threshold)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for Changes {
+    type Item = Vec<Change>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while !self.done {
+            if self.sleep.poll_unpin(cx).is_pending() {
+                return Poll::Pending;
+            }
+            self.sleep = self.clock.sleep(self.interval);
+            // Read first, so that the last snapshot is not missed.
+            self.done = self.subscribers.is_closed();
+
+            let tasks = self.snapshot.tasks();
+            let changes = self.diff(&tasks);
+            self.previous = tasks;
+            if !changes.is_empty() {
+                return Poll::Ready(Some(changes));
+            }
+        }
+        Poll::Ready(None)
+    }
+}
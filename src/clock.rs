@@ -0,0 +1,99 @@
+use futures::future::{BoxFuture, FutureExt};
+use std::fmt;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
+
+/// The source of time of a watcher: every delay, deadline and timestamp goes
+/// through it, from backoff delays to restart budgets and [`crate::TaskInfo`]
+/// instants. Set with [`crate::Builder::clock`].
+///
+/// The default [`TokioClock`] follows [`tokio::time`]. Implement [`Clock`] to
+/// bring another source of time, such as the timer of an embedded target, or
+/// use [`crate::testing::MockClock`] to drive time by hand in tests.
+pub trait Clock: Send + Sync {
+    /// Returns the current instant.
+    fn now(&self) -> Instant;
+
+    /// Returns a future resolving once `deadline` is reached.
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'static, ()>;
+
+    /// Returns a future resolving once `duration` elapsed.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        self.sleep_until(after(self.now(), duration))
+    }
+
+    /// Returns the current wall-clock time, to tell when a
+    /// [`crate::RestartWindow`] is open. Defaults to [`SystemTime::now`].
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Returns the instant `duration` after `now`, or one far in the future if
+/// that can't be represented, as with [`tokio::time::sleep`], so that
+/// [`Duration::MAX`] waits forever.
+pub(crate) fn after(now: Instant, duration: Duration) -> Instant {
+    // About 30 years, as Tokio does.
+    const FAR_FUTURE: Duration = Duration::from_secs(86_400 * 365 * 30);
+    now.checked_add(duration)
+        .or_else(|| now.checked_add(FAR_FUTURE))
+        .unwrap_or(now)
+}
+
+impl fmt::Debug for dyn Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Clock").field("now", &self.now()).finish()
+    }
+}
+
+/// The [`Clock`] of [`tokio::time`], which can be paused and advanced by
+/// hand with [`tokio::time::pause`]. Requires a Tokio runtime with time
+/// enabled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioClock;
+
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        tokio::time::Instant::now().into_std()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'static, ()> {
+        tokio::time::sleep_until(deadline.into()).boxed()
+    }
+}
+
+/// Wakes a watcher at a deadline that may move from one poll to the next,
+/// keeping the same sleep for as long as the deadline stays the same.
+#[derive(Default)]
+pub(crate) struct Recheck {
+    /// The deadline, and its sleep until it resolved.
+    deadline: Option<(Instant, Option<BoxFuture<'static, ()>>)>,
+}
+
+impl Recheck {
+    /// Makes sure the task of `cx` is woken once at `deadline`, if any.
+    pub(crate) fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+        clock: &dyn Clock,
+        deadline: Option<Instant>,
+    ) {
+        let deadline = match deadline {
+            Some(deadline) => deadline,
+            None => {
+                self.deadline = None;
+                return;
+            }
+        };
+        if !matches!(&self.deadline, Some((at, _)) if *at == deadline) {
+            self.deadline = Some((deadline, Some(clock.sleep_until(deadline))));
+        }
+        if let Some((_, sleep)) = &mut self.deadline {
+            if let Some(Poll::Ready(())) = sleep.as_mut().map(|sleep| sleep.poll_unpin(cx)) {
+                // The deadline only wakes once, even if it stays the same.
+                *sleep = None;
+                cx.waker().wake_by_ref();
+            }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::clock::Clock;
+use crate::error::Escalation;
+use crate::shutdown::ShutdownSignal;
+use futures::channel::oneshot;
+use std::any::Any;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The application context of a watcher, see [`crate::Builder::context`].
+pub(crate) type Shared = Arc<dyn Any + Send + Sync>;
+
+/// The type of application context a task needs, see [`crate::Task::shared`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Needs {
+    name: &'static str,
+    is: fn(&Shared) -> bool,
+}
+
+impl Needs {
+    pub(crate) fn context<C>() -> Self
+    where
+        C: Send + Sync + 'static,
+    {
+        Self {
+            name: std::any::type_name::<C>(),
+            is: |shared| (**shared).is::<C>(),
+        }
+    }
+
+    /// Returns the name of the type of context needed, unless `shared` is
+    /// of that type.
+    pub(crate) fn missing(&self, shared: Option<&Shared>) -> Option<&'static str> {
+        match shared {
+            Some(shared) if (self.is)(shared) => None,
+            _ => Some(self.name),
+        }
+    }
+}
+
+/// Handed to the factories of tasks created with [`crate::Task::with_context`]
+/// every time they are called, and shared by the resulting instance.
+///
+/// Contexts are cheap to clone, clones refer to the same instance.
+#[derive(Clone)]
+pub struct TaskContext {
+    ready: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+    instance: u64,
+    /// Where the instance reports the escalation of the child watcher it ran,
+    /// see [`crate::Task::supervisor`].
+    escalation: Arc<Mutex<Option<Escalation>>>,
+    shared: Option<Shared>,
+    clock: Arc<dyn Clock>,
+    cancelled: ShutdownSignal,
+    /// Set once the instance is asked to return, see
+    /// [`crate::WatchHandle::restart_at_yield`].
+    restart: Arc<AtomicBool>,
+}
+
+impl TaskContext {
+    pub(crate) fn new(
+        ready: oneshot::Sender<()>,
+        instance: u64,
+        escalation: Arc<Mutex<Option<Escalation>>>,
+        shared: Option<Shared>,
+        clock: Arc<dyn Clock>,
+        cancelled: ShutdownSignal,
+        restart: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            ready: Arc::new(Mutex::new(Some(ready))),
+            instance,
+            escalation,
+            shared,
+            clock,
+            cancelled,
+            restart,
+        }
+    }
+
+    /// Returns the clock of the watcher, see [`crate::Builder::clock`].
+    pub(crate) fn clock(&self) -> &dyn Clock {
+        &*self.clock
+    }
+
+    /// Reports that the child watcher run by the instance gave up.
+    pub(crate) fn escalated(&self, escalation: Escalation) {
+        if let Ok(mut slot) = self.escalation.lock() {
+            *slot = Some(escalation);
+        }
+    }
+
+    /// Returns the number of the instance among the instances of its task,
+    /// starting at one. This is its attempt number, see [`crate::Event`].
+    pub fn instance(&self) -> u64 {
+        self.instance
+    }
+
+    /// Returns the application context of the watcher, see
+    /// [`crate::Builder::context`], if it has one of type `C`.
+    pub fn shared<C>(&self) -> Option<Arc<C>>
+    where
+        C: Send + Sync + 'static,
+    {
+        Arc::clone(self.shared.as_ref()?).downcast().ok()
+    }
+
+    /// Returns a signal resolving once the watcher asks the instance to stop,
+    /// for tasks cancelled cooperatively, see [`crate::Cancellation`].
+    pub fn cancelled(&self) -> ShutdownSignal {
+        self.cancelled.clone()
+    }
+
+    /// Returns whether the instance was asked to return so that a new one
+    /// takes over, see [`crate::WatchHandle::restart_at_yield`]. Long-running
+    /// instances check it between units of work.
+    pub fn restart_requested(&self) -> bool {
+        self.restart.load(Ordering::Relaxed)
+    }
+
+    /// Reports the instance as ready, for tasks that signal their readiness,
+    /// see [`crate::Task::signals_readiness`]. Only the first call has an
+    /// effect.
+    pub fn ready(&self) {
+        let sender = match self.ready.lock() {
+            Ok(mut ready) => ready.take(),
+            Err(_) => None,
+        };
+        if let Some(sender) = sender {
+            let _ = sender.send(());
+        }
+    }
+}
+
+impl fmt::Debug for TaskContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskContext")
+            .field("instance", &self.instance)
+            .finish()
+    }
+}
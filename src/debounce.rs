@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+/// Coalesces the restarts of flapping tasks, see [`crate::Builder::debounce`].
+///
+/// A task is flapping once `times` instances in a row exited within `within`
+/// of being spawned. Its restarts are then held back for at least the
+/// cool-down, and a single [`crate::Event::Flapping`] is emitted, until an
+/// instance runs for longer than `within` again.
+///
+/// ```no_run
+/// # async fn connect() {}
+/// # async fn run() {
+/// use std::time::Duration;
+/// use watch::{Builder, Debounce};
+///
+/// // Five instances in a row exiting within a second cool down for a minute.
+/// Builder::new()
+///     .task(connect)
+///     .debounce(Debounce::new(5, Duration::from_secs(1), Duration::from_secs(60)))
+///     .run()
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Debounce {
+    times: u32,
+    within: Duration,
+    cool_down: Duration,
+}
+
+impl Debounce {
+    /// Creates a [`Debounce`] holding back restarts for `cool_down` once
+    /// `times` instances in a row exited within `within`. `times` is at
+    /// least 1.
+    pub fn new(times: u32, within: Duration, cool_down: Duration) -> Self {
+        Self {
+            times: times.max(1),
+            within,
+            cool_down,
+        }
+    }
+
+    /// Returns how many instances in a row exited quickly once an instance
+    /// exited after `uptime`, out of `quick` before it.
+    pub(crate) fn count(&self, quick: u32, uptime: Duration) -> u32 {
+        if uptime < self.within {
+            quick.saturating_add(1)
+        } else {
+            0
+        }
+    }
+
+    /// Returns whether `quick` instances in a row exiting quickly is
+    /// flapping.
+    pub(crate) fn flapping(&self, quick: u32) -> bool {
+        quick >= self.times
+    }
+
+    /// Returns the delay replacing `delay` while flapping.
+    pub(crate) fn cool_down(&self, delay: Duration) -> Duration {
+        delay.max(self.cool_down)
+    }
+}
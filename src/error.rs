@@ -0,0 +1,166 @@
+use crate::exit::ExitReason;
+use crate::id::TaskId;
+use std::error::Error;
+use std::fmt;
+
+/// Reasons for which the watcher stopped.
+///
+/// More reasons may be added as the watcher learns new ways to give up, so
+/// matches need a catch-all arm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WatchError {
+    /// There was no task to watch.
+    EmptySet,
+    /// The [`crate::RestartPolicy`] of a task returned
+    /// [`crate::RestartDecision::Escalate`].
+    Escalated(Escalation),
+    /// The task `task` would have restarted, but a [`crate::RestartBudget`]
+    /// it spends was spent and escalates, see
+    /// [`crate::RestartBudget::exhausted`].
+    BudgetExhausted { task: TaskId },
+    /// Some tasks did not come up within the deadline set with
+    /// [`crate::Builder::startup_deadline`]. Holds their identifiers, in the
+    /// order tasks were added.
+    StartupTimeout { tasks: Vec<TaskId> },
+    /// The instances of the task `task` completed on their first poll
+    /// `exits` times in a row, before any of them was up, see
+    /// [`crate::Builder::max_immediate_exits`].
+    StartupFailed { task: TaskId, exits: u32 },
+    /// The quorum number `quorum` was lost, with only `healthy` tasks left,
+    /// and escalated, see [`crate::Quorum::escalate`].
+    QuorumLost { quorum: usize, healthy: usize },
+    /// The task `task` borrows an application context of the type named
+    /// `context`, see [`crate::Task::shared`], but the watcher has none of
+    /// that type, see [`crate::Builder::context`].
+    MissingContext { task: TaskId, context: &'static str },
+}
+
+impl fmt::Display for WatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchError::EmptySet => write!(f, "no task to watch"),
+            WatchError::Escalated(escalation) => escalation.fmt(f),
+            WatchError::BudgetExhausted { task } => {
+                write!(f, "task {} exhausted its restart budget", task)
+            }
+            WatchError::StartupTimeout { tasks } => {
+                write!(f, "tasks {:?} did not come up in time", tasks)
+            }
+            WatchError::StartupFailed { task, exits } => {
+                write!(
+                    f,
+                    "task {} exited immediately {} times at startup",
+                    task, exits
+                )
+            }
+            WatchError::QuorumLost { quorum, healthy } => {
+                write!(f, "quorum {} lost with {} tasks healthy", quorum, healthy)
+            }
+            WatchError::MissingContext { task, context } => {
+                write!(f, "task {} needs a context of type {}", task, context)
+            }
+        }
+    }
+}
+
+impl Error for WatchError {}
+
+/// Which task made a watcher give up, and why.
+///
+/// When the task is itself a watcher, see [`crate::Task::supervisor`], the
+/// escalation of that child watcher is kept, so the whole chain down to the
+/// task that started it can be followed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Escalation {
+    task: TaskId,
+    name: Option<String>,
+    reason: ExitReason,
+    child: Option<Box<Escalation>>,
+}
+
+impl Escalation {
+    pub(crate) fn new(
+        task: TaskId,
+        name: Option<String>,
+        reason: ExitReason,
+        child: Option<Escalation>,
+    ) -> Self {
+        Self {
+            task,
+            name,
+            reason,
+            child: child.map(Box::new),
+        }
+    }
+
+    /// Returns the identifier of the task.
+    pub fn task(&self) -> TaskId {
+        self.task
+    }
+
+    /// Returns the name of the task, see [`crate::Task::name`].
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns why the instance that led to the escalation exited.
+    pub fn reason(&self) -> ExitReason {
+        self.reason.clone()
+    }
+
+    /// Returns the escalation of the child watcher the task ran, if it ran
+    /// one that gave up.
+    pub fn child(&self) -> Option<&Escalation> {
+        self.child.as_deref()
+    }
+
+    /// Returns the escalation at the bottom of the chain, that started it all.
+    pub fn root(&self) -> &Escalation {
+        let mut escalation = self;
+        while let Some(child) = escalation.child() {
+            escalation = child;
+        }
+        escalation
+    }
+}
+
+impl fmt::Display for Escalation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "task {} ({:?}) escalated", self.task, name)?,
+            None => write!(f, "task {} escalated", self.task)?,
+        }
+        match &self.child {
+            Some(child) => write!(f, ": {}", child),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Error for Escalation {}
+
+/// Reasons for which a [`crate::WatchHandle::call`] got no response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallError {
+    /// The task has no mailbox of calls of that type, see
+    /// [`crate::Task::with_mailbox`], or the watcher stopped.
+    NoMailbox,
+    /// The call was dropped without a response, such as by an instance
+    /// that stopped before responding.
+    Dropped,
+    /// No response came within the timeout.
+    TimedOut,
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::NoMailbox => write!(f, "the task has no mailbox for the call"),
+            CallError::Dropped => write!(f, "the call was dropped without a response"),
+            CallError::TimedOut => write!(f, "the call timed out"),
+        }
+    }
+}
+
+impl Error for CallError {}
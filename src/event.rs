@@ -0,0 +1,625 @@
+use crate::clock::Clock;
+use crate::exit::ExitReason;
+use crate::handle::WatchCommand;
+use crate::id::TaskId;
+use crate::info::Snapshot;
+use crate::labels::Selector;
+use crate::observer::WatchObserver;
+use futures::channel::mpsc::{self, UnboundedSender};
+use futures::future::{self, BoxFuture, FutureExt};
+use futures::stream::{Stream, StreamExt};
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError, Receiver, Sender};
+
+/// Something that happened to a watched task.
+///
+/// Tasks are identified by their [`TaskId`], and their instances by how many were spawned before,
+/// starting at one. The number of an instance is thus its attempt number: the
+/// 57th instance of a task is its 56th respawn. Decisions about a task carry
+/// the number of the instance that led to them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A new instance was spawned.
+    Started { task: TaskId, instance: u64 },
+    /// The factory of the task panicked while creating the instance
+    /// `instance`, which then exits with [`ExitReason::Panicked`] right away
+    /// and goes through the [`crate::PanicBehavior`] of the task.
+    FactoryPanicked {
+        task: TaskId,
+        instance: u64,
+        panic: crate::Panic,
+    },
+    /// An instance is ready, see [`crate::Task::signals_readiness`].
+    Ready { task: TaskId, instance: u64 },
+    /// An instance returned, or was dropped by the watcher with
+    /// [`ExitReason::Cancelled`].
+    Exited {
+        task: TaskId,
+        instance: u64,
+        reason: ExitReason,
+    },
+    /// The instance `instance` completed on its first poll, before any
+    /// instance of the task was up, which makes `exits` in a row. Emitted
+    /// right after its [`Event::Exited`], see
+    /// [`crate::Builder::max_immediate_exits`].
+    StartupImmediateExit {
+        task: TaskId,
+        instance: u64,
+        exits: u32,
+    },
+    /// The task will be spawned again after `delay`.
+    RestartScheduled {
+        task: TaskId,
+        instance: u64,
+        delay: Duration,
+    },
+    /// The task will be spawned again once a trigger fired, see
+    /// [`crate::RestartDecision::AwaitTrigger`].
+    RestartAwaiting { task: TaskId, instance: u64 },
+    /// The task is flapping and cools down for `cool_down` before being
+    /// spawned again, see [`crate::Debounce`]. Emitted once per bout of
+    /// flapping, before the first [`Event::RestartScheduled`] held back.
+    Flapping {
+        task: TaskId,
+        instance: u64,
+        cool_down: Duration,
+    },
+    /// The task was retired, and won't be spawned again.
+    Retired { task: TaskId, instance: u64 },
+    /// The task was removed through [`crate::WatchHandle::remove`].
+    Removed { task: TaskId },
+    /// The task was paused through [`crate::WatchHandle::pause`].
+    Paused { task: TaskId },
+    /// The policy of the task escalated, the watcher stops.
+    Escalated { task: TaskId, instance: u64 },
+    /// The task is no longer healthy, which made the quorum number `quorum`
+    /// lost, with only `healthy` tasks left. See [`crate::Quorum`].
+    QuorumLost {
+        task: TaskId,
+        quorum: usize,
+        healthy: usize,
+    },
+    /// A command about the task was dropped before being applied, to make
+    /// room for newer ones, see [`crate::Backpressure::DropOldest`].
+    CommandDropped { task: TaskId, command: WatchCommand },
+    /// The task is healthy again, which made the quorum number `quorum` met
+    /// again, with `healthy` tasks. See [`crate::Quorum`].
+    QuorumRegained {
+        task: TaskId,
+        quorum: usize,
+        healthy: usize,
+    },
+}
+
+impl Event {
+    /// Returns the task the event is about.
+    pub fn task(&self) -> TaskId {
+        match *self {
+            Event::Started { task, .. }
+            | Event::FactoryPanicked { task, .. }
+            | Event::Ready { task, .. }
+            | Event::Exited { task, .. }
+            | Event::StartupImmediateExit { task, .. }
+            | Event::RestartScheduled { task, .. }
+            | Event::RestartAwaiting { task, .. }
+            | Event::Flapping { task, .. }
+            | Event::Retired { task, .. }
+            | Event::Removed { task }
+            | Event::Paused { task }
+            | Event::Escalated { task, .. }
+            | Event::QuorumLost { task, .. }
+            | Event::QuorumRegained { task, .. }
+            | Event::CommandDropped { task, .. } => task,
+        }
+    }
+
+    /// Returns the kind of the event.
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::Started { .. } => EventKind::Started,
+            Event::FactoryPanicked { .. } => EventKind::FactoryPanicked,
+            Event::Ready { .. } => EventKind::Ready,
+            Event::Exited { .. } => EventKind::Exited,
+            Event::StartupImmediateExit { .. } => EventKind::StartupImmediateExit,
+            Event::RestartScheduled { .. } => EventKind::RestartScheduled,
+            Event::RestartAwaiting { .. } => EventKind::RestartAwaiting,
+            Event::Flapping { .. } => EventKind::Flapping,
+            Event::Retired { .. } => EventKind::Retired,
+            Event::Removed { .. } => EventKind::Removed,
+            Event::Paused { .. } => EventKind::Paused,
+            Event::Escalated { .. } => EventKind::Escalated,
+            Event::QuorumLost { .. } => EventKind::QuorumLost,
+            Event::QuorumRegained { .. } => EventKind::QuorumRegained,
+            Event::CommandDropped { .. } => EventKind::CommandDropped,
+        }
+    }
+}
+
+/// The kinds of [`Event`], to filter them with [`EventFilter::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Started,
+    FactoryPanicked,
+    Ready,
+    Exited,
+    StartupImmediateExit,
+    RestartScheduled,
+    RestartAwaiting,
+    Flapping,
+    Retired,
+    Removed,
+    Paused,
+    Escalated,
+    QuorumLost,
+    QuorumRegained,
+    CommandDropped,
+}
+
+/// Selects the [`Event`]'s a subscriber receives, see
+/// [`crate::WatchHandle::subscribe`].
+///
+/// An empty filter lets every event through. Otherwise events must be about
+/// one of the selected tasks, by identifier, name or labels, if any was, and
+/// of one of the selected kinds, if any was:
+///
+/// ```
+/// use watch::{EventFilter, EventKind};
+///
+/// // Exits and retirements of the task named "db", of the first task, or of
+/// // the tasks of the tenant "acme".
+/// let filter = EventFilter::new()
+///     .name("db")
+///     .task(0)
+///     .matching("tenant=acme")
+///     .kind(EventKind::Exited)
+///     .kind(EventKind::Retired);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    tasks: Vec<TaskId>,
+    names: Vec<String>,
+    selectors: Vec<Selector>,
+    kinds: Vec<EventKind>,
+}
+
+impl EventFilter {
+    /// Creates a filter that lets every event through.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Selects the task `task`.
+    pub fn task<T>(mut self, task: T) -> Self
+    where
+        T: Into<TaskId>,
+    {
+        self.tasks.push(task.into());
+        self
+    }
+
+    /// Selects the tasks named `name`, see [`crate::Task::name`].
+    pub fn name<N>(mut self, name: N) -> Self
+    where
+        N: Into<String>,
+    {
+        self.names.push(name.into());
+        self
+    }
+
+    /// Selects the tasks whose labels match `selector`, see
+    /// [`crate::Task::label`].
+    pub fn matching<S>(mut self, selector: S) -> Self
+    where
+        S: Into<Selector>,
+    {
+        self.selectors.push(selector.into());
+        self
+    }
+
+    /// Selects the events of kind `kind`.
+    pub fn kind(mut self, kind: EventKind) -> Self {
+        self.kinds.push(kind);
+        self
+    }
+
+    /// Returns whether `event` goes through, `snapshot` telling the names and
+    /// labels of the tasks, including the ones added at runtime.
+    fn matches(&self, event: &Event, snapshot: &Snapshot) -> bool {
+        let task = event.task();
+        let selected =
+            (self.tasks.is_empty() && self.names.is_empty() && self.selectors.is_empty())
+                || self.tasks.contains(&task)
+                || ((!self.names.is_empty() || !self.selectors.is_empty())
+                    && snapshot
+                        .describe(task.index(), |name, labels| {
+                            matches!(name, Some(name) if self.names.iter().any(|n| n == name))
+                                || self
+                                    .selectors
+                                    .iter()
+                                    .any(|selector| selector.matches(labels))
+                        })
+                        .unwrap_or(false));
+
+        selected && (self.kinds.is_empty() || self.kinds.contains(&event.kind()))
+    }
+}
+
+/// Waits for the next event of a receiver, handing the receiver back.
+type Recv = BoxFuture<'static, (Result<Event, RecvError>, Receiver<Event>)>;
+
+fn recv(mut receiver: Receiver<Event>) -> Recv {
+    async move { (receiver.recv().await, receiver) }.boxed()
+}
+
+/// The [`Event`]'s of a watcher, as returned by
+/// [`crate::WatchHandle::events`] and [`crate::WatchHandle::subscribe`]. Ends
+/// once the watcher stopped.
+///
+/// Events are buffered up to the capacity set with
+/// [`crate::Builder::event_capacity`]. A subscriber lagging further behind
+/// misses the oldest events, and receives the following ones as usual. See
+/// [`Events::missed`].
+pub struct Events {
+    recv: Recv,
+    filter: EventFilter,
+    snapshot: Snapshot,
+    missed: u64,
+}
+
+impl Events {
+    /// Returns the next event if one already happened, without waiting.
+    pub fn try_next(&mut self) -> Option<Event> {
+        self.next().now_or_never().flatten()
+    }
+
+    /// Returns how many events were missed so far because the subscriber
+    /// lagged behind, whether they would have gone through the filter or not.
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+}
+
+impl fmt::Debug for Events {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Events")
+            .field("filter", &self.filter)
+            .field("missed", &self.missed)
+            .finish()
+    }
+}
+
+impl Stream for Events {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let (result, receiver) = match self.recv.poll_unpin(cx) {
+                Poll::Ready(received) => received,
+                Poll::Pending => return Poll::Pending,
+            };
+            self.recv = recv(receiver);
+
+            match result {
+                Ok(event) if self.filter.matches(&event, &self.snapshot) => {
+                    return Poll::Ready(Some(event))
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(missed)) => self.missed += missed,
+                Err(RecvError::Closed) => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+/// The subscribers to the events of a watcher, shared with its handles. There
+/// are none left once the watcher stopped.
+#[derive(Debug, Clone)]
+pub(crate) struct Subscribers {
+    sender: Arc<Mutex<Option<Sender<Event>>>>,
+    /// The names and labels of the tasks by index, to filter events.
+    snapshot: Snapshot,
+}
+
+impl Subscribers {
+    /// Creates the subscribers of a watcher, keeping up to `capacity` events
+    /// for the ones lagging behind.
+    pub(crate) fn new(capacity: usize, snapshot: Snapshot) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self {
+            sender: Arc::new(Mutex::new(Some(sender))),
+            snapshot,
+        }
+    }
+
+    /// Returns the [`Events`] of a new subscriber, filtered by `filter`. They
+    /// end right away if the watcher stopped.
+    pub(crate) fn subscribe(&self, filter: EventFilter) -> Events {
+        let receiver = match self.sender.lock() {
+            Ok(sender) => sender.as_ref().map(Sender::subscribe),
+            Err(_) => None,
+        };
+        // A receiver whose sender is gone is closed.
+        let receiver = receiver.unwrap_or_else(|| broadcast::channel(1).1);
+
+        Events {
+            recv: recv(receiver),
+            filter,
+            snapshot: self.snapshot.clone(),
+            missed: 0,
+        }
+    }
+
+    /// Sends `event` to every subscriber.
+    pub(crate) fn emit(&self, event: Event) {
+        if let Ok(sender) = self.sender.lock() {
+            if let Some(sender) = &*sender {
+                // Fails when there is no subscriber, which is fine.
+                let _ = sender.send(event);
+            }
+        }
+    }
+
+    /// Returns whether the watcher stopped, see [`Subscribers::close`].
+    pub(crate) fn is_closed(&self) -> bool {
+        match self.sender.lock() {
+            Ok(sender) => sender.is_none(),
+            Err(_) => true,
+        }
+    }
+
+    /// Ends the [`Events`] of every subscriber, current and future.
+    pub(crate) fn close(&self) {
+        if let Ok(mut sender) = self.sender.lock() {
+            *sender = None;
+        }
+    }
+}
+
+/// Hands futures off to an executor, see [`crate::Builder::observer_executor`].
+pub(crate) type Executor = Arc<dyn Fn(BoxFuture<'static, ()>) + Send + Sync>;
+
+/// An [`Event`] for the listeners of a watcher, along with its record if it
+/// has a history.
+struct Notice {
+    event: Event,
+    #[cfg(feature = "history")]
+    record: Option<crate::history::Record>,
+}
+
+/// The observers and history sinks of a watcher.
+#[derive(Default)]
+struct Listeners {
+    observers: Vec<Box<dyn WatchObserver>>,
+    #[cfg(feature = "history")]
+    history: Vec<Box<dyn crate::HistorySink>>,
+    /// Called when a sink fails, see [`crate::Builder::on_history_error`].
+    #[cfg(feature = "history")]
+    history_errors: Option<crate::history::ErrorHook>,
+}
+
+impl Listeners {
+    /// Calls the observers interested in the event of `notice`, and appends
+    /// its record to every history sink.
+    fn notify(&mut self, notice: Notice) {
+        #[cfg(feature = "history")]
+        if let Some(record) = &notice.record {
+            for sink in &mut self.history {
+                if let Err(error) = sink.append(record) {
+                    #[cfg(feature = "log")]
+                    log::warn!("could not append to the history: {}", error);
+                    if let Some(hook) = &self.history_errors {
+                        hook(&error);
+                    }
+                }
+            }
+        }
+        for observer in &mut self.observers {
+            match &notice.event {
+                Event::Started { task, instance } => observer.on_start(*task, *instance),
+                Event::Exited {
+                    task,
+                    instance,
+                    reason,
+                } => observer.on_exit(*task, *instance, reason.clone()),
+                Event::RestartScheduled {
+                    task,
+                    instance,
+                    delay,
+                } => observer.on_restart_scheduled(*task, *instance, *delay),
+                Event::Retired { task, instance } => observer.on_retire(*task, *instance),
+                Event::FactoryPanicked { .. }
+                | Event::Ready { .. }
+                | Event::StartupImmediateExit { .. }
+                | Event::RestartAwaiting { .. }
+                | Event::Flapping { .. }
+                | Event::Removed { .. }
+                | Event::Paused { .. }
+                | Event::Escalated { .. }
+                | Event::QuorumLost { .. }
+                | Event::QuorumRegained { .. }
+                | Event::CommandDropped { .. } => {}
+            }
+        }
+    }
+}
+
+/// Where the listeners of a watcher are notified.
+enum Dispatch {
+    /// Right away, as events happen.
+    Inline(Listeners),
+    /// By a future spawned onto the executor the first time an event
+    /// happens, see [`crate::Builder::observer_executor`].
+    Deferred(Listeners, Executor),
+    /// By the future spawned onto the executor, which stops once the sender
+    /// is dropped.
+    Spawned(UnboundedSender<Notice>),
+}
+
+/// Notifies the listeners and subscribers of a watcher of its [`Event`]'s.
+pub(crate) struct Emitter {
+    dispatch: Dispatch,
+    /// Whether there is any listener to notify.
+    listening: bool,
+    subscribers: Subscribers,
+    #[cfg(feature = "log")]
+    logger: crate::logging::Logger,
+    /// Whether events are recorded into a history, see [`Emitter::record`].
+    #[cfg(feature = "history")]
+    recording: bool,
+    /// The events the [`crate::Watch`] has yet to yield as a [`Stream`],
+    /// once it was polled as one.
+    buffer: Option<VecDeque<Event>>,
+    /// How many events are kept in `buffer` at most, the oldest being dropped
+    /// first.
+    capacity: usize,
+    /// How many events were dropped from `buffer` so far.
+    missed: u64,
+}
+
+impl Emitter {
+    /// Creates the emitter of a watcher, keeping up to `capacity` events for
+    /// it to yield as a [`Stream`], see [`Emitter::buffer`].
+    pub(crate) fn new(
+        observers: Vec<Box<dyn WatchObserver>>,
+        subscribers: Subscribers,
+        capacity: usize,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        // Only the logger tells time, to detect restart storms.
+        #[cfg(not(feature = "log"))]
+        let _ = clock;
+        Self {
+            listening: !observers.is_empty(),
+            dispatch: Dispatch::Inline(Listeners {
+                observers,
+                #[cfg(feature = "history")]
+                history: Vec::new(),
+                #[cfg(feature = "history")]
+                history_errors: None,
+            }),
+            #[cfg(feature = "log")]
+            logger: crate::logging::Logger::new(subscribers.snapshot.clone(), clock),
+            #[cfg(feature = "history")]
+            recording: false,
+            subscribers,
+            buffer: None,
+            capacity: capacity.max(1),
+            missed: 0,
+        }
+    }
+
+    /// Keeps every event from now on, until taken with [`Emitter::take`]. Once
+    /// the capacity is reached, the oldest events are dropped, see
+    /// [`Emitter::missed`].
+    pub(crate) fn buffer(&mut self) {
+        self.buffer.get_or_insert_with(VecDeque::new);
+    }
+
+    /// Returns how many events were dropped so far because they were not
+    /// taken soon enough.
+    pub(crate) fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    /// Takes the oldest event kept since [`Emitter::buffer`], if any.
+    pub(crate) fn take(&mut self) -> Option<Event> {
+        self.buffer.as_mut()?.pop_front()
+    }
+
+    /// Appends every event to `history` from now on, calling `errors` when a
+    /// sink fails.
+    #[cfg(feature = "history")]
+    pub(crate) fn record(
+        &mut self,
+        history: Vec<Box<dyn crate::HistorySink>>,
+        errors: Option<crate::history::ErrorHook>,
+    ) {
+        self.recording = !history.is_empty();
+        self.listening |= self.recording;
+        if let Dispatch::Inline(listeners) | Dispatch::Deferred(listeners, _) = &mut self.dispatch {
+            listeners.history = history;
+            listeners.history_errors = errors;
+        }
+    }
+
+    /// Notifies the listeners from a future spawned with `executor` from now
+    /// on, rather than right away.
+    pub(crate) fn offload(&mut self, executor: Executor) {
+        let dispatch = mem::replace(&mut self.dispatch, Dispatch::Inline(Listeners::default()));
+        self.dispatch = match dispatch {
+            Dispatch::Inline(listeners) | Dispatch::Deferred(listeners, _) => {
+                Dispatch::Deferred(listeners, executor)
+            }
+            spawned => spawned,
+        };
+    }
+
+    /// Notifies the listeners of `event`, then sends it to every subscriber.
+    pub(crate) fn emit(&mut self, event: Event) {
+        #[cfg(feature = "log")]
+        self.logger.log(&event);
+        if self.listening {
+            let notice = Notice {
+                event: event.clone(),
+                #[cfg(feature = "history")]
+                record: self.recording.then(|| {
+                    let name = self
+                        .subscribers
+                        .snapshot
+                        .describe(event.task().index(), |name, _| name.map(str::to_owned))
+                        .flatten();
+                    crate::history::Record::new(event.clone(), name)
+                }),
+            };
+            self.notify(notice);
+        }
+        if let Some(buffer) = &mut self.buffer {
+            if buffer.len() >= self.capacity {
+                buffer.pop_front();
+                self.missed += 1;
+            }
+            buffer.push_back(event.clone());
+        }
+        self.subscribers.emit(event);
+    }
+
+    fn notify(&mut self, notice: Notice) {
+        if let Dispatch::Deferred(..) = self.dispatch {
+            let (sender, notices) = mpsc::unbounded();
+            let dispatch = mem::replace(&mut self.dispatch, Dispatch::Spawned(sender));
+            if let Dispatch::Deferred(mut listeners, executor) = dispatch {
+                executor(
+                    notices
+                        .for_each(move |notice| {
+                            listeners.notify(notice);
+                            future::ready(())
+                        })
+                        .boxed(),
+                );
+            }
+        }
+        match &mut self.dispatch {
+            Dispatch::Inline(listeners) => listeners.notify(notice),
+            Dispatch::Spawned(sender) => {
+                // Fails once the executor dropped the future, which is fine.
+                let _ = sender.unbounded_send(notice);
+            }
+            Dispatch::Deferred(..) => {}
+        }
+    }
+
+    /// Ends the [`Events`] of every subscriber, see [`Subscribers::close`],
+    /// and lets the listeners notified on an executor go.
+    pub(crate) fn close(&mut self) {
+        if let Dispatch::Spawned(sender) = &self.dispatch {
+            sender.close_channel();
+        }
+        self.subscribers.close();
+    }
+}
@@ -0,0 +1,104 @@
+use crate::panic::Panic;
+
+/// Why an instance of a task exited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The instance completed. Tasks created with [`crate::Task::new`] always
+    /// complete, whatever their output is.
+    Completed,
+    /// The instance of a task created with [`crate::Task::fallible`] returned
+    /// an error, classified as given.
+    Failed(FailureKind),
+    /// The instance was dropped by the watcher, such as through
+    /// [`crate::WatchHandle::cancel_current`] or during a shutdown.
+    Cancelled,
+    /// The instance ran for longer than a [`crate::TimeoutLayer`] allows, or
+    /// did not get ready within [`crate::Task::ready_within`], and was
+    /// dropped. It goes through the [`crate::RestartPolicy`] of its task
+    /// like a [`FailureKind::Transient`] failure.
+    TimedOut,
+    /// The instance panicked. The panic is caught so that it does not bring
+    /// the watcher down, and by default goes through the
+    /// [`crate::RestartPolicy`] of its task like a [`FailureKind::Transient`]
+    /// failure, see [`crate::PanicBehavior`].
+    Panicked(Panic),
+}
+
+impl ExitReason {
+    /// Whether the instance failed, timing out included.
+    pub(crate) fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            ExitReason::Failed(_) | ExitReason::TimedOut | ExitReason::Panicked(_)
+        )
+    }
+}
+
+/// How many instances of a task exited, by [`ExitReason`], as found in
+/// [`crate::TaskInfo::exits`]. Tells a worker that keeps crashing from one
+/// that finishes cleanly, but too early.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExitCounts {
+    completed: u64,
+    failed: u64,
+    cancelled: u64,
+    timed_out: u64,
+    panicked: u64,
+}
+
+impl ExitCounts {
+    /// Counts an instance that exited with `reason`.
+    pub(crate) fn record(&mut self, reason: &ExitReason) {
+        let count = match reason {
+            ExitReason::Completed => &mut self.completed,
+            ExitReason::Failed(_) => &mut self.failed,
+            ExitReason::Cancelled => &mut self.cancelled,
+            ExitReason::TimedOut => &mut self.timed_out,
+            ExitReason::Panicked(_) => &mut self.panicked,
+        };
+        *count += 1;
+    }
+
+    /// Returns how many instances exited with [`ExitReason::Completed`].
+    pub fn completed(&self) -> u64 {
+        self.completed
+    }
+
+    /// Returns how many instances exited with [`ExitReason::Failed`],
+    /// whatever the [`FailureKind`].
+    pub fn failed(&self) -> u64 {
+        self.failed
+    }
+
+    /// Returns how many instances exited with [`ExitReason::Cancelled`].
+    pub fn cancelled(&self) -> u64 {
+        self.cancelled
+    }
+
+    /// Returns how many instances exited with [`ExitReason::TimedOut`].
+    pub fn timed_out(&self) -> u64 {
+        self.timed_out
+    }
+
+    /// Returns how many instances exited with [`ExitReason::Panicked`].
+    pub fn panicked(&self) -> u64 {
+        self.panicked
+    }
+
+    /// Returns how many instances exited, whatever the reason.
+    pub fn total(&self) -> u64 {
+        self.completed + self.failed + self.cancelled + self.timed_out + self.panicked
+    }
+}
+
+/// How bad an error returned by a task is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// The error may go away by itself, such as a timeout or a reset
+    /// connection. The task goes through its [`crate::RestartPolicy`].
+    Transient,
+    /// Retrying won't help, such as with bad credentials or an invalid
+    /// configuration. The task is retired right away, without consulting its
+    /// [`crate::RestartPolicy`].
+    Permanent,
+}
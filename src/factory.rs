@@ -0,0 +1,75 @@
+use crate::task::Task;
+use futures::future::{BoxFuture, FutureExt};
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+/// A factory whose type is erased up front, cheap to clone. Factories of
+/// different types fit in a single collection once converted, and the same
+/// factory can be cloned into several watchers.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use watch::{ArcFactory, Builder, RestartDecision};
+///
+/// let factories: Vec<ArcFactory> = vec![
+///     ArcFactory::from(|| async {}),
+///     ArcFactory::from(|| async { 42 }),
+/// ];
+///
+/// for _ in 0..2 {
+///     let summary = Builder::new()
+///         .tasks(factories.clone())
+///         .policy(|_: &_| RestartDecision::Retire)
+///         .run()
+///         .await
+///         .unwrap();
+///     assert_eq!(summary.spawned(), 2);
+/// }
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ArcFactory {
+    factory: Arc<dyn Fn() -> BoxFuture<'static, ()> + Send + Sync>,
+}
+
+impl ArcFactory {
+    /// Creates an [`ArcFactory`] out of a factory. Outputs are ignored.
+    pub fn new<F, T>(factory: F) -> Self
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+        T: Future + Send + 'static,
+    {
+        Self {
+            factory: Arc::new(move || factory().map(drop).boxed()),
+        }
+    }
+
+    /// Calls the factory, returning a new instance.
+    pub fn call(&self) -> BoxFuture<'static, ()> {
+        (self.factory)()
+    }
+}
+
+impl<F, T> From<F> for ArcFactory
+where
+    F: Fn() -> T + Send + Sync + 'static,
+    T: Future + Send + 'static,
+{
+    fn from(factory: F) -> Self {
+        ArcFactory::new(factory)
+    }
+}
+
+impl From<ArcFactory> for Task {
+    fn from(factory: ArcFactory) -> Self {
+        Task::new(move || factory.call())
+    }
+}
+
+impl fmt::Debug for ArcFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArcFactory").finish_non_exhaustive()
+    }
+}
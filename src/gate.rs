@@ -0,0 +1,94 @@
+use crate::backpressure::Sender;
+use crate::handle::Command;
+use futures::channel::oneshot;
+use std::fmt;
+use std::sync::Arc;
+
+/// Blocks the restarts of a watcher while held, for code that must not see
+/// tasks come back during a critical section, such as while a database
+/// migration runs. As returned by [`crate::WatchHandle::restart_gate`].
+///
+/// Holding the gate leaves running instances alone: only the restarts
+/// decided by policies wait, even the ones of tasks marked with
+/// [`crate::Task::critical`], and go ahead once every [`RestartHold`] was
+/// released. Restarts through [`crate::WatchHandle::restart`] go ahead all
+/// the same. Gates are cheap to clone, and holds of any clone add up.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// # async fn migrate() {}
+/// use watch::Builder;
+///
+/// let (watch, handle) = Builder::new().task(|| async {}).build();
+/// let watch = tokio::spawn(watch);
+///
+/// let hold = handle.restart_gate().acquire().await;
+/// migrate().await;
+/// hold.release();
+/// # handle.shutdown();
+/// # watch.await.unwrap().unwrap();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RestartGate {
+    commands: Sender,
+}
+
+impl RestartGate {
+    pub(crate) fn new(commands: Sender) -> Self {
+        Self { commands }
+    }
+
+    /// Blocks restarts until the returned [`RestartHold`] is released.
+    /// Resolves once the watcher applied the hold, so that no restart happens
+    /// from then on, or right away if the watcher stopped.
+    pub async fn acquire(&self) -> RestartHold {
+        let (acknowledge, acknowledged) = oneshot::channel();
+        // Created first, so that dropping this future releases the hold.
+        let token = Arc::new(());
+        let command = Command::Hold(Arc::downgrade(&token), acknowledge);
+        let hold = RestartHold {
+            token: Some(token),
+            commands: self.commands.clone(),
+        };
+        if self.commands.send(command).is_ok() {
+            let _ = acknowledged.await;
+        }
+        hold
+    }
+}
+
+impl fmt::Debug for RestartGate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RestartGate").finish_non_exhaustive()
+    }
+}
+
+/// Blocks the restarts of a watcher until released or dropped, as returned
+/// by [`RestartGate::acquire`].
+pub struct RestartHold {
+    /// Tells the hold apart from the others, while it is held.
+    token: Option<Arc<()>>,
+    commands: Sender,
+}
+
+impl RestartHold {
+    /// Releases the hold, letting restarts go ahead if no other hold is left.
+    pub fn release(self) {}
+}
+
+impl Drop for RestartHold {
+    fn drop(&mut self) {
+        // The watcher tells holds apart by their token, gone by the time it
+        // goes through its holds again.
+        self.token = None;
+        let _ = self.commands.send(Command::Release);
+    }
+}
+
+impl fmt::Debug for RestartHold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RestartHold").finish_non_exhaustive()
+    }
+}
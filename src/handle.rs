@@ -0,0 +1,1126 @@
+use crate::backpressure::Sender;
+use crate::builder::Builder;
+use crate::changes::Changes;
+use crate::clock::{self, Clock};
+use crate::error::CallError;
+use crate::event::{EventFilter, EventKind, Events, Subscribers};
+use crate::exit::ExitReason;
+use crate::gate::RestartGate;
+use crate::id::{TaskId, TaskRef};
+use crate::info::{Snapshot, TaskInfo, TaskState};
+use crate::keyed::KeyedShards;
+use crate::labels::Selector;
+use crate::mailbox::{self, Mailboxes};
+use crate::monitor::{Monitor, NextExit};
+use crate::report::Report;
+use crate::shutdown::ShutdownSignal;
+use crate::task::Task;
+use crate::template::Template;
+use futures::channel::oneshot;
+use futures::future::{self, BoxFuture, Either, FutureExt};
+use futures::sink::Sink;
+use futures::stream::{BoxStream, StreamExt};
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Weak};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Commands sent by a [`WatchHandle`] to its [`crate::Watch`].
+#[derive(Debug)]
+pub(crate) enum Command {
+    Restart(usize),
+    /// Asks the running instance to return, see
+    /// [`WatchHandle::restart_at_yield`].
+    RestartAtYield(usize),
+    CancelCurrent(usize),
+    /// Skips the restart delay of a task, see [`WatchHandle::expedite`].
+    Expedite(usize),
+    Remove(usize),
+    /// Removes a task once its running instance returned, see
+    /// [`WatchHandle::reload`].
+    Retire(usize),
+    /// Makes the next instances of a task out of another one, see
+    /// [`WatchHandle::reload`].
+    Replace(usize, Box<Task>),
+    /// Makes a future spawned elsewhere the running instance of a task, see
+    /// [`WatchHandle::adopt`].
+    Adopt(usize, Adopted),
+    /// Removes a task if it is still the one of the dropped guard, see
+    /// [`WatchHandle::start_guarded`].
+    Disown(usize, Weak<()>),
+    Pause(usize),
+    Resume(usize),
+    /// Adds a task under the identifier handed out for it.
+    StartChild(usize, Box<Task>),
+    /// Adds the tasks yielded by a stream as they are, see
+    /// [`WatchHandle::merge`].
+    Incoming(Box<Incoming>),
+    /// Spawns a deferred task, see [`crate::Task::deferred`].
+    Trigger(usize),
+    /// Spawns the tasks held back by [`crate::Builder::start_paused`].
+    Start,
+    /// Blocks restarts while the hold is alive, acknowledging once applied,
+    /// see [`crate::RestartGate`].
+    Hold(Weak<()>, oneshot::Sender<()>),
+    /// Lets restarts go ahead if no hold is alive anymore.
+    Release,
+    /// Defers restarts until the instant, if any, see [`WatchHandle::freeze`].
+    Freeze(Option<Instant>),
+    Drain,
+    Shutdown,
+}
+
+/// A stream of tasks to add, along with the handle adding them.
+pub(crate) struct Incoming {
+    pub(crate) tasks: BoxStream<'static, Task>,
+    pub(crate) handle: WatchHandle,
+}
+
+impl fmt::Debug for Incoming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Incoming").finish_non_exhaustive()
+    }
+}
+
+/// A future spawned elsewhere, to supervise as an instance of a task.
+pub(crate) struct Adopted(pub(crate) BoxFuture<'static, ExitReason>);
+
+impl fmt::Debug for Adopted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Adopted").finish()
+    }
+}
+
+impl Command {
+    /// Returns the command as sent through a [`WatchHandle`], if it is about
+    /// a task and safe to lose, see [`crate::Backpressure`].
+    pub(crate) fn droppable(&self) -> Option<(TaskId, WatchCommand)> {
+        let (id, command): (_, fn(TaskId) -> WatchCommand) = match *self {
+            Command::Restart(id) => (id, WatchCommand::Restart),
+            Command::RestartAtYield(id) => (id, WatchCommand::RestartAtYield),
+            Command::CancelCurrent(id) => (id, WatchCommand::CancelCurrent),
+            Command::Expedite(id) => (id, WatchCommand::Expedite),
+            Command::Remove(id) => (id, WatchCommand::Remove),
+            Command::Pause(id) => (id, WatchCommand::Pause),
+            Command::Resume(id) => (id, WatchCommand::Resume),
+            Command::Trigger(id) => (id, WatchCommand::Trigger),
+            _ => return None,
+        };
+        let task = TaskId::from(id);
+        Some((task, command(task)))
+    }
+}
+
+/// A command sent through the [`Sink`] of a [`WatchHandle`], applied as by
+/// the method of the same name.
+///
+/// ```
+/// use futures::{stream, SinkExt, StreamExt};
+/// use watch::{Builder, TaskId, WatchCommand};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let (watch, mut handle) = Builder::new().task(|| async {}).build();
+/// # let watch = tokio::spawn(watch);
+/// // Such as commands received from an admin channel.
+/// let mut commands = stream::iter([WatchCommand::Pause(TaskId::from(0)), WatchCommand::Shutdown]).map(Ok);
+/// handle.send_all(&mut commands).await.unwrap();
+/// # watch.await.unwrap().unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WatchCommand {
+    /// See [`WatchHandle::restart`].
+    Restart(TaskId),
+    /// See [`WatchHandle::restart_at_yield`].
+    RestartAtYield(TaskId),
+    /// See [`WatchHandle::cancel_current`].
+    CancelCurrent(TaskId),
+    /// See [`WatchHandle::expedite`].
+    Expedite(TaskId),
+    /// See [`WatchHandle::remove`].
+    Remove(TaskId),
+    /// See [`WatchHandle::pause`].
+    Pause(TaskId),
+    /// See [`WatchHandle::resume`].
+    Resume(TaskId),
+    /// See [`WatchHandle::trigger`].
+    Trigger(TaskId),
+    /// See [`WatchHandle::start`].
+    Start,
+    /// See [`WatchHandle::freeze`].
+    Freeze(Duration),
+    /// See [`WatchHandle::thaw`].
+    Thaw,
+    /// See [`WatchHandle::drain`].
+    Drain,
+    /// See [`WatchHandle::shutdown`].
+    Shutdown,
+}
+
+/// Controls a running [`crate::Watch`], as returned by
+/// [`crate::Builder::build`].
+///
+/// Handles are cheap to clone, and can be sent to and shared between threads,
+/// so every subsystem that needs one can hold its own: admin endpoints, signal
+/// handlers and so on. Commands are queued and applied in order the next time
+/// the [`crate::Watch`] is polled. Commands sent once it stopped are ignored, as
+/// are commands targeting a task that does not exist.
+///
+/// Tasks are identified by their [`TaskId`]. Methods taking one also take
+/// the index of a task, in the order tasks were added to the
+/// [`crate::Builder`], children started with [`WatchHandle::start_child`]
+/// coming after.
+///
+/// Handles are also a [`Sink`] of [`WatchCommand`]'s, to be fed by other
+/// streams. The sink never fails, and is always ready unless the watcher
+/// holds senders back, see [`crate::Backpressure::Block`].
+#[derive(Debug, Clone)]
+pub struct WatchHandle {
+    commands: Sender,
+    shutdown: ShutdownSignal,
+    events: Subscribers,
+    snapshot: Snapshot,
+    mailboxes: Mailboxes,
+    template: Option<Arc<Template>>,
+    keyed: Option<Arc<KeyedShards>>,
+    clock: Arc<dyn Clock>,
+    #[cfg(feature = "metrics")]
+    metrics: crate::metrics::Registry,
+}
+
+impl WatchHandle {
+    pub(crate) fn new(
+        commands: Sender,
+        shutdown: ShutdownSignal,
+        events: Subscribers,
+        snapshot: Snapshot,
+        mailboxes: Mailboxes,
+        template: Option<Arc<Template>>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            commands,
+            shutdown,
+            events,
+            snapshot,
+            mailboxes,
+            template,
+            keyed: None,
+            clock,
+            #[cfg(feature = "metrics")]
+            metrics: Default::default(),
+        }
+    }
+
+    /// Reshards the workers of `keyed`, see [`WatchHandle::reshard`].
+    pub(crate) fn with_keyed(mut self, keyed: Option<KeyedShards>) -> Self {
+        self.keyed = keyed.map(Arc::new);
+        self
+    }
+
+    /// Reads the probes of tasks from `metrics`.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn with_metrics(mut self, metrics: crate::metrics::Registry) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    fn command(&self, command: Command) {
+        // The watcher stopped if the receiver was dropped, there is nothing
+        // left to control.
+        let _ = self.commands.send(command);
+    }
+
+    /// Spawns the tasks of a watcher built with
+    /// [`crate::Builder::start_paused`], which are held back until then. Does
+    /// nothing otherwise, or once started.
+    pub fn start(&self) {
+        self.command(Command::Start);
+    }
+
+    /// Drops the running instance of `task`, if any, and spawns a new one right
+    /// away, without consulting its [`crate::RestartPolicy`]. A retired or
+    /// paused task is spawned again.
+    pub fn restart<T>(&self, task: T)
+    where
+        T: Into<TaskId>,
+    {
+        self.command(Command::Restart(task.into().index()));
+    }
+
+    /// Asks the running instance of `task`, if any, to return at its next safe
+    /// point, through [`crate::TaskContext::restart_requested`], and spawns a
+    /// new one once it did, without consulting its [`crate::RestartPolicy`].
+    /// Unlike [`WatchHandle::restart`], the instance finishes its current unit
+    /// of work rather than being dropped, and keeps running for as long as it
+    /// does not check.
+    ///
+    /// The new instance is spawned as for any other restart: it waits for
+    /// the [`crate::RestartGate`], freezes and restart windows, and for the
+    /// limits on starting and running instances, and spends the
+    /// [`crate::RestartBudget`]'s of the task, whose exhaustion is applied as
+    /// usual.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// # async fn work() {}
+    /// use watch::{Builder, Task, TaskContext};
+    ///
+    /// let (watch, handle) = Builder::new()
+    ///     .task(Task::with_context(|context: TaskContext| async move {
+    ///         while !context.restart_requested() {
+    ///             work().await;
+    ///         }
+    ///     }))
+    ///     .build();
+    /// # let watch = tokio::spawn(watch);
+    ///
+    /// handle.restart_at_yield(0);
+    /// # handle.shutdown();
+    /// # watch.await.unwrap().unwrap();
+    /// # }
+    /// ```
+    pub fn restart_at_yield<T>(&self, task: T)
+    where
+        T: Into<TaskId>,
+    {
+        self.command(Command::RestartAtYield(task.into().index()));
+    }
+
+    /// Drops the running instance of `task`, if any, and lets its
+    /// [`crate::RestartPolicy`] decide when a new one starts, as if the
+    /// instance returned with [`crate::ExitReason::Cancelled`]. Handy to kick a
+    /// stuck worker.
+    pub fn cancel_current<T>(&self, task: T)
+    where
+        T: Into<TaskId>,
+    {
+        self.command(Command::CancelCurrent(task.into().index()));
+    }
+
+    /// Skips what is left of the restart delay of `task`, if it is
+    /// [`crate::TaskState::Delayed`], to force an early retry. Unlike
+    /// [`WatchHandle::restart`], the restart goes through freezes, the
+    /// [`crate::RestartGate`] and the [`crate::Builder::restart_window`] like
+    /// any other once its delay is over.
+    pub fn expedite<T>(&self, task: T)
+    where
+        T: Into<TaskId>,
+    {
+        self.command(Command::Expedite(task.into().index()));
+    }
+
+    /// Drops the running instance of `task`, if any, and stops watching it for
+    /// good.
+    ///
+    /// Once its last instance is over, the identifier of a removed task is
+    /// handed out again to the next task added at runtime, so that adding and
+    /// removing tasks does not grow the watcher.
+    pub fn remove<T>(&self, task: T)
+    where
+        T: Into<TaskId>,
+    {
+        self.command(Command::Remove(task.into().index()));
+    }
+
+    /// Drops the running instance of `task`, if any, and does not spawn it
+    /// again until it is resumed with [`WatchHandle::resume`] or
+    /// [`WatchHandle::restart`]. Paused tasks keep the [`crate::Watch`] going,
+    /// unless it is drained or shut down.
+    pub fn pause<T>(&self, task: T)
+    where
+        T: Into<TaskId>,
+    {
+        self.command(Command::Pause(task.into().index()));
+    }
+
+    /// Spawns `task` if it waits to be triggered, see [`crate::Task::deferred`].
+    /// Does nothing once it was spawned.
+    pub fn trigger<T>(&self, task: T)
+    where
+        T: Into<TaskId>,
+    {
+        self.command(Command::Trigger(task.into().index()));
+    }
+
+    /// Spawns `task` again if it was paused with [`WatchHandle::pause`].
+    pub fn resume<T>(&self, task: T)
+    where
+        T: Into<TaskId>,
+    {
+        self.command(Command::Resume(task.into().index()));
+    }
+
+    /// Sends `message` to the [`crate::Mailbox`] of `task`, see
+    /// [`crate::Task::with_mailbox`]. Messages are kept until an instance
+    /// receives them, even while the task restarts.
+    ///
+    /// Hands `message` back if `task` has no mailbox of messages of type `M`,
+    /// or if the watcher stopped.
+    pub fn send<M>(&self, task: impl Into<TaskId>, message: M) -> Result<(), M>
+    where
+        M: Send + 'static,
+    {
+        self.mailboxes.send(task.into().index(), message)
+    }
+
+    /// Starts a new child out of the template of the watcher, see
+    /// [`crate::Builder::template`], and returns its identifier. The child
+    /// is a task like any other: it goes through the default policy of the
+    /// [`crate::Builder`], and can be controlled by the other methods of the
+    /// handle.
+    ///
+    /// Returns [`None`] if there is no template, or if it takes arguments of
+    /// another type than `A`. Children started once the watcher stopped are
+    /// never spawned.
+    ///
+    /// ```no_run
+    /// # async fn serve(tenant: String) {}
+    /// # async fn run() {
+    /// use watch::Builder;
+    ///
+    /// let (watch, handle) = Builder::new().template(serve).build();
+    /// let acme = handle.start_child("acme".to_string()).unwrap();
+    /// watch.await.unwrap();
+    /// # }
+    /// ```
+    pub fn start_child<A>(&self, args: A) -> Option<TaskId>
+    where
+        A: Send + 'static,
+    {
+        let task = self.template.as_ref()?.child(Box::new(args))?;
+        self.add(task)
+    }
+
+    /// Spreads the key space of the watcher over `count` shards, see
+    /// [`crate::Builder::keyed_shards`]. Shards past the new count are
+    /// removed, after their keys were handed to the remaining shards, and
+    /// missing shards are started. The other shards keep running, and only
+    /// lose the keys taken by the new shards, see [`crate::Shard::owns`].
+    ///
+    /// Returns whether the watcher has keyed shards.
+    pub fn reshard(&self, count: usize) -> bool {
+        let keyed = match &self.keyed {
+            Some(keyed) => keyed,
+            None => return false,
+        };
+        keyed.reshard(count, |task| self.add(task), |task| self.remove(task));
+        true
+    }
+
+    /// Places `running`, a future already spawned elsewhere, under the
+    /// supervision of the watcher as the running instance of `task`, a
+    /// watched task given by identifier or name, see [`crate::Task::name`].
+    /// Once `running` returns, `task` is restarted through its factory and
+    /// policy like any other task. This moves existing spawn sites under the
+    /// watcher one at a time: the task is added with the factory the spawn
+    /// site would have restarted, usually deferred so that it does not start
+    /// on its own, see [`crate::Task::deferred`], and the spawned future is
+    /// then adopted.
+    ///
+    /// The instance of `task` running at the time, if any, is cancelled and
+    /// replaced by `running`, whatever the policy. A deferred task counts as
+    /// triggered. Adopted before the watcher starts, `running` is only polled
+    /// once it does.
+    ///
+    /// Returns the identifier of `task`, or [`None`] if no watched task has
+    /// that identifier or name as of the last snapshot, see
+    /// [`WatchHandle::tasks`]. Futures adopted by removed tasks, or once the
+    /// watcher is draining or stopped, are dropped. Outputs are ignored.
+    ///
+    /// ```no_run
+    /// # async fn poll_feed() {}
+    /// # async fn run() {
+    /// use watch::{Builder, Task};
+    ///
+    /// let (watch, handle) = Builder::new()
+    ///     .task(Task::new(poll_feed).name("feed").deferred())
+    ///     .build();
+    /// // What used to be spawned on its own, and restarted by hand.
+    /// let feed = poll_feed();
+    /// handle.adopt("feed", feed).unwrap();
+    /// watch.await.unwrap();
+    /// # }
+    /// ```
+    pub fn adopt<T, F>(&self, task: T, running: F) -> Option<TaskId>
+    where
+        T: Into<TaskRef>,
+        F: Future + Send + 'static,
+    {
+        let watched = |info: &TaskInfo| info.state() != TaskState::Removed;
+        let id = match task.into() {
+            TaskRef::Id(id) => self.snapshot.task(id.index()).filter(watched)?.id(),
+            TaskRef::Name(name) => self
+                .snapshot
+                .tasks()
+                .into_iter()
+                .find(|info| watched(info) && info.name() == Some(name.as_str()))?
+                .id(),
+        };
+        let running = running.map(|_| ExitReason::Completed).boxed();
+        self.command(Command::Adopt(id.index(), Adopted(running)));
+        Some(id)
+    }
+
+    /// Applies `tasks` as the whole set of the watcher, telling tasks apart by
+    /// name, see [`crate::Task::name`]: tasks whose name is already watched
+    /// are kept, the others are added, and the watched tasks left out are
+    /// removed once their running instance returned, without being respawned.
+    /// Tasks without a name are always added, or removed.
+    ///
+    /// Kept tasks are replaced at their next restart, as factories cannot be
+    /// compared: the running instance is left alone, and the following ones
+    /// are made out of the new task, with its policy, readiness, panic and
+    /// cancellation settings. What tells the task apart and orders it among
+    /// the others, such as its labels, dependencies, priority or phase, is
+    /// kept. A kept task that is not respawned anymore stays so.
+    ///
+    /// Returns the identifiers of `tasks`, in order, whether they were kept
+    /// or added. Tasks are told apart as of the last snapshot, see
+    /// [`WatchHandle::tasks`].
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use futures::future;
+    /// use watch::{Builder, Task, TaskId};
+    ///
+    /// let task = |name: &str| Task::new(future::pending::<()>).name(name);
+    /// let (mut watch, handle) = Builder::new()
+    ///     .task(task("api"))
+    ///     .task(task("cron"))
+    ///     .build();
+    /// watch.tick();
+    ///
+    /// let ids = handle.reload([task("api"), task("worker")]);
+    /// assert_eq!(ids, [TaskId::from(0), TaskId::from(2)]);
+    /// # }
+    /// ```
+    pub fn reload<I>(&self, tasks: I) -> Vec<TaskId>
+    where
+        I: IntoIterator,
+        I::Item: Into<Task>,
+    {
+        use crate::info::TaskState;
+
+        let mut current: Vec<_> = self
+            .tasks()
+            .into_iter()
+            .filter(|info| info.state() != TaskState::Removed)
+            .map(Some)
+            .collect();
+        let ids = tasks
+            .into_iter()
+            .filter_map(|task| {
+                let task = task.into();
+                let kept = task.metadata.name.as_deref().and_then(|name| {
+                    current
+                        .iter_mut()
+                        .find(|info| info.as_ref().and_then(TaskInfo::name) == Some(name))?
+                        .take()
+                });
+                match kept {
+                    Some(info) => {
+                        self.command(Command::Replace(info.id().index(), Box::new(task)));
+                        Some(info.id())
+                    }
+                    None => self.add(task),
+                }
+            })
+            .collect();
+        for info in current.into_iter().flatten() {
+            self.command(Command::Retire(info.id().index()));
+        }
+        ids
+    }
+
+    /// Adds `task` to the watcher, tied to the returned [`TaskGuard`]: once
+    /// the guard is dropped, the task is removed as with
+    /// [`WatchHandle::remove`]. This makes workers per connection or per
+    /// subscription clean up after themselves, along with whatever owns the
+    /// guard.
+    ///
+    /// Returns [`None`] if the snapshot of the watcher is unavailable.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use watch::{Builder, TaskState};
+    ///
+    /// let (mut watch, handle) = Builder::new().task(futures::future::pending::<()>).build();
+    ///
+    /// struct Connection {
+    ///     worker: watch::TaskGuard,
+    /// }
+    ///
+    /// let worker = handle.start_guarded(futures::future::pending::<()>).unwrap();
+    /// let id = worker.id();
+    /// let connection = Connection { worker };
+    /// watch.tick();
+    /// assert_eq!(handle.task_info(id).unwrap().state(), TaskState::Running);
+    ///
+    /// drop(connection);
+    /// watch.tick();
+    /// assert_eq!(handle.task_info(id).unwrap().state(), TaskState::Removed);
+    /// # }
+    /// ```
+    pub fn start_guarded<T>(&self, task: T) -> Option<TaskGuard>
+    where
+        T: Into<Task>,
+    {
+        let owner = Arc::new(());
+        let mut task = task.into();
+        task.owner = Some(Arc::downgrade(&owner));
+        let id = self.add(task)?;
+        Some(TaskGuard {
+            id,
+            owner,
+            commands: self.commands.clone(),
+        })
+    }
+
+    /// Adds the tasks of `other` to the watcher, which may have started
+    /// already, and returns their identifiers, in order. The tasks go through
+    /// this handle and its events from then on, as if they had been added to
+    /// the watcher from the start.
+    ///
+    /// As with [`Builder::merge`], the tasks keep the default policy and the
+    /// layers of `other`, and the dependencies between them, and so do the
+    /// tasks its streams yield, see [`Builder::task_stream`]. Anything else
+    /// `other` configured, such as its budgets, observers or template, is
+    /// dropped: use [`Builder::merge`] before the watcher starts for it to be
+    /// kept. Nothing is added once the watcher is draining or shutting down.
+    ///
+    /// ```
+    /// use futures::future;
+    /// use watch::{Builder, TaskState};
+    ///
+    /// let (mut watch, handle) = Builder::new().task(future::pending::<()>).build();
+    /// watch.tick();
+    ///
+    /// // Exported by a library, loaded once the application runs.
+    /// let plugin = Builder::new().task(future::pending::<()>);
+    /// let ids = handle.merge(plugin);
+    /// watch.tick();
+    /// assert_eq!(handle.task_info(ids[0]).unwrap().state(), TaskState::Running);
+    /// ```
+    pub fn merge(&self, other: Builder) -> Vec<TaskId> {
+        let (tasks, incoming) = other.into_tasks();
+        let ids = self.add_all(tasks);
+        if let Some(tasks) = incoming {
+            self.command(Command::Incoming(Box::new(Incoming {
+                tasks,
+                handle: self.clone(),
+            })));
+        }
+        ids
+    }
+
+    /// Adds `tasks` to the watcher, the dependencies between them pointing to
+    /// the identifiers they are given, and returns those identifiers, or none
+    /// if the snapshot is unavailable.
+    fn add_all(&self, tasks: Vec<Task>) -> Vec<TaskId> {
+        let metadata: Vec<_> = tasks.iter().map(|task| task.metadata.clone()).collect();
+        self.snapshot
+            .push_all(&metadata, |ids| {
+                for (mut task, &id) in tasks.into_iter().zip(ids) {
+                    task.dependencies = task
+                        .dependencies
+                        .iter()
+                        .filter_map(|&dependency| ids.get(dependency).copied())
+                        .collect();
+                    self.mailboxes.set(id, task.mailbox.take());
+                    self.command(Command::StartChild(id, Box::new(task)));
+                }
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .map(TaskId::from)
+            .collect()
+    }
+
+    /// Adds `task` to the watcher, returning its identifier, or [`None`] if
+    /// the snapshot is unavailable.
+    pub(crate) fn add(&self, mut task: Task) -> Option<TaskId> {
+        // Identifiers are handed out in the order commands are sent, which
+        // is the order the watcher adds tasks in.
+        let metadata = task.metadata.clone();
+        let mailbox = task.mailbox.take();
+        self.snapshot
+            .push(&metadata, |id| {
+                self.mailboxes.set(id, mailbox);
+                self.command(Command::StartChild(id, Box::new(task)))
+            })
+            .map(TaskId::from)
+    }
+
+    /// Applies `command` to every task whose labels match `selector`, as of
+    /// the last snapshot. Returns how many tasks matched.
+    fn send_matching(&self, selector: Selector, command: fn(usize) -> Command) -> usize {
+        let tasks = self.snapshot.matching(&selector);
+        for task in &tasks {
+            self.command(command(task.id().index()));
+        }
+        tasks.len()
+    }
+
+    /// Restarts every task whose labels match `selector`, see
+    /// [`WatchHandle::restart`] and [`crate::Task::label`]. Returns how many
+    /// tasks matched.
+    ///
+    /// ```no_run
+    /// # async fn query() {}
+    /// use watch::{Builder, Task};
+    ///
+    /// let (watch, handle) = Builder::new()
+    ///     .task(Task::new(query).label("group", "db"))
+    ///     .task(Task::new(query).label("group", "db"))
+    ///     .build();
+    /// # drop(watch);
+    ///
+    /// assert_eq!(handle.restart_matching("group=db"), 2);
+    /// ```
+    pub fn restart_matching<S>(&self, selector: S) -> usize
+    where
+        S: Into<Selector>,
+    {
+        self.send_matching(selector.into(), Command::Restart)
+    }
+
+    /// Cancels the running instance of every task whose labels match
+    /// `selector`, see [`WatchHandle::cancel_current`]. Returns how many tasks
+    /// matched.
+    pub fn cancel_matching<S>(&self, selector: S) -> usize
+    where
+        S: Into<Selector>,
+    {
+        self.send_matching(selector.into(), Command::CancelCurrent)
+    }
+
+    /// Removes every task whose labels match `selector`, see
+    /// [`WatchHandle::remove`]. Returns how many tasks matched.
+    pub fn remove_matching<S>(&self, selector: S) -> usize
+    where
+        S: Into<Selector>,
+    {
+        self.send_matching(selector.into(), Command::Remove)
+    }
+
+    /// Pauses every task whose labels match `selector`, see
+    /// [`WatchHandle::pause`]. Returns how many tasks matched.
+    pub fn pause_matching<S>(&self, selector: S) -> usize
+    where
+        S: Into<Selector>,
+    {
+        self.send_matching(selector.into(), Command::Pause)
+    }
+
+    /// Resumes every task whose labels match `selector`, see
+    /// [`WatchHandle::resume`]. Returns how many tasks matched.
+    pub fn resume_matching<S>(&self, selector: S) -> usize
+    where
+        S: Into<Selector>,
+    {
+        self.send_matching(selector.into(), Command::Resume)
+    }
+
+    /// Defers the restarts decided by policies for the next `duration`, such
+    /// as during a change freeze. Tasks whose restart delay is over by then
+    /// stay delayed until the freeze is over, or until
+    /// [`WatchHandle::thaw`]. Freezing again replaces the previous freeze.
+    ///
+    /// As with [`crate::Builder::restart_window`], tasks marked with
+    /// [`crate::Task::critical`] are restarted all the same, and so are
+    /// restarts through [`WatchHandle::restart`].
+    pub fn freeze(&self, duration: Duration) {
+        self.command(Command::Freeze(Some(clock::after(
+            self.clock.now(),
+            duration,
+        ))));
+    }
+
+    /// Returns the [`RestartGate`] of the watcher, for external code to block
+    /// restarts during critical sections.
+    pub fn restart_gate(&self) -> RestartGate {
+        RestartGate::new(self.commands.clone())
+    }
+
+    /// Lifts the freeze of [`WatchHandle::freeze`], restarting every task
+    /// whose restart was deferred if the restart window allows it.
+    pub fn thaw(&self) {
+        self.command(Command::Freeze(None));
+    }
+
+    /// Sends `request` to the [`crate::Mailbox`] of `task` as a
+    /// [`crate::Call`], and waits for the response for up to `timeout`. This
+    /// queries live workers through the watcher, such as for admin
+    /// operations, without plumbing of their own. See [`WatchHandle::send`]:
+    /// the types of the request and response must be the ones of the
+    /// mailbox, which may take spelling them out.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CallError::NoMailbox`] if `task` has no mailbox of calls of
+    /// that type, [`CallError::Dropped`] if the call was dropped without a
+    /// response, and [`CallError::TimedOut`] if no response came in time.
+    pub async fn call<Q, R>(
+        &self,
+        task: impl Into<TaskId>,
+        request: Q,
+        timeout: Duration,
+    ) -> Result<R, CallError>
+    where
+        Q: Send + 'static,
+        R: Send + 'static,
+    {
+        let (call, response) = mailbox::call(request);
+        self.send::<crate::Call<Q, R>>(task, call)
+            .map_err(|_| CallError::NoMailbox)?;
+        match future::select(response, self.clock.sleep(timeout)).await {
+            Either::Left((Ok(response), _)) => Ok(response),
+            Either::Left((Err(_), _)) => Err(CallError::Dropped),
+            Either::Right(_) => Err(CallError::TimedOut),
+        }
+    }
+
+    /// Begins draining: no task is respawned anymore, and running instances
+    /// are left to return by themselves. The [`crate::Watch`] then returns its
+    /// [`crate::Summary`]. This is the shape of "finish in-flight work then
+    /// exit".
+    ///
+    /// Restarts are ignored while draining, but the watcher can still be shut
+    /// down.
+    pub fn drain(&self) {
+        self.command(Command::Drain);
+    }
+
+    /// Begins shutting down: every [`ShutdownSignal`] resolves, no task is
+    /// respawned anymore and running instances are given the grace period to
+    /// return, see [`crate::Builder::grace_period`]. The [`crate::Watch`] then
+    /// drops whatever is left and returns its [`crate::Summary`].
+    pub fn shutdown(&self) {
+        self.command(Command::Shutdown);
+    }
+
+    /// Returns a [`WatchGuard`] shutting the watcher down once dropped, such
+    /// as at the end of a test or of `main`, so that no instance keeps being
+    /// restarted in the background. See [`WatchGuard::close`] to wait for the
+    /// watcher to have stopped.
+    pub fn guard(&self) -> WatchGuard {
+        WatchGuard {
+            handle: self.clone(),
+            stopped: self.subscribe(EventFilter::new()),
+        }
+    }
+
+    /// Returns a [`ShutdownSignal`] resolving once the watcher begins shutting
+    /// down.
+    pub fn shutdown_signal(&self) -> ShutdownSignal {
+        self.shutdown.clone()
+    }
+
+    /// Subscribes to the [`Events`] of the watcher: instances starting,
+    /// getting ready, returning, and what is decided about their task. Only
+    /// the events happening after the subscription are received, and the
+    /// stream ends once the watcher stopped.
+    ///
+    /// Tasks are only spawned once the [`crate::Watch`] is first polled, so
+    /// subscribing right after [`crate::Builder::build`] receives every event.
+    pub fn events(&self) -> Events {
+        self.subscribe(EventFilter::new())
+    }
+
+    /// Subscribes to the [`Events`] of the watcher that go through `filter`.
+    /// See [`WatchHandle::events`].
+    ///
+    /// Every subscriber gets its own copy of the events, so several parts of
+    /// an application can each subscribe to what they care about.
+    pub fn subscribe(&self, filter: EventFilter) -> Events {
+        self.events.subscribe(filter)
+    }
+
+    /// Subscribes to the [`Changes`] of the tasks of the watcher, comparing
+    /// snapshots every `interval` rather than receiving every event.
+    pub fn changes(&self, interval: Duration) -> Changes {
+        Changes::new(
+            self.snapshot.clone(),
+            self.events.clone(),
+            Arc::clone(&self.clock),
+            interval,
+        )
+    }
+
+    /// Monitors the task `task`, receiving every [`crate::Exit`] of its
+    /// instances from now on. See [`Monitor`].
+    ///
+    /// Like other subscribers, monitors lagging too far behind miss exits,
+    /// see [`crate::Builder::event_capacity`].
+    pub fn monitor<T>(&self, task: T) -> Monitor
+    where
+        T: Into<TaskId>,
+    {
+        Monitor::new(
+            self.subscribe(
+                EventFilter::new()
+                    .task(task)
+                    .kind(EventKind::Exited)
+                    .kind(EventKind::Removed),
+            ),
+        )
+    }
+
+    /// Returns a future resolving with why the next instance of `task`
+    /// exited, from now on, see [`NextExit`]. This awaits a single exit
+    /// without handling a whole [`Monitor`].
+    pub fn next_exit<T>(&self, task: T) -> NextExit
+    where
+        T: Into<TaskId>,
+    {
+        NextExit::new(self.monitor(task))
+    }
+
+    /// Returns a snapshot of what the task `task` is doing, as of the last
+    /// time the [`crate::Watch`] was polled, or [`None`] if it does not
+    /// exist.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+    /// # async fn main() {
+    /// use std::time::Duration;
+    /// use watch::{Backoff, Builder, TaskState};
+    ///
+    /// let second = Duration::from_secs(1);
+    /// let (mut watch, handle) = Builder::new()
+    ///     .task(|| async {})
+    ///     .backoff(Backoff::new(second, second))
+    ///     .build();
+    ///
+    /// watch.tick();
+    /// let info = handle.task_info(0).unwrap();
+    /// assert_eq!(info.state(), TaskState::Delayed);
+    /// assert_eq!(
+    ///     info.next_restart_at().unwrap() - info.last_exited_at().unwrap(),
+    ///     second,
+    /// );
+    /// # }
+    /// ```
+    pub fn task_info<T>(&self, task: T) -> Option<TaskInfo>
+    where
+        T: Into<TaskId>,
+    {
+        self.snapshot.task(task.into().index())
+    }
+
+    /// Returns what is left of the restart delay of `task`, as of now, if it
+    /// is [`crate::TaskState::Delayed`], see [`TaskInfo::next_restart_at`].
+    /// A delay that is over but whose task was not polled again yet is left
+    /// with zero.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+    /// # async fn main() {
+    /// use std::time::Duration;
+    /// use watch::{Backoff, Builder};
+    ///
+    /// let second = Duration::from_secs(1);
+    /// let (mut watch, handle) = Builder::new()
+    ///     .task(|| async {})
+    ///     .backoff(Backoff::new(second, second))
+    ///     .build();
+    ///
+    /// watch.tick();
+    /// assert_eq!(handle.restart_delay(0), Some(second));
+    /// // The second instance returns right away too, and is delayed in turn.
+    /// handle.expedite(0);
+    /// watch.tick();
+    /// assert_eq!(handle.task_info(0).unwrap().instances(), 2);
+    /// # }
+    /// ```
+    pub fn restart_delay<T>(&self, task: T) -> Option<Duration>
+    where
+        T: Into<TaskId>,
+    {
+        let until = self.task_info(task)?.next_restart_at()?;
+        Some(until.saturating_duration_since(self.clock.now()))
+    }
+
+    /// Returns a snapshot of every task, in order, see
+    /// [`WatchHandle::task_info`]. Removed tasks are included, as
+    /// [`crate::TaskState::Removed`].
+    ///
+    /// ```no_run
+    /// # async fn serve() {}
+    /// # async fn flush() {}
+    /// use watch::{Builder, Task};
+    ///
+    /// let (watch, handle) = Builder::new()
+    ///     .task(Task::new(serve).name("serve"))
+    ///     .task(Task::new(flush).name("flush"))
+    ///     .build();
+    /// # drop(watch);
+    ///
+    /// for task in handle.tasks() {
+    ///     println!("{} {:?}: {:?}", task.id(), task.name(), task.state());
+    /// }
+    /// ```
+    pub fn tasks(&self) -> Vec<TaskInfo> {
+        self.snapshot.tasks()
+    }
+
+    /// Returns how often instances fail across every task, in failures per
+    /// second, as of the last snapshot. This is the sum of the
+    /// [`TaskInfo::failure_rate`] of the tasks.
+    pub fn failure_rate(&self) -> f64 {
+        self.tasks().iter().map(TaskInfo::failure_rate).sum()
+    }
+
+    /// Returns a [`Report`] of every task, to display as a table such as on a
+    /// debug endpoint. See [`WatchHandle::tasks`].
+    ///
+    /// ```no_run
+    /// # async fn serve() {}
+    /// use watch::Builder;
+    ///
+    /// let (watch, handle) = Builder::new().task(serve).build();
+    /// # drop(watch);
+    /// println!("{}", handle.report());
+    /// ```
+    pub fn report(&self) -> Report {
+        Report::new(self.tasks(), self.clock.now())
+    }
+
+    /// Returns the histograms of poll durations and ready latencies of every
+    /// task, with the `metrics` feature. See [`crate::Metrics`].
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> crate::Metrics {
+        self.metrics.collect(self.tasks())
+    }
+
+    /// Returns a snapshot of every task whose labels match `selector`, in
+    /// order. See [`WatchHandle::tasks`] and [`crate::Task::label`].
+    pub fn tasks_matching<S>(&self, selector: S) -> Vec<TaskInfo>
+    where
+        S: Into<Selector>,
+    {
+        self.snapshot.matching(&selector.into())
+    }
+}
+
+/// Removes its task from the watcher once dropped, as returned by
+/// [`WatchHandle::start_guarded`].
+#[derive(Debug)]
+pub struct TaskGuard {
+    id: TaskId,
+    owner: Arc<()>,
+    commands: Sender,
+}
+
+impl TaskGuard {
+    /// Returns the identifier of the task.
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        // The identifier may go to another task once this one is removed
+        // some other way, which the watcher tells by the owner.
+        let owner = Arc::downgrade(&self.owner);
+        let _ = self.commands.send(Command::Disown(self.id.index(), owner));
+    }
+}
+
+/// Shuts the watcher down once dropped, as returned by
+/// [`WatchHandle::guard`].
+///
+/// Dropping the guard only begins the shutdown, see
+/// [`WatchHandle::shutdown`], as [`Drop`] can't wait. Call
+/// [`WatchGuard::close`] instead to also wait for the watcher to have
+/// stopped, its last instances dropped.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use watch::Builder;
+///
+/// let (watch, handle) = Builder::new()
+///     .task(futures::future::pending::<()>)
+///     .build();
+/// let guard = handle.guard();
+/// let watch = tokio::spawn(watch);
+///
+/// guard.close().await;
+/// assert!(watch.is_finished());
+/// # }
+/// ```
+#[derive(Debug)]
+#[must_use = "the watcher shuts down as soon as the guard is dropped"]
+pub struct WatchGuard {
+    handle: WatchHandle,
+    /// Ends once the watcher stopped.
+    stopped: Events,
+}
+
+impl WatchGuard {
+    /// Returns the handle of the watcher.
+    pub fn handle(&self) -> &WatchHandle {
+        &self.handle
+    }
+
+    /// Shuts the watcher down, and waits for it to have stopped. The
+    /// [`crate::Watch`] must keep being polled meanwhile, such as by being
+    /// spawned.
+    pub async fn close(mut self) {
+        self.handle.shutdown();
+        while self.stopped.next().await.is_some() {}
+    }
+}
+
+impl Drop for WatchGuard {
+    fn drop(&mut self) {
+        self.handle.shutdown();
+    }
+}
+
+impl Sink<WatchCommand> for WatchHandle {
+    type Error = Infallible;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.commands.poll_ready(cx).map(Ok)
+    }
+
+    fn start_send(self: Pin<&mut Self>, command: WatchCommand) -> Result<(), Self::Error> {
+        self.command(match command {
+            WatchCommand::Restart(task) => Command::Restart(task.index()),
+            WatchCommand::RestartAtYield(task) => Command::RestartAtYield(task.index()),
+            WatchCommand::CancelCurrent(task) => Command::CancelCurrent(task.index()),
+            WatchCommand::Expedite(task) => Command::Expedite(task.index()),
+            WatchCommand::Remove(task) => Command::Remove(task.index()),
+            WatchCommand::Pause(task) => Command::Pause(task.index()),
+            WatchCommand::Resume(task) => Command::Resume(task.index()),
+            WatchCommand::Trigger(task) => Command::Trigger(task.index()),
+            WatchCommand::Start => Command::Start,
+            WatchCommand::Freeze(duration) => {
+                Command::Freeze(Some(clock::after(self.clock.now(), duration)))
+            }
+            WatchCommand::Thaw => Command::Freeze(None),
+            WatchCommand::Drain => Command::Drain,
+            WatchCommand::Shutdown => Command::Shutdown,
+        });
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,46 @@
+use crate::clock::Recheck;
+use std::fmt;
+use std::time::Duration;
+
+/// How many restarts of a task within [`STORM_WINDOW`] make a restart storm.
+pub(crate) const STORM_RESTARTS: usize = 5;
+
+/// The window over which restarts are counted to detect storms.
+pub(crate) const STORM_WINDOW: Duration = Duration::from_secs(10);
+
+/// Whether a watcher runs as intended, as told to the callback of
+/// [`crate::Builder::on_health_change`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    /// Every task runs as intended.
+    Healthy,
+    /// A task was retired, a [`crate::Quorum`] is lost, or a task is in a
+    /// restart storm: it was restarted 5 times within 10 seconds.
+    Degraded,
+}
+
+/// Calls a callback as the [`Health`] of a watcher changes.
+pub(crate) struct HealthMonitor {
+    pub(crate) on_change: Box<dyn FnMut(Health) + Send>,
+    pub(crate) health: Health,
+    /// When a restart storm may be over, to check again.
+    pub(crate) recheck: Recheck,
+}
+
+impl HealthMonitor {
+    pub(crate) fn new(on_change: Box<dyn FnMut(Health) + Send>) -> Self {
+        Self {
+            on_change,
+            health: Health::Healthy,
+            recheck: Recheck::default(),
+        }
+    }
+}
+
+impl fmt::Debug for HealthMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HealthMonitor")
+            .field("health", &self.health)
+            .finish()
+    }
+}
@@ -0,0 +1,326 @@
+use crate::event::Event;
+use crate::id::TaskId;
+use futures::channel::mpsc::{self as channel, UnboundedReceiver, UnboundedSender};
+use futures::StreamExt;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::future::Future;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Called with the errors of the history sinks, see
+/// [`crate::Builder::on_history_error`].
+pub(crate) type ErrorHook = Arc<dyn Fn(&io::Error) + Send + Sync>;
+
+/// An [`Event`] along with when it happened and the name of its task, as
+/// appended to a [`HistorySink`], with the `history` feature.
+///
+/// Records are timestamped with the wall clock, so that they can be put
+/// together across runs of the process. Displaying a record prints it on a
+/// single line, with the milliseconds since the Unix epoch first:
+///
+/// ```text
+/// 1718035200123 task=0 name="db" Exited { task: TaskId(0), instance: 3, reason: Failed(Transient) }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    at: SystemTime,
+    name: Option<String>,
+    event: Event,
+}
+
+impl Record {
+    pub(crate) fn new(event: Event, name: Option<String>) -> Self {
+        Self {
+            at: SystemTime::now(),
+            name,
+            event,
+        }
+    }
+
+    /// Returns when the event happened.
+    pub fn at(&self) -> SystemTime {
+        self.at
+    }
+
+    /// Returns the task the event is about.
+    pub fn task(&self) -> TaskId {
+        self.event.task()
+    }
+
+    /// Returns the name of the task, see [`crate::Task::name`].
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the event.
+    pub fn event(&self) -> Event {
+        self.event.clone()
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = self
+            .at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis());
+        write!(f, "{} task={}", millis, self.task())?;
+        if let Some(name) = &self.name {
+            write!(f, " name={:?}", name)?;
+        }
+        write!(f, " {:?}", self.event)
+    }
+}
+
+/// Where the lifecycle of the tasks is recorded, so that the restart history
+/// survives the process and can be analyzed after the supervisor itself
+/// crashed. Registered with [`crate::Builder::history`], with the `history`
+/// feature.
+///
+/// Sinks are called synchronously from the watcher with every [`Event`].
+/// Errors are ignored, so that a full disk does not bring the tasks down.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use std::io;
+/// use std::sync::{Arc, Mutex};
+/// use watch::{Builder, Event, HistorySink, Record, RestartDecision, TaskId};
+///
+/// struct Memory(Arc<Mutex<Vec<Record>>>);
+///
+/// impl HistorySink for Memory {
+///     fn append(&mut self, record: &Record) -> io::Result<()> {
+///         self.0.lock().unwrap().push(record.clone());
+///         Ok(())
+///     }
+/// }
+///
+/// let records = Arc::new(Mutex::new(Vec::new()));
+/// Builder::new()
+///     .task(|| async {})
+///     .policy(|_: &_| RestartDecision::Retire)
+///     .history(Memory(Arc::clone(&records)))
+///     .run()
+///     .await
+///     .unwrap();
+///
+/// let records = records.lock().unwrap();
+/// assert_eq!(records.last().unwrap().event(), Event::Retired { task: TaskId::from(0), instance: 1 });
+/// # }
+/// ```
+pub trait HistorySink: Send {
+    /// Appends `record` to the history.
+    fn append(&mut self, record: &Record) -> io::Result<()>;
+}
+
+/// A [`HistorySink`] appending every [`Record`] to a file, one per line, as
+/// displayed.
+///
+/// Lines are written by a thread of their own, so that the watcher never
+/// waits for the file: they are flushed as soon as the thread caught up, and
+/// all of them are once the history is dropped along with the watcher. An
+/// error writing to the file is returned by the next [`HistorySink::append`].
+///
+/// ```no_run
+/// # async fn serve() {}
+/// # async fn run() -> std::io::Result<()> {
+/// use watch::{Builder, FileHistory};
+///
+/// Builder::new()
+///     .task(serve)
+///     .history(FileHistory::open("/var/log/app/restarts.log")?)
+///     .run()
+///     .await
+///     .unwrap();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FileHistory {
+    lines: Option<Sender<String>>,
+    writer: Option<JoinHandle<()>>,
+    /// The last error of the writer, until it is returned.
+    error: Arc<Mutex<Option<io::Error>>>,
+}
+
+impl FileHistory {
+    /// Opens the file at `path` to append to it, creating it if needed.
+    pub fn open<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let (lines, received) = mpsc::channel();
+        let error = Arc::default();
+        let writer = {
+            let error = Arc::clone(&error);
+            thread::Builder::new()
+                .name("watch-history".to_string())
+                .spawn(move || write(BufWriter::new(file), received, error))?
+        };
+        Ok(Self {
+            lines: Some(lines),
+            writer: Some(writer),
+            error,
+        })
+    }
+}
+
+/// Writes the lines received to `file` until the [`FileHistory`] is dropped,
+/// flushing whenever there is none left to write.
+fn write<W>(mut file: W, lines: Receiver<String>, error: Arc<Mutex<Option<io::Error>>>)
+where
+    W: Write,
+{
+    while let Ok(line) = lines.recv() {
+        // Lines are written whole, which keeps them apart when several
+        // processes append to the same file.
+        let mut result = file.write_all(line.as_bytes());
+        while result.is_ok() {
+            match lines.try_recv() {
+                Ok(line) => result = file.write_all(line.as_bytes()),
+                Err(_) => break,
+            }
+        }
+        if let Err(failed) = result.and_then(|()| file.flush()) {
+            if let Ok(mut error) = error.lock() {
+                *error = Some(failed);
+            }
+        }
+    }
+}
+
+impl HistorySink for FileHistory {
+    fn append(&mut self, record: &Record) -> io::Result<()> {
+        if let Some(error) = self.error.lock().ok().and_then(|mut error| error.take()) {
+            return Err(error);
+        }
+        let sent = match &self.lines {
+            Some(lines) => lines.send(format!("{}\n", record)).is_ok(),
+            None => false,
+        };
+        if sent {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the history writer stopped",
+            ))
+        }
+    }
+}
+
+impl Drop for FileHistory {
+    fn drop(&mut self) {
+        // The writer writes the lines left, then stops.
+        self.lines = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// A [`HistorySink`] handing the [`Record`]s over to an asynchronous
+/// exporter in batches, to stream the history into a message broker or a
+/// database, with the `history` feature.
+///
+/// [`BatchedHistory::new`] returns the sink along with the future exporting
+/// the records, to run on any executor. Records are sent to that future
+/// without ever waiting, and batched while the previous batch is exported:
+/// each batch holds every record appended meanwhile, up to `max_batch`. Once
+/// the sink is dropped along with the watcher, the future exports the records
+/// left and resolves. An error exporting a batch is returned by the next
+/// [`HistorySink::append`], see [`crate::Builder::on_history_error`].
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use std::sync::{Arc, Mutex};
+/// use watch::{BatchedHistory, Builder, RestartDecision};
+///
+/// let batches = Arc::new(Mutex::new(Vec::new()));
+/// let exported = Arc::clone(&batches);
+/// let (history, export) = BatchedHistory::new(100, move |batch| {
+///     exported.lock().unwrap().push(batch);
+///     async { Ok(()) }
+/// });
+/// let export = tokio::spawn(export);
+///
+/// Builder::new()
+///     .task(|| async {})
+///     .policy(|_: &_| RestartDecision::Retire)
+///     .history(history)
+///     .run()
+///     .await
+///     .unwrap();
+///
+/// export.await.unwrap();
+/// assert!(!batches.lock().unwrap().is_empty());
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct BatchedHistory {
+    records: UnboundedSender<Record>,
+    /// The last error of the exporter, until it is returned.
+    error: Arc<Mutex<Option<io::Error>>>,
+}
+
+impl BatchedHistory {
+    /// Creates a sink whose records are exported by `export`, at most
+    /// `max_batch` at once, and at least one. Returns it along with the
+    /// future calling `export`.
+    pub fn new<F, T>(max_batch: usize, export: F) -> (Self, impl Future<Output = ()> + Send)
+    where
+        F: FnMut(Vec<Record>) -> T + Send + 'static,
+        T: Future<Output = io::Result<()>> + Send,
+    {
+        let (records, received) = channel::unbounded();
+        let error = Arc::default();
+        let exporter = batch(received, max_batch.max(1), export, Arc::clone(&error));
+        (Self { records, error }, exporter)
+    }
+}
+
+/// Exports the records received in batches of at most `max_batch`, until the
+/// [`BatchedHistory`] is dropped.
+async fn batch<F, T>(
+    mut records: UnboundedReceiver<Record>,
+    max_batch: usize,
+    mut export: F,
+    error: Arc<Mutex<Option<io::Error>>>,
+) where
+    F: FnMut(Vec<Record>) -> T,
+    T: Future<Output = io::Result<()>>,
+{
+    while let Some(record) = records.next().await {
+        let mut batch = vec![record];
+        while batch.len() < max_batch {
+            match records.try_recv() {
+                Ok(record) => batch.push(record),
+                Err(_) => break,
+            }
+        }
+        if let Err(failed) = export(batch).await {
+            if let Ok(mut error) = error.lock() {
+                *error = Some(failed);
+            }
+        }
+    }
+}
+
+impl HistorySink for BatchedHistory {
+    fn append(&mut self, record: &Record) -> io::Result<()> {
+        if let Some(error) = self.error.lock().ok().and_then(|mut error| error.take()) {
+            return Err(error);
+        }
+        self.records
+            .unbounded_send(record.clone())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the history exporter stopped"))
+    }
+}
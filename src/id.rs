@@ -0,0 +1,97 @@
+use std::fmt;
+
+/// Identifies a task of a watcher: its index, in the order tasks were added
+/// to the [`crate::Builder`], children and tasks added at runtime coming
+/// after. This is what [`crate::WatchHandle`] commands take, and what events
+/// and snapshots are about.
+///
+/// An identifier always refers to the same task for as long as the task is
+/// watched. Once a removed task is over, its identifier may be handed out
+/// again to a task added at runtime, see [`crate::WatchHandle::remove`].
+///
+/// Identifiers convert from and to their index, so the tasks of a builder
+/// can be referred to by position:
+///
+/// ```
+/// use watch::TaskId;
+///
+/// let id = TaskId::from(2);
+/// assert_eq!(id.index(), 2);
+/// assert_eq!(id, 2);
+/// assert_eq!(id.to_string(), "2");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TaskId(usize);
+
+impl TaskId {
+    /// Returns the index of the task.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl From<usize> for TaskId {
+    fn from(index: usize) -> Self {
+        TaskId(index)
+    }
+}
+
+impl From<TaskId> for usize {
+    fn from(id: TaskId) -> Self {
+        id.0
+    }
+}
+
+impl PartialEq<usize> for TaskId {
+    fn eq(&self, index: &usize) -> bool {
+        self.0 == *index
+    }
+}
+
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Refers to a watched task by identifier or by name, see
+/// [`crate::Task::name`], for the [`crate::WatchHandle`] commands that take
+/// either, such as [`crate::WatchHandle::adopt`].
+///
+/// ```
+/// use watch::{TaskId, TaskRef};
+///
+/// assert_eq!(TaskRef::from(2), TaskRef::Id(TaskId::from(2)));
+/// assert_eq!(TaskRef::from("feed"), TaskRef::Name("feed".to_owned()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TaskRef {
+    /// The task with this identifier.
+    Id(TaskId),
+    /// The watched task with this name. If several have it, the first one.
+    Name(String),
+}
+
+impl From<TaskId> for TaskRef {
+    fn from(id: TaskId) -> Self {
+        TaskRef::Id(id)
+    }
+}
+
+impl From<usize> for TaskRef {
+    fn from(index: usize) -> Self {
+        TaskRef::Id(TaskId::from(index))
+    }
+}
+
+impl From<&str> for TaskRef {
+    fn from(name: &str) -> Self {
+        TaskRef::Name(name.to_owned())
+    }
+}
+
+impl From<String> for TaskRef {
+    fn from(name: String) -> Self {
+        TaskRef::Name(name)
+    }
+}
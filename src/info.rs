@@ -0,0 +1,273 @@
+use crate::exit::{ExitCounts, ExitReason};
+use crate::id::TaskId;
+use crate::labels::{Labels, Selector};
+use crate::task::Metadata;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Where a task is in its lifecycle, as seen through a [`TaskInfo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    /// An instance was spawned, but is not ready yet, see
+    /// [`crate::Task::signals_readiness`].
+    Starting,
+    /// An instance is running and ready.
+    Running,
+    /// The task waits for the delay decided by its policy before being
+    /// spawned again, see [`TaskInfo::next_restart_at`].
+    Delayed,
+    /// The task waits for other instances to be ready before being spawned,
+    /// see [`crate::Builder::max_concurrent_starts`], or for the tasks of
+    /// earlier phases to be up, see [`crate::Task::phase`].
+    Queued,
+    /// The task was not spawned yet, was retired by its policy, or the
+    /// watcher is stopping.
+    Stopped,
+    /// The task was paused through [`crate::WatchHandle::pause`].
+    Paused,
+    /// The task was removed through [`crate::WatchHandle::remove`].
+    Removed,
+}
+
+/// A snapshot of what a watched task is doing, as returned by
+/// [`crate::WatchHandle::task_info`] and [`crate::WatchHandle::tasks`].
+///
+/// Snapshots are taken every time the [`crate::Watch`] is polled. Instants
+/// follow the [`crate::Clock`] of the watcher, see [`crate::Builder::clock`].
+/// With the default [`crate::TokioClock`], compare them to
+/// `tokio::time::Instant::now().into_std()` for them to hold while time is
+/// paused.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskInfo {
+    id: TaskId,
+    name: Option<String>,
+    labels: Labels,
+    pub(crate) state: TaskState,
+    pub(crate) instances: u64,
+    pub(crate) last_exit: Option<ExitReason>,
+    pub(crate) exits: ExitCounts,
+    pub(crate) started_at: Option<Instant>,
+    pub(crate) last_exited_at: Option<Instant>,
+    pub(crate) next_restart_at: Option<Instant>,
+    pub(crate) failure_rate: f64,
+}
+
+impl TaskInfo {
+    fn new(id: usize, metadata: &Metadata) -> Self {
+        Self {
+            id: TaskId::from(id),
+            name: metadata.name.clone(),
+            labels: metadata.labels.clone(),
+            state: TaskState::Stopped,
+            instances: 0,
+            last_exit: None,
+            exits: ExitCounts::default(),
+            started_at: None,
+            last_exited_at: None,
+            next_restart_at: None,
+            failure_rate: 0.0,
+        }
+    }
+
+    /// Returns the identifier of the task. This is what
+    /// [`crate::WatchHandle`] commands take.
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Returns the name of the task, see [`crate::Task::name`].
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the labels of the task, see [`crate::Task::label`].
+    pub fn labels(&self) -> &Labels {
+        &self.labels
+    }
+
+    /// Returns where the task is in its lifecycle.
+    pub fn state(&self) -> TaskState {
+        self.state
+    }
+
+    /// Returns how many instances of the task were spawned.
+    pub fn instances(&self) -> u64 {
+        self.instances
+    }
+
+    /// Returns why the last instance exited, if any did.
+    /// Instances dropped by the watcher do not count.
+    pub fn last_exit(&self) -> Option<ExitReason> {
+        self.last_exit.clone()
+    }
+
+    /// Returns how many instances exited, by reason. As with
+    /// [`TaskInfo::last_exit`], instances dropped by the watcher do not
+    /// count.
+    pub fn exits(&self) -> ExitCounts {
+        self.exits
+    }
+
+    /// Returns when the running instance was spawned, if one is running.
+    pub fn started_at(&self) -> Option<Instant> {
+        self.started_at
+    }
+
+    /// Returns when the last instance exited, if any did. Along
+    /// with [`TaskState::Delayed`], tells for how long the task has been down.
+    pub fn last_exited_at(&self) -> Option<Instant> {
+        self.last_exited_at
+    }
+
+    /// Returns when the task will be spawned again, if it is
+    /// [`TaskState::Delayed`].
+    pub fn next_restart_at(&self) -> Option<Instant> {
+        self.next_restart_at
+    }
+
+    /// Returns how often instances of the task fail, in failures per second,
+    /// weighted exponentially over the window set with
+    /// [`crate::Builder::failure_window`]. Each failure adds one over the
+    /// window, in seconds, to the rate, which then decays by a factor of `e`
+    /// every window. This makes for inputs to autoscaling and alerting.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+    /// # async fn main() {
+    /// use std::time::Duration;
+    /// use watch::testing::FailAfter;
+    /// use watch::{Backoff, Builder};
+    ///
+    /// let minute = Duration::from_secs(60);
+    /// let (mut watch, handle) = Builder::new()
+    ///     .task(FailAfter(0))
+    ///     .backoff(Backoff::new(minute, minute))
+    ///     .failure_window(minute)
+    ///     .build();
+    ///
+    /// watch.tick();
+    /// let rate = handle.task_info(0).unwrap().failure_rate();
+    /// assert!((rate - 1.0 / 60.0).abs() < 1e-9);
+    /// # }
+    /// ```
+    pub fn failure_rate(&self) -> f64 {
+        self.failure_rate
+    }
+}
+
+/// The latest [`TaskInfo`] of every task of a watcher, indexed by task and
+/// shared with its handles.
+#[derive(Debug, Clone)]
+pub(crate) struct Snapshot {
+    tasks: Arc<Mutex<Vec<TaskInfo>>>,
+    /// The identifiers of removed tasks, to hand out again.
+    free: Arc<Mutex<Vec<usize>>>,
+}
+
+impl Snapshot {
+    pub(crate) fn new(metadata: &[Metadata]) -> Self {
+        Self {
+            tasks: Arc::new(Mutex::new(
+                metadata
+                    .iter()
+                    .enumerate()
+                    .map(|(id, metadata)| TaskInfo::new(id, metadata))
+                    .collect(),
+            )),
+            free: Arc::default(),
+        }
+    }
+
+    /// Returns the latest snapshot of the task `task`, if it exists.
+    pub(crate) fn task(&self, task: usize) -> Option<TaskInfo> {
+        self.tasks.lock().ok()?.get(task).cloned()
+    }
+
+    /// Calls `describe` with the name and labels of the task `task`, which
+    /// are known as soon as it is added, or returns [`None`] if it does not
+    /// exist.
+    pub(crate) fn describe<F, R>(&self, task: usize, describe: F) -> Option<R>
+    where
+        F: FnOnce(Option<&str>, &Labels) -> R,
+    {
+        let tasks = self.tasks.lock().ok()?;
+        let info = tasks.get(task)?;
+        Some(describe(info.name.as_deref(), &info.labels))
+    }
+
+    /// Returns the latest snapshot of every task, in order.
+    pub(crate) fn tasks(&self) -> Vec<TaskInfo> {
+        match self.tasks.lock() {
+            Ok(tasks) => tasks.clone(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Returns the latest snapshot of every task whose labels match
+    /// `selector`, in order.
+    pub(crate) fn matching(&self, selector: &Selector) -> Vec<TaskInfo> {
+        match self.tasks.lock() {
+            Ok(tasks) => tasks
+                .iter()
+                .filter(|task| selector.matches(&task.labels))
+                .cloned()
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Adds the snapshot of a new task, calling `added` with its identifier
+    /// while no other task can be added. The identifier of a released task is
+    /// handed out first, if any.
+    pub(crate) fn push<F>(&self, metadata: &Metadata, added: F) -> Option<usize>
+    where
+        F: FnOnce(usize),
+    {
+        self.push_all(std::slice::from_ref(metadata), |ids| added(ids[0]))
+            .map(|ids| ids[0])
+    }
+
+    /// Adds the snapshots of several tasks as with [`Snapshot::push`], calling
+    /// `added` with all of their identifiers at once, in order.
+    pub(crate) fn push_all<F>(&self, metadata: &[Metadata], added: F) -> Option<Vec<usize>>
+    where
+        F: FnOnce(&[usize]),
+    {
+        let mut tasks = self.tasks.lock().ok()?;
+        let mut free = self.free.lock().ok()?;
+        let ids: Vec<_> = metadata
+            .iter()
+            .map(|metadata| match free.pop() {
+                Some(id) => {
+                    tasks[id] = TaskInfo::new(id, metadata);
+                    id
+                }
+                None => {
+                    let id = tasks.len();
+                    tasks.push(TaskInfo::new(id, metadata));
+                    id
+                }
+            })
+            .collect();
+        drop(free);
+        added(&ids);
+        Some(ids)
+    }
+
+    /// Lets the identifier of the removed task `task` be handed out again.
+    pub(crate) fn release(&self, task: usize) {
+        if let Ok(mut free) = self.free.lock() {
+            free.push(task);
+        }
+    }
+
+    /// Updates the snapshots of every task in place.
+    pub(crate) fn update<F>(&self, update: F)
+    where
+        F: FnOnce(&mut [TaskInfo]),
+    {
+        if let Ok(mut tasks) = self.tasks.lock() {
+            update(&mut tasks);
+        }
+    }
+}
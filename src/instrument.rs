@@ -0,0 +1,44 @@
+use crate::exit::ExitReason;
+use crate::id::TaskId;
+use crate::layer::Instance;
+use futures::FutureExt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Called around each poll of an instance, see [`crate::Builder::on_poll`].
+pub(crate) type PollHook = Arc<dyn Fn(TaskId, Duration, Poll<&ExitReason>) + Send + Sync>;
+
+/// Calls `hook` after each poll of `instance`, with how long the poll took.
+pub(crate) fn instrumented(instance: Instance, task: TaskId, hook: PollHook) -> Instance {
+    Instrumented {
+        instance,
+        task,
+        hook,
+    }
+    .boxed()
+}
+
+struct Instrumented {
+    instance: Instance,
+    task: TaskId,
+    hook: PollHook,
+}
+
+impl Future for Instrumented {
+    type Output = ExitReason;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let start = Instant::now();
+        let poll = self.instance.poll_unpin(cx);
+        let elapsed = start.elapsed();
+        let result = match &poll {
+            Poll::Ready(reason) => Poll::Ready(reason),
+            Poll::Pending => Poll::Pending,
+        };
+        (self.hook)(self.task, elapsed, result);
+        poll
+    }
+}
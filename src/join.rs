@@ -0,0 +1,109 @@
+use crate::exit::ExitReason;
+use crate::panic::Panic;
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::future::{BoxFuture, FutureExt};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::task::{JoinHandle, JoinSet};
+
+/// Returns an instance watching the task behind `handle`, aborting it once
+/// dropped.
+pub(crate) fn instance<T>(handle: JoinHandle<T>) -> BoxFuture<'static, ExitReason>
+where
+    T: Send + 'static,
+{
+    Joined { handle }.boxed()
+}
+
+/// A task spawned elsewhere, returning once it finished.
+struct Joined<T> {
+    handle: JoinHandle<T>,
+}
+
+impl<T> Future for Joined<T> {
+    type Output = ExitReason;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.handle.poll_unpin(cx).map(|joined| match joined {
+            Ok(_) => ExitReason::Completed,
+            Err(error) if error.is_panic() => {
+                ExitReason::Panicked(Panic::new(error.into_panic(), None))
+            }
+            Err(_) => ExitReason::Cancelled,
+        })
+    }
+}
+
+impl<T> Drop for Joined<T> {
+    fn drop(&mut self) {
+        // Stopping the instance stops the task, which would otherwise keep
+        // running unsupervised next to its replacement.
+        self.handle.abort();
+    }
+}
+
+/// Spawns instances onto a join set, keeping track of the ones dropped before
+/// they returned, as when the runtime shuts down: the set then yields an
+/// error that does not tell which instance it was.
+#[derive(Debug)]
+pub(crate) struct Unjoined {
+    sender: UnboundedSender<(usize, u64)>,
+    dropped: UnboundedReceiver<(usize, u64)>,
+}
+
+impl Unjoined {
+    pub(crate) fn new() -> Self {
+        let (sender, dropped) = mpsc::unbounded();
+        Self { sender, dropped }
+    }
+
+    /// Spawns the instance `instance` of the task `id` onto `join_set`.
+    pub(crate) fn spawn<T>(
+        &self,
+        join_set: &mut JoinSet<T::Output>,
+        future: T,
+        id: usize,
+        instance: u64,
+    ) where
+        T: Future + Send + 'static,
+        T::Output: Send + 'static,
+    {
+        let mut guard = Guard {
+            instance: Some((id, instance)),
+            dropped: self.sender.clone(),
+        };
+        join_set.spawn(async move {
+            let output = future.await;
+            guard.returned();
+            output
+        });
+    }
+
+    /// Returns the task and number of an instance that was dropped, if any
+    /// is left.
+    pub(crate) fn next(&mut self) -> Option<(usize, u64)> {
+        self.dropped.try_recv().ok()
+    }
+}
+
+/// Sends its instance through `dropped` once dropped, unless it returned.
+struct Guard {
+    instance: Option<(usize, u64)>,
+    dropped: UnboundedSender<(usize, u64)>,
+}
+
+impl Guard {
+    fn returned(&mut self) {
+        self.instance = None;
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if let Some(instance) = self.instance.take() {
+            // The watcher stopped if the receiver was dropped.
+            let _ = self.dropped.unbounded_send(instance);
+        }
+    }
+}
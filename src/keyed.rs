@@ -0,0 +1,158 @@
+use crate::id::TaskId;
+use crate::task::Task;
+use futures::future::{BoxFuture, FutureExt};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A shard of a key space, given to the instances of the workers started
+/// with [`crate::Builder::keyed_shards`]. Tells which keys the worker owns,
+/// as of the current shard count.
+///
+/// Keys are spread with a consistent hash: when the shard count changes
+/// through [`crate::WatchHandle::reshard`], keys only move to the shards
+/// being added, or away from the shards being removed. The other shards keep
+/// running, and keep every key they owned that was not handed to a new shard.
+///
+/// ```
+/// use watch::{Builder, Shard};
+///
+/// async fn consume(shard: Shard) {
+///     for partition in 0..64_u32 {
+///         if shard.owns(&partition) {
+///             // Consumes the partition.
+///         }
+///     }
+/// }
+///
+/// let (watch, handle) = Builder::new().keyed_shards(4, consume).build();
+/// // Starts shards 4 to 7, leaving shards 0 to 3 running.
+/// handle.reshard(8);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Shard {
+    index: usize,
+    count: Arc<AtomicUsize>,
+}
+
+impl Shard {
+    /// Returns the index of this shard, from `0`.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns how many shards the key space is spread over right now.
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Acquire)
+    }
+
+    /// Returns whether `key` belongs to this shard, as of the current shard
+    /// count.
+    pub fn owns<K>(&self, key: &K) -> bool
+    where
+        K: Hash + ?Sized,
+    {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        jump(hasher.finish(), self.count()) == self.index
+    }
+}
+
+/// Returns the bucket of `key` among `buckets`, with the jump consistent hash
+/// of Lamping and Veach: going from `n` to `n + 1` buckets only moves keys to
+/// the new bucket.
+fn jump(mut key: u64, buckets: usize) -> usize {
+    let (mut bucket, mut next) = (0_i64, 0_i64);
+    while next < buckets as i64 {
+        bucket = next;
+        key = key.wrapping_mul(2_862_933_555_777_941_757).wrapping_add(1);
+        next = ((bucket + 1) as f64 * ((1_u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    bucket as usize
+}
+
+/// Makes the instance of a shard.
+type Worker = dyn Fn(Shard) -> BoxFuture<'static, ()> + Send + Sync;
+
+/// The workers of a key space spread over shards, see
+/// [`crate::Builder::keyed_shards`].
+pub(crate) struct KeyedShards {
+    worker: Arc<Worker>,
+    count: Arc<AtomicUsize>,
+    /// The tasks of the shards, by index.
+    tasks: Mutex<Vec<TaskId>>,
+}
+
+impl KeyedShards {
+    pub(crate) fn new<F, T>(count: usize, worker: F) -> Self
+    where
+        F: Fn(Shard) -> T + Send + Sync + 'static,
+        T: Future + Send + 'static,
+    {
+        Self {
+            worker: Arc::new(move |shard| worker(shard).map(drop).boxed()),
+            count: Arc::new(AtomicUsize::new(count)),
+            tasks: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the task of every shard, to add to the watcher.
+    pub(crate) fn tasks(&self) -> Vec<Task> {
+        (0..self.count.load(Ordering::Acquire))
+            .map(|index| self.task(index))
+            .collect()
+    }
+
+    /// Records the identifiers the tasks of the shards were given, in order.
+    pub(crate) fn added(&self, tasks: Vec<TaskId>) {
+        if let Ok(mut shards) = self.tasks.lock() {
+            *shards = tasks;
+        }
+    }
+
+    /// Spreads the key space over `count` shards, adding the missing shards
+    /// with `add` and removing the extra ones with `remove`.
+    pub(crate) fn reshard<A, R>(&self, count: usize, mut add: A, mut remove: R)
+    where
+        A: FnMut(Task) -> Option<TaskId>,
+        R: FnMut(TaskId),
+    {
+        let mut tasks = match self.tasks.lock() {
+            Ok(tasks) => tasks,
+            Err(_) => return,
+        };
+        // Shards going away hand their keys over first, and new shards only
+        // take keys once started.
+        if count < tasks.len() {
+            self.count.store(count, Ordering::Release);
+            tasks.drain(count..).for_each(&mut remove);
+        }
+        while tasks.len() < count {
+            match add(self.task(tasks.len())) {
+                Some(task) => tasks.push(task),
+                None => break,
+            }
+        }
+        self.count.store(tasks.len(), Ordering::Release);
+    }
+
+    fn task(&self, index: usize) -> Task {
+        let worker = Arc::clone(&self.worker);
+        let shard = Shard {
+            index,
+            count: Arc::clone(&self.count),
+        };
+        Task::new(move || worker(shard.clone())).name(format!("shard-{}", index))
+    }
+}
+
+impl fmt::Debug for KeyedShards {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyedShards")
+            .field("count", &self.count)
+            .finish_non_exhaustive()
+    }
+}
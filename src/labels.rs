@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+
+/// The key/value labels of a task, see [`crate::Task::label`].
+pub type Labels = BTreeMap<String, String>;
+
+/// Selects tasks by their [`Labels`].
+///
+/// A selector is a set of requirements that tasks must all meet. It can be
+/// built requirement by requirement, or parsed from a comma-separated list
+/// where `key=value` requires the label `key` to be `value` and `key` alone
+/// requires the label to be present:
+///
+/// ```
+/// use watch::{Labels, Selector};
+///
+/// let mut labels = Labels::new();
+/// labels.insert("group".to_string(), "db".to_string());
+/// labels.insert("shard".to_string(), "3".to_string());
+///
+/// assert!(Selector::from("group=db,shard").matches(&labels));
+/// assert!(Selector::new().label("group", "db").matches(&labels));
+/// assert!(!Selector::from("group=cache").matches(&labels));
+/// ```
+///
+/// The empty selector matches every task.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selector {
+    requirements: Vec<(String, Option<String>)>,
+}
+
+impl Selector {
+    /// Creates a selector matching every task.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requires the label `key` to be `value`.
+    pub fn label<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.requirements.push((key.into(), Some(value.into())));
+        self
+    }
+
+    /// Requires the label `key` to be present, whatever its value.
+    pub fn has<K>(mut self, key: K) -> Self
+    where
+        K: Into<String>,
+    {
+        self.requirements.push((key.into(), None));
+        self
+    }
+
+    /// Returns whether `labels` meet every requirement.
+    pub fn matches(&self, labels: &Labels) -> bool {
+        self.requirements
+            .iter()
+            .all(|(key, value)| match (labels.get(key), value) {
+                (Some(actual), Some(value)) => actual == value,
+                (Some(_), None) => true,
+                (None, _) => false,
+            })
+    }
+}
+
+impl From<&str> for Selector {
+    fn from(selector: &str) -> Self {
+        selector
+            .split(',')
+            .map(str::trim)
+            .filter(|requirement| !requirement.is_empty())
+            .fold(Selector::new(), |selector, requirement| {
+                match requirement.split_once('=') {
+                    Some((key, value)) => selector.label(key.trim(), value.trim()),
+                    None => selector.has(requirement),
+                }
+            })
+    }
+}
+
+impl From<String> for Selector {
+    fn from(selector: String) -> Self {
+        Selector::from(selector.as_str())
+    }
+}
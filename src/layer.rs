@@ -0,0 +1,112 @@
+use crate::context::TaskContext;
+use crate::exit::ExitReason;
+use futures::future::{self, BoxFuture, Either, FutureExt};
+use std::time::Duration;
+
+/// A freshly created instance of a task, as handed to a [`Layer`]. It returns
+/// why it exited.
+pub type Instance = BoxFuture<'static, ExitReason>;
+
+/// Wraps every freshly created instance of a task, so that cross-cutting
+/// behaviors such as timeouts, tracing spans or metrics live outside of task
+/// bodies. Layers are added to every task with [`crate::Builder::layer`], or
+/// to a single one with [`crate::Task::layer`].
+///
+/// Closures taking the [`Instance`] along with its [`TaskContext`] are
+/// layers.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use futures::FutureExt;
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use std::sync::Arc;
+/// use watch::{Builder, Instance, RestartDecision, TaskContext};
+///
+/// let instances = Arc::new(AtomicU64::new(0));
+/// let counter = Arc::clone(&instances);
+/// Builder::new()
+///     .task(|| async {})
+///     .layer(move |instance: Instance, _: &TaskContext| {
+///         counter.fetch_add(1, Ordering::SeqCst);
+///         instance
+///     })
+///     .policy(|_: &_| RestartDecision::Retire)
+///     .run()
+///     .await
+///     .unwrap();
+///
+/// assert_eq!(instances.load(Ordering::SeqCst), 1);
+/// # }
+/// ```
+pub trait Layer: Send + Sync {
+    /// Wraps `instance`, created along with `context`.
+    fn layer(&self, instance: Instance, context: &TaskContext) -> Instance;
+}
+
+impl<F> Layer for F
+where
+    F: Fn(Instance, &TaskContext) -> Instance + Send + Sync,
+{
+    fn layer(&self, instance: Instance, context: &TaskContext) -> Instance {
+        self(instance, context)
+    }
+}
+
+/// A [`Layer`] dropping instances that run for longer than a timeout, so that
+/// they exit with [`ExitReason::TimedOut`] and go through the
+/// [`crate::RestartPolicy`] of their task, without each task embedding
+/// [`tokio::time::timeout`] itself. The timeout starts as the instance is
+/// spawned, and follows the [`crate::Builder::clock`].
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use std::time::Duration;
+/// use watch::{
+///     Builder, ExitReason, RestartContext, RestartDecision, TimeoutLayer, WatchError,
+/// };
+///
+/// tokio::time::pause();
+/// let error = Builder::new()
+///     .task(|| futures::future::pending::<()>())
+///     .layer(TimeoutLayer::new(Duration::from_secs(30)))
+///     .policy(|context: &RestartContext| match context.reason() {
+///         ExitReason::TimedOut => RestartDecision::Escalate,
+///         _ => RestartDecision::Retire,
+///     })
+///     .run()
+///     .await
+///     .unwrap_err();
+///
+/// match error {
+///     WatchError::Escalated(escalation) => {
+///         assert_eq!(escalation.reason(), ExitReason::TimedOut);
+///     }
+///     _ => unreachable!(),
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct TimeoutLayer {
+    timeout: Duration,
+}
+
+impl TimeoutLayer {
+    /// Creates a [`TimeoutLayer`] allowing instances to run for `timeout`.
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl Layer for TimeoutLayer {
+    fn layer(&self, instance: Instance, context: &TaskContext) -> Instance {
+        let deadline = context.clock().sleep(self.timeout);
+        future::select(instance, deadline)
+            .map(|either| match either {
+                Either::Left((reason, _)) => reason,
+                Either::Right(_) => ExitReason::TimedOut,
+            })
+            .boxed()
+    }
+}
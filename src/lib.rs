@@ -1,29 +1,119 @@
-use futures::future::select_all;
-use std::collections::HashMap;
-use std::future::Future;
-use std::pin::Pin;
-
-struct Indexed<T> {
-    index: usize,
-    inner: T,
-}
+mod alert;
+mod array;
+mod backoff;
+mod backpressure;
+mod budget;
+mod builder;
+mod cancellation;
+mod changes;
+mod clock;
+mod context;
+mod debounce;
+mod error;
+mod event;
+mod exit;
+mod factory;
+mod gate;
+mod handle;
+mod health;
+#[cfg(feature = "history")]
+mod history;
+mod id;
+mod info;
+#[cfg(feature = "instrument")]
+mod instrument;
+#[cfg(feature = "join-set")]
+mod join;
+mod keyed;
+mod labels;
+mod layer;
+#[cfg(feature = "log")]
+mod logging;
+mod mailbox;
+mod map;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod monitor;
+mod observer;
+mod pacing;
+mod panic;
+mod policy;
+mod quorum;
+mod rate;
+mod report;
+mod respawn;
+#[cfg(feature = "service")]
+mod service;
+mod set;
+mod shards;
+mod shutdown;
+#[cfg(all(unix, feature = "signals"))]
+mod signals;
+mod state;
+mod strategy;
+mod summary;
+mod task;
+mod template;
+pub mod testing;
+mod watcher;
+mod window;
 
-impl<T> Future for Indexed<T>
-where
-    T: Future + Unpin,
-{
-    type Output = T::Output;
+pub use alert::{Alert, AlertKind, Thresholds};
+pub use array::WatchArray;
+pub use backoff::Backoff;
+pub use backpressure::Backpressure;
+pub use budget::RestartBudget;
+pub use builder::Builder;
+pub use cancellation::Cancellation;
+pub use changes::{Change, Changes};
+pub use clock::{Clock, TokioClock};
+pub use context::TaskContext;
+pub use debounce::Debounce;
+pub use error::{CallError, Escalation, WatchError};
+pub use event::{Event, EventFilter, EventKind, Events};
+pub use exit::{ExitCounts, ExitReason, FailureKind};
+pub use factory::ArcFactory;
+pub use gate::{RestartGate, RestartHold};
+pub use handle::{TaskGuard, WatchCommand, WatchGuard, WatchHandle};
+pub use health::Health;
+#[cfg(feature = "history")]
+pub use history::{BatchedHistory, FileHistory, HistorySink, Record};
+pub use id::{TaskId, TaskRef};
+pub use info::{TaskInfo, TaskState};
+pub use keyed::Shard;
+pub use labels::{Labels, Selector};
+pub use layer::{Instance, Layer, TimeoutLayer};
+pub use mailbox::{Call, Mailbox};
+pub use map::WatchMap;
+#[cfg(feature = "metrics")]
+pub use metrics::{Histogram, Metrics, TaskMetrics};
+pub use monitor::{Exit, Monitor, NextExit};
+pub use observer::WatchObserver;
+pub use pacing::RespawnPacing;
+pub use panic::{Panic, PanicBehavior};
+#[cfg(feature = "backoff")]
+pub use policy::FromBackoff;
+pub use policy::{RestartContext, RestartDecision, RestartPolicy, RestartTrigger};
+pub use quorum::Quorum;
+pub use report::Report;
+pub use respawn::{respawn, Respawner};
+#[cfg(feature = "service")]
+pub use service::ServiceExit;
+pub use set::TaskSet;
+pub use shutdown::ShutdownSignal;
+pub use state::{Handoff, StateHandle};
+pub use strategy::{OneForAll, OneForOne, RestForOne, RestartOrder, SupervisionStrategy};
+pub use summary::{ShutdownReport, Summary, TaskSummary};
+pub use task::Task;
+#[cfg(all(unix, feature = "signals"))]
+pub use tokio::signal::unix::SignalKind;
+pub use watcher::{Tick, Watch};
+pub use window::RestartWindow;
 
-    fn poll(
-        self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<Self::Output> {
-        Pin::new(&mut self.get_mut().inner).poll(cx)
-    }
-}
+use std::future::Future;
 
 /// Spawns and watches all the futures returned by the given [`Future`]
-/// factories. Any [`Future`] that gets ready is dropped and a new version of it
+/// factories. Any [`Future`] that completes is dropped and a new version of it
 /// is spawned.
 ///
 /// A factory is a closure that returns a [`Future`]. It is called every time
@@ -32,65 +122,22 @@ where
 ///
 /// There will never be more than one instance of a task running.
 ///
+/// Use a [`Builder`] to configure how tasks are respawned.
+///
 /// # Return
 ///
 /// Returns a [`Future`] that never returns. Every time it is polled, it
 /// internally polls the `[Future]`'s it is watching.
 ///
-/// # Panic
+/// # Errors
 ///
-/// This function panics if `factories` is empty.
-pub async fn watch<F, T>(factories: F)
+/// Returns [`WatchError::EmptySet`] if `factories` is empty.
+pub async fn watch<F, T>(factories: F) -> Result<(), WatchError>
 where
     F: IntoIterator,
-    F::Item: Fn() -> T,
+    F::Item: Fn() -> T + Send + Sync + 'static,
 
-    T: Future + Unpin,
+    T: Future + Send + 'static,
 {
-    let factories: Vec<F::Item> = factories.into_iter().collect();
-
-    let mut indexed_tasks: Vec<Indexed<T>> = factories
-        .iter()
-        .map(|f| f())
-        .enumerate()
-        .map(|(index, inner)| Indexed { index, inner })
-        .collect();
-
-    let mut factory_index: HashMap<usize, F::Item> = factories.into_iter().enumerate().collect();
-
-    loop {
-        let (_, stopped_index, other_tasks) = select_all(indexed_tasks).await;
-
-        let mut from_to: HashMap<usize, usize> = other_tasks
-            .iter()
-            .map(|Indexed { index, .. }| index)
-            .enumerate()
-            .map(|(to, from)| (*from, to))
-            .collect();
-        from_to.insert(stopped_index, other_tasks.len());
-
-        let mut tasks: Vec<T> = other_tasks
-            .into_iter()
-            .map(|Indexed { inner, .. }| inner)
-            .collect();
-
-        let respawned_task = factory_index
-            .get(&stopped_index)
-            .expect("invalid index state")();
-        tasks.push(respawned_task);
-
-        factory_index = factory_index
-            .into_iter()
-            .map(|(from, factory)| {
-                let to = *from_to.get(&from).expect("invalid index state");
-                (to, factory)
-            })
-            .collect();
-
-        indexed_tasks = tasks
-            .into_iter()
-            .enumerate()
-            .map(|(index, inner)| Indexed { index, inner })
-            .collect();
-    }
+    Builder::new().tasks(factories).run().await.map(drop)
 }
@@ -0,0 +1,180 @@
+use crate::clock::Clock;
+use crate::event::Event;
+use crate::exit::ExitReason;
+use crate::health::{STORM_RESTARTS, STORM_WINDOW};
+use crate::info::Snapshot;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Emits `log` records about restarts, retirements and restart storms, with
+/// the `log` feature.
+pub(crate) struct Logger {
+    snapshot: Snapshot,
+    clock: Arc<dyn Clock>,
+    tasks: Vec<TaskLog>,
+}
+
+/// What the [`Logger`] remembers about a task.
+#[derive(Default)]
+struct TaskLog {
+    last_exit: Option<ExitReason>,
+    /// When the task was restarted within the last [`STORM_WINDOW`].
+    restarts: VecDeque<Instant>,
+}
+
+impl Logger {
+    pub(crate) fn new(snapshot: Snapshot, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            snapshot,
+            clock,
+            tasks: Vec::new(),
+        }
+    }
+
+    pub(crate) fn log(&mut self, event: &Event) {
+        let task = event.task().index();
+        let name = self
+            .snapshot
+            .describe(task, |name, _| name.map(str::to_owned))
+            .flatten();
+        let name = TaskName(task, name.as_deref());
+        // Children started at runtime come after the tasks known so far.
+        if task >= self.tasks.len() {
+            self.tasks.resize_with(task + 1, TaskLog::default);
+        }
+        let log = &mut self.tasks[task];
+
+        match *event {
+            Event::Exited { ref reason, .. } => log.last_exit = Some(reason.clone()),
+            Event::RestartScheduled {
+                instance, delay, ..
+            } => {
+                match &log.last_exit {
+                    Some(ExitReason::Failed(kind)) => log::warn!(
+                        "{} failed ({:?}) on attempt {}, restarting in {:?}",
+                        name,
+                        kind,
+                        instance,
+                        delay
+                    ),
+                    Some(ExitReason::Panicked(panic)) => log::warn!(
+                        "{} panicked ({}) on attempt {}, restarting in {:?}",
+                        name,
+                        panic.message(),
+                        instance,
+                        delay
+                    ),
+                    Some(ExitReason::TimedOut) => log::warn!(
+                        "{} timed out on attempt {}, restarting in {:?}",
+                        name,
+                        instance,
+                        delay
+                    ),
+                    _ => log::info!(
+                        "{} exited on attempt {}, restarting in {:?}",
+                        name,
+                        instance,
+                        delay
+                    ),
+                }
+
+                let now = self.clock.now();
+                while matches!(log.restarts.front(), Some(at) if now - *at >= STORM_WINDOW) {
+                    log.restarts.pop_front();
+                }
+                log.restarts.push_back(now);
+                if log.restarts.len() >= STORM_RESTARTS {
+                    log::warn!(
+                        "{} is in a restart storm: restarted {} times within {:?}, {} attempts so far",
+                        name,
+                        log.restarts.len(),
+                        STORM_WINDOW,
+                        instance
+                    );
+                    // Only warn again once as many restarts happened.
+                    log.restarts.clear();
+                }
+            }
+            Event::StartupImmediateExit {
+                instance, exits, ..
+            } => log::warn!(
+                "{} exited on its first poll on attempt {}, {} times in a row before coming up",
+                name,
+                instance,
+                exits
+            ),
+            Event::RestartAwaiting { instance, .. } => log::info!(
+                "{} exited on attempt {}, restarting once triggered",
+                name,
+                instance
+            ),
+            Event::Retired { instance, .. } => log::warn!(
+                "{} retired after {} attempts, last exit: {:?}",
+                name,
+                instance,
+                log.last_exit
+            ),
+            Event::Escalated { instance, .. } => log::warn!(
+                "{} escalated after {} attempts, stopping every task",
+                name,
+                instance
+            ),
+            Event::QuorumLost {
+                quorum, healthy, ..
+            } => log::warn!(
+                "quorum {} lost as {} went down, {} tasks still healthy",
+                quorum,
+                name,
+                healthy
+            ),
+            Event::QuorumRegained {
+                quorum, healthy, ..
+            } => log::info!(
+                "quorum {} regained as {} came up, {} tasks healthy",
+                quorum,
+                name,
+                healthy
+            ),
+            Event::CommandDropped { ref command, .. } => {
+                log::warn!("dropped {:?} for {}, queued too long", command, name)
+            }
+            Event::Flapping {
+                instance,
+                cool_down,
+                ..
+            } => log::warn!(
+                "{} is flapping on attempt {}, cooling down for {:?}",
+                name,
+                instance,
+                cool_down
+            ),
+            Event::FactoryPanicked {
+                instance,
+                ref panic,
+                ..
+            } => log::error!(
+                "the factory of {} panicked ({}) on attempt {}",
+                name,
+                panic.message(),
+                instance
+            ),
+            // The identifier may go to a new task.
+            Event::Removed { .. } => *log = TaskLog::default(),
+            Event::Started { .. } | Event::Ready { .. } | Event::Paused { .. } => {}
+        }
+    }
+}
+
+/// Displays a task by name if it has one, by index otherwise.
+struct TaskName<'a>(usize, Option<&'a str>);
+
+impl fmt::Display for TaskName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.1 {
+            Some(name) => write!(f, "task {:?}", name),
+            None => write!(f, "task {}", self.0),
+        }
+    }
+}
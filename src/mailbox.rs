@@ -0,0 +1,181 @@
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::channel::oneshot;
+use futures::lock::Mutex;
+use futures::stream::StreamExt;
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, PoisonError};
+
+/// The sender of the mailbox of a task, whatever the type of its messages.
+pub(crate) type Sender = Arc<dyn Any + Send + Sync>;
+
+/// The messages sent to a task through [`crate::WatchHandle::send`], as
+/// handed to the factories of tasks created with
+/// [`crate::Task::with_mailbox`].
+///
+/// The mailbox lives for as long as the task: every new instance receives
+/// the messages the previous ones did not, so messages sent while the task
+/// restarts are not lost. Mailboxes are cheap to clone, clones receive from
+/// the same messages.
+pub struct Mailbox<M> {
+    receiver: Arc<Mutex<UnboundedReceiver<M>>>,
+}
+
+impl<M> Mailbox<M> {
+    /// Waits for the next message. Returns [`None`] once the watcher
+    /// stopped and every message was received.
+    ///
+    /// A message is only received once: an instance dropped while waiting
+    /// leaves the next message to the following one.
+    pub async fn recv(&self) -> Option<M> {
+        self.receiver.lock().await.next().await
+    }
+
+    /// Returns the next message if one was already sent, without waiting.
+    /// Returns [`None`] as well while another clone is waiting for one.
+    pub fn try_recv(&self) -> Option<M> {
+        self.receiver.try_lock()?.try_recv().ok()
+    }
+}
+
+impl<M> Clone for Mailbox<M> {
+    fn clone(&self) -> Self {
+        Self {
+            receiver: Arc::clone(&self.receiver),
+        }
+    }
+}
+
+impl<M> fmt::Debug for Mailbox<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mailbox").finish_non_exhaustive()
+    }
+}
+
+/// A request of type `Q` expecting a response of type `R`, as sent with
+/// [`crate::WatchHandle::call`] to the [`Mailbox`] of a task.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use std::time::Duration;
+/// use watch::{Builder, Call, Mailbox, Task};
+///
+/// let (watch, handle) = Builder::new()
+///     .task(Task::with_mailbox(|calls: Mailbox<Call<u32, u32>>| async move {
+///         while let Some(call) = calls.recv().await {
+///             let doubled = call.request() * 2;
+///             let _ = call.reply(doubled);
+///         }
+///     }))
+///     .build();
+/// let watch = tokio::spawn(watch);
+///
+/// let timeout = Duration::from_secs(1);
+/// let doubled = handle.call::<u32, u32>(0, 21, timeout).await;
+/// assert_eq!(doubled, Ok(42));
+/// handle.shutdown();
+/// # watch.await.unwrap().unwrap();
+/// # }
+/// ```
+pub struct Call<Q, R> {
+    request: Q,
+    reply: oneshot::Sender<R>,
+}
+
+impl<Q, R> Call<Q, R> {
+    /// Returns the request.
+    pub fn request(&self) -> &Q {
+        &self.request
+    }
+
+    /// Responds with `response`. Hands `response` back if the caller is not
+    /// waiting for it anymore, such as after its timeout.
+    pub fn reply(self, response: R) -> Result<(), R> {
+        self.reply.send(response)
+    }
+
+    /// Returns the request, along with a call responding to it.
+    pub fn into_request(self) -> (Q, Call<(), R>) {
+        (
+            self.request,
+            Call {
+                request: (),
+                reply: self.reply,
+            },
+        )
+    }
+}
+
+impl<Q, R> fmt::Debug for Call<Q, R>
+where
+    Q: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Call")
+            .field("request", &self.request)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns a call of `request`, along with where its response comes from.
+pub(crate) fn call<Q, R>(request: Q) -> (Call<Q, R>, oneshot::Receiver<R>) {
+    let (reply, response) = oneshot::channel();
+    (Call { request, reply }, response)
+}
+
+/// Returns the sender of a new mailbox, along with the mailbox.
+pub(crate) fn channel<M>() -> (Sender, Mailbox<M>)
+where
+    M: Send + 'static,
+{
+    let (sender, receiver) = mpsc::unbounded::<M>();
+    let mailbox = Mailbox {
+        receiver: Arc::new(Mutex::new(receiver)),
+    };
+    (Arc::new(sender), mailbox)
+}
+
+/// The senders of the mailboxes of the tasks of a watcher, by task. Shared
+/// by the watcher, which forgets the ones of released tasks, and its handles.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Mailboxes {
+    senders: Arc<std::sync::Mutex<HashMap<usize, Sender>>>,
+}
+
+impl Mailboxes {
+    /// Sets the sender of the mailbox of the task `id`, if it has one.
+    pub(crate) fn set(&self, id: usize, sender: Option<Sender>) {
+        // Senders are only inserted and removed, which can't panic halfway.
+        let mut senders = self.senders.lock().unwrap_or_else(PoisonError::into_inner);
+        match sender {
+            Some(sender) => senders.insert(id, sender),
+            None => senders.remove(&id),
+        };
+    }
+
+    /// Sends `message` to the mailbox of the task `id`. Hands `message` back
+    /// if the task has no mailbox of messages of type `M`, or if the watcher
+    /// stopped.
+    pub(crate) fn send<M>(&self, id: usize, message: M) -> Result<(), M>
+    where
+        M: Send + 'static,
+    {
+        let sender = self
+            .senders
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&id)
+            .cloned();
+        match sender
+            .as_deref()
+            .and_then(|sender| sender.downcast_ref::<UnboundedSender<M>>())
+        {
+            Some(sender) => sender
+                .unbounded_send(message)
+                .map_err(|error| error.into_inner()),
+            None => Err(message),
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::handle::WatchHandle;
+use crate::id::TaskId;
+use crate::info::TaskInfo;
+use crate::monitor::Monitor;
+use crate::task::Task;
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Controls a running [`crate::Watch`] whose tasks are registered by a key
+/// of type `K`, such as a shard or customer identifier, as returned by
+/// [`crate::Builder::build_map`].
+///
+/// Keyed tasks are supervised like any other: they go through the default
+/// policy of the [`crate::Builder`] unless they have their own. A key stays
+/// in the map until its task is removed through the map, even if the task
+/// was retired by its policy. Maps are cheap to clone, clones refer to the
+/// same tasks.
+///
+/// ```
+/// # async fn consume(shard: u32) {}
+/// use watch::{Builder, Task};
+///
+/// let (watch, shards) = Builder::new().build_map();
+/// # drop(watch);
+/// for shard in 0..4 {
+///     shards.insert(shard, Task::new(move || consume(shard)));
+/// }
+///
+/// assert!(shards.remove(&2));
+/// assert!(!shards.contains_key(&2));
+/// assert_eq!(shards.len(), 3);
+/// ```
+pub struct WatchMap<K> {
+    handle: WatchHandle,
+    keys: Arc<Mutex<HashMap<K, TaskId>>>,
+}
+
+impl<K> WatchMap<K>
+where
+    K: Hash + Eq,
+{
+    pub(crate) fn new(handle: WatchHandle) -> Self {
+        Self {
+            handle,
+            keys: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<K, TaskId>> {
+        // Keys are only updated along with the commands to the watcher, a
+        // panic in between leaves them as good as they get.
+        self.keys.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the identifier of the task of `key`, if any.
+    fn id<Q>(&self, key: &Q) -> Option<TaskId>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lock().get(key).copied()
+    }
+
+    /// Adds `task` to the watcher under `key`. If `key` already had a task,
+    /// it is removed and replaced, and `true` is returned.
+    ///
+    /// Tasks inserted once the watcher stopped are never spawned.
+    pub fn insert<T>(&self, key: K, task: T) -> bool
+    where
+        T: Into<Task>,
+    {
+        let mut keys = self.lock();
+        let previous = match self.handle.add(task.into()) {
+            Some(id) => keys.insert(key, id),
+            None => return false,
+        };
+        if let Some(previous) = previous {
+            self.handle.remove(previous);
+        }
+        previous.is_some()
+    }
+
+    /// Removes the task of `key` from the watcher, dropping its running
+    /// instance if any. Returns whether `key` had a task.
+    pub fn remove<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.lock().remove(key) {
+            Some(id) => {
+                self.handle.remove(id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns whether `key` has a task.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lock().contains_key(key)
+    }
+
+    /// Returns how many keys have a task.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns whether no key has a task.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Returns every key that has a task, in no particular order.
+    pub fn keys(&self) -> Vec<K>
+    where
+        K: Clone,
+    {
+        self.lock().keys().cloned().collect()
+    }
+
+    /// Restarts the task of `key`, see [`WatchHandle::restart`]. Returns
+    /// whether `key` has a task.
+    pub fn restart<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.id(key).map(|id| self.handle.restart(id)).is_some()
+    }
+
+    /// Cancels the running instance of the task of `key`, see
+    /// [`WatchHandle::cancel_current`]. Returns whether `key` has a task.
+    pub fn cancel_current<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.id(key)
+            .map(|id| self.handle.cancel_current(id))
+            .is_some()
+    }
+
+    /// Pauses the task of `key`, see [`WatchHandle::pause`]. Returns whether
+    /// `key` has a task.
+    pub fn pause<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.id(key).map(|id| self.handle.pause(id)).is_some()
+    }
+
+    /// Resumes the task of `key`, see [`WatchHandle::resume`]. Returns
+    /// whether `key` has a task.
+    pub fn resume<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.id(key).map(|id| self.handle.resume(id)).is_some()
+    }
+
+    /// Returns a snapshot of what the task of `key` is doing, see
+    /// [`WatchHandle::task_info`].
+    pub fn task_info<Q>(&self, key: &Q) -> Option<TaskInfo>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.handle.task_info(self.id(key)?)
+    }
+
+    /// Monitors the task of `key`, see [`WatchHandle::monitor`].
+    pub fn monitor<Q>(&self, key: &Q) -> Option<Monitor>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Some(self.handle.monitor(self.id(key)?))
+    }
+
+    /// Returns the [`WatchHandle`] of the watcher, to drain it, shut it down
+    /// or subscribe to its events. Keyed tasks are identified by their
+    /// [`TaskId`] there, see [`WatchMap::task_info`].
+    pub fn handle(&self) -> &WatchHandle {
+        &self.handle
+    }
+}
+
+impl<K> Clone for WatchMap<K> {
+    fn clone(&self) -> Self {
+        Self {
+            handle: self.handle.clone(),
+            keys: Arc::clone(&self.keys),
+        }
+    }
+}
+
+impl<K> fmt::Debug for WatchMap<K>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        f.debug_struct("WatchMap").field("keys", &*keys).finish()
+    }
+}
@@ -0,0 +1,422 @@
+use crate::exit::ExitCounts;
+use crate::id::TaskId;
+use crate::info::TaskInfo;
+use crate::layer::Instance;
+use futures::FutureExt;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// The upper bounds of the buckets of every [`Histogram`], in microseconds.
+const BOUNDS: [u64; 21] = [
+    10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000,
+    500_000, 1_000_000, 2_500_000, 5_000_000, 10_000_000, 30_000_000, 60_000_000,
+];
+
+/// A distribution of durations, in buckets from 10µs to 60s, as found in
+/// [`TaskMetrics`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    /// How many durations fell in each bucket, the last one being unbounded.
+    counts: [u64; BOUNDS.len() + 1],
+    sum: Duration,
+}
+
+impl Histogram {
+    fn record(&mut self, duration: Duration) {
+        let micros = duration.as_micros();
+        let bucket = BOUNDS
+            .iter()
+            .position(|&bound| micros <= u128::from(bound))
+            .unwrap_or(BOUNDS.len());
+        self.counts[bucket] += 1;
+        self.sum += duration;
+    }
+
+    /// Returns how many durations were recorded.
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Returns the sum of every duration recorded.
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// Returns the upper bound of every bucket, along with how many durations
+    /// were at most as long, from the shortest bound up. Durations longer
+    /// than the last bound are only part of [`Histogram::count`].
+    pub fn buckets(&self) -> impl Iterator<Item = (Duration, u64)> + '_ {
+        BOUNDS
+            .iter()
+            .zip(&self.counts)
+            .scan(0, |cumulative, (&bound, &count)| {
+                *cumulative += count;
+                Some((Duration::from_micros(bound), *cumulative))
+            })
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; BOUNDS.len() + 1],
+            sum: Duration::ZERO,
+        }
+    }
+}
+
+/// What is measured about the instances of a task.
+#[derive(Debug, Default)]
+pub(crate) struct Probe {
+    polls: Histogram,
+    /// How many polls returned [`Poll::Pending`].
+    pending: u64,
+    ready: Histogram,
+}
+
+impl Probe {
+    /// Records that an instance got ready `latency` after being spawned.
+    pub(crate) fn ready(&mut self, latency: Duration) {
+        self.ready.record(latency);
+    }
+}
+
+/// How many times the watcher itself was polled.
+#[derive(Debug, Default)]
+struct Polls {
+    total: AtomicU64,
+    /// The polls during which nothing moved.
+    idle: AtomicU64,
+}
+
+/// The probes of every task, by index, along with the polls of the watcher.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Registry {
+    probes: Arc<Mutex<Vec<Arc<Mutex<Probe>>>>>,
+    polls: Arc<Polls>,
+}
+
+impl Registry {
+    /// Records a poll of the watcher, `idle` if nothing moved during it.
+    pub(crate) fn polled(&self, idle: bool) {
+        self.polls.total.fetch_add(1, Ordering::Relaxed);
+        if idle {
+            self.polls.idle.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn push(&self, probe: Arc<Mutex<Probe>>) {
+        if let Ok(mut probes) = self.probes.lock() {
+            probes.push(probe);
+        }
+    }
+
+    /// Registers the probe of the task `id`, either new or replacing a
+    /// removed task.
+    pub(crate) fn set(&self, id: usize, probe: Arc<Mutex<Probe>>) {
+        if let Ok(mut probes) = self.probes.lock() {
+            match probes.get_mut(id) {
+                Some(registered) => *registered = probe,
+                None => probes.push(probe),
+            }
+        }
+    }
+
+    /// Returns the metrics of `tasks`, skipping the ones without a probe yet.
+    pub(crate) fn collect(&self, tasks: Vec<TaskInfo>) -> Metrics {
+        let probes = match self.probes.lock() {
+            Ok(probes) => probes.clone(),
+            Err(_) => Vec::new(),
+        };
+        let tasks = tasks
+            .into_iter()
+            .zip(probes)
+            .filter_map(|(info, probe)| {
+                let probe = probe.lock().ok()?;
+                Some(TaskMetrics {
+                    id: info.id(),
+                    name: info.name().map(str::to_string),
+                    polls: probe.polls.clone(),
+                    pending: probe.pending,
+                    ready: probe.ready.clone(),
+                    exits: info.exits(),
+                })
+            })
+            .collect();
+        Metrics {
+            tasks,
+            polls: self.polls.total.load(Ordering::Relaxed),
+            idle_polls: self.polls.idle.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Records how long each poll of `instance` takes into `probe`.
+pub(crate) fn timed(instance: Instance, probe: Arc<Mutex<Probe>>) -> Instance {
+    Timed { instance, probe }.boxed()
+}
+
+struct Timed {
+    instance: Instance,
+    probe: Arc<Mutex<Probe>>,
+}
+
+impl Future for Timed {
+    type Output = crate::ExitReason;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let start = Instant::now();
+        let poll = self.instance.poll_unpin(cx);
+        let elapsed = start.elapsed();
+        if let Ok(mut probe) = self.probe.lock() {
+            probe.polls.record(elapsed);
+            if poll.is_pending() {
+                probe.pending += 1;
+            }
+        }
+        poll
+    }
+}
+
+/// What was measured about the instances of a task, see [`Metrics`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskMetrics {
+    id: TaskId,
+    name: Option<String>,
+    polls: Histogram,
+    pending: u64,
+    ready: Histogram,
+    exits: ExitCounts,
+}
+
+impl TaskMetrics {
+    /// Returns the identifier of the task.
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Returns the name of the task, see [`crate::Task::name`].
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns how long polling instances of the task took, measured with the
+    /// system clock whatever the [`crate::Builder::clock`]. Long polls block
+    /// the thread running the watcher, or the runtime with
+    /// [`crate::Builder::join_set`].
+    pub fn poll_durations(&self) -> &Histogram {
+        &self.polls
+    }
+
+    /// Returns how many polls of instances of the task returned
+    /// [`Poll::Pending`], out of the [`Histogram::count`] of
+    /// [`TaskMetrics::poll_durations`]. Instances that are woken without
+    /// having made progress, such as by a broken waker, show a share close
+    /// to one, and cost the whole watcher a poll every time.
+    pub fn pending_polls(&self) -> u64 {
+        self.pending
+    }
+
+    /// Returns how many instances of the task exited, by reason, see
+    /// [`crate::TaskInfo::exits`].
+    pub fn exits(&self) -> ExitCounts {
+        self.exits
+    }
+
+    /// Returns how long instances of the task took to be ready once spawned,
+    /// for tasks that signal their readiness, see
+    /// [`crate::Task::signals_readiness`].
+    pub fn ready_latencies(&self) -> &Histogram {
+        &self.ready
+    }
+}
+
+/// Per-task histograms of poll durations and of the latency from restarts to
+/// readiness, along with counts of polls that made no progress, as returned
+/// by [`crate::WatchHandle::metrics`], with the `metrics` feature.
+///
+/// Displaying metrics prints them in the Prometheus text format, to be served
+/// as is to a scraper:
+///
+/// ```text
+/// # TYPE watch_poll_duration_seconds histogram
+/// watch_poll_duration_seconds_bucket{task="0",name="db",le="0.00001"} 12
+/// ...
+/// watch_poll_duration_seconds_bucket{task="0",name="db",le="+Inf"} 14
+/// watch_poll_duration_seconds_sum{task="0",name="db"} 0.000341
+/// watch_poll_duration_seconds_count{task="0",name="db"} 14
+/// ```
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use watch::{Builder, RestartDecision};
+///
+/// let (watch, handle) = Builder::new()
+///     .task(|| async {})
+///     .policy(|_: &_| RestartDecision::Retire)
+///     .build();
+/// watch.await.unwrap();
+///
+/// let metrics = handle.metrics();
+/// assert_eq!(metrics.tasks()[0].poll_durations().count(), 1);
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metrics {
+    tasks: Vec<TaskMetrics>,
+    polls: u64,
+    idle_polls: u64,
+}
+
+impl Metrics {
+    /// Returns the metrics of every task, in order.
+    pub fn tasks(&self) -> &[TaskMetrics] {
+        &self.tasks
+    }
+
+    /// Returns how many times the watcher was polled.
+    pub fn polls(&self) -> u64 {
+        self.polls
+    }
+
+    /// Returns how many polls of the watcher found nothing to do: no
+    /// command, delay, readiness signal nor instance completed. Some are
+    /// expected, as the watcher checks its health and alerts, but most come
+    /// from instances woken to no avail, see [`TaskMetrics::pending_polls`].
+    pub fn idle_polls(&self) -> u64 {
+        self.idle_polls
+    }
+
+    /// Writes the histogram of every task returned by `histogram` as the
+    /// metric family `family`.
+    fn family(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        family: &str,
+        help: &str,
+        histogram: fn(&TaskMetrics) -> &Histogram,
+    ) -> fmt::Result {
+        writeln!(f, "# HELP {} {}", family, help)?;
+        writeln!(f, "# TYPE {} histogram", family)?;
+        for task in &self.tasks {
+            let labels = task.labels();
+            let histogram = histogram(task);
+            for (bound, count) in histogram.buckets() {
+                writeln!(
+                    f,
+                    "{}_bucket{{{},le=\"{}\"}} {}",
+                    family,
+                    labels,
+                    bound.as_secs_f64(),
+                    count
+                )?;
+            }
+            let count = histogram.count();
+            writeln!(f, "{}_bucket{{{},le=\"+Inf\"}} {}", family, labels, count)?;
+            writeln!(
+                f,
+                "{}_sum{{{}}} {}",
+                family,
+                labels,
+                histogram.sum().as_secs_f64()
+            )?;
+            writeln!(f, "{}_count{{{}}} {}", family, labels, count)?;
+        }
+        Ok(())
+    }
+}
+
+impl TaskMetrics {
+    /// Returns the labels of the task in the Prometheus text format.
+    fn labels(&self) -> String {
+        match &self.name {
+            Some(name) => format!("task=\"{}\",name=\"{}\"", self.id, escape(name)),
+            None => format!("task=\"{}\"", self.id),
+        }
+    }
+}
+
+/// Escapes a label value of the Prometheus text format.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.family(
+            f,
+            "watch_poll_duration_seconds",
+            "How long polling instances of each task took.",
+            TaskMetrics::poll_durations,
+        )?;
+        self.family(
+            f,
+            "watch_ready_latency_seconds",
+            "How long instances of each task took to be ready once spawned.",
+            TaskMetrics::ready_latencies,
+        )?;
+        writeln!(
+            f,
+            "# HELP watch_pending_polls_total How many polls of instances of each task returned pending."
+        )?;
+        writeln!(f, "# TYPE watch_pending_polls_total counter")?;
+        for task in &self.tasks {
+            writeln!(
+                f,
+                "watch_pending_polls_total{{{}}} {}",
+                task.labels(),
+                task.pending
+            )?;
+        }
+        writeln!(
+            f,
+            "# HELP watch_exits_total How many instances of each task exited, by reason."
+        )?;
+        writeln!(f, "# TYPE watch_exits_total counter")?;
+        for task in &self.tasks {
+            let exits = task.exits;
+            for (reason, count) in [
+                ("completed", exits.completed()),
+                ("failed", exits.failed()),
+                ("cancelled", exits.cancelled()),
+                ("timed_out", exits.timed_out()),
+                ("panicked", exits.panicked()),
+            ] {
+                writeln!(
+                    f,
+                    "watch_exits_total{{{},reason=\"{}\"}} {}",
+                    task.labels(),
+                    reason,
+                    count
+                )?;
+            }
+        }
+        writeln!(
+            f,
+            "# HELP watch_polls_total How many times the watcher was polled."
+        )?;
+        writeln!(f, "# TYPE watch_polls_total counter")?;
+        writeln!(f, "watch_polls_total {}", self.polls)?;
+        writeln!(
+            f,
+            "# HELP watch_idle_polls_total How many polls of the watcher found nothing to do."
+        )?;
+        writeln!(f, "# TYPE watch_idle_polls_total counter")?;
+        writeln!(f, "watch_idle_polls_total {}", self.idle_polls)
+    }
+}
@@ -0,0 +1,126 @@
+use crate::event::{Event, Events};
+use crate::exit::ExitReason;
+use futures::stream::{Stream, StreamExt};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// An instance of a monitored task that returned, or was dropped by the
+/// watcher, as received through a [`Monitor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exit {
+    instance: u64,
+    reason: ExitReason,
+}
+
+impl Exit {
+    /// Returns the number of the instance, see [`crate::Event`].
+    pub fn instance(&self) -> u64 {
+        self.instance
+    }
+
+    /// Returns why the instance exited.
+    pub fn reason(&self) -> ExitReason {
+        self.reason.clone()
+    }
+}
+
+/// Receives every [`Exit`] of a single task, as returned by
+/// [`crate::WatchHandle::monitor`]. Ends once the task was removed, or the
+/// watcher stopped.
+///
+/// Monitors watch a task from the outside: they are not part of the set, and
+/// dropping them has no effect on the task.
+///
+/// ```no_run
+/// # async fn serve() {}
+/// # async fn run() {
+/// use futures::StreamExt;
+/// use watch::Builder;
+///
+/// let (watch, handle) = Builder::new().task(serve).build();
+/// let mut monitor = handle.monitor(0);
+///
+/// tokio::spawn(watch);
+/// while let Some(exit) = monitor.next().await {
+///     println!("instance {} exited: {:?}", exit.instance(), exit.reason());
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Monitor {
+    events: Events,
+    done: bool,
+}
+
+impl Monitor {
+    pub(crate) fn new(events: Events) -> Self {
+        Self {
+            events,
+            done: false,
+        }
+    }
+}
+
+impl Stream for Monitor {
+    type Item = Exit;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            match self.events.poll_next_unpin(cx) {
+                Poll::Ready(Some(Event::Exited {
+                    instance, reason, ..
+                })) => return Poll::Ready(Some(Exit { instance, reason })),
+                Poll::Ready(Some(Event::Removed { .. })) | Poll::Ready(None) => {
+                    self.done = true;
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(Some(_)) => {}
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Resolves with why the next instance of a task exited, as returned by
+/// [`crate::WatchHandle::next_exit`], or with [`None`] if the task was
+/// removed or the watcher stopped first.
+///
+/// ```no_run
+/// # async fn index() {}
+/// # async fn run() {
+/// use watch::{Builder, Task};
+///
+/// let (watch, handle) = Builder::new().task(Task::new(index).name("indexer")).build();
+/// let exit = handle.next_exit(0);
+///
+/// tokio::spawn(watch);
+/// if let Some(reason) = exit.await {
+///     println!("the indexer restarts: {:?}", reason);
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct NextExit {
+    monitor: Monitor,
+}
+
+impl NextExit {
+    pub(crate) fn new(monitor: Monitor) -> Self {
+        Self { monitor }
+    }
+}
+
+impl Future for NextExit {
+    type Output = Option<ExitReason>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.monitor
+            .poll_next_unpin(cx)
+            .map(|exit| exit.map(|exit| exit.reason))
+    }
+}
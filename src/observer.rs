@@ -0,0 +1,64 @@
+use crate::exit::ExitReason;
+use crate::id::TaskId;
+use std::time::Duration;
+
+/// Gets called by the watcher as things happen to its tasks, for custom
+/// instrumentation such as metrics.
+///
+/// Observers are registered with [`crate::Builder::observer`] and called
+/// synchronously from the watcher, without allocating, which makes them a
+/// lower-level alternative to [`crate::WatchHandle::events`]. Every method
+/// does nothing by default, so implementations only override what they need.
+/// Tasks are identified by their [`crate::TaskId`], and their instances by
+/// number, see [`crate::Event`].
+///
+/// ```no_run
+/// # async fn serve() {}
+/// # async fn run() {
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use watch::{Builder, TaskId, WatchObserver};
+///
+/// struct Restarts(Arc<AtomicU64>);
+///
+/// impl WatchObserver for Restarts {
+///     fn on_restart_scheduled(&mut self, _task: TaskId, _instance: u64, _delay: Duration) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let restarts = Arc::new(AtomicU64::new(0));
+///
+/// Builder::new()
+///     .task(serve)
+///     .observer(Restarts(Arc::clone(&restarts)))
+///     .run()
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub trait WatchObserver: Send {
+    /// Called when the instance number `instance` of `task` was spawned.
+    fn on_start(&mut self, task: TaskId, instance: u64) {
+        let _ = (task, instance);
+    }
+
+    /// Called when an instance of `task` returned, or was dropped by the
+    /// watcher with [`ExitReason::Cancelled`].
+    fn on_exit(&mut self, task: TaskId, instance: u64, reason: ExitReason) {
+        let _ = (task, instance, reason);
+    }
+
+    /// Called when `task` will be spawned again after `delay`, once its
+    /// instance number `instance` exited.
+    fn on_restart_scheduled(&mut self, task: TaskId, instance: u64, delay: Duration) {
+        let _ = (task, instance, delay);
+    }
+
+    /// Called when `task` was retired once its instance number `instance`
+    /// exited, and won't be spawned again.
+    fn on_retire(&mut self, task: TaskId, instance: u64) {
+        let _ = (task, instance);
+    }
+}
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+/// How the watcher paces the instances it spawns, so that the synchronous
+/// work of factories, such as parsing configuration or setting TLS up, does
+/// not hold back the other futures of the runtime. Set with
+/// [`crate::Builder::respawn_pacing`].
+///
+/// ```
+/// use futures::future;
+/// use watch::testing::MockClock;
+/// use watch::{Builder, RespawnPacing, TaskState};
+///
+/// let (mut watch, handle) = Builder::new()
+///     .tasks((0..3).map(|_| future::pending::<()>))
+///     .respawn_pacing(RespawnPacing::YieldEvery(2))
+///     .clock(MockClock::new())
+///     .build();
+///
+/// watch.tick();
+/// assert_eq!(handle.task_info(2).unwrap().state(), TaskState::Queued);
+/// watch.tick();
+/// assert_eq!(handle.task_info(2).unwrap().state(), TaskState::Running);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RespawnPacing {
+    /// Instances are spawned as soon as they can be. This is the default.
+    #[default]
+    Eager,
+    /// Once that many instances were spawned during a single poll, the
+    /// watcher yields to the runtime, and spawns the next ones on the poll
+    /// right after.
+    YieldEvery(usize),
+    /// Instances restarted right away by their policy wait for at least that
+    /// long instead.
+    Delay(Duration),
+}
+
+impl RespawnPacing {
+    /// Returns whether no instance may be spawned anymore during a poll that
+    /// already spawned `spawns`.
+    pub(crate) fn exhausted(self, spawns: usize) -> bool {
+        match self {
+            RespawnPacing::YieldEvery(budget) => spawns >= budget.max(1),
+            RespawnPacing::Eager | RespawnPacing::Delay(_) => false,
+        }
+    }
+
+    /// Returns the delay before restarting an instance the policy decided to
+    /// restart after `delay`.
+    pub(crate) fn delay(self, delay: Duration) -> Duration {
+        match self {
+            RespawnPacing::Delay(pause) => delay.max(pause),
+            RespawnPacing::Eager | RespawnPacing::YieldEvery(_) => delay,
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::exit::ExitReason;
+use crate::layer::Instance;
+use futures::FutureExt;
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Once};
+use std::task::{Context, Poll};
+
+/// What an instance that panicked left behind, see [`ExitReason::Panicked`].
+///
+/// Panics are cheap to clone, clones share the message and the backtrace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Panic {
+    message: Arc<str>,
+    backtrace: Option<Arc<str>>,
+}
+
+impl Panic {
+    pub(crate) fn new(payload: Box<dyn Any + Send>, backtrace: Option<String>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => message.as_str().into(),
+            Err(payload) => match payload.downcast::<&'static str>() {
+                Ok(message) => (*message).into(),
+                Err(_) => "Box<dyn Any>".into(),
+            },
+        };
+        Self {
+            message,
+            backtrace: backtrace.map(Into::into),
+        }
+    }
+
+    /// Returns the message the instance panicked with, or `Box<dyn Any>` if
+    /// it panicked with something else than a string.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns where the instance panicked, if the watcher captures
+    /// backtraces, see [`crate::Builder::capture_backtraces`].
+    pub fn backtrace(&self) -> Option<&str> {
+        self.backtrace.as_deref()
+    }
+}
+
+/// What happens when an instance panics, set for every task with
+/// [`crate::Builder::panic_behavior`] or for a single one with
+/// [`crate::Task::panic_behavior`].
+///
+/// Factories panicking while creating an instance are handled the same way,
+/// as if the instance panicked right away, see
+/// [`crate::Event::FactoryPanicked`].
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use watch::{Builder, PanicBehavior, Task};
+///
+/// let summary = Builder::new()
+///     .task(
+///         Task::new(|| async { panic!("corrupted state") })
+///             .panic_behavior(PanicBehavior::Retire),
+///     )
+///     .run()
+///     .await
+///     .unwrap();
+///
+/// assert_eq!(summary.spawned(), 1);
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PanicBehavior {
+    /// The panic is a restartable failure, the task goes through its
+    /// [`crate::RestartPolicy`]. This is the default.
+    #[default]
+    Restart,
+    /// The task is retired right away, without consulting its
+    /// [`crate::RestartPolicy`].
+    Retire,
+    /// The panic is not caught and unwinds through the watcher, with its
+    /// original payload, as if the instance was polled directly.
+    Propagate,
+}
+
+thread_local! {
+    /// Whether a panic on this thread should capture a backtrace.
+    static CAPTURING: Cell<bool> = const { Cell::new(false) };
+    /// The backtrace of the last panic on this thread, if captured.
+    static BACKTRACE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Chains a hook capturing backtraces while instances are polled to the
+/// panic hook in place, once per process.
+fn install_hook() {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if CAPTURING.with(Cell::get) {
+                let backtrace = Backtrace::force_capture().to_string();
+                BACKTRACE.with(|slot| *slot.borrow_mut() = Some(backtrace));
+            }
+            previous(info);
+        }));
+    });
+}
+
+/// Turns panics of `instance` into [`ExitReason::Panicked`], capturing
+/// backtraces if `backtraces`.
+pub(crate) fn catch(instance: Instance, backtraces: bool) -> Instance {
+    if backtraces {
+        install_hook();
+    }
+    CatchPanic {
+        instance,
+        backtraces,
+    }
+    .boxed()
+}
+
+/// Calls `factory`, turning its panic into a [`Panic`], capturing its
+/// backtrace if `backtraces`.
+pub(crate) fn construct<F>(factory: F, backtraces: bool) -> Result<Instance, Panic>
+where
+    F: FnOnce() -> Instance,
+{
+    if backtraces {
+        install_hook();
+        BACKTRACE.with(|slot| slot.borrow_mut().take());
+    }
+    let previous = CAPTURING.with(|capturing| capturing.replace(backtraces));
+    let instance = panic::catch_unwind(AssertUnwindSafe(factory));
+    CAPTURING.with(|capturing| capturing.set(previous));
+    instance.map_err(|payload| {
+        let backtrace = BACKTRACE.with(|slot| slot.borrow_mut().take());
+        Panic::new(payload, backtrace)
+    })
+}
+
+struct CatchPanic {
+    instance: Instance,
+    backtraces: bool,
+}
+
+impl Future for CatchPanic {
+    type Output = ExitReason;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if this.backtraces {
+            // Panics caught by the instance itself may have left one behind.
+            BACKTRACE.with(|slot| slot.borrow_mut().take());
+        }
+        let previous = CAPTURING.with(|capturing| capturing.replace(this.backtraces));
+        let poll = panic::catch_unwind(AssertUnwindSafe(|| this.instance.poll_unpin(cx)));
+        CAPTURING.with(|capturing| capturing.set(previous));
+        match poll {
+            Ok(poll) => poll,
+            Err(payload) => {
+                let backtrace = BACKTRACE.with(|slot| slot.borrow_mut().take());
+                Poll::Ready(ExitReason::Panicked(Panic::new(payload, backtrace)))
+            }
+        }
+    }
+}
@@ -0,0 +1,307 @@
+use crate::exit::ExitReason;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Decides what happens to a task every time one of its instances exits.
+///
+/// Every task owns its own instance of the policy, so implementations can keep
+/// per task state such as an attempt counter.
+///
+/// Closures taking a [`RestartContext`] are policies too, which is handy for
+/// one-off logic:
+///
+/// ```no_run
+/// # async fn serve() {}
+/// # async fn run() {
+/// use std::time::Duration;
+/// use watch::{Builder, RestartContext, RestartDecision, Task};
+///
+/// Builder::new()
+///     .task(Task::new(serve).policy(|context: &RestartContext| {
+///         if context.uptime() < Duration::from_millis(10) {
+///             RestartDecision::Escalate
+///         } else {
+///             RestartDecision::RestartAfter(Duration::from_secs(1))
+///         }
+///     }))
+///     .run()
+///     .await
+///     .unwrap_err();
+/// # }
+/// ```
+pub trait RestartPolicy: Send {
+    /// Returns what to do with the task whose instance just exited.
+    fn decide(&mut self, context: &RestartContext) -> RestartDecision;
+
+    /// Returns how long an instance must stay up for its task to be
+    /// considered healthy, in which case [`RestartContext::attempt`] goes back
+    /// to zero. By default it never does.
+    fn healthy_after(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl<F> RestartPolicy for F
+where
+    F: FnMut(&RestartContext) -> RestartDecision + Send,
+{
+    fn decide(&mut self, context: &RestartContext) -> RestartDecision {
+        self(context)
+    }
+}
+
+/// What a [`RestartPolicy`] knows about the instance that just exited.
+#[derive(Debug, Clone)]
+pub struct RestartContext {
+    uptime: Duration,
+    reason: ExitReason,
+    instance: u64,
+    attempt: u32,
+}
+
+impl RestartContext {
+    pub(crate) fn new(uptime: Duration, reason: ExitReason, instance: u64, attempt: u32) -> Self {
+        Self {
+            uptime,
+            reason,
+            instance,
+            attempt,
+        }
+    }
+
+    /// Returns for how long the instance was up.
+    pub fn uptime(&self) -> Duration {
+        self.uptime
+    }
+
+    /// Returns why the instance exited. Permanent failures never reach a
+    /// [`RestartPolicy`].
+    pub fn reason(&self) -> ExitReason {
+        self.reason.clone()
+    }
+
+    /// Returns the number of the instance among the instances of its task,
+    /// starting at one. This is its attempt number, see [`crate::Event`].
+    pub fn instance(&self) -> u64 {
+        self.instance
+    }
+
+    /// Returns how many times in a row the policy restarted the task so far,
+    /// starting at zero. Goes back to zero once an instance stayed up for
+    /// [`RestartPolicy::healthy_after`].
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+}
+
+/// What a [`RestartPolicy`] decided to do with a task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RestartDecision {
+    /// Spawns a new instance of the task after the given delay.
+    RestartAfter(Duration),
+    /// Spawns a new instance of the task once the trigger fired, such as once
+    /// a circuit closes, a dependency is healthy again or an operator
+    /// approved. The task is [`crate::TaskState::Delayed`] meanwhile, with
+    /// no [`crate::TaskInfo::next_restart_at`], and
+    /// [`crate::WatchHandle::expedite`] spawns it without waiting.
+    AwaitTrigger(RestartTrigger),
+    /// Stops respawning the task.
+    Retire,
+    /// Gives up on the whole set: every task is dropped and the watcher stops
+    /// with [`crate::WatchError::Escalated`].
+    Escalate,
+}
+
+/// A condition a task waits for before being spawned again, see
+/// [`RestartDecision::AwaitTrigger`]. Fires once the future it was created
+/// with completes, and stays fired: decisions awaiting it from then on
+/// restart their task right away. Triggers are cheap to clone, and equal to
+/// their clones only.
+///
+/// ```
+/// use futures::channel::oneshot;
+/// use futures::future;
+/// use watch::testing::MockClock;
+/// use watch::{Builder, RestartDecision, RestartTrigger, TaskState};
+///
+/// let (approve, approved) = oneshot::channel::<()>();
+/// let approval = RestartTrigger::new(approved);
+/// let (mut watch, handle) = Builder::new()
+///     .task(future::pending::<()>)
+///     .policy(move |_: &_| RestartDecision::AwaitTrigger(approval.clone()))
+///     .clock(MockClock::new())
+///     .build();
+///
+/// handle.cancel_current(0);
+/// watch.tick();
+/// assert_eq!(handle.task_info(0).unwrap().state(), TaskState::Delayed);
+///
+/// approve.send(()).unwrap();
+/// watch.tick();
+/// assert_eq!(handle.task_info(0).unwrap().instances(), 2);
+/// ```
+#[derive(Clone)]
+pub struct RestartTrigger {
+    fired: Shared<BoxFuture<'static, ()>>,
+}
+
+impl RestartTrigger {
+    /// Creates a trigger firing once `trigger` completes. Outputs are
+    /// ignored.
+    pub fn new<F>(trigger: F) -> Self
+    where
+        F: Future + Send + 'static,
+    {
+        Self {
+            fired: trigger.map(drop).boxed().shared(),
+        }
+    }
+
+    /// Returns a future resolving once the trigger fired.
+    pub(crate) fn fired(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.fired.clone()
+    }
+
+    /// Polls whether the trigger fired.
+    pub(crate) fn poll_fired(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.fired.poll_unpin(cx)
+    }
+}
+
+impl PartialEq for RestartTrigger {
+    fn eq(&self, other: &Self) -> bool {
+        self.fired.ptr_eq(&other.fired)
+    }
+}
+
+impl Eq for RestartTrigger {}
+
+impl fmt::Debug for RestartTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RestartTrigger").finish_non_exhaustive()
+    }
+}
+
+/// Creates the [`RestartPolicy`] of every task that has none of its own.
+pub(crate) type PolicyFactory = Arc<dyn Fn() -> Box<dyn RestartPolicy> + Send + Sync>;
+
+/// The policy used when none was given: tasks are respawned immediately.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Immediate;
+
+impl RestartPolicy for Immediate {
+    fn decide(&mut self, _context: &RestartContext) -> RestartDecision {
+        RestartDecision::RestartAfter(Duration::ZERO)
+    }
+}
+
+/// Retires a task once its policy restarted it a number of times in a row,
+/// see [`crate::Builder::with_max_attempts`].
+pub(crate) struct MaxAttempts {
+    policy: Box<dyn RestartPolicy>,
+    max: u32,
+}
+
+impl MaxAttempts {
+    pub(crate) fn new(policy: Box<dyn RestartPolicy>, max: u32) -> Self {
+        Self { policy, max }
+    }
+}
+
+impl RestartPolicy for MaxAttempts {
+    fn decide(&mut self, context: &RestartContext) -> RestartDecision {
+        match self.policy.decide(context) {
+            RestartDecision::RestartAfter(_) if context.attempt() >= self.max => {
+                RestartDecision::Retire
+            }
+            decision => decision,
+        }
+    }
+
+    fn healthy_after(&self) -> Option<Duration> {
+        self.policy.healthy_after()
+    }
+}
+
+/// Uses an implementation of the [`backoff`] crate as a [`RestartPolicy`].
+/// The task is retired once the [`backoff::backoff::Backoff`] gives up by
+/// returning [`None`].
+///
+/// The backoff is reset before the first restart of a streak, that is on the
+/// first exit of the task and whenever an instance stayed up for
+/// [`FromBackoff::reset_after`]. Without it, the task never starts over.
+///
+/// Beware that [`backoff::ExponentialBackoff`] gives up once its
+/// `max_elapsed_time` passed since it was last reset, 15 minutes by default:
+/// a task failing on and off for longer than that without a healthy run is
+/// retired for good. Set `max_elapsed_time` to [`None`] for tasks that must
+/// never be retired.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+/// # async fn main() {
+/// use backoff::ExponentialBackoff;
+/// use std::time::Duration;
+/// use watch::{Builder, FromBackoff, Task};
+///
+/// // Retired after 15 minutes of failures in a row, see above.
+/// let policy = FromBackoff::new(ExponentialBackoff::default())
+///     .reset_after(Duration::from_secs(60));
+///
+/// let mut watch = Builder::new()
+///     .task(Task::new(|| async {}).policy(policy))
+///     .run();
+/// let tick = watch.tick();
+/// assert_eq!(tick.decisions().len(), 1);
+/// # }
+/// ```
+#[cfg(feature = "backoff")]
+#[derive(Debug, Clone)]
+pub struct FromBackoff<B> {
+    backoff: B,
+    reset_after: Option<Duration>,
+}
+
+#[cfg(feature = "backoff")]
+impl<B> FromBackoff<B> {
+    /// Takes the delays between restarts from `backoff`.
+    pub fn new(backoff: B) -> Self {
+        Self {
+            backoff,
+            reset_after: None,
+        }
+    }
+
+    /// Sets how long a task must stay up to be considered healthy, in which
+    /// case the backoff is reset before the next restart.
+    ///
+    /// By default it is never reset past the first exit.
+    pub fn reset_after(mut self, uptime: Duration) -> Self {
+        self.reset_after = Some(uptime);
+        self
+    }
+}
+
+#[cfg(feature = "backoff")]
+impl<B> RestartPolicy for FromBackoff<B>
+where
+    B: backoff::backoff::Backoff + Send,
+{
+    fn decide(&mut self, context: &RestartContext) -> RestartDecision {
+        if context.attempt() == 0 {
+            self.backoff.reset();
+        }
+        match self.backoff.next_backoff() {
+            Some(delay) => RestartDecision::RestartAfter(delay),
+            None => RestartDecision::Retire,
+        }
+    }
+
+    fn healthy_after(&self) -> Option<Duration> {
+        self.reset_after
+    }
+}
@@ -0,0 +1,149 @@
+use crate::labels::Selector;
+use std::fmt;
+
+/// Requires at least some of a group of tasks to be healthy, that is to have
+/// a ready instance, see [`crate::Builder::quorum`]. Replicated workers often
+/// have exactly this requirement.
+///
+/// Once the quorum was met, dropping below it emits
+/// [`crate::Event::QuorumLost`], calls the [`Quorum::on_lost`] callback if
+/// any, and applies the action of the quorum: nothing more by default, or
+/// escalating with [`Quorum::escalate`], or shutting down with
+/// [`Quorum::shutdown`]. Getting back to the quorum emits
+/// [`crate::Event::QuorumRegained`]. Quorums are not checked while the
+/// watcher drains or shuts down.
+///
+/// ```no_run
+/// # async fn replica() {}
+/// # async fn run() {
+/// use watch::{Builder, Quorum, Task};
+///
+/// Builder::new()
+///     .tasks((0..3).map(|_| Task::new(replica).label("role", "replica")))
+///     .quorum(
+///         "role=replica",
+///         Quorum::new(2).on_lost(|healthy| eprintln!("only {} replicas left", healthy)),
+///     )
+///     .run()
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub struct Quorum {
+    pub(crate) min: usize,
+    pub(crate) action: QuorumAction,
+    pub(crate) on_lost: Option<Box<dyn FnMut(usize) + Send>>,
+}
+
+/// What is done once a [`Quorum`] is lost, besides emitting an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum QuorumAction {
+    Notify,
+    Escalate,
+    Shutdown,
+}
+
+impl Quorum {
+    /// Creates a [`Quorum`] requiring at least `min` tasks to be healthy.
+    pub fn new(min: usize) -> Self {
+        Self {
+            min,
+            action: QuorumAction::Notify,
+            on_lost: None,
+        }
+    }
+
+    /// Calls `on_lost` with how many tasks are healthy every time the quorum
+    /// is lost.
+    pub fn on_lost<F>(mut self, on_lost: F) -> Self
+    where
+        F: FnMut(usize) + Send + 'static,
+    {
+        self.on_lost = Some(Box::new(on_lost));
+        self
+    }
+
+    /// Stops the watcher once the quorum is lost, dropping every instance.
+    /// The [`crate::Watch`] returns [`crate::WatchError::QuorumLost`].
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+    /// # async fn main() {
+    /// use std::time::Duration;
+    /// use watch::{Builder, FailureKind, Quorum, Task, WatchError};
+    ///
+    /// let crashing = || {
+    ///     Task::fallible(
+    ///         || async {
+    ///             tokio::time::sleep(Duration::from_secs(1)).await;
+    ///             Err::<(), _>("out of memory")
+    ///         },
+    ///         |_| FailureKind::Permanent,
+    ///     )
+    /// };
+    ///
+    /// let error = Builder::new()
+    ///     .task(Task::new(|| futures::future::pending::<()>()).label("role", "replica"))
+    ///     .task(crashing().label("role", "replica"))
+    ///     .task(crashing().label("role", "replica"))
+    ///     .quorum("role=replica", Quorum::new(2).escalate())
+    ///     .run()
+    ///     .await
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(error, WatchError::QuorumLost { quorum: 0, healthy: 1 });
+    /// # }
+    /// ```
+    pub fn escalate(mut self) -> Self {
+        self.action = QuorumAction::Escalate;
+        self
+    }
+
+    /// Shuts the watcher down once the quorum is lost, see
+    /// [`crate::WatchHandle::shutdown`].
+    pub fn shutdown(mut self) -> Self {
+        self.action = QuorumAction::Shutdown;
+        self
+    }
+}
+
+impl fmt::Debug for Quorum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Quorum")
+            .field("min", &self.min)
+            .field("action", &self.action)
+            .finish()
+    }
+}
+
+/// A [`Quorum`] along with the tasks it is about, and whether it is met.
+pub(crate) struct Group {
+    pub(crate) selector: Selector,
+    pub(crate) quorum: Quorum,
+    pub(crate) members: Vec<usize>,
+    /// The members that were healthy when last checked.
+    pub(crate) healthy: Vec<usize>,
+    pub(crate) status: Status,
+}
+
+/// Whether a [`Quorum`] is met.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Status {
+    /// The quorum was never met yet. Quorums are only lost once met, sparing
+    /// the tasks that are starting.
+    Pending,
+    Met,
+    Lost,
+}
+
+impl Group {
+    pub(crate) fn new(selector: Selector, quorum: Quorum) -> Self {
+        Self {
+            selector,
+            quorum,
+            members: Vec::new(),
+            healthy: Vec::new(),
+            status: Status::Pending,
+        }
+    }
+}
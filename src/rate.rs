@@ -0,0 +1,29 @@
+use std::time::{Duration, Instant};
+
+/// How often something happens, in occurrences per second, weighted
+/// exponentially so that occurrences older than the window barely count.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Rate {
+    rate: f64,
+    /// When the rate was last updated, if ever.
+    updated_at: Option<Instant>,
+}
+
+impl Rate {
+    /// Records an occurrence happening `now`.
+    pub(crate) fn record(&mut self, now: Instant, window: Duration) {
+        self.rate = self.at(now, window) + 1.0 / window.as_secs_f64();
+        self.updated_at = Some(now);
+    }
+
+    /// Returns the rate as of `now`, decayed since the last occurrence.
+    pub(crate) fn at(&self, now: Instant, window: Duration) -> f64 {
+        match self.updated_at {
+            Some(updated_at) => {
+                let elapsed = now.saturating_duration_since(updated_at);
+                self.rate * (-elapsed.as_secs_f64() / window.as_secs_f64()).exp()
+            }
+            None => 0.0,
+        }
+    }
+}
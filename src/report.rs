@@ -0,0 +1,112 @@
+use crate::exit::{ExitReason, FailureKind};
+use crate::info::{TaskInfo, TaskState};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// A human-readable table of every task of a watcher, as returned by
+/// [`crate::WatchHandle::report`]. Displaying it prints one line per task,
+/// with its state, how many times it was restarted and for how long its
+/// running instance has been up:
+///
+/// ```text
+/// ID  NAME   STATE    RESTARTS  UPTIME  LAST EXIT
+/// 0   serve  running  0         1h02m   -
+/// 1   flush  delayed  12        -       failed (transient)
+/// ```
+#[derive(Debug, Clone)]
+pub struct Report {
+    tasks: Vec<TaskInfo>,
+    now: Instant,
+}
+
+impl Report {
+    pub(crate) fn new(tasks: Vec<TaskInfo>, now: Instant) -> Self {
+        Self { tasks, now }
+    }
+
+    /// Returns the snapshots the report is made of.
+    pub fn tasks(&self) -> &[TaskInfo] {
+        &self.tasks
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = ["ID", "NAME", "STATE", "RESTARTS", "UPTIME", "LAST EXIT"];
+        let rows: Vec<[String; 6]> = self
+            .tasks
+            .iter()
+            .map(|task| {
+                [
+                    task.id().to_string(),
+                    task.name().unwrap_or("-").to_string(),
+                    state(task.state()).to_string(),
+                    task.instances().saturating_sub(1).to_string(),
+                    task.started_at()
+                        .map(|started_at| uptime(self.now.saturating_duration_since(started_at)))
+                        .unwrap_or_else(|| "-".to_string()),
+                    task.last_exit().map(exit).unwrap_or("-").to_string(),
+                ]
+            })
+            .collect();
+
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        let header = header.map(str::to_string);
+        for row in std::iter::once(&header).chain(&rows) {
+            let last = row.len() - 1;
+            for (column, (cell, width)) in row.iter().zip(widths).enumerate() {
+                if column == last {
+                    writeln!(f, "{}", cell)?;
+                } else {
+                    write!(f, "{:width$}  ", cell, width = width)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn state(state: TaskState) -> &'static str {
+    match state {
+        TaskState::Starting => "starting",
+        TaskState::Running => "running",
+        TaskState::Delayed => "delayed",
+        TaskState::Queued => "queued",
+        TaskState::Stopped => "stopped",
+        TaskState::Paused => "paused",
+        TaskState::Removed => "removed",
+    }
+}
+
+fn exit(reason: ExitReason) -> &'static str {
+    match reason {
+        ExitReason::Completed => "completed",
+        ExitReason::Failed(FailureKind::Transient) => "failed (transient)",
+        ExitReason::Failed(FailureKind::Permanent) => "failed (permanent)",
+        ExitReason::Cancelled => "cancelled",
+        ExitReason::TimedOut => "timed out",
+        ExitReason::Panicked(_) => "panicked",
+    }
+}
+
+/// Formats `uptime` with its two most significant units.
+fn uptime(uptime: Duration) -> String {
+    let seconds = uptime.as_secs();
+    let (days, hours, minutes) = (seconds / 86_400, seconds / 3600 % 24, seconds / 60 % 60);
+
+    if days > 0 {
+        format!("{}d{:02}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h{:02}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m{:02}s", minutes, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
@@ -0,0 +1,124 @@
+use futures::stream::{FusedStream, Stream};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Supervises a single future without the machinery of a [`crate::Watch`]:
+/// runs an instance made by `factory`, and once it completes, makes the next
+/// one. Yields the output of every instance, and never ends on its own.
+///
+/// Instances are stored inline, without boxing, and the next one is only made
+/// once the stream is polled again, so consumers decide when to stop, and how
+/// to pace restarts, with the usual [`futures::StreamExt`] combinators.
+///
+/// This is not what a [`crate::Watch`] schedules its tasks with: the watcher
+/// keeps its own slots, so none of its policies, events or handles apply
+/// here. Use a [`crate::Builder`] with a single task for any of them.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use futures::{future, StreamExt};
+/// use std::sync::atomic::{AtomicU32, Ordering};
+///
+/// let attempts = AtomicU32::new(0);
+/// let connect = || async {
+///     match attempts.fetch_add(1, Ordering::Relaxed) {
+///         attempt if attempt < 2 => Err(attempt),
+///         _ => Ok("connected"),
+///     }
+/// };
+///
+/// let connected = watch::respawn(connect).filter_map(|result| future::ready(result.ok()));
+/// futures::pin_mut!(connected);
+/// assert_eq!(connected.next().await, Some("connected"));
+/// assert_eq!(attempts.into_inner(), 3);
+/// # }
+/// ```
+pub fn respawn<F, T>(factory: F) -> Respawner<F, T>
+where
+    F: FnMut() -> T,
+    T: Future,
+{
+    Respawner {
+        factory,
+        instance: None,
+        instances: 0,
+    }
+}
+
+/// The stream returned by [`respawn`].
+pub struct Respawner<F, T> {
+    factory: F,
+    /// The instance running, if any.
+    instance: Option<T>,
+    /// How many instances were made so far.
+    instances: u64,
+}
+
+impl<F, T> Respawner<F, T> {
+    /// Returns how many instances were made so far, including the one
+    /// running.
+    pub fn instances(&self) -> u64 {
+        self.instances
+    }
+
+    /// Returns whether an instance is running, that is whether it was made
+    /// and did not complete yet.
+    pub fn is_running(&self) -> bool {
+        self.instance.is_some()
+    }
+}
+
+impl<F, T> Stream for Respawner<F, T>
+where
+    F: FnMut() -> T,
+    T: Future,
+{
+    type Item = T::Output;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // SAFETY: the instance is pinned along with the respawner, and is
+        // never moved out of: it is only polled in place, or overwritten,
+        // which drops it in place first. The factory is not pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let (factory, instances) = (&mut this.factory, &mut this.instances);
+        let instance = this.instance.get_or_insert_with(|| {
+            *instances += 1;
+            factory()
+        });
+        // SAFETY: see above.
+        let instance = unsafe { Pin::new_unchecked(instance) };
+        match instance.poll(cx) {
+            Poll::Ready(output) => {
+                this.instance = None;
+                Poll::Ready(Some(output))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<F, T> FusedStream for Respawner<F, T>
+where
+    F: FnMut() -> T,
+    T: Future,
+{
+    fn is_terminated(&self) -> bool {
+        false
+    }
+}
+
+impl<F, T> fmt::Debug for Respawner<F, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Respawner")
+            .field("instances", &self.instances)
+            .field("running", &self.is_running())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,144 @@
+use crate::clock::Clock;
+use crate::error::WatchError;
+use crate::handle::WatchHandle;
+use crate::summary::Summary;
+use crate::watcher::Watch;
+use futures::future::{self, Either};
+use futures::stream::{self, BoxStream, StreamExt};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How a service run by [`crate::Builder::serve`] ended, with the `service`
+/// feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceExit {
+    result: Result<Summary, WatchError>,
+    clean: bool,
+}
+
+impl ServiceExit {
+    /// Returns whether the service stopped cleanly: the watcher returned a
+    /// [`Summary`], and finished draining within the deadline if it was
+    /// asked to terminate.
+    pub fn is_clean(&self) -> bool {
+        self.clean
+    }
+
+    /// Returns what the [`Watch`] returned.
+    pub fn result(&self) -> &Result<Summary, WatchError> {
+        &self.result
+    }
+
+    /// Returns what the [`Watch`] returned, consuming the exit.
+    pub fn into_result(self) -> Result<Summary, WatchError> {
+        self.result
+    }
+
+    /// Returns the status the process should exit with: `0` if the service
+    /// stopped cleanly, `1` otherwise.
+    ///
+    /// ```no_run
+    /// # async fn serve() {}
+    /// use std::time::Duration;
+    /// use watch::Builder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let exit = Builder::new()
+    ///         .task(serve)
+    ///         .serve(Duration::from_secs(30))
+    ///         .await;
+    ///     std::process::exit(exit.code());
+    /// }
+    /// ```
+    pub fn code(&self) -> i32 {
+        if self.clean {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+/// Runs `watch` until it stops, draining it once the process is asked to
+/// terminate, and shutting it down if it did not drain within `deadline`, as
+/// measured by `clock`.
+pub(crate) async fn serve(
+    mut watch: Watch,
+    handle: WatchHandle,
+    deadline: Duration,
+    clock: Arc<dyn Clock>,
+) -> ServiceExit {
+    let terminated = terminated();
+    futures::pin_mut!(terminated);
+    if let Either::Left((result, _)) = future::select(&mut watch, terminated).await {
+        let clean = result.is_ok();
+        return ServiceExit { result, clean };
+    }
+
+    handle.drain();
+    match future::select(&mut watch, clock.sleep(deadline)).await {
+        Either::Left((result, _)) => {
+            let clean = result.is_ok();
+            ServiceExit { result, clean }
+        }
+        Either::Right(_) => {
+            handle.shutdown();
+            ServiceExit {
+                result: watch.await,
+                clean: false,
+            }
+        }
+    }
+}
+
+/// Resolves once the process is asked to terminate: it received `SIGTERM` or
+/// `SIGINT` on Unix, or a Ctrl+C, Ctrl+Break, console close, logoff or system
+/// shutdown event on Windows.
+async fn terminated() {
+    let mut signals: Vec<BoxStream<'static, ()>> = Vec::new();
+
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        for kind in [SignalKind::terminate(), SignalKind::interrupt()] {
+            if let Ok(mut signal) = signal(kind) {
+                signals.push(stream::poll_fn(move |cx| signal.poll_recv(cx)).boxed());
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        use tokio::signal::windows;
+
+        if let Ok(mut signal) = windows::ctrl_c() {
+            signals.push(stream::poll_fn(move |cx| signal.poll_recv(cx)).boxed());
+        }
+        if let Ok(mut signal) = windows::ctrl_break() {
+            signals.push(stream::poll_fn(move |cx| signal.poll_recv(cx)).boxed());
+        }
+        if let Ok(mut signal) = windows::ctrl_close() {
+            signals.push(stream::poll_fn(move |cx| signal.poll_recv(cx)).boxed());
+        }
+        if let Ok(mut signal) = windows::ctrl_logoff() {
+            signals.push(stream::poll_fn(move |cx| signal.poll_recv(cx)).boxed());
+        }
+        if let Ok(mut signal) = windows::ctrl_shutdown() {
+            signals.push(stream::poll_fn(move |cx| signal.poll_recv(cx)).boxed());
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    signals.push(
+        stream::once(tokio::signal::ctrl_c())
+            .filter_map(|result| future::ready(result.ok()))
+            .boxed(),
+    );
+
+    // Without any handler left, there is nothing to wait for.
+    if stream::select_all(signals).next().await.is_none() {
+        future::pending::<()>().await;
+    }
+}
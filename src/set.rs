@@ -0,0 +1,87 @@
+use crate::policy::{PolicyFactory, RestartPolicy};
+use crate::task::Task;
+use std::fmt;
+use std::sync::Arc;
+
+/// A set of tasks for a library to export, that applications mount under
+/// their own watcher with [`crate::Builder::mount`], instead of the library
+/// spawning tasks nobody supervises.
+///
+/// A set only carries what is about its tasks: the tasks themselves, with
+/// their names and policies, and the default policy of the ones without.
+/// Everything else is up to the watcher it is mounted under.
+///
+/// ```
+/// # async fn flush() {}
+/// # async fn compact() {}
+/// use std::time::Duration;
+/// use watch::{Backoff, Task, TaskSet};
+///
+/// pub fn tasks() -> TaskSet {
+///     let second = Duration::from_secs(1);
+///     TaskSet::new()
+///         .task(Task::new(flush).name("flush"))
+///         .task(Task::new(compact).name("compact"))
+///         .policy(Backoff::new(second, 60 * second))
+/// }
+/// ```
+#[derive(Default)]
+pub struct TaskSet {
+    pub(crate) tasks: Vec<Task>,
+    pub(crate) policy: Option<PolicyFactory>,
+}
+
+impl TaskSet {
+    /// Creates an empty [`TaskSet`], whose tasks go through the default
+    /// policy of the watcher they are mounted under.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a task to the set, see [`crate::Builder::task`].
+    pub fn task<T>(mut self, task: T) -> Self
+    where
+        T: Into<Task>,
+    {
+        self.tasks.push(task.into());
+        self
+    }
+
+    /// Decides how tasks of the set without a policy of their own are
+    /// respawned, see [`crate::Builder::policy`].
+    pub fn policy<P>(self, policy: P) -> Self
+    where
+        P: RestartPolicy + Clone + Sync + 'static,
+    {
+        self.policy_with(move || policy.clone())
+    }
+
+    /// Decides how tasks of the set without a policy of their own are
+    /// respawned, see [`crate::Builder::policy_with`].
+    pub fn policy_with<F, P>(mut self, policy: F) -> Self
+    where
+        F: Fn() -> P + Send + Sync + 'static,
+        P: RestartPolicy + 'static,
+    {
+        self.policy = Some(Arc::new(move || Box::new(policy())));
+        self
+    }
+
+    /// Returns how many tasks are in the set.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Returns whether the set has no task.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
+
+impl fmt::Debug for TaskSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskSet")
+            .field("tasks", &self.tasks)
+            .finish()
+    }
+}
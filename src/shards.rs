@@ -0,0 +1,68 @@
+use futures::stream::{FuturesUnordered, Stream, StreamExt};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Futures spread over several sets by key, see [`crate::Builder::shards`].
+/// Yields their outputs as they complete, visiting the sets in turn.
+pub(crate) struct Shards<F> {
+    shards: Vec<FuturesUnordered<F>>,
+    /// The set visited first on the next poll.
+    next: usize,
+}
+
+impl<F> Shards<F>
+where
+    F: Future + Unpin,
+{
+    /// Creates `count` empty sets, at least one.
+    pub(crate) fn new(count: usize) -> Self {
+        Self {
+            shards: (0..count.max(1)).map(|_| FuturesUnordered::new()).collect(),
+            next: 0,
+        }
+    }
+
+    /// Adds `future` to the set of `key`.
+    pub(crate) fn push(&mut self, key: usize, future: F) {
+        let count = self.shards.len();
+        self.shards[key % count].push(future);
+    }
+
+    /// Returns whether every set is empty.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.shards.iter().all(FuturesUnordered::is_empty)
+    }
+
+    /// Drops every future.
+    pub(crate) fn clear(&mut self) {
+        for shard in &mut self.shards {
+            *shard = FuturesUnordered::new();
+        }
+    }
+}
+
+impl<F> Stream for Shards<F>
+where
+    F: Future + Unpin,
+{
+    type Item = F::Output;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let count = this.shards.len();
+        for offset in 0..count {
+            let shard = (this.next + offset) % count;
+            if let Poll::Ready(Some(output)) = this.shards[shard].poll_next_unpin(cx) {
+                // Busy sets do not hold the others back.
+                this.next = (shard + 1) % count;
+                return Poll::Ready(Some(output));
+            }
+        }
+        if this.is_empty() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use futures::channel::oneshot;
+use futures::future::{FutureExt, Shared};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Resolves once the watcher begins shutting down, or was dropped.
+///
+/// Tasks can await it alongside their own work to tear down cleanly during the
+/// grace period, see [`crate::Builder::grace_period`]:
+///
+/// ```no_run
+/// # async fn work() {}
+/// # async fn flush() {}
+/// # async fn run() {
+/// use futures::FutureExt;
+/// use std::time::Duration;
+/// use watch::Builder;
+///
+/// let builder = Builder::new().grace_period(Duration::from_secs(5));
+/// let shutdown = builder.shutdown_signal();
+///
+/// builder
+///     .task(move || {
+///         let shutdown = shutdown.clone();
+///         async move {
+///             futures::select! {
+///                 _ = work().fuse() => {}
+///                 _ = shutdown.fuse() => flush().await,
+///             }
+///         }
+///     })
+///     .run()
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ShutdownSignal {
+    inner: Shared<oneshot::Receiver<()>>,
+}
+
+impl ShutdownSignal {
+    /// Returns whether the watcher began shutting down.
+    pub fn is_triggered(&self) -> bool {
+        self.inner.peek().is_some()
+    }
+}
+
+impl fmt::Debug for ShutdownSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShutdownSignal")
+            .field("triggered", &self.is_triggered())
+            .finish()
+    }
+}
+
+impl Future for ShutdownSignal {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // The sender being dropped means the watcher is gone, which is as good
+        // as a shutdown for tasks.
+        self.inner.poll_unpin(cx).map(drop)
+    }
+}
+
+/// Triggers the [`ShutdownSignal`]'s of a watcher.
+pub(crate) struct Shutdown {
+    sender: Option<oneshot::Sender<()>>,
+    signal: ShutdownSignal,
+}
+
+impl Shutdown {
+    pub(crate) fn signal(&self) -> ShutdownSignal {
+        self.signal.clone()
+    }
+
+    pub(crate) fn trigger(&mut self) {
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.send(());
+        }
+    }
+
+    pub(crate) fn is_triggered(&self) -> bool {
+        self.sender.is_none()
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        let (sender, receiver) = oneshot::channel();
+        Self {
+            sender: Some(sender),
+            signal: ShutdownSignal {
+                inner: receiver.shared(),
+            },
+        }
+    }
+}
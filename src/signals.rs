@@ -0,0 +1,44 @@
+use crate::handle::WatchHandle;
+use std::task::{Context, Poll};
+use tokio::signal::unix::{signal, Signal, SignalKind};
+
+/// What is done when a signal is received, see [`crate::Builder::on_signal`].
+pub(crate) type Hook = Box<dyn Fn(&WatchHandle) + Send + Sync>;
+
+/// Calls hooks with the handle of a watcher as the process receives signals,
+/// with the `signals` feature.
+pub(crate) struct SignalHooks {
+    handle: WatchHandle,
+    /// The hooks to call, by signal, until the watcher listens to them.
+    hooks: Vec<(SignalKind, Hook)>,
+    listening: Vec<(Signal, Hook)>,
+}
+
+impl SignalHooks {
+    pub(crate) fn new(hooks: Vec<(SignalKind, Hook)>, handle: WatchHandle) -> Self {
+        Self {
+            handle,
+            hooks,
+            listening: Vec::new(),
+        }
+    }
+
+    /// Begins listening to the signals, which requires a Tokio runtime.
+    /// Signals that cannot be listened to are ignored.
+    pub(crate) fn listen(&mut self) {
+        for (kind, hook) in self.hooks.drain(..) {
+            if let Ok(signal) = signal(kind) {
+                self.listening.push((signal, hook));
+            }
+        }
+    }
+
+    /// Calls the hook of every signal received since the last poll.
+    pub(crate) fn poll(&mut self, cx: &mut Context<'_>) {
+        for (signal, hook) in &mut self.listening {
+            while let Poll::Ready(Some(())) = signal.poll_recv(cx) {
+                hook(&self.handle);
+            }
+        }
+    }
+}
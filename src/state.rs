@@ -0,0 +1,132 @@
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// The state of a task, kept across its instances, as handed to the
+/// factories of tasks created with [`crate::Task::with_state`].
+///
+/// The state lives for as long as the task: every new instance gets a handle
+/// to what the previous ones left, so workers can resume from a checkpoint
+/// without global statics or external storage. Handles are cheap to clone,
+/// clones refer to the same state.
+pub struct StateHandle<S> {
+    state: Arc<Mutex<S>>,
+}
+
+impl<S> StateHandle<S> {
+    pub(crate) fn new(state: S) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, S> {
+        // An instance that panicked while holding the lock may have left the
+        // state half updated, see `update`. The state is kept as is rather
+        // than lost for every later instance.
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns a copy of the state.
+    pub fn get(&self) -> S
+    where
+        S: Clone,
+    {
+        self.lock().clone()
+    }
+
+    /// Replaces the state, returning the previous one.
+    pub fn set(&self, state: S) -> S {
+        std::mem::replace(&mut *self.lock(), state)
+    }
+
+    /// Updates the state in place with `update`, returning what it returns.
+    ///
+    /// If `update` panics, the state is left as `update` left it, which may
+    /// be half updated. Update a copy and [`StateHandle::set`] it for changes
+    /// that must apply at once.
+    pub fn update<F, R>(&self, update: F) -> R
+    where
+        F: FnOnce(&mut S) -> R,
+    {
+        update(&mut self.lock())
+    }
+}
+
+impl<S> Clone for StateHandle<S> {
+    fn clone(&self) -> Self {
+        Self {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<S> fmt::Debug for StateHandle<S>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateHandle")
+            .field("state", &*self.lock())
+            .finish()
+    }
+}
+
+/// A slot through which instances of a task hand a value over to the next
+/// one, as handed to the factories of tasks created with
+/// [`crate::Task::with_handoff`].
+///
+/// An instance deposits what its replacement can reuse, such as a listening
+/// socket or a session token, and the replacement takes it when it starts,
+/// instead of acquiring it again. Along with [`crate::Task::rolling_restart`],
+/// the instance being replaced can deposit a copy while the new one starts,
+/// for restarts without downtime. Handles are cheap to clone, clones refer to
+/// the same slot.
+pub struct Handoff<T> {
+    value: Arc<Mutex<Option<T>>>,
+}
+
+impl<T> Handoff<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            value: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<T>> {
+        // Depositing and taking can't panic halfway.
+        self.value.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Deposits `value` for the next instance, returning the value that was
+    /// deposited before and not taken, if any.
+    pub fn deposit(&self, value: T) -> Option<T> {
+        self.lock().replace(value)
+    }
+
+    /// Takes the value deposited by a previous instance, if any. It is then
+    /// gone until deposited again.
+    pub fn take(&self) -> Option<T> {
+        self.lock().take()
+    }
+
+    /// Returns whether a value was deposited and not taken yet.
+    pub fn is_deposited(&self) -> bool {
+        self.lock().is_some()
+    }
+}
+
+impl<T> Clone for Handoff<T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: Arc::clone(&self.value),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Handoff<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handoff")
+            .field("deposited", &self.is_deposited())
+            .finish()
+    }
+}
@@ -0,0 +1,104 @@
+use crate::id::TaskId;
+use crate::info::TaskInfo;
+
+/// Decides which other tasks are restarted along with a task whose instance
+/// exited and that is about to be restarted.
+///
+/// By default only the task itself is, see [`OneForOne`]. [`OneForAll`] and
+/// [`RestForOne`] cover tasks that depend on one another, and implementations
+/// can scope restarts in any other way, such as by labels:
+///
+/// ```no_run
+/// # async fn serve() {}
+/// # async fn run() {
+/// use watch::{Builder, SupervisionStrategy, TaskId, TaskInfo};
+///
+/// /// Restarts every task of the same shard.
+/// struct SameShard;
+///
+/// impl SupervisionStrategy for SameShard {
+///     fn scope(&mut self, task: TaskId, tasks: &[TaskInfo]) -> Vec<TaskId> {
+///         let shard = tasks[task.index()].labels().get("shard");
+///         tasks
+///             .iter()
+///             .filter(|other| other.labels().get("shard") == shard)
+///             .map(TaskInfo::id)
+///             .collect()
+///     }
+/// }
+///
+/// Builder::new()
+///     .task(serve)
+///     .strategy(SameShard)
+///     .run()
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub trait SupervisionStrategy: Send {
+    /// Returns the tasks to restart along with `task`, given a snapshot of
+    /// every task indexed by task. Including `task` itself, or tasks that do
+    /// not exist, has no effect.
+    ///
+    /// Only running tasks are restarted: their running instance is cancelled
+    /// as with [`crate::WatchHandle::cancel_current`], and their own
+    /// [`crate::RestartPolicy`] decides when a new one is spawned, which
+    /// spends their budgets and waits for their turn to start like any other
+    /// restart, then in the [`RestartOrder`]. Tasks waiting for their restart
+    /// delay already restart, and retired, paused and removed tasks are left
+    /// alone.
+    fn scope(&mut self, task: TaskId, tasks: &[TaskInfo]) -> Vec<TaskId>;
+}
+
+/// Only restarts the task whose instance exited. This is the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OneForOne;
+
+impl SupervisionStrategy for OneForOne {
+    fn scope(&mut self, _task: TaskId, _tasks: &[TaskInfo]) -> Vec<TaskId> {
+        Vec::new()
+    }
+}
+
+/// Restarts every task along with the one whose instance exited, for sets
+/// of tasks that can't work without one another.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OneForAll;
+
+impl SupervisionStrategy for OneForAll {
+    fn scope(&mut self, _task: TaskId, tasks: &[TaskInfo]) -> Vec<TaskId> {
+        tasks.iter().map(TaskInfo::id).collect()
+    }
+}
+
+/// Restarts the tasks added after the one whose instance exited, for
+/// pipelines where every task depends on the ones added before it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RestForOne;
+
+impl SupervisionStrategy for RestForOne {
+    fn scope(&mut self, task: TaskId, tasks: &[TaskInfo]) -> Vec<TaskId> {
+        (task.index() + 1..tasks.len()).map(TaskId::from).collect()
+    }
+}
+
+/// In which order the tasks a [`SupervisionStrategy`] restarts together
+/// start again, see [`crate::Builder::restart_order`].
+///
+/// Ordered restarts go through the tasks by registration order, the task
+/// whose instance exited included: each waits for its turn once its own
+/// [`crate::RestartPolicy`] let it start, and tasks that are not going to
+/// start again, such as retired or paused ones, give their turn up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestartOrder {
+    /// Each task starts once the tasks added before it started. This is the
+    /// default.
+    #[default]
+    Registration,
+    /// Each task starts once the tasks added before it are ready, see
+    /// [`crate::Task::signals_readiness`], as Erlang supervisors do, so that
+    /// tasks do not race the ones they depend on.
+    Ready,
+    /// Each task starts as soon as its own policy lets it.
+    Unordered,
+}
@@ -0,0 +1,132 @@
+use crate::exit::ExitReason;
+use crate::id::TaskId;
+use std::time::Duration;
+
+/// What a [`crate::Watch`] went through, returned once it stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    tasks: Vec<TaskSummary>,
+    shutdown: Option<ShutdownReport>,
+}
+
+impl Summary {
+    pub(crate) fn new(tasks: Vec<TaskSummary>, shutdown: Option<ShutdownReport>) -> Self {
+        Self { tasks, shutdown }
+    }
+
+    /// Returns how the shutdown went, if the watcher was shut down rather
+    /// than drained or left without tasks, see
+    /// [`crate::WatchHandle::shutdown`].
+    pub fn shutdown(&self) -> Option<&ShutdownReport> {
+        self.shutdown.as_ref()
+    }
+
+    /// Returns the summary of every task, indexed by task.
+    pub fn tasks(&self) -> &[TaskSummary] {
+        &self.tasks
+    }
+
+    /// Returns how many instances were spawned across all tasks.
+    pub fn spawned(&self) -> u64 {
+        self.tasks.iter().map(TaskSummary::spawned).sum()
+    }
+
+    /// Returns how many instances were spawned to replace another across all
+    /// tasks, that is every instance but the first of each task.
+    pub fn restarts(&self) -> u64 {
+        self.tasks
+            .iter()
+            .map(|task| task.spawned().saturating_sub(1))
+            .sum()
+    }
+}
+
+/// What a single task went through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskSummary {
+    spawned: u64,
+    last_exit: Option<ExitReason>,
+}
+
+impl TaskSummary {
+    pub(crate) fn new(spawned: u64, last_exit: Option<ExitReason>) -> Self {
+        Self { spawned, last_exit }
+    }
+
+    /// Returns how many instances of the task were spawned.
+    pub fn spawned(&self) -> u64 {
+        self.spawned
+    }
+
+    /// Returns why the last instance exited, if any did.
+    /// Instances dropped by the watcher do not count.
+    pub fn last_exit(&self) -> Option<ExitReason> {
+        self.last_exit.clone()
+    }
+}
+
+/// How the shutdown of a [`crate::Watch`] went, see [`Summary::shutdown`]:
+/// which of the tasks running when it began returned within the grace
+/// period, which were dropped once it was over, and how long it took. Deploy
+/// tooling can assert on clean shutdowns with it.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+/// # async fn main() {
+/// use std::time::Duration;
+/// use watch::{Builder, TaskId};
+///
+/// let builder = Builder::new().grace_period(Duration::from_secs(5));
+/// let shutdown = builder.shutdown_signal();
+/// let (watch, handle) = builder
+///     .task(move || shutdown.clone())
+///     .task(futures::future::pending::<()>)
+///     .build();
+/// handle.shutdown();
+///
+/// let report = watch.await.unwrap().shutdown().cloned().unwrap();
+/// assert_eq!(report.exited(), &[TaskId::from(0)]);
+/// assert_eq!(report.aborted(), &[TaskId::from(1)]);
+/// assert_eq!(report.took(), Duration::from_secs(5));
+/// assert!(!report.is_clean());
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownReport {
+    exited: Vec<TaskId>,
+    aborted: Vec<TaskId>,
+    took: Duration,
+}
+
+impl ShutdownReport {
+    pub(crate) fn new(exited: Vec<TaskId>, aborted: Vec<TaskId>, took: Duration) -> Self {
+        Self {
+            exited,
+            aborted,
+            took,
+        }
+    }
+
+    /// Returns the tasks whose instance returned within the grace period,
+    /// in order.
+    pub fn exited(&self) -> &[TaskId] {
+        &self.exited
+    }
+
+    /// Returns the tasks whose instance was still running once the grace
+    /// period was over, and was dropped, in order.
+    pub fn aborted(&self) -> &[TaskId] {
+        &self.aborted
+    }
+
+    /// Returns how long it took from the beginning of the shutdown until
+    /// the watcher stopped.
+    pub fn took(&self) -> Duration {
+        self.took
+    }
+
+    /// Returns whether every instance returned within the grace period.
+    pub fn is_clean(&self) -> bool {
+        self.aborted.is_empty()
+    }
+}
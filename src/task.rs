@@ -0,0 +1,728 @@
+use crate::builder::Builder;
+use crate::cancellation::Cancellation;
+use crate::context::{Needs, TaskContext};
+use crate::error::WatchError;
+use crate::exit::{ExitReason, FailureKind};
+use crate::id::TaskId;
+use crate::labels::Labels;
+use crate::layer::Layer;
+use crate::mailbox::{self, Mailbox};
+use crate::panic::PanicBehavior;
+use crate::policy::RestartPolicy;
+use crate::state::{Handoff, StateHandle};
+use futures::future::{BoxFuture, FutureExt};
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+pub(crate) type Factory = Arc<dyn Fn(TaskContext) -> BoxFuture<'static, ExitReason> + Send + Sync>;
+
+/// What tells a task apart besides its index, shared with whatever reports on
+/// tasks.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Metadata {
+    pub(crate) name: Option<String>,
+    pub(crate) labels: Labels,
+}
+
+/// How much the watcher depends on a task, see [`Task::critical`] and
+/// [`Task::best_effort`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Criticality {
+    Critical,
+    BestEffort,
+}
+
+/// A task to watch, along with the configuration that only applies to it.
+///
+/// Anything not configured here falls back to the defaults of the
+/// [`crate::Builder`] the task is added to.
+///
+/// ```no_run
+/// # async fn serve() {}
+/// # async fn run() {
+/// use std::time::Duration;
+/// use watch::{Backoff, Builder, Task};
+///
+/// Builder::new()
+///     .task(Task::new(serve).policy(Backoff::new(
+///         Duration::from_secs(1),
+///         Duration::from_secs(60),
+///     )))
+///     .run()
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub struct Task {
+    pub(crate) factory: Factory,
+    pub(crate) metadata: Metadata,
+    pub(crate) policy: Option<Box<dyn RestartPolicy>>,
+    pub(crate) signals_readiness: bool,
+    pub(crate) ready_within: Option<Duration>,
+    pub(crate) rolling_restart: bool,
+    pub(crate) priority: i32,
+    pub(crate) phase: u32,
+    pub(crate) panic_behavior: Option<PanicBehavior>,
+    pub(crate) cancellation: Option<Cancellation>,
+    pub(crate) needs: Option<Needs>,
+    pub(crate) criticality: Option<Criticality>,
+    pub(crate) dependencies: Vec<usize>,
+    pub(crate) deferred: bool,
+    pub(crate) mailbox: Option<mailbox::Sender>,
+    /// The guard the task is removed along with, see
+    /// [`crate::WatchHandle::start_guarded`].
+    pub(crate) owner: Option<Weak<()>>,
+}
+
+impl Task {
+    /// Creates a [`Task`] out of a factory. `factory` is called every time the
+    /// task needs to be (re)spawned. Outputs are ignored.
+    pub fn new<F, T>(factory: F) -> Self
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+        T: Future + Send + 'static,
+    {
+        Self::with_context(move |_| factory())
+    }
+
+    /// Creates a [`Task`] out of a factory that takes the [`TaskContext`] of
+    /// the instance it creates. Outputs are ignored.
+    pub fn with_context<F, T>(factory: F) -> Self
+    where
+        F: Fn(TaskContext) -> T + Send + Sync + 'static,
+        T: Future + Send + 'static,
+    {
+        Self::from_factory(Arc::new(move |context| {
+            factory(context).map(|_| ExitReason::Completed).boxed()
+        }))
+    }
+
+    /// Creates a [`Task`] out of a factory that borrows the application
+    /// context of the watcher, see [`Builder::context`], instead of capturing
+    /// and cloning the same handles. Outputs are ignored.
+    ///
+    /// A watcher without a context of type `C` stops with
+    /// [`crate::WatchError::MissingContext`] as soon as the task is added, so
+    /// that instances never lack it. Were one to, it would fail with
+    /// [`FailureKind::Permanent`] rather than panic.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use watch::{Builder, RestartDecision, Task};
+    ///
+    /// struct App {
+    ///     requests: AtomicUsize,
+    /// }
+    ///
+    /// let app = Arc::new(App { requests: AtomicUsize::new(0) });
+    /// Builder::new()
+    ///     .context(Arc::clone(&app))
+    ///     .task(Task::shared(|app: &App| {
+    ///         app.requests.fetch_add(1, Ordering::SeqCst);
+    ///         async {}
+    ///     }))
+    ///     .task(Task::shared(|app: &App| {
+    ///         app.requests.fetch_add(1, Ordering::SeqCst);
+    ///         async {}
+    ///     }))
+    ///     .policy(|_: &_| RestartDecision::Retire)
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// assert_eq!(app.requests.load(Ordering::SeqCst), 2);
+    /// # }
+    /// ```
+    pub fn shared<F, C, T>(factory: F) -> Self
+    where
+        F: Fn(&C) -> T + Send + Sync + 'static,
+        C: Send + Sync + 'static,
+        T: Future + Send + 'static,
+    {
+        let mut task = Self::from_factory(Arc::new(move |context| {
+            match context.shared::<C>() {
+                Some(shared) => factory(&shared).map(|_| ExitReason::Completed).boxed(),
+                // Checked as the task is added, the context being set for good
+                // once the watcher is built.
+                None => futures::future::ready(ExitReason::Failed(FailureKind::Permanent)).boxed(),
+            }
+        }));
+        task.needs = Some(Needs::context::<C>());
+        task
+    }
+
+    /// Creates a [`Task`] out of a factory that takes a [`StateHandle`] to the
+    /// state of the task, which starts as `initial` and is kept across
+    /// instances. Outputs are ignored.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use std::time::Duration;
+    /// use watch::{Builder, RestartContext, RestartDecision, Task};
+    ///
+    /// let summary = Builder::new()
+    ///     .task(Task::with_state(0, |offset| async move {
+    ///         // Resume from where the previous instance stopped.
+    ///         let from = offset.get();
+    ///         offset.set(from + 10);
+    ///     }))
+    ///     .policy(|context: &RestartContext| match context.instance() {
+    ///         1 | 2 => RestartDecision::RestartAfter(Duration::ZERO),
+    ///         _ => RestartDecision::Retire,
+    ///     })
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// assert_eq!(summary.spawned(), 3);
+    /// # }
+    /// ```
+    pub fn with_state<F, S, T>(initial: S, factory: F) -> Self
+    where
+        F: Fn(StateHandle<S>) -> T + Send + Sync + 'static,
+        S: Send + 'static,
+        T: Future + Send + 'static,
+    {
+        let state = StateHandle::new(initial);
+        Self::new(move || factory(state.clone()))
+    }
+
+    /// Creates a [`Task`] out of a factory that takes a [`Handoff`] through
+    /// which each instance hands a value of type `H` over to the next one.
+    /// Outputs are ignored.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use std::time::Duration;
+    /// use watch::{Builder, RestartContext, RestartDecision, Task};
+    ///
+    /// let summary = Builder::new()
+    ///     .task(Task::with_handoff(|session| async move {
+    ///         // Reuse the session of the previous instance, if it left one.
+    ///         let token = session.take().unwrap_or_else(|| "fresh".to_string());
+    ///         session.deposit(token);
+    ///     }))
+    ///     .policy(|context: &RestartContext| match context.instance() {
+    ///         1 => RestartDecision::RestartAfter(Duration::ZERO),
+    ///         _ => RestartDecision::Retire,
+    ///     })
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// assert_eq!(summary.spawned(), 2);
+    /// # }
+    /// ```
+    pub fn with_handoff<F, H, T>(factory: F) -> Self
+    where
+        F: Fn(Handoff<H>) -> T + Send + Sync + 'static,
+        H: Send + 'static,
+        T: Future + Send + 'static,
+    {
+        let handoff = Handoff::new();
+        Self::new(move || factory(handoff.clone()))
+    }
+
+    /// Creates a [`Task`] out of a factory that takes the [`Mailbox`] of the
+    /// task, which receives the messages of type `M` sent with
+    /// [`crate::WatchHandle::send`]. Outputs are ignored.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use watch::{Builder, Mailbox, Task};
+    ///
+    /// let (watch, handle) = Builder::new()
+    ///     .task(Task::with_mailbox(|mailbox: Mailbox<&str>| async move {
+    ///         while let Some(word) = mailbox.recv().await {
+    ///             println!("got {}", word);
+    ///         }
+    ///     }))
+    ///     .build();
+    /// let watch = tokio::spawn(watch);
+    ///
+    /// handle.send(0, "hello").unwrap();
+    /// assert_eq!(handle.send(0, 42), Err(42));
+    /// handle.shutdown();
+    /// # watch.await.unwrap().unwrap();
+    /// # }
+    /// ```
+    pub fn with_mailbox<F, M, T>(factory: F) -> Self
+    where
+        F: Fn(Mailbox<M>) -> T + Send + Sync + 'static,
+        M: Send + 'static,
+        T: Future + Send + 'static,
+    {
+        let (sender, mailbox) = mailbox::channel();
+        let mut task = Self::new(move || factory(mailbox.clone()));
+        task.mailbox = Some(sender);
+        task
+    }
+
+    /// Creates a [`Task`] out of a factory whose instances return a
+    /// [`Result`]. Errors are passed to `classify`, and the task is retired
+    /// right away when they are [`FailureKind::Permanent`].
+    ///
+    /// ```no_run
+    /// # use std::io;
+    /// # async fn connect() -> io::Result<()> { Ok(()) }
+    /// # async fn run() {
+    /// use std::io::ErrorKind;
+    /// use watch::{Builder, FailureKind, Task};
+    ///
+    /// Builder::new()
+    ///     .task(Task::fallible(connect, |error: &io::Error| match error.kind() {
+    ///         ErrorKind::PermissionDenied => FailureKind::Permanent,
+    ///         _ => FailureKind::Transient,
+    ///     }))
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn fallible<F, T, O, E, C>(factory: F, classify: C) -> Self
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+        T: Future<Output = Result<O, E>> + Send + 'static,
+        C: Fn(&E) -> FailureKind + Send + Sync + 'static,
+    {
+        Self::fallible_with_context(move |_| factory(), classify)
+    }
+
+    /// Creates a [`Task`] out of a factory that takes the [`TaskContext`] of
+    /// the instance it creates, and whose instances return a [`Result`]. See
+    /// [`Task::fallible`].
+    pub fn fallible_with_context<F, T, O, E, C>(factory: F, classify: C) -> Self
+    where
+        F: Fn(TaskContext) -> T + Send + Sync + 'static,
+        T: Future<Output = Result<O, E>> + Send + 'static,
+        C: Fn(&E) -> FailureKind + Send + Sync + 'static,
+    {
+        let classify = Arc::new(classify);
+
+        Self::from_factory(Arc::new(move |context| {
+            let classify = Arc::clone(&classify);
+            factory(context)
+                .map(move |output| match output {
+                    Ok(_) => ExitReason::Completed,
+                    Err(error) => ExitReason::Failed(classify(&error)),
+                })
+                .boxed()
+        }))
+    }
+
+    /// Creates a [`Task`] out of a factory that spawns its instances itself,
+    /// such as through an application-specific wrapper around
+    /// [`tokio::spawn`], and returns their [`tokio::task::JoinHandle`], with
+    /// the `join-set` feature. Outputs are ignored.
+    ///
+    /// Spawned tasks panicking are reported as [`ExitReason::Panicked`], and
+    /// spawned tasks aborted elsewhere as [`ExitReason::Cancelled`]. The
+    /// watcher aborts the spawned task whenever it stops an instance, see
+    /// [`Task::join_handle`] to supervise a task spawned already.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use watch::{Builder, RestartDecision, Task};
+    ///
+    /// let summary = Builder::new()
+    ///     .task(Task::spawned(|| tokio::spawn(async {})))
+    ///     .policy(|_: &_| RestartDecision::Retire)
+    ///     .run()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// assert_eq!(summary.spawned(), 1);
+    /// # }
+    /// ```
+    #[cfg(feature = "join-set")]
+    pub fn spawned<F, T>(factory: F) -> Self
+    where
+        F: Fn() -> tokio::task::JoinHandle<T> + Send + Sync + 'static,
+        T: Send + 'static,
+    {
+        Self::from_factory(Arc::new(move |_| crate::join::instance(factory())))
+    }
+
+    /// Creates a [`Task`] supervising `handle`, a task spawned elsewhere, as
+    /// its first instance, then respawned through `factory`, with the
+    /// `join-set` feature. See [`Task::spawned`].
+    #[cfg(feature = "join-set")]
+    pub fn join_handle<F, T>(handle: tokio::task::JoinHandle<T>, factory: F) -> Self
+    where
+        F: Fn() -> tokio::task::JoinHandle<T> + Send + Sync + 'static,
+        T: Send + 'static,
+    {
+        let running = std::sync::Mutex::new(Some(crate::join::instance(handle)));
+        let factory = Self::spawned(factory).factory;
+        Self::from_factory(Arc::new(move |context: TaskContext| {
+            // Only taken, which can't panic halfway.
+            let adopted = running
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .take();
+            adopted.unwrap_or_else(|| factory(context))
+        }))
+    }
+
+    /// Creates a [`Task`] whose instances run a whole child watcher, built by
+    /// `factory` every time the task needs to be (re)spawned. This composes
+    /// watchers into supervision trees: the parent sees the child subtree as
+    /// a single task, and applies its own policy to it.
+    ///
+    /// An instance completes when the child watcher returns a
+    /// [`crate::Summary`], and fails when it escalates, misses its
+    /// [`Builder::startup_deadline`] or loses a [`crate::Quorum`] that
+    /// escalates, as [`FailureKind::Transient`]. The [`crate::Escalation`] of
+    /// the child is kept as the [`crate::Escalation::child`] of the parent's,
+    /// should the parent escalate in turn. Child watchers without tasks, or
+    /// missing a context, fail as [`FailureKind::Permanent`].
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use watch::{Builder, RestartDecision, Task, WatchError};
+    ///
+    /// let error = Builder::new()
+    ///     .task(Task::supervisor(|| {
+    ///         Builder::new()
+    ///             .task(Task::new(|| async {}).name("reader"))
+    ///             .policy(|_: &_| RestartDecision::Escalate)
+    ///     }))
+    ///     .policy(|_: &_| RestartDecision::Escalate)
+    ///     .run()
+    ///     .await
+    ///     .unwrap_err();
+    ///
+    /// match error {
+    ///     WatchError::Escalated(escalation) => {
+    ///         assert_eq!(escalation.root().name(), Some("reader"));
+    ///     }
+    ///     _ => unreachable!(),
+    /// }
+    /// # }
+    /// ```
+    pub fn supervisor<F>(factory: F) -> Self
+    where
+        F: Fn() -> Builder + Send + Sync + 'static,
+    {
+        Self::from_factory(Arc::new(move |context: TaskContext| {
+            let watch = factory().run();
+            async move {
+                match watch.await {
+                    Ok(_) => ExitReason::Completed,
+                    Err(WatchError::EmptySet | WatchError::MissingContext { .. }) => {
+                        ExitReason::Failed(FailureKind::Permanent)
+                    }
+                    Err(WatchError::Escalated(escalation)) => {
+                        context.escalated(escalation);
+                        ExitReason::Failed(FailureKind::Transient)
+                    }
+                    Err(
+                        WatchError::StartupTimeout { .. }
+                        | WatchError::StartupFailed { .. }
+                        | WatchError::QuorumLost { .. }
+                        | WatchError::BudgetExhausted { .. },
+                    ) => ExitReason::Failed(FailureKind::Transient),
+                }
+            }
+            .boxed()
+        }))
+    }
+
+    fn from_factory(factory: Factory) -> Self {
+        Self {
+            factory,
+            metadata: Metadata::default(),
+            policy: None,
+            signals_readiness: false,
+            ready_within: None,
+            rolling_restart: false,
+            priority: 0,
+            phase: 0,
+            panic_behavior: None,
+            cancellation: None,
+            needs: None,
+            criticality: None,
+            dependencies: Vec::new(),
+            deferred: false,
+            mailbox: None,
+            owner: None,
+        }
+    }
+
+    /// Names this task, so it can be told apart by more than its index, such
+    /// as when filtering [`crate::Event`]'s with [`crate::EventFilter::name`].
+    pub fn name<N>(mut self, name: N) -> Self
+    where
+        N: Into<String>,
+    {
+        self.metadata.name = Some(name.into());
+        self
+    }
+
+    /// Attaches the label `key` with `value` to this task, replacing any
+    /// previous value. Labels carry metadata such as the tenant or shard of a
+    /// task, are part of its [`crate::TaskInfo`], and select tasks through a
+    /// [`crate::Selector`].
+    pub fn label<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.metadata.labels.insert(key.into(), value.into());
+        self
+    }
+
+    /// Decides how this task is respawned according to `policy`, instead of
+    /// the default policy of the [`crate::Builder`].
+    pub fn policy<P>(mut self, policy: P) -> Self
+    where
+        P: RestartPolicy + 'static,
+    {
+        self.policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Only considers an instance of this task ready once it called
+    /// [`TaskContext::ready`], instead of as soon as it is spawned.
+    pub fn signals_readiness(mut self) -> Self {
+        self.signals_readiness = true;
+        self
+    }
+
+    /// Only considers an instance of this task ready once it called
+    /// [`TaskContext::ready`], as with [`Task::signals_readiness`], and drops
+    /// the instances that did not within `deadline` of being spawned. They
+    /// exit with [`ExitReason::TimedOut`] and go through the policy of the
+    /// task like a failed start, so that an instance stuck starting up is
+    /// restarted with backoff instead of lingering forever.
+    ///
+    /// ```
+    /// use futures::future;
+    /// use std::time::Duration;
+    /// use watch::testing::MockClock;
+    /// use watch::{Builder, ExitReason, Task};
+    ///
+    /// let clock = MockClock::new();
+    /// let (mut watch, handle) = Builder::new()
+    ///     .task(Task::new(future::pending::<()>).ready_within(Duration::from_secs(5)))
+    ///     .clock(clock.clone())
+    ///     .build();
+    /// watch.tick();
+    ///
+    /// clock.advance(Duration::from_secs(5));
+    /// watch.tick();
+    /// let info = handle.task_info(0).unwrap();
+    /// assert_eq!(info.last_exit(), Some(ExitReason::TimedOut));
+    /// ```
+    pub fn ready_within(mut self, deadline: Duration) -> Self {
+        self.signals_readiness = true;
+        self.ready_within = Some(deadline);
+        self
+    }
+
+    /// Sets the priority of this task, `0` by default. When tasks wait to be
+    /// spawned, see [`crate::Builder::max_concurrent_starts`], the ones with
+    /// the highest priority go first, so critical workers recover first after
+    /// an incident. Tasks of equal priority go in the order they were queued.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets the startup phase of this task, `0` by default. A task is only
+    /// spawned once every task of an earlier phase is up: it got ready once,
+    /// and is not waiting to be respawned nor starting again. Phases thus
+    /// encode "migrations, then servers, then background jobs", and tasks
+    /// wait for earlier phases to recover before being respawned in turn.
+    ///
+    /// Tasks of earlier phases that were retired once up do not hold later
+    /// phases back, nor do removed tasks. Restarts through
+    /// [`crate::WatchHandle::restart`] spawn tasks right away.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use futures::StreamExt;
+    /// use watch::{Builder, Event, RestartDecision, Task, TaskId};
+    ///
+    /// let (watch, handle) = Builder::new()
+    ///     .task(Task::new(|| async {}).phase(1))
+    ///     .task(Task::new(|| async {}))
+    ///     .policy(|_: &_| RestartDecision::Retire)
+    ///     .build();
+    /// let mut events = handle.events();
+    /// watch.await.unwrap();
+    ///
+    /// assert_eq!(
+    ///     events.next().await,
+    ///     Some(Event::Started { task: TaskId::from(1), instance: 1 }),
+    /// );
+    /// # }
+    /// ```
+    pub fn phase(mut self, phase: u32) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /// Decides what happens when an instance of this task panics, instead of
+    /// the [`crate::Builder::panic_behavior`].
+    pub fn panic_behavior(mut self, behavior: PanicBehavior) -> Self {
+        self.panic_behavior = Some(behavior);
+        self
+    }
+
+    /// Decides how the watcher stops instances of this task, instead of the
+    /// [`crate::Builder::cancellation`].
+    pub fn cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Wraps every instance of this task with `layer`, see [`Layer`]. Layers
+    /// added later wrap the ones added before, and the layers of the
+    /// [`crate::Builder`] wrap the ones of the task.
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer + 'static,
+    {
+        let factory = self.factory;
+        self.factory = Arc::new(move |context: TaskContext| {
+            let instance = factory(context.clone());
+            layer.layer(instance, &context)
+        });
+        self
+    }
+
+    /// Marks this task as critical: the watcher can't do without it, so an
+    /// instance failing for good escalates instead of retiring the task, as
+    /// with [`crate::Builder::fail_fast`] but for this task only.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use watch::{Builder, FailureKind, Task, WatchError};
+    ///
+    /// let error = Builder::new()
+    ///     .task(|| futures::future::pending::<()>())
+    ///     .task(Task::fallible(|| async { Err::<(), _>("bad config") }, |_| FailureKind::Permanent).critical())
+    ///     .run()
+    ///     .await
+    ///     .unwrap_err();
+    /// assert!(matches!(error, WatchError::Escalated(escalation) if escalation.task() == 1));
+    /// # }
+    /// ```
+    pub fn critical(mut self) -> Self {
+        self.criticality = Some(Criticality::Critical);
+        self
+    }
+
+    /// Marks this task as best-effort: the watcher does fine without it, so
+    /// it is quietly retired whenever it would escalate, be it through its
+    /// policy, an exhausted [`crate::RestartBudget`] or
+    /// [`crate::Builder::fail_fast`].
+    pub fn best_effort(mut self) -> Self {
+        self.criticality = Some(Criticality::BestEffort);
+        self
+    }
+
+    /// Declares that this task depends on the task `task`, such as on the
+    /// provider of a connection it holds. Whenever a new instance of `task`
+    /// gets ready after a restart, the running instance of this task is
+    /// restarted as with [`crate::WatchHandle::restart`], so that it does not
+    /// keep what the previous instance handed out. Restarts thus cascade
+    /// along dependency edges, once each dependency is ready again. Tasks
+    /// ready right away restart at most once per cascade, even along cycles.
+    ///
+    /// ```
+    /// # async fn connect() {}
+    /// # async fn serve() {}
+    /// use watch::{Builder, Task};
+    ///
+    /// let watch = Builder::new()
+    ///     .task(connect)
+    ///     .task(Task::new(serve).depends_on(0))
+    ///     .run();
+    /// # drop(watch);
+    /// ```
+    pub fn depends_on<T>(mut self, task: T) -> Self
+    where
+        T: Into<TaskId>,
+    {
+        self.dependencies.push(task.into().index());
+        self
+    }
+
+    /// Registers this task without spawning it, until it is triggered with
+    /// [`crate::WatchHandle::trigger`] or one of the tasks it depends on gets
+    /// ready, see [`Task::depends_on`]. From then on it is supervised like any
+    /// other task. Tasks waiting to be triggered keep the watcher going.
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use watch::{Builder, Task, TaskState};
+    ///
+    /// let (mut watch, handle) = Builder::new()
+    ///     .task(Task::new(futures::future::pending::<()>).signals_readiness())
+    ///     .task(Task::new(futures::future::pending::<()>).deferred())
+    ///     .task(Task::new(futures::future::pending::<()>).depends_on(0).deferred())
+    ///     .build();
+    ///
+    /// watch.tick();
+    /// assert_eq!(handle.task_info(1).unwrap().state(), TaskState::Stopped);
+    ///
+    /// handle.trigger(1);
+    /// watch.tick();
+    /// assert_eq!(handle.task_info(1).unwrap().state(), TaskState::Running);
+    /// assert_eq!(handle.task_info(2).unwrap().state(), TaskState::Stopped);
+    /// # }
+    /// ```
+    pub fn deferred(mut self) -> Self {
+        self.deferred = true;
+        self
+    }
+
+    /// Overlaps instances when this task is restarted through
+    /// [`crate::WatchHandle::restart`]: the new instance is spawned first, and
+    /// the old one is only dropped once the new one is ready. This bounds the
+    /// downtime of server-like tasks, especially along with
+    /// [`Task::signals_readiness`].
+    ///
+    /// If the new instance returns before being ready, it is dropped and the
+    /// old one keeps running.
+    pub fn rolling_restart(mut self) -> Self {
+        self.rolling_restart = true;
+        self
+    }
+}
+
+impl<F, T> From<F> for Task
+where
+    F: Fn() -> T + Send + Sync + 'static,
+    T: Future + Send + 'static,
+{
+    fn from(factory: F) -> Self {
+        Task::new(factory)
+    }
+}
+
+impl fmt::Debug for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Task")
+            .field("name", &self.metadata.name)
+            .field("labels", &self.metadata.labels)
+            .field("priority", &self.priority)
+            .field("phase", &self.phase)
+            .finish()
+    }
+}
@@ -0,0 +1,45 @@
+use crate::task::Task;
+use std::any::Any;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Turns arguments into a [`Task`], if they are of the type the template takes.
+type Spawn = dyn Fn(Box<dyn Any + Send>) -> Option<Task> + Send + Sync;
+
+/// The factory of the children started at runtime through
+/// [`crate::WatchHandle::start_child`], see [`crate::Builder::template`].
+pub(crate) struct Template {
+    spawn: Box<Spawn>,
+}
+
+impl Template {
+    pub(crate) fn new<F, A, T>(template: F) -> Self
+    where
+        F: Fn(A) -> T + Send + Sync + 'static,
+        A: Clone + Send + Sync + 'static,
+        T: Future + Send + 'static,
+    {
+        let template = Arc::new(template);
+
+        Self {
+            spawn: Box::new(move |args| {
+                let args = *args.downcast::<A>().ok()?;
+                let template = Arc::clone(&template);
+                Some(Task::new(move || template(args.clone())))
+            }),
+        }
+    }
+
+    /// Returns the task of a new child taking `args`, or [`None`] if the
+    /// template takes arguments of another type.
+    pub(crate) fn child(&self, args: Box<dyn Any + Send>) -> Option<Task> {
+        (self.spawn)(args)
+    }
+}
+
+impl fmt::Debug for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Template").finish()
+    }
+}
@@ -0,0 +1,312 @@
+//! Fake tasks and helpers to test supervision setups without hand-rolling
+//! mock futures.
+//!
+//! ```
+//! # #[tokio::main(flavor = "current_thread", start_paused = true)]
+//! # async fn main() {
+//! use watch::testing::{CompleteOnCommand, EventRecorder, NeverComplete};
+//! use watch::{Builder, Event, ExitReason, TaskId};
+//!
+//! let (task, controller) = CompleteOnCommand::new();
+//! let (mut watch, handle) = Builder::new().task(task).task(NeverComplete).build();
+//! let mut recorder = EventRecorder::new(&handle);
+//!
+//! watch.tick();
+//! controller.complete();
+//! watch.tick();
+//!
+//! assert!(recorder.events().contains(&Event::Exited {
+//!     task: TaskId::from(0),
+//!     instance: 1,
+//!     reason: ExitReason::Completed,
+//! }));
+//! # }
+//! ```
+
+use crate::clock::Clock;
+use crate::event::{Event, Events};
+use crate::exit::FailureKind;
+use crate::handle::WatchHandle;
+use crate::task::Task;
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::future::{self, BoxFuture, FutureExt};
+use futures::lock::Mutex;
+use futures::stream::StreamExt;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, MutexGuard, PoisonError};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant, SystemTime};
+
+/// A task whose instances never return.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NeverComplete;
+
+impl From<NeverComplete> for Task {
+    fn from(_: NeverComplete) -> Self {
+        Task::new(future::pending::<()>)
+    }
+}
+
+/// A task whose first instances complete right away, and whose instances
+/// fail with [`FailureKind::Transient`] once that many completed.
+#[derive(Debug, Clone, Copy)]
+pub struct FailAfter(pub u64);
+
+impl From<FailAfter> for Task {
+    fn from(FailAfter(completions): FailAfter) -> Self {
+        let spawned = AtomicU64::new(0);
+
+        Task::fallible(
+            move || {
+                let output = if spawned.fetch_add(1, Ordering::Relaxed) < completions {
+                    Ok(())
+                } else {
+                    Err(FailureKind::Transient)
+                };
+                future::ready(output)
+            },
+            |kind: &FailureKind| *kind,
+        )
+    }
+}
+
+/// A task whose instances only return when told so by the [`Controller`]
+/// returned along with it.
+#[derive(Debug)]
+pub struct CompleteOnCommand {
+    commands: UnboundedReceiver<Result<(), FailureKind>>,
+}
+
+impl CompleteOnCommand {
+    /// Creates a [`CompleteOnCommand`] task, along with the [`Controller`]
+    /// telling its instances when to return.
+    pub fn new() -> (Self, Controller) {
+        let (commands, receiver) = mpsc::unbounded();
+        (Self { commands: receiver }, Controller { commands })
+    }
+}
+
+impl From<CompleteOnCommand> for Task {
+    fn from(task: CompleteOnCommand) -> Self {
+        let commands = Arc::new(Mutex::new(task.commands));
+
+        Task::fallible(
+            move || {
+                let commands = Arc::clone(&commands);
+                async move {
+                    match commands.lock().await.next().await {
+                        Some(output) => output,
+                        // Nobody can tell the instance to return anymore.
+                        None => future::pending().await,
+                    }
+                }
+            },
+            |kind: &FailureKind| *kind,
+        )
+    }
+}
+
+/// Tells the instances of a [`CompleteOnCommand`] task when to return.
+///
+/// Every command makes one instance return. Commands sent while no instance
+/// is running are kept for the next ones.
+#[derive(Debug, Clone)]
+pub struct Controller {
+    commands: UnboundedSender<Result<(), FailureKind>>,
+}
+
+impl Controller {
+    /// Makes an instance complete.
+    pub fn complete(&self) {
+        let _ = self.commands.unbounded_send(Ok(()));
+    }
+
+    /// Makes an instance fail as `kind`.
+    pub fn fail(&self, kind: FailureKind) {
+        let _ = self.commands.unbounded_send(Err(kind));
+    }
+}
+
+/// Records the [`Event`]'s of a watcher, to assert on them.
+#[derive(Debug)]
+pub struct EventRecorder {
+    events: Events,
+    recorded: Vec<Event>,
+}
+
+impl EventRecorder {
+    /// Starts recording the events of the watcher controlled by `handle`.
+    pub fn new(handle: &WatchHandle) -> Self {
+        Self {
+            events: handle.events(),
+            recorded: Vec::new(),
+        }
+    }
+
+    /// Returns every event recorded so far, in order.
+    pub fn events(&mut self) -> &[Event] {
+        while let Some(event) = self.events.try_next() {
+            self.recorded.push(event);
+        }
+        &self.recorded
+    }
+}
+
+/// A [`Clock`] whose time only moves when told to, with
+/// [`MockClock::advance`]. Watchers using it need no Tokio runtime for their
+/// delays and deadlines, so tests can drive them with [`crate::Watch::tick`]
+/// from anywhere.
+///
+/// ```
+/// use std::time::Duration;
+/// use watch::testing::MockClock;
+/// use watch::{Backoff, Builder, RestartDecision, TaskId};
+///
+/// let second = Duration::from_secs(1);
+/// let clock = MockClock::new();
+/// let mut watch = Builder::new()
+///     .task(|| async {})
+///     .backoff(Backoff::new(second, 60 * second))
+///     .clock(clock.clone())
+///     .run();
+///
+/// let tick = watch.tick();
+/// assert_eq!(tick.decisions(), &[(TaskId::from(0), RestartDecision::RestartAfter(second))]);
+///
+/// clock.advance(second);
+/// let tick = watch.tick();
+/// assert_eq!(tick.decisions(), &[(TaskId::from(0), RestartDecision::RestartAfter(2 * second))]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    state: Arc<std::sync::Mutex<MockState>>,
+}
+
+#[derive(Debug)]
+struct MockState {
+    now: Instant,
+    /// The wall-clock time matching `now`.
+    system_time: SystemTime,
+    /// The sleeps waiting for time to move, by identifier: until when, and
+    /// the waker of the last poll.
+    sleeping: HashMap<u64, (Instant, Waker)>,
+    /// The identifier of the next sleep.
+    next: u64,
+}
+
+impl MockState {
+    fn lock(state: &std::sync::Mutex<MockState>) -> MutexGuard<'_, MockState> {
+        state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl MockClock {
+    /// Creates a [`MockClock`] standing still at the current instant.
+    pub fn new() -> Self {
+        Self::at(SystemTime::now())
+    }
+
+    /// Creates a [`MockClock`] standing still at the current instant, which
+    /// tells the wall-clock time is `time`, see [`Clock::system_time`].
+    pub fn at(time: SystemTime) -> Self {
+        Self {
+            state: Arc::new(std::sync::Mutex::new(MockState {
+                now: Instant::now(),
+                system_time: time,
+                sleeping: HashMap::new(),
+                next: 0,
+            })),
+        }
+    }
+
+    /// Moves the time forward by `duration`, waking the sleeps that are
+    /// over.
+    pub fn advance(&self, duration: Duration) {
+        let over: Vec<Waker> = {
+            let mut state = MockState::lock(&self.state);
+            state.now += duration;
+            state.system_time += duration;
+            let now = state.now;
+            let over: Vec<u64> = state
+                .sleeping
+                .iter()
+                .filter(|(_, (deadline, _))| *deadline <= now)
+                .map(|(id, _)| *id)
+                .collect();
+            over.into_iter()
+                .filter_map(|id| state.sleeping.remove(&id))
+                .map(|(_, waker)| waker)
+                .collect()
+        };
+        over.into_iter().for_each(Waker::wake);
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        MockState::lock(&self.state).now
+    }
+
+    fn system_time(&self) -> SystemTime {
+        MockState::lock(&self.state).system_time
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'static, ()> {
+        let id = {
+            let mut state = MockState::lock(&self.state);
+            state.next += 1;
+            state.next
+        };
+        MockSleep {
+            state: Arc::clone(&self.state),
+            deadline,
+            id,
+        }
+        .boxed()
+    }
+}
+
+/// A sleep of a [`MockClock`], keeping a single waker however many times it
+/// is polled.
+struct MockSleep {
+    state: Arc<std::sync::Mutex<MockState>>,
+    deadline: Instant,
+    id: u64,
+}
+
+impl Future for MockSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = MockState::lock(&self.state);
+        if state.now >= self.deadline {
+            state.sleeping.remove(&self.id);
+            return Poll::Ready(());
+        }
+        match state.sleeping.get_mut(&self.id) {
+            Some((_, waker)) if waker.will_wake(cx.waker()) => {}
+            Some((_, waker)) => *waker = cx.waker().clone(),
+            None => {
+                state
+                    .sleeping
+                    .insert(self.id, (self.deadline, cx.waker().clone()));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for MockSleep {
+    fn drop(&mut self) {
+        MockState::lock(&self.state).sleeping.remove(&self.id);
+    }
+}
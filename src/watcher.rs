@@ -0,0 +1,2464 @@
+use crate::alert::Alerter;
+use crate::backpressure::Backpressure;
+use crate::budget::{self, Budget};
+use crate::cancellation::Cancellation;
+use crate::clock::{self, Clock};
+use crate::context::{Needs, Shared, TaskContext};
+use crate::debounce::Debounce;
+use crate::error::{Escalation, WatchError};
+use crate::event::{Emitter, Event, Executor, Subscribers};
+use crate::exit::{ExitCounts, ExitReason, FailureKind};
+use crate::handle::{Adopted, Command, WatchHandle};
+use crate::health::{Health, HealthMonitor, STORM_RESTARTS, STORM_WINDOW};
+use crate::id::TaskId;
+use crate::info::{Snapshot, TaskState};
+use crate::keyed::KeyedShards;
+use crate::layer::Layer;
+use crate::mailbox::{self, Mailboxes};
+use crate::observer::WatchObserver;
+use crate::pacing::RespawnPacing;
+use crate::panic::{self, PanicBehavior};
+use crate::policy::{
+    Immediate, PolicyFactory, RestartContext, RestartDecision, RestartPolicy, RestartTrigger,
+};
+use crate::quorum::{Group, QuorumAction, Status};
+use crate::rate::Rate;
+use crate::shards::Shards;
+use crate::shutdown::Shutdown;
+#[cfg(all(unix, feature = "signals"))]
+use crate::signals::SignalHooks;
+use crate::strategy::{RestartOrder, SupervisionStrategy};
+use crate::summary::{ShutdownReport, Summary, TaskSummary};
+use crate::task::{Criticality, Factory, Metadata, Task};
+use crate::template::Template;
+use crate::window::RestartWindow;
+use futures::channel::oneshot;
+use futures::future::{self, AbortHandle, Abortable, Aborted, BoxFuture, Either, FutureExt};
+use futures::stream::{self, BoxStream, FuturesUnordered, StreamExt};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// A task being watched.
+pub(crate) struct Slot {
+    factory: Factory,
+    metadata: Metadata,
+    policy: Box<dyn RestartPolicy>,
+    signals_readiness: bool,
+    /// How long instances have to get ready, see [`Task::ready_within`].
+    ready_within: Option<Duration>,
+    rolling_restart: bool,
+    priority: i32,
+    phase: u32,
+    panic_behavior: Option<PanicBehavior>,
+    cancellation: Option<Cancellation>,
+    /// The indices of the budgets the task spends when it restarts.
+    budgets: Vec<usize>,
+    /// The application context the factory borrows, if any.
+    needs: Option<Needs>,
+    /// Whether the task escalates when failing for good, or never does,
+    /// instead of what [`crate::Builder::fail_fast`] says.
+    criticality: Option<Criticality>,
+    /// The tasks whose new ready instances restart this task, see
+    /// [`Task::depends_on`].
+    dependencies: Vec<usize>,
+    /// Whether the task waits to be triggered before its first instance is
+    /// spawned, see [`Task::deferred`].
+    held: bool,
+    /// The sender of the mailbox of the task, if it has one, until the
+    /// handles know about it.
+    mailbox: Option<mailbox::Sender>,
+    /// The guard the task is removed along with, if any.
+    owner: Option<Weak<()>>,
+    /// Whether the restart delay of the task was extended until restarts
+    /// are allowed again, see [`crate::Builder::restart_window`].
+    deferred: bool,
+    /// Whether the task is removed once its running instance returned, see
+    /// [`WatchHandle::reload`].
+    retiring: bool,
+    /// What the next instance is made out of instead, see
+    /// [`WatchHandle::reload`].
+    replacement: Option<(Box<Task>, Box<dyn RestartPolicy>)>,
+    /// What the next instance is instead of calling the factory, see
+    /// [`WatchHandle::adopt`].
+    adopted: Option<BoxFuture<'static, ExitReason>>,
+    /// How many times in a row the policy restarted the task, see
+    /// [`RestartContext::attempt`].
+    attempt: u32,
+    /// How many instances in a row exited quickly, see [`Debounce`].
+    quick_exits: u32,
+    /// How many instances of the task were spawned so far. Numbers
+    /// instances, so that events about an instance that was replaced since
+    /// can be told apart.
+    instances: u64,
+    last_exit: Option<ExitReason>,
+    /// How many instances exited, by reason.
+    exits: ExitCounts,
+    last_exited_at: Option<Instant>,
+    /// How often instances of the task fail.
+    failures: Rate,
+    /// When the task was restarted within the last [`STORM_WINDOW`], oldest
+    /// first, if health is tracked.
+    restarts: VecDeque<Instant>,
+    /// Whether an instance of the task was ready once.
+    came_up: bool,
+    /// Whether an instance of the task was pending once, rather than
+    /// completing on its first poll. Set by the instances themselves.
+    stayed_up: Arc<AtomicBool>,
+    /// Whether the task was spawned as the watcher started, and none of its
+    /// instances stayed up since, see [`crate::Builder::max_immediate_exits`].
+    starting: bool,
+    /// How many instances in a row completed on their first poll, until one
+    /// stayed up.
+    immediate_exits: u32,
+    /// How many futures of the instances of the task, running or signaling
+    /// readiness, are not over yet. A removed task is released once none is
+    /// left, so that its identifier cannot be confused with the next one.
+    pending: usize,
+    #[cfg(feature = "metrics")]
+    probe: Arc<Mutex<crate::metrics::Probe>>,
+    state: State,
+}
+
+impl Slot {
+    pub(crate) fn new(task: Task, policy: Box<dyn RestartPolicy>, budgets: Vec<usize>) -> Self {
+        Self {
+            factory: task.factory,
+            metadata: task.metadata,
+            policy,
+            signals_readiness: task.signals_readiness,
+            ready_within: task.ready_within,
+            rolling_restart: task.rolling_restart,
+            priority: task.priority,
+            phase: task.phase,
+            panic_behavior: task.panic_behavior,
+            cancellation: task.cancellation,
+            budgets,
+            needs: task.needs,
+            criticality: task.criticality,
+            dependencies: task.dependencies,
+            held: task.deferred,
+            mailbox: task.mailbox,
+            owner: task.owner,
+            deferred: false,
+            retiring: false,
+            replacement: None,
+            adopted: None,
+            attempt: 0,
+            quick_exits: 0,
+            instances: 0,
+            last_exit: None,
+            exits: ExitCounts::default(),
+            last_exited_at: None,
+            failures: Rate::default(),
+            restarts: VecDeque::new(),
+            came_up: false,
+            stayed_up: Arc::default(),
+            starting: false,
+            immediate_exits: 0,
+            pending: 0,
+            #[cfg(feature = "metrics")]
+            probe: Arc::default(),
+            state: State::Stopped,
+        }
+    }
+
+    pub(crate) fn labels(&self) -> &crate::Labels {
+        &self.metadata.labels
+    }
+
+    /// Makes instances out of `task` from now on. What tells the task apart
+    /// and orders it among the others, such as its name, labels,
+    /// dependencies, priority or phase, is kept.
+    fn redefine(&mut self, task: Task, policy: Box<dyn RestartPolicy>) {
+        self.factory = task.factory;
+        self.policy = policy;
+        self.signals_readiness = task.signals_readiness;
+        self.ready_within = task.ready_within;
+        self.rolling_restart = task.rolling_restart;
+        self.panic_behavior = task.panic_behavior;
+        self.cancellation = task.cancellation;
+        self.needs = task.needs;
+        self.criticality = task.criticality;
+        self.attempt = 0;
+    }
+
+    /// Returns whether the task has a ready instance.
+    fn healthy(&self) -> bool {
+        match &self.state {
+            // The instance being replaced was ready.
+            State::Running { current, previous } => current.ready || previous.is_some(),
+            _ => false,
+        }
+    }
+}
+
+/// How a [`Watch`] behaves, as configured by a [`crate::Builder`].
+pub(crate) struct Config {
+    pub(crate) shutdown: Shutdown,
+    pub(crate) grace_period: Duration,
+    pub(crate) max_starting: usize,
+    pub(crate) max_running: usize,
+    pub(crate) shards: usize,
+    pub(crate) observers: Vec<Box<dyn WatchObserver>>,
+    pub(crate) backpressure: Backpressure,
+    pub(crate) incoming: Option<BoxStream<'static, Task>>,
+    pub(crate) executor: Option<Executor>,
+    pub(crate) event_capacity: usize,
+    pub(crate) budgets: Vec<Budget>,
+    pub(crate) quorums: Vec<Group>,
+    pub(crate) health: Option<HealthMonitor>,
+    pub(crate) alerter: Option<Alerter>,
+    pub(crate) strategy: Option<Box<dyn SupervisionStrategy>>,
+    pub(crate) restart_order: RestartOrder,
+    pub(crate) pacing: RespawnPacing,
+    pub(crate) template: Option<Template>,
+    pub(crate) keyed: Option<KeyedShards>,
+    /// Whether tasks may be added at runtime, through a template or a
+    /// [`crate::WatchMap`].
+    pub(crate) open: bool,
+    /// The factory of the policy of tasks added at runtime without one.
+    pub(crate) policy: Option<PolicyFactory>,
+    pub(crate) startup_deadline: Option<Duration>,
+    pub(crate) max_immediate_exits: Option<u32>,
+    /// Whether tasks wait for [`WatchHandle::start`] to be spawned.
+    pub(crate) held: bool,
+    pub(crate) fail_fast: bool,
+    pub(crate) failure_window: Duration,
+    pub(crate) restart_window: Option<RestartWindow>,
+    pub(crate) debounce: Option<Debounce>,
+    pub(crate) clock: Arc<dyn Clock>,
+    pub(crate) context: Option<Shared>,
+    pub(crate) layers: Vec<Arc<dyn Layer>>,
+    pub(crate) backtraces: bool,
+    pub(crate) panic_behavior: PanicBehavior,
+    pub(crate) cancellation: Cancellation,
+    #[cfg(feature = "join-set")]
+    pub(crate) join_set: bool,
+    #[cfg(all(unix, feature = "signals"))]
+    pub(crate) signals: Vec<(crate::SignalKind, crate::signals::Hook)>,
+    #[cfg(feature = "history")]
+    pub(crate) history: Vec<Box<dyn crate::HistorySink>>,
+    #[cfg(feature = "history")]
+    pub(crate) history_errors: Option<crate::history::ErrorHook>,
+    #[cfg(feature = "instrument")]
+    pub(crate) on_poll: Option<crate::instrument::PollHook>,
+}
+
+/// A running instance of a task.
+struct Running {
+    instance: u64,
+    since: Instant,
+    ready: bool,
+    abort: AbortHandle,
+    /// Resolves the [`TaskContext::cancelled`] signal of the instance.
+    cancel: Shutdown,
+    /// Whether the instance is cancelled cooperatively rather than dropped.
+    cooperative: bool,
+    /// The escalation of the child watcher the instance ran, if it gave up.
+    escalation: Arc<Mutex<Option<Escalation>>>,
+    /// Whether the instance was asked to return, see
+    /// [`WatchHandle::restart_at_yield`].
+    restart: Arc<AtomicBool>,
+}
+
+impl Running {
+    /// Stops the instance, see [`Cancellation`].
+    fn stop(&mut self) {
+        if self.cooperative {
+            self.cancel.trigger();
+        } else {
+            self.abort.abort();
+        }
+    }
+
+    /// Stops the instance of the task `id`.
+    fn cancel(&mut self, id: usize, events: &mut Emitter) {
+        self.stop();
+        events.emit(Event::Exited {
+            task: TaskId::from(id),
+            instance: self.instance,
+            reason: ExitReason::Cancelled,
+        });
+    }
+}
+
+/// Where a task is in its lifecycle.
+enum State {
+    /// An instance of the task is running. During a rolling restart,
+    /// `previous` is the instance being replaced, which keeps running until
+    /// `current` is ready.
+    Running {
+        current: Running,
+        previous: Option<Running>,
+    },
+    /// The task waits for the delay decided by its policy before being
+    /// spawned again, `until` it is over, or for a trigger to fire.
+    Delayed {
+        abort: AbortHandle,
+        until: Option<Instant>,
+    },
+    /// The task waits for other instances to be ready, or to return, before
+    /// being spawned, see [`crate::Builder::max_concurrent_starts`] and
+    /// [`crate::Builder::max_running`].
+    Queued,
+    /// The task was retired by its policy, or the watcher is shutting down.
+    Stopped,
+    /// The task was paused through a [`WatchHandle`], and waits to be resumed.
+    Paused,
+    /// The task was removed through a [`WatchHandle`].
+    Removed,
+}
+
+impl State {
+    /// Stops the running instances or aborts the delay of the task `id`, if
+    /// any.
+    fn abort(&mut self, id: usize, events: &mut Emitter) {
+        match self {
+            State::Running { current, previous } => {
+                current.cancel(id, events);
+                if let Some(previous) = previous {
+                    previous.cancel(id, events);
+                }
+            }
+            State::Delayed { abort, .. } => abort.abort(),
+            State::Queued | State::Stopped | State::Paused | State::Removed => {}
+        }
+    }
+}
+
+/// How an instance of a task ended, along with the identifiers of its task and
+/// itself.
+type Outcome = (usize, u64, Result<ExitReason, Aborted>);
+
+/// A running instance of a task, that resolves once it exited or was
+/// aborted.
+type Instance = BoxFuture<'static, Outcome>;
+
+/// The delay before spawning a task, that resolves with the identifier of the
+/// task once it is over or was aborted.
+type Delay = BoxFuture<'static, (usize, Result<(), Aborted>)>;
+
+/// When a task restarting is spawned again, as decided by its policy.
+enum Resume {
+    After(Duration),
+    On(RestartTrigger),
+}
+
+/// Resolves with the identifiers of a task and one of its instances once the
+/// instance signaled its readiness, dropped its [`TaskContext`] without
+/// doing so, or ran out of time to, see [`Task::ready_within`].
+type Readiness = BoxFuture<'static, (usize, u64, Signaled)>;
+
+/// How an instance of a task that signals its readiness stopped waiting to.
+enum Signaled {
+    Ready,
+    /// The instance dropped its [`TaskContext`] without signaling.
+    Dropped,
+    /// The deadline of the instance to get ready is over.
+    Overdue,
+}
+
+/// What happened during a [`Watch::tick`].
+#[derive(Debug)]
+pub struct Tick {
+    decisions: Vec<(TaskId, RestartDecision)>,
+    result: Option<Result<Summary, WatchError>>,
+}
+
+impl Tick {
+    /// Returns the decisions taken about tasks whose instance exited, in
+    /// order, along with the task they are about.
+    pub fn decisions(&self) -> &[(TaskId, RestartDecision)] {
+        &self.decisions
+    }
+
+    /// Returns what the [`Watch`] returned, if it stopped.
+    pub fn result(&self) -> Option<&Result<Summary, WatchError>> {
+        self.result.as_ref()
+    }
+
+    /// Returns what the [`Watch`] returned, if it stopped.
+    pub fn into_result(self) -> Option<Result<Summary, WatchError>> {
+        self.result
+    }
+}
+
+/// The [`Future`] watching a set of tasks, returned by
+/// [`crate::Builder::run`] and [`crate::Builder::build`].
+///
+/// Tasks are identified by their index in `slots`, which is the order they
+/// were added in, children started at runtime being pushed at the end. Slots
+/// are never removed, so an identifier always refers to the same task.
+///
+/// A [`Watch`] is also a [`futures::Stream`] of its [`Event`]'s, from the
+/// first time it is polled as one, which drives it as well. The stream ends
+/// once the watcher stopped, and awaiting the watcher then returns what it
+/// returned:
+///
+/// ```no_run
+/// # async fn serve() {}
+/// # async fn run() {
+/// use futures::StreamExt;
+/// use watch::Builder;
+///
+/// let mut watch = Builder::new().task(serve).run();
+/// while let Some(event) = watch.next().await {
+///     println!("{:?}", event);
+/// }
+/// let summary = watch.await.unwrap();
+/// # }
+/// ```
+///
+/// As with [`crate::Events`], events are kept up to the capacity set with
+/// [`crate::Builder::event_capacity`] until the stream yields them, for
+/// instance while the watcher is awaited or ticked in between. The oldest
+/// ones are dropped beyond it, see [`Watch::missed_events`].
+pub struct Watch {
+    slots: Vec<Slot>,
+    running: Shards<Instance>,
+    /// Where instances run instead, if spawned onto the runtime, see
+    /// [`crate::Builder::join_set`].
+    #[cfg(feature = "join-set")]
+    join_set: Option<tokio::task::JoinSet<Outcome>>,
+    /// The instances the join set dropped before they returned.
+    #[cfg(feature = "join-set")]
+    unjoined: crate::join::Unjoined,
+    delayed: FuturesUnordered<Delay>,
+    readiness: FuturesUnordered<Readiness>,
+    commands: crate::backpressure::Receiver,
+    shutdown: Shutdown,
+    grace_period: Duration,
+    /// When the grace period given to instances during shutdown is over.
+    deadline: Option<BoxFuture<'static, ()>>,
+    /// When the shutdown began, and the tasks running then, see
+    /// [`crate::ShutdownReport`].
+    shutting_down: Option<(Instant, Vec<usize>)>,
+    /// The tasks whose instance was dropped once the grace period was over.
+    aborted: Vec<usize>,
+    /// Whether the watcher waits for running instances to return by
+    /// themselves before stopping.
+    draining: bool,
+    /// How many instances may be starting, that is running but not ready
+    /// yet, at once.
+    max_starting: usize,
+    /// How many tasks may have a running instance at once.
+    max_running: usize,
+    /// The tasks waiting to be spawned once fewer instances are starting,
+    /// ordered by priority then by when they were queued.
+    queue: BinaryHeap<(i32, Reverse<u64>, usize)>,
+    /// How many tasks were queued so far, ordering tasks of equal priority.
+    queued: u64,
+    /// Which other tasks are restarted along with a task, if any is. None
+    /// stands for [`crate::OneForOne`], sparing snapshots.
+    strategy: Option<Box<dyn SupervisionStrategy>>,
+    /// In which order the tasks the strategy restarts together start again.
+    restart_order: RestartOrder,
+    pacing: RespawnPacing,
+    /// How many instances were spawned during the current poll, see
+    /// [`RespawnPacing::YieldEvery`].
+    spawns: usize,
+    /// How many more instances may be spawned to replace another before the
+    /// watcher is shut down, see [`crate::Builder::run_restarts`].
+    restarts_left: Option<u64>,
+    /// The tasks restarting together that did not have their turn yet, by
+    /// registration order, see [`crate::RestartOrder`].
+    ordered: Vec<usize>,
+    /// The tasks among `ordered` whose restart waits for their turn, queued
+    /// once it comes.
+    parked: Vec<usize>,
+    /// The budgets shared by groups of tasks, see [`crate::RestartBudget`].
+    budgets: Vec<Budget>,
+    /// The quorums groups of tasks must meet, see [`crate::Quorum`].
+    quorums: Vec<Group>,
+    /// Tells the callback of [`crate::Builder::on_health_change`] the health
+    /// of the watcher, if any.
+    health: Option<HealthMonitor>,
+    /// Raises alerts as tasks cross thresholds, see
+    /// [`crate::Builder::on_alert`].
+    alerter: Option<Alerter>,
+    /// Whether tasks may be added through the handles, in which case the
+    /// watcher keeps going without tasks, waiting for them.
+    open: bool,
+    /// The factory of the policy of tasks added at runtime without one.
+    policy: Option<PolicyFactory>,
+    /// How long the tasks have to come up once the watcher started.
+    startup_deadline: Option<Duration>,
+    /// When the tasks must have come up by, until they all did.
+    startup: Option<BoxFuture<'static, ()>>,
+    /// After how many immediate exits in a row startup fails, see
+    /// [`crate::Builder::max_immediate_exits`].
+    max_immediate_exits: Option<u32>,
+    /// How many tasks were added to the builder, before any child.
+    initial: usize,
+    /// Whether a task failing for good escalates, see
+    /// [`crate::Builder::fail_fast`].
+    fail_fast: bool,
+    /// Over which failure rates are weighted.
+    failure_window: Duration,
+    /// When policies may restart tasks, see
+    /// [`crate::Builder::restart_window`].
+    restart_window: Option<RestartWindow>,
+    /// How the restarts of flapping tasks are coalesced, see
+    /// [`crate::Builder::debounce`].
+    debounce: Option<Debounce>,
+    /// Until when policies may not restart tasks, see
+    /// [`WatchHandle::freeze`].
+    frozen_until: Option<Instant>,
+    /// The holds of the [`crate::RestartGate`], alive or not, see
+    /// [`Watch::holding`].
+    holds: Vec<Weak<()>>,
+    /// The tasks whose restart waits for the gate to be released, queued
+    /// once it is.
+    gated: Vec<usize>,
+    /// The tasks added as they are yielded, through a handle of the watcher,
+    /// see [`crate::Builder::task_stream`].
+    incoming: Option<(BoxStream<'static, Task>, WatchHandle)>,
+    /// Whether a handle sent a stream of tasks to add since the watcher was
+    /// polled, see [`WatchHandle::merge`].
+    incoming_added: bool,
+    #[cfg(all(unix, feature = "signals"))]
+    signals: SignalHooks,
+    /// The decisions taken during the current [`Watch::tick`], if any.
+    decisions: Option<Vec<(TaskId, RestartDecision)>>,
+    events: Emitter,
+    clock: Arc<dyn Clock>,
+    /// The application context handed to every instance.
+    context: Option<Shared>,
+    /// Wrapping every instance, outermost last.
+    layers: Vec<Arc<dyn Layer>>,
+    /// Called around each poll of an instance, see
+    /// [`crate::Builder::on_poll`].
+    #[cfg(feature = "instrument")]
+    on_poll: Option<crate::instrument::PollHook>,
+    /// Whether panics of instances capture a backtrace.
+    backtraces: bool,
+    /// What happens when tasks without a behavior of their own panic.
+    panic_behavior: PanicBehavior,
+    /// How instances of tasks without a cancellation of their own are
+    /// stopped.
+    cancellation: Cancellation,
+    snapshot: Snapshot,
+    /// The mailboxes of the tasks, see [`Task::with_mailbox`].
+    mailboxes: Mailboxes,
+    /// Where the probes of tasks added at runtime are registered.
+    #[cfg(feature = "metrics")]
+    registry: crate::metrics::Registry,
+    /// Whether the tasks were spawned yet. They are on the first poll, so
+    /// that subscribers see their first instances start.
+    started: bool,
+    /// Whether spawning tasks waits for [`WatchHandle::start`], see
+    /// [`crate::Builder::start_paused`].
+    held: bool,
+    /// Whether tasks are being restarted along dependency edges, see
+    /// [`Watch::restart_dependents`].
+    cascading: bool,
+    /// What the watcher returned while polled as a [`futures::Stream`], for
+    /// the next poll as a [`Future`].
+    result: Option<Result<Summary, WatchError>>,
+}
+
+impl Watch {
+    /// Polls the watcher once, applying everything that is ready: commands,
+    /// instances that exited, delays that are over and so on. Returns what
+    /// happened.
+    ///
+    /// This lets tests drive the watcher deterministically instead of
+    /// sleeping, especially along with [`tokio::time::pause`]:
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+    /// # async fn main() {
+    /// use std::time::Duration;
+    /// use watch::{Backoff, Builder, RestartDecision, Task, TaskId};
+    ///
+    /// let second = Duration::from_secs(1);
+    /// let mut watch = Builder::new()
+    ///     .task(Task::new(|| async {}).policy(Backoff::new(second, 60 * second)))
+    ///     .run();
+    ///
+    /// let tick = watch.tick();
+    /// assert_eq!(tick.decisions(), &[(TaskId::from(0), RestartDecision::RestartAfter(second))]);
+    ///
+    /// tokio::time::advance(second).await;
+    /// let tick = watch.tick();
+    /// assert_eq!(tick.decisions(), &[(TaskId::from(0), RestartDecision::RestartAfter(2 * second))]);
+    /// assert!(tick.result().is_none());
+    /// # }
+    /// ```
+    pub fn tick(&mut self) -> Tick {
+        self.decisions = Some(Vec::new());
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+        let result = match Pin::new(&mut *self).poll(&mut cx) {
+            Poll::Ready(result) => Some(result),
+            Poll::Pending => None,
+        };
+
+        Tick {
+            decisions: self.decisions.take().unwrap_or_default(),
+            result,
+        }
+    }
+
+    /// Returns how many events the watcher dropped so far, as a
+    /// [`futures::Stream`], because they were not yielded before the capacity
+    /// set with [`crate::Builder::event_capacity`] was reached.
+    ///
+    /// ```
+    /// use futures::{future, FutureExt, StreamExt};
+    /// use watch::Builder;
+    ///
+    /// let mut watch = Builder::new()
+    ///     .tasks((0..4).map(|_| future::pending::<()>))
+    ///     .event_capacity(2)
+    ///     .build()
+    ///     .0;
+    /// assert!(watch.next().now_or_never().is_some());
+    /// watch.tick();
+    /// assert!(watch.missed_events() > 0);
+    /// ```
+    pub fn missed_events(&self) -> u64 {
+        self.events.missed()
+    }
+
+    /// Shuts the watcher down once `restarts` instances were spawned to
+    /// replace another, across all tasks.
+    pub(crate) fn shut_down_after(&mut self, restarts: u64) {
+        self.restarts_left = Some(restarts);
+    }
+
+    pub(crate) fn new(mut slots: Vec<Slot>, config: Config) -> (Self, WatchHandle) {
+        let Config {
+            shutdown,
+            grace_period,
+            max_starting,
+            max_running,
+            shards,
+            observers,
+            backpressure,
+            incoming,
+            executor,
+            event_capacity,
+            budgets,
+            quorums,
+            health,
+            alerter,
+            strategy,
+            restart_order,
+            pacing,
+            template,
+            keyed,
+            open,
+            policy,
+            startup_deadline,
+            max_immediate_exits,
+            held,
+            fail_fast,
+            failure_window,
+            restart_window,
+            debounce,
+            clock,
+            context,
+            layers,
+            backtraces,
+            panic_behavior,
+            cancellation,
+            #[cfg(feature = "join-set")]
+            join_set,
+            #[cfg(all(unix, feature = "signals"))]
+            signals,
+            #[cfg(feature = "history")]
+            history,
+            #[cfg(feature = "history")]
+            history_errors,
+            #[cfg(feature = "instrument")]
+            on_poll,
+        } = config;
+        let (sender, commands) = crate::backpressure::channel(backpressure);
+        let initial = slots.len();
+        let metadata: Vec<Metadata> = slots.iter().map(|slot| slot.metadata.clone()).collect();
+        let snapshot = Snapshot::new(&metadata);
+        let subscribers = Subscribers::new(event_capacity, snapshot.clone());
+        let mailboxes = Mailboxes::default();
+        for (id, slot) in slots.iter_mut().enumerate() {
+            mailboxes.set(id, slot.mailbox.take());
+        }
+        let handle = WatchHandle::new(
+            sender,
+            shutdown.signal(),
+            subscribers.clone(),
+            snapshot.clone(),
+            mailboxes.clone(),
+            template.map(Arc::new),
+            Arc::clone(&clock),
+        )
+        .with_keyed(keyed);
+        #[cfg(feature = "metrics")]
+        let registry = {
+            let registry = crate::metrics::Registry::default();
+            for slot in &slots {
+                registry.push(Arc::clone(&slot.probe));
+            }
+            registry
+        };
+        #[cfg(feature = "metrics")]
+        let handle = handle.with_metrics(registry.clone());
+
+        let watch = Self {
+            slots,
+            running: Shards::new(shards),
+            #[cfg(feature = "join-set")]
+            join_set: join_set.then(tokio::task::JoinSet::new),
+            #[cfg(feature = "join-set")]
+            unjoined: crate::join::Unjoined::new(),
+            delayed: FuturesUnordered::new(),
+            readiness: FuturesUnordered::new(),
+            commands,
+            shutdown,
+            grace_period,
+            deadline: None,
+            shutting_down: None,
+            aborted: Vec::new(),
+            draining: false,
+            max_starting: max_starting.max(1),
+            max_running: max_running.max(1),
+            queue: BinaryHeap::new(),
+            queued: 0,
+            strategy,
+            restart_order,
+            pacing,
+            spawns: 0,
+            restarts_left: None,
+            ordered: Vec::new(),
+            parked: Vec::new(),
+            budgets,
+            quorums,
+            health,
+            alerter,
+            open,
+            policy,
+            startup_deadline,
+            startup: None,
+            max_immediate_exits,
+            initial,
+            fail_fast,
+            failure_window,
+            restart_window,
+            debounce,
+            frozen_until: None,
+            holds: Vec::new(),
+            gated: Vec::new(),
+            incoming: incoming.map(|incoming| (incoming, handle.clone())),
+            incoming_added: false,
+            #[cfg(all(unix, feature = "signals"))]
+            signals: SignalHooks::new(signals, handle.clone()),
+            decisions: None,
+            events: {
+                let mut events =
+                    Emitter::new(observers, subscribers, event_capacity, Arc::clone(&clock));
+                #[cfg(feature = "history")]
+                events.record(history, history_errors);
+                if let Some(executor) = executor {
+                    events.offload(executor);
+                }
+                events
+            },
+            clock,
+            context,
+            layers,
+            #[cfg(feature = "instrument")]
+            on_poll,
+            backtraces,
+            panic_behavior,
+            cancellation,
+            snapshot,
+            mailboxes,
+            #[cfg(feature = "metrics")]
+            registry,
+            started: false,
+            held,
+            cascading: false,
+            result: None,
+        };
+
+        (watch, handle)
+    }
+
+    /// Calls the factory of a task and starts watching the new instance.
+    fn instance(&mut self, id: usize) -> Running {
+        self.spawns += 1;
+        let slot = &mut self.slots[id];
+        if let Some((mut task, policy)) = slot.replacement.take() {
+            self.mailboxes.set(id, task.mailbox.take());
+            slot.redefine(*task, policy);
+        }
+        if slot.instances > 0 {
+            if let Some(left) = &mut self.restarts_left {
+                *left = left.saturating_sub(1);
+            }
+        }
+        slot.instances += 1;
+        let instance = slot.instances;
+
+        let (ready, signaled) = oneshot::channel();
+        let (abort, registration) = AbortHandle::new_pair();
+        let escalation = Arc::new(Mutex::new(None));
+        let cancel = Shutdown::default();
+        let restart = Arc::new(AtomicBool::new(false));
+        let context = TaskContext::new(
+            ready,
+            instance,
+            Arc::clone(&escalation),
+            self.context.clone(),
+            Arc::clone(&self.clock),
+            cancel.signal(),
+            Arc::clone(&restart),
+        );
+        let layers = &self.layers;
+        let adopted = slot.adopted.take();
+        let build = || {
+            let future = match adopted {
+                Some(future) => future,
+                None => (slot.factory)(context.clone()),
+            };
+            layers
+                .iter()
+                .fold(future, |future, layer| layer.layer(future, &context))
+        };
+        let future = match slot.panic_behavior.unwrap_or(self.panic_behavior) {
+            PanicBehavior::Propagate => build(),
+            PanicBehavior::Restart | PanicBehavior::Retire => {
+                match panic::construct(build, self.backtraces) {
+                    Ok(future) => panic::catch(future, self.backtraces),
+                    Err(panic) => {
+                        self.events.emit(Event::FactoryPanicked {
+                            task: TaskId::from(id),
+                            instance,
+                            panic: panic.clone(),
+                        });
+                        future::ready(ExitReason::Panicked(panic)).boxed()
+                    }
+                }
+            }
+        };
+        let cancellation = slot.cancellation.unwrap_or(self.cancellation);
+        let future = match cancellation {
+            Cancellation::Abort => future,
+            Cancellation::Cooperative(period) => {
+                // Dropped once the period to wind down is over.
+                let clock = Arc::clone(&self.clock);
+                let overdue = cancel.signal().then(move |()| clock.sleep(period));
+                future::select(future, overdue)
+                    .map(|either| match either {
+                        Either::Left((reason, _)) => reason,
+                        Either::Right(_) => ExitReason::Cancelled,
+                    })
+                    .boxed()
+            }
+        };
+        // Instances tell whether the task is up by being pending once.
+        let future = if slot.stayed_up.load(Ordering::Relaxed) {
+            future
+        } else {
+            let (stayed_up, mut future) = (Arc::clone(&slot.stayed_up), future);
+            future::poll_fn(move |cx| {
+                let poll = future.poll_unpin(cx);
+                if poll.is_pending() {
+                    stayed_up.store(true, Ordering::Relaxed);
+                }
+                poll
+            })
+            .boxed()
+        };
+        self.events.emit(Event::Started {
+            task: TaskId::from(id),
+            instance,
+        });
+
+        #[cfg(feature = "metrics")]
+        let future = crate::metrics::timed(future, Arc::clone(&slot.probe));
+        #[cfg(feature = "instrument")]
+        let future = match &self.on_poll {
+            Some(hook) => {
+                crate::instrument::instrumented(future, TaskId::from(id), Arc::clone(hook))
+            }
+            None => future,
+        };
+        let future = Abortable::new(future, registration).map(move |reason| (id, instance, reason));
+        #[cfg(feature = "join-set")]
+        if let Some(join_set) = &mut self.join_set {
+            self.unjoined.spawn(join_set, future, id, instance);
+        } else {
+            self.running.push(id, future.boxed());
+        }
+        #[cfg(not(feature = "join-set"))]
+        self.running.push(id, future.boxed());
+        slot.pending += 1;
+        if slot.signals_readiness {
+            slot.pending += 1;
+            let signaled = match slot.ready_within {
+                // Instances that dropped their context without signaling
+                // can't get ready anymore, and are dropped once overdue.
+                Some(deadline) => {
+                    let overdue = self.clock.sleep(deadline);
+                    async move {
+                        match future::select(signaled, overdue).await {
+                            Either::Left((Ok(()), _)) => Signaled::Ready,
+                            Either::Left((Err(_), overdue)) => {
+                                overdue.await;
+                                Signaled::Overdue
+                            }
+                            Either::Right(_) => Signaled::Overdue,
+                        }
+                    }
+                    .boxed()
+                }
+                None => signaled
+                    .map(|signaled| match signaled {
+                        Ok(()) => Signaled::Ready,
+                        Err(_) => Signaled::Dropped,
+                    })
+                    .boxed(),
+            };
+            self.readiness.push(
+                signaled
+                    .map(move |signaled| (id, instance, signaled))
+                    .boxed(),
+            );
+        } else {
+            slot.came_up = true;
+            self.events.emit(Event::Ready {
+                task: TaskId::from(id),
+                instance,
+            });
+        }
+
+        Running {
+            instance,
+            since: self.clock.now(),
+            ready: !slot.signals_readiness,
+            abort,
+            cancel,
+            cooperative: cancellation != Cancellation::Abort,
+            escalation,
+            restart,
+        }
+    }
+
+    /// Spawns a new instance of a task.
+    fn spawn(&mut self, id: usize) {
+        let restarted = self.slots[id].came_up;
+        let current = self.instance(id);
+        let ready = current.ready;
+        self.slots[id].held = false;
+        self.slots[id].state = State::Running {
+            current,
+            previous: None,
+        };
+        // Instances that do not signal readiness are ready right away.
+        if restarted && ready {
+            self.restart_dependents(id);
+        }
+        if ready {
+            self.trigger_dependents(id);
+        }
+    }
+
+    /// Spawns the task `id` if it waits to be triggered, see
+    /// [`Task::deferred`]. Tasks of a watcher that is not started yet are then
+    /// spawned along with the others.
+    fn trigger(&mut self, id: usize) {
+        let slot = match self.slots.get_mut(id) {
+            Some(slot) if slot.held => slot,
+            _ => return,
+        };
+        slot.held = false;
+        if let State::Stopped = slot.state {
+            if self.started && !self.held && !self.draining {
+                self.schedule(id);
+            }
+        }
+    }
+
+    /// Triggers the deferred tasks that depend on the task `id`, whose
+    /// instance just got ready.
+    fn trigger_dependents(&mut self, id: usize) {
+        for other in 0..self.slots.len() {
+            if self.slots[other].held && self.slots[other].dependencies.contains(&id) {
+                self.trigger(other);
+            }
+        }
+    }
+
+    /// Spawns a new instance of a task, or queues it if too many instances are
+    /// starting or running already, or earlier phases are not up.
+    fn schedule(&mut self, id: usize) {
+        // Bounded runs are over, see [`crate::Builder::run_restarts`].
+        if self.restarts_left == Some(0) && self.slots[id].instances > 0 {
+            self.slots[id].state = State::Stopped;
+            return;
+        }
+        if self.ordered.first().is_some_and(|&first| first != id) && self.ordered.contains(&id) {
+            // Left out of the queue until its turn comes.
+            self.slots[id].state = State::Queued;
+            self.parked.push(id);
+            return;
+        }
+        if self.can_start() && self.phase_up(self.slots[id].phase) {
+            self.spawn(id);
+        } else {
+            let slot = &mut self.slots[id];
+            slot.state = State::Queued;
+            self.queued += 1;
+            self.queue.push((slot.priority, Reverse(self.queued), id));
+        }
+    }
+
+    /// Spawns queued tasks by order of priority, as long as not too many
+    /// instances are starting or running. Tasks whose earlier phases are not up stay
+    /// queued. Returns whether any was spawned.
+    fn dequeue(&mut self) -> bool {
+        let mut spawned = false;
+        let mut held = Vec::new();
+        while self.can_start() {
+            let queued = match self.queue.pop() {
+                Some(queued) => queued,
+                None => break,
+            };
+            let (_, _, id) = queued;
+            // The task may have been removed or restarted since.
+            if let State::Queued = self.slots[id].state {
+                if self.phase_up(self.slots[id].phase) {
+                    self.spawn(id);
+                    spawned = true;
+                } else {
+                    held.push(queued);
+                }
+            }
+        }
+        self.queue.extend(held);
+        spawned
+    }
+
+    /// Lets the tasks restarting in order have their turn, once the ones
+    /// before them did, see [`RestartOrder`]. Returns whether any was
+    /// scheduled.
+    fn advance(&mut self) -> bool {
+        let mut scheduled = false;
+        while let Some(&id) = self.ordered.first() {
+            if let Some(at) = self.parked.iter().position(|&parked| parked == id) {
+                self.parked.remove(at);
+                // The task may have been restarted since.
+                if let State::Queued = self.slots[id].state {
+                    self.schedule(id);
+                    scheduled = true;
+                }
+            }
+            let done = match &self.slots[id].state {
+                State::Running { current, .. } => {
+                    current.ready || self.restart_order != RestartOrder::Ready
+                }
+                State::Delayed { .. } | State::Queued => false,
+                // Tasks that are not restarting give their turn up.
+                State::Stopped | State::Paused | State::Removed => true,
+            };
+            if !done {
+                break;
+            }
+            self.ordered.remove(0);
+        }
+        scheduled
+    }
+
+    /// Returns whether every task of a phase earlier than `phase` is up: it
+    /// got ready once, and is neither waiting to be respawned nor starting.
+    fn phase_up(&self, phase: u32) -> bool {
+        phase == 0
+            || self
+                .slots
+                .iter()
+                .filter(|slot| slot.phase < phase)
+                .all(|slot| match &slot.state {
+                    State::Running { current, .. } => current.ready,
+                    State::Delayed { .. } | State::Queued => false,
+                    // Deferred tasks do not hold later phases back.
+                    State::Stopped if slot.held => true,
+                    State::Stopped | State::Paused => slot.came_up,
+                    State::Removed => true,
+                })
+    }
+
+    /// Returns whether fewer instances than the caps are running but not
+    /// ready yet, and fewer tasks are running.
+    fn can_start(&self) -> bool {
+        if self.pacing.exhausted(self.spawns) {
+            return false;
+        }
+        if self.max_starting == usize::MAX && self.max_running == usize::MAX {
+            return true;
+        }
+
+        let (mut starting, mut running) = (0, 0);
+        for slot in &self.slots {
+            if let State::Running { current, .. } = &slot.state {
+                running += 1;
+                if !current.ready {
+                    starting += 1;
+                }
+            }
+        }
+        starting < self.max_starting && running < self.max_running
+    }
+
+    /// Spawns a new instance of a running task, that replaces the current one
+    /// once ready.
+    fn replace(&mut self, id: usize) {
+        let restarted = self.slots[id].came_up;
+        let current = self.instance(id);
+        let previous = match mem::replace(&mut self.slots[id].state, State::Stopped) {
+            // A replacement that is not ready yet is itself replaced.
+            State::Running {
+                current: mut replacement,
+                previous: Some(previous),
+            } => {
+                replacement.cancel(id, &mut self.events);
+                previous
+            }
+            State::Running { current, .. } => current,
+            mut state => {
+                state.abort(id, &mut self.events);
+                let ready = current.ready;
+                self.slots[id].state = State::Running {
+                    current,
+                    previous: None,
+                };
+                if restarted && ready {
+                    self.restart_dependents(id);
+                }
+                return;
+            }
+        };
+
+        let ready = current.ready;
+        self.slots[id].state = State::Running {
+            current,
+            previous: Some(previous),
+        };
+        if ready {
+            self.ready(id, self.slots[id].instances);
+            if restarted {
+                self.restart_dependents(id);
+            }
+        }
+    }
+
+    /// Drops the running instance of a task, if any, and spawns a new one
+    /// right away.
+    fn restart(&mut self, id: usize) {
+        if let Some(slot) = self.slots.get_mut(id) {
+            match slot.state {
+                State::Removed | State::Queued => {}
+                State::Running { .. } if slot.rolling_restart => self.replace(id),
+                _ => {
+                    slot.state.abort(id, &mut self.events);
+                    self.spawn(id);
+                }
+            }
+        }
+    }
+
+    /// Restarts the task `id` once `delay` is over, along with the tasks the
+    /// [`SupervisionStrategy`] scopes. Their running instances are cancelled,
+    /// and their own policies decide when they start again, as with
+    /// [`WatchHandle::cancel_current`], in the [`RestartOrder`]. Returns an
+    /// error if the watcher must stop.
+    fn restart_scope(&mut self, id: usize, resume: Resume) -> Result<(), WatchError> {
+        // Taken out while peers restart, so that they do not scope others.
+        let mut strategy = match self.strategy.take() {
+            Some(strategy) => strategy,
+            None => {
+                self.resume(id, resume);
+                return Ok(());
+            }
+        };
+
+        self.publish();
+        let peers: Vec<_> = strategy
+            .scope(TaskId::from(id), &self.snapshot.tasks())
+            .into_iter()
+            .map(TaskId::index)
+            .filter(|&other| {
+                other != id
+                    && matches!(
+                        self.slots.get(other).map(|slot| &slot.state),
+                        Some(State::Running { .. })
+                    )
+            })
+            .collect();
+        if self.restart_order != RestartOrder::Unordered && !peers.is_empty() {
+            self.ordered.extend(peers.iter().copied().chain(Some(id)));
+            self.ordered.sort_unstable();
+            self.ordered.dedup();
+        }
+        self.resume(id, resume);
+        let result = peers
+            .into_iter()
+            .try_for_each(|other| self.cancel_current(other));
+        self.strategy = Some(strategy);
+        result
+    }
+
+    /// Cancels the running instance of the task `id`, if any, letting its
+    /// policy decide when it starts again. Returns an error if the watcher
+    /// must stop.
+    fn cancel_current(&mut self, id: usize) -> Result<(), WatchError> {
+        if let Some(slot) = self.slots.get_mut(id) {
+            if let State::Running { current, previous } = &mut slot.state {
+                current.stop();
+                if let Some(mut previous) = previous.take() {
+                    previous.cancel(id, &mut self.events);
+                }
+                let instance = current.instance;
+                return self.exited(id, instance, ExitReason::Cancelled);
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops the instance of the task `id` that did not get ready in time,
+    /// see [`Task::ready_within`], as if it timed out. Returns an error if
+    /// the watcher must stop.
+    fn overdue(&mut self, id: usize, instance: u64) -> Result<(), WatchError> {
+        if let State::Running { current, .. } = &mut self.slots[id].state {
+            if current.instance == instance && !current.ready {
+                current.stop();
+                return self.exited(id, instance, ExitReason::TimedOut);
+            }
+        }
+        Ok(())
+    }
+
+    /// Spawns a task once it may be, as decided by its policy.
+    fn resume(&mut self, id: usize, resume: Resume) {
+        match resume {
+            Resume::After(delay) => self.delay(id, delay),
+            Resume::On(trigger) => {
+                let (abort, registration) = AbortHandle::new_pair();
+                self.slots[id].state = State::Delayed { abort, until: None };
+                self.delayed.push(
+                    Abortable::new(trigger.fired(), registration)
+                        .map(move |fired| (id, fired))
+                        .boxed(),
+                );
+            }
+        }
+    }
+
+    /// Spawns a task once `delay` is over.
+    fn delay(&mut self, id: usize, delay: Duration) {
+        if delay == Duration::ZERO {
+            if !self.gate(id) {
+                self.schedule(id);
+            }
+            return;
+        }
+
+        let (abort, registration) = AbortHandle::new_pair();
+        self.slots[id].state = State::Delayed {
+            abort,
+            until: Some(clock::after(self.clock.now(), delay)),
+        };
+        self.delayed.push(
+            Abortable::new(self.clock.sleep(delay), registration)
+                .map(move |delay| (id, delay))
+                .boxed(),
+        );
+    }
+
+    /// Schedules a task whose restart delay is over, unless restarts are not
+    /// allowed yet, in which case the delay is extended until they are.
+    fn respawn(&mut self, id: usize) {
+        if self.gate(id) {
+            return;
+        }
+        let slot = &mut self.slots[id];
+        slot.deferred = false;
+        if slot.criticality != Some(Criticality::Critical) {
+            let now = self.clock.now();
+            let mut allowed = self.frozen_until.map_or(now, |until| until.max(now));
+            if let Some(window) = &self.restart_window {
+                let time = self.clock.system_time() + (allowed - now);
+                allowed += window.opens_in(time);
+            }
+            if allowed > now {
+                self.delay(id, allowed - now);
+                self.slots[id].deferred = true;
+                return;
+            }
+        }
+        self.schedule(id);
+    }
+
+    /// Returns whether a hold of the [`crate::RestartGate`] is alive,
+    /// forgetting the ones that are not.
+    fn holding(&mut self) -> bool {
+        self.holds.retain(|hold| hold.strong_count() > 0);
+        !self.holds.is_empty()
+    }
+
+    /// Holds the restart of the task `id` back if the gate is held. Returns
+    /// whether it was.
+    fn gate(&mut self, id: usize) -> bool {
+        if !self.holding() {
+            return false;
+        }
+        // Left out of the queue until the gate is released.
+        self.slots[id].state = State::Queued;
+        self.gated.push(id);
+        true
+    }
+
+    /// Marks an instance as ready, dropping the instance it replaces if any.
+    fn ready(&mut self, id: usize, instance: u64) {
+        let slot = &mut self.slots[id];
+        if let State::Running { current, previous } = &mut slot.state {
+            if current.instance == instance {
+                let got_ready = !current.ready;
+                let restarted = got_ready && slot.came_up;
+                if got_ready {
+                    current.ready = true;
+                    slot.came_up = true;
+                    #[cfg(feature = "metrics")]
+                    if let Ok(mut probe) = slot.probe.lock() {
+                        probe.ready(self.clock.now() - current.since);
+                    }
+                    self.events.emit(Event::Ready {
+                        task: TaskId::from(id),
+                        instance,
+                    });
+                }
+                if let Some(mut previous) = previous.take() {
+                    previous.cancel(id, &mut self.events);
+                }
+                if restarted {
+                    self.restart_dependents(id);
+                }
+                if got_ready {
+                    self.trigger_dependents(id);
+                }
+            }
+        }
+    }
+
+    /// Restarts the running tasks that depend on the task `id`, whose new
+    /// instance just got ready, see [`Task::depends_on`]. Dependents ready
+    /// right away restart their own dependents in turn, each task restarting
+    /// at most once, so that cycles do not restart tasks forever.
+    fn restart_dependents(&mut self, id: usize) {
+        if self.cascading {
+            return;
+        }
+        self.cascading = true;
+        let mut restarted = vec![id];
+        let mut next = 0;
+        while let Some(&task) = restarted.get(next) {
+            next += 1;
+            let dependents: Vec<_> = self
+                .slots
+                .iter()
+                .enumerate()
+                .filter(|(other, slot)| {
+                    !restarted.contains(other)
+                        && slot.dependencies.contains(&task)
+                        && matches!(slot.state, State::Running { .. })
+                })
+                .map(|(other, _)| other)
+                .collect();
+            for dependent in dependents {
+                self.restart(dependent);
+                if let State::Running { current, .. } = &self.slots[dependent].state {
+                    if current.ready {
+                        restarted.push(dependent);
+                    }
+                }
+            }
+        }
+        self.cascading = false;
+    }
+
+    /// Counts the instance `instance` of the task `id`, which completed on its
+    /// first poll since it was spawned as the watcher started, before it was
+    /// ever up. Returns what the watcher
+    /// returns from [`Watch::exited`] if startup fails, see
+    /// [`crate::Builder::max_immediate_exits`].
+    fn immediate_exit(&mut self, id: usize, instance: u64) -> Option<Result<(), WatchError>> {
+        let slot = &mut self.slots[id];
+        slot.immediate_exits += 1;
+        let exits = slot.immediate_exits;
+        let task = TaskId::from(id);
+        self.events.emit(Event::StartupImmediateExit {
+            task,
+            instance,
+            exits,
+        });
+        if self.max_immediate_exits.is_none_or(|max| exits < max) {
+            return None;
+        }
+        let best_effort = slot.criticality == Some(Criticality::BestEffort);
+        let decision = if best_effort {
+            RestartDecision::Retire
+        } else {
+            RestartDecision::Escalate
+        };
+        if let Some(decisions) = &mut self.decisions {
+            decisions.push((task, decision));
+        }
+        if best_effort {
+            self.events.emit(Event::Retired { task, instance });
+            Some(Ok(()))
+        } else {
+            self.events.emit(Event::Escalated { task, instance });
+            Some(Err(WatchError::StartupFailed { task, exits }))
+        }
+    }
+
+    /// Applies the policy of the task whose instance exited. Returns an
+    /// error if the watcher must stop.
+    fn exited(&mut self, id: usize, instance: u64, reason: ExitReason) -> Result<(), WatchError> {
+        let slot = &mut self.slots[id];
+
+        let (current, previous) = match mem::replace(&mut slot.state, State::Stopped) {
+            State::Running { current, previous } => (current, previous),
+            state => {
+                slot.state = state;
+                return Ok(());
+            }
+        };
+        let exited = Event::Exited {
+            task: TaskId::from(id),
+            instance,
+            reason: reason.clone(),
+        };
+        match previous {
+            // The instance being replaced returned by itself.
+            Some(previous) if previous.instance == instance => {
+                slot.state = State::Running {
+                    current,
+                    previous: None,
+                };
+                self.events.emit(exited);
+                return Ok(());
+            }
+            // The replacement returned before being ready.
+            Some(previous) if current.instance == instance => {
+                slot.state = State::Running {
+                    current: previous,
+                    previous: None,
+                };
+                self.events.emit(exited);
+                return Ok(());
+            }
+            previous if current.instance != instance => {
+                slot.state = State::Running { current, previous };
+                return Ok(());
+            }
+            _ => {}
+        }
+        slot.exits.record(&reason);
+        slot.last_exit = Some(reason.clone());
+        let now = self.clock.now();
+        slot.last_exited_at = Some(now);
+        if reason.is_failure() {
+            slot.failures.record(now, self.failure_window);
+        }
+        if let Some(alerter) = &mut self.alerter {
+            alerter.exited(id, slot.metadata.name.as_deref(), &reason);
+        }
+        self.events.emit(exited);
+
+        if self.shutdown.is_triggered() || self.draining {
+            return Ok(());
+        }
+        // The task was left out of a reload, see [`WatchHandle::reload`].
+        if slot.retiring {
+            return self.command(Command::Remove(id));
+        }
+        // The instance returned as asked, see
+        // [`WatchHandle::restart_at_yield`]: its policy is not consulted, but
+        // the restart is paced and accounted for as any other.
+        let asked = current.restart.load(Ordering::Relaxed);
+        slot.starting &= !slot.stayed_up.load(Ordering::Relaxed);
+        if !asked && slot.starting {
+            if let Some(startup) = self.immediate_exit(id, instance) {
+                return startup;
+            }
+        }
+        let slot = &mut self.slots[id];
+
+        let panic_behavior = slot.panic_behavior.unwrap_or(self.panic_behavior);
+        let mut decision = match &reason {
+            _ if asked => RestartDecision::RestartAfter(Duration::ZERO),
+            ExitReason::Failed(FailureKind::Permanent) => RestartDecision::Retire,
+            ExitReason::Panicked(_) if panic_behavior == PanicBehavior::Retire => {
+                RestartDecision::Retire
+            }
+            _ => {
+                let uptime = now - current.since;
+                if matches!(slot.policy.healthy_after(), Some(after) if uptime >= after) {
+                    slot.attempt = 0;
+                }
+                let context = RestartContext::new(uptime, reason.clone(), instance, slot.attempt);
+                let decision = slot.policy.decide(&context);
+                if let RestartDecision::RestartAfter(_) = decision {
+                    slot.attempt = slot.attempt.saturating_add(1);
+                }
+                decision
+            }
+        };
+
+        // Flapping tasks cool down, once per bout of flapping.
+        let mut flapping = None;
+        if let Some(debounce) = self.debounce.as_ref().filter(|_| !asked) {
+            let was_flapping = debounce.flapping(slot.quick_exits);
+            slot.quick_exits = debounce.count(slot.quick_exits, now - current.since);
+            if let RestartDecision::RestartAfter(delay) = decision {
+                if debounce.flapping(slot.quick_exits) {
+                    let cool_down = debounce.cool_down(delay);
+                    decision = RestartDecision::RestartAfter(cool_down);
+                    if !was_flapping {
+                        flapping = Some(cool_down);
+                    }
+                }
+            }
+        }
+
+        let mut exhausted_budget = false;
+        if let RestartDecision::RestartAfter(_) = decision {
+            let budgets = &mut self.budgets;
+            match slot
+                .budgets
+                .iter()
+                .find_map(|&budget| budgets[budget].check(now))
+            {
+                Some(exhausted) => {
+                    exhausted_budget = true;
+                    decision = exhausted;
+                }
+                None => {
+                    for &budget in &slot.budgets {
+                        budgets[budget].spend(now);
+                    }
+                }
+            }
+        }
+
+        // Failing for good brings the whole watcher down for critical tasks,
+        // as all tasks are in fail-fast mode, and best-effort tasks never do.
+        let criticality = self.slots[id].criticality;
+        match decision {
+            RestartDecision::Retire
+                if reason.is_failure()
+                    && criticality.map_or(self.fail_fast, |c| c == Criticality::Critical) =>
+            {
+                decision = RestartDecision::Escalate;
+            }
+            RestartDecision::Escalate if criticality == Some(Criticality::BestEffort) => {
+                decision = RestartDecision::Retire;
+            }
+            _ => {}
+        }
+
+        if asked && !exhausted_budget {
+            if self.health.is_some() {
+                self.slots[id].restarts.push_back(now);
+            }
+            if let Some(alerter) = &mut self.alerter {
+                alerter.restarted(id, self.slots[id].metadata.name.as_deref(), now);
+            }
+            self.respawn(id);
+            return Ok(());
+        }
+        if let Some(decisions) = &mut self.decisions {
+            decisions.push((TaskId::from(id), decision.clone()));
+        }
+
+        match decision {
+            RestartDecision::RestartAfter(delay) => {
+                let delay = self.pacing.delay(delay);
+                if self.health.is_some() {
+                    self.slots[id].restarts.push_back(now);
+                }
+                if let Some(alerter) = &mut self.alerter {
+                    alerter.restarted(id, self.slots[id].metadata.name.as_deref(), now);
+                }
+                if let Some(cool_down) = flapping {
+                    self.events.emit(Event::Flapping {
+                        task: TaskId::from(id),
+                        instance,
+                        cool_down,
+                    });
+                }
+                self.events.emit(Event::RestartScheduled {
+                    task: TaskId::from(id),
+                    instance,
+                    delay,
+                });
+                self.restart_scope(id, Resume::After(delay))
+            }
+            RestartDecision::AwaitTrigger(trigger) => {
+                if self.health.is_some() {
+                    self.slots[id].restarts.push_back(now);
+                }
+                if let Some(alerter) = &mut self.alerter {
+                    alerter.restarted(id, self.slots[id].metadata.name.as_deref(), now);
+                }
+                self.events.emit(Event::RestartAwaiting {
+                    task: TaskId::from(id),
+                    instance,
+                });
+                self.restart_scope(id, Resume::On(trigger))
+            }
+            RestartDecision::Retire => {
+                self.events.emit(Event::Retired {
+                    task: TaskId::from(id),
+                    instance,
+                });
+                Ok(())
+            }
+            RestartDecision::Escalate if exhausted_budget => {
+                self.events.emit(Event::Escalated {
+                    task: TaskId::from(id),
+                    instance,
+                });
+                Err(WatchError::BudgetExhausted {
+                    task: TaskId::from(id),
+                })
+            }
+            RestartDecision::Escalate => {
+                self.events.emit(Event::Escalated {
+                    task: TaskId::from(id),
+                    instance,
+                });
+                let child = match current.escalation.lock() {
+                    Ok(mut escalation) => escalation.take(),
+                    Err(_) => None,
+                };
+                let name = self.slots[id].metadata.name.clone();
+                Err(WatchError::Escalated(Escalation::new(
+                    TaskId::from(id),
+                    name,
+                    reason,
+                    child,
+                )))
+            }
+        }
+    }
+
+    /// Applies a command sent by a [`WatchHandle`]. Returns an error if the
+    /// watcher must stop.
+    fn command(&mut self, command: Command) -> Result<(), WatchError> {
+        // Holds are acknowledged whatever happens, their future waits for it.
+        if let Command::Hold(hold, acknowledge) = command {
+            self.holds.push(hold);
+            let _ = acknowledge.send(());
+            return Ok(());
+        }
+
+        // Children get a slot whatever happens, matching the identifiers
+        // handed out by the handles.
+        if let Command::StartChild(id, mut task) = command {
+            let policy = task.policy.take().unwrap_or_else(|| match &self.policy {
+                Some(policy) => policy(),
+                None => Box::new(Immediate),
+            });
+            let budgets = budget::matching(&self.budgets, &task.metadata.labels);
+            let slot = Slot::new(*task, policy, budgets);
+            for group in &mut self.quorums {
+                if group.selector.matches(slot.labels()) {
+                    group.members.push(id);
+                }
+            }
+            #[cfg(feature = "metrics")]
+            self.registry.set(id, Arc::clone(&slot.probe));
+            if let Some(alerter) = &mut self.alerter {
+                alerter.forget(id);
+            }
+            // The identifier is either new or the one of a released task.
+            if id < self.slots.len() {
+                self.slots[id] = slot;
+            } else {
+                self.slots.push(slot);
+            }
+            self.check_context(id)?;
+            if !self.shutdown.is_triggered() && !self.draining && !self.held && !self.slots[id].held
+            {
+                self.schedule(id);
+            }
+            return Ok(());
+        }
+
+        if self.shutdown.is_triggered() {
+            return Ok(());
+        }
+
+        match command {
+            Command::Restart(_) if self.draining || self.held => {}
+            Command::Restart(id) => self.restart(id),
+            Command::RestartAtYield(id) => {
+                if let Some(State::Running { current, .. }) =
+                    self.slots.get(id).map(|slot| &slot.state)
+                {
+                    current.restart.store(true, Ordering::Relaxed);
+                }
+            }
+            Command::CancelCurrent(id) => return self.cancel_current(id),
+            Command::Expedite(id) => {
+                if let Some(State::Delayed { abort, .. }) =
+                    self.slots.get(id).map(|slot| &slot.state)
+                {
+                    abort.abort();
+                    self.respawn(id);
+                }
+            }
+            Command::Disown(id, owner) => {
+                let owned = self.slots.get(id).and_then(|slot| slot.owner.as_ref());
+                if owned.is_some_and(|owned| owned.ptr_eq(&owner)) {
+                    return self.command(Command::Remove(id));
+                }
+            }
+            Command::Retire(id) => {
+                if let Some(slot) = self.slots.get_mut(id) {
+                    match slot.state {
+                        State::Running { .. } => slot.retiring = true,
+                        _ => return self.command(Command::Remove(id)),
+                    }
+                }
+            }
+            Command::Replace(id, mut task) => {
+                if let Some(needs) = &task.needs {
+                    if let Some(context) = needs.missing(self.context.as_ref()) {
+                        return Err(WatchError::MissingContext {
+                            task: TaskId::from(id),
+                            context,
+                        });
+                    }
+                }
+                let policy = task.policy.take().unwrap_or_else(|| match &self.policy {
+                    Some(policy) => policy(),
+                    None => Box::new(Immediate),
+                });
+                if let Some(slot) = self.slots.get_mut(id) {
+                    if !matches!(slot.state, State::Removed) {
+                        slot.replacement = Some((task, policy));
+                    }
+                }
+            }
+            Command::Adopt(_, _) if self.draining => {}
+            Command::Adopt(id, Adopted(running)) => {
+                if let Some(slot) = self.slots.get_mut(id) {
+                    if let State::Removed = slot.state {
+                        return Ok(());
+                    }
+                    slot.adopted = Some(running);
+                    slot.held = false;
+                    // Otherwise spawned along with the others once started.
+                    if self.started && !self.held {
+                        slot.state.abort(id, &mut self.events);
+                        self.spawn(id);
+                    }
+                }
+            }
+            Command::Remove(id) => {
+                if let Some(slot) = self.slots.get_mut(id) {
+                    if let State::Removed = slot.state {
+                        return Ok(());
+                    }
+                    slot.state.abort(id, &mut self.events);
+                    slot.state = State::Removed;
+                    self.events.emit(Event::Removed {
+                        task: TaskId::from(id),
+                    });
+                    if slot.pending == 0 {
+                        self.release(id);
+                    }
+                }
+            }
+            Command::Pause(id) => {
+                if let Some(slot) = self.slots.get_mut(id) {
+                    if let State::Paused | State::Removed = slot.state {
+                        return Ok(());
+                    }
+                    slot.state.abort(id, &mut self.events);
+                    slot.state = State::Paused;
+                    self.events.emit(Event::Paused {
+                        task: TaskId::from(id),
+                    });
+                }
+            }
+            Command::Resume(id) => {
+                if let Some(slot) = self.slots.get_mut(id) {
+                    match slot.state {
+                        // Spawned along with the others once started or
+                        // triggered.
+                        State::Paused if self.held || slot.held => slot.state = State::Stopped,
+                        State::Paused => self.schedule(id),
+                        _ => {}
+                    }
+                }
+            }
+            Command::Trigger(id) => self.trigger(id),
+            Command::Hold(..) => {}
+            Command::Release => {
+                if !self.holding() {
+                    for id in mem::take(&mut self.gated) {
+                        // The task may have been removed or restarted since.
+                        if let State::Queued = self.slots[id].state {
+                            self.schedule(id);
+                        }
+                    }
+                }
+            }
+            Command::Start => {
+                if self.held {
+                    self.held = false;
+                    if !self.draining {
+                        self.begin();
+                    }
+                }
+            }
+            Command::StartChild(..) => {}
+            Command::Incoming(incoming) => {
+                if self.draining {
+                    return Ok(());
+                }
+                self.incoming = Some(match self.incoming.take() {
+                    Some((tasks, handle)) => {
+                        (stream::select(tasks, incoming.tasks).boxed(), handle)
+                    }
+                    None => (incoming.tasks, incoming.handle),
+                });
+                // Polled along with the commands sent by the tasks it yields.
+                self.incoming_added = true;
+            }
+            Command::Freeze(until) => {
+                self.frozen_until = until;
+                // Deferred restarts may be allowed sooner than they were.
+                for id in 0..self.slots.len() {
+                    let slot = &mut self.slots[id];
+                    if let (true, State::Delayed { abort, .. }) = (slot.deferred, &slot.state) {
+                        abort.abort();
+                        self.respawn(id);
+                    }
+                }
+            }
+            Command::Drain => self.begin_drain(),
+            Command::Shutdown => self.begin_shutdown(),
+        }
+        Ok(())
+    }
+
+    /// Spawns every task but the paused and removed ones, and starts the
+    /// startup deadline.
+    fn begin(&mut self) {
+        self.startup = self
+            .startup_deadline
+            .map(|deadline| self.clock.sleep(deadline));
+        for id in 0..self.slots.len() {
+            let slot = &mut self.slots[id];
+            if let (State::Stopped, false) = (&slot.state, slot.held) {
+                slot.starting = true;
+                self.schedule(id);
+            }
+        }
+    }
+
+    /// Stops respawning tasks, letting running instances return by
+    /// themselves.
+    fn begin_drain(&mut self) {
+        self.draining = true;
+        self.stop_delayed();
+    }
+
+    /// Aborts the delays of tasks waiting to be respawned, empties the queue,
+    /// gives up on paused tasks and stops adding the tasks yielded.
+    fn stop_delayed(&mut self) {
+        for slot in &mut self.slots {
+            match &slot.state {
+                State::Delayed { abort, .. } => abort.abort(),
+                State::Queued | State::Paused => {}
+                _ => continue,
+            }
+            slot.state = State::Stopped;
+        }
+        self.queue.clear();
+        self.gated.clear();
+        self.ordered.clear();
+        self.parked.clear();
+        self.incoming = None;
+    }
+
+    /// Triggers the [`crate::ShutdownSignal`], stops respawning tasks and gives
+    /// running instances the grace period to return. Instances cancelled
+    /// cooperatively are told to stop through [`TaskContext::cancelled`] too.
+    fn begin_shutdown(&mut self) {
+        self.shutdown.trigger();
+        self.stop_delayed();
+        let running = (0..self.slots.len())
+            .filter(|&id| matches!(self.slots[id].state, State::Running { .. }))
+            .collect();
+        self.shutting_down = Some((self.clock.now(), running));
+        for slot in &mut self.slots {
+            if let State::Running { current, previous } = &mut slot.state {
+                for running in std::iter::once(current).chain(previous) {
+                    if running.cooperative {
+                        running.cancel.trigger();
+                    }
+                }
+            }
+        }
+
+        if self.grace_period > Duration::ZERO {
+            self.deadline = Some(self.clock.sleep(self.grace_period));
+        } else {
+            self.drop_running();
+        }
+    }
+
+    /// Returns an error if the task `id` borrows a context the watcher does
+    /// not have.
+    fn check_context(&self, id: usize) -> Result<(), WatchError> {
+        let needs = match &self.slots[id].needs {
+            Some(needs) => needs,
+            None => return Ok(()),
+        };
+        match needs.missing(self.context.as_ref()) {
+            Some(context) => Err(WatchError::MissingContext {
+                task: TaskId::from(id),
+                context,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Takes note that a future of an instance of the task `id` is over,
+    /// releasing the task if it was removed and nothing of it is left.
+    fn settle(&mut self, id: usize) {
+        let slot = &mut self.slots[id];
+        slot.pending -= 1;
+        if slot.pending == 0 && matches!(slot.state, State::Removed) {
+            self.release(id);
+        }
+    }
+
+    /// Hands the identifier of the removed task `id` out again, see
+    /// [`WatchHandle::remove`].
+    fn release(&mut self, id: usize) {
+        for group in &mut self.quorums {
+            group.members.retain(|&member| member != id);
+        }
+        self.queue.retain(|&(_, _, queued)| queued != id);
+        self.gated.retain(|&gated| gated != id);
+        self.ordered.retain(|&ordered| ordered != id);
+        self.parked.retain(|&parked| parked != id);
+        self.mailboxes.set(id, None);
+        self.snapshot.release(id);
+    }
+
+    /// Drops every running instance.
+    fn drop_running(&mut self) {
+        for (id, slot) in self.slots.iter_mut().enumerate() {
+            if let State::Running { .. } = slot.state {
+                if self.shutting_down.is_some() {
+                    self.aborted.push(id);
+                }
+                slot.state.abort(id, &mut self.events);
+                slot.state = State::Stopped;
+            }
+        }
+        self.running.clear();
+        #[cfg(feature = "join-set")]
+        if let Some(join_set) = &mut self.join_set {
+            // Dropping a set aborts its tasks.
+            *join_set = tokio::task::JoinSet::new();
+        }
+    }
+
+    /// Returns whether no instance is running.
+    fn idle(&self) -> bool {
+        #[cfg(feature = "join-set")]
+        if let Some(join_set) = &self.join_set {
+            return self.running.is_empty() && join_set.is_empty();
+        }
+        self.running.is_empty()
+    }
+
+    /// Checks every quorum, applying the action of the ones that were lost.
+    /// Returns an error if the watcher must stop.
+    fn check_quorums(&mut self, cx: &mut Context<'_>) -> Result<(), WatchError> {
+        if self.draining || self.shutdown.is_triggered() {
+            return Ok(());
+        }
+
+        for index in 0..self.quorums.len() {
+            let slots = &self.slots;
+            let group = &mut self.quorums[index];
+            let healthy: Vec<usize> = group
+                .members
+                .iter()
+                .copied()
+                .filter(|&id| slots[id].healthy())
+                .collect();
+            let met = healthy.len() >= group.quorum.min;
+            let previous = mem::replace(&mut group.healthy, healthy);
+            let healthy = group.healthy.len();
+
+            match (group.status, met) {
+                (Status::Met, false) => {
+                    group.status = Status::Lost;
+                    // Some task that was healthy is not anymore.
+                    let task = previous
+                        .iter()
+                        .copied()
+                        .find(|id| !group.healthy.contains(id))
+                        .unwrap_or_default();
+                    if let Some(on_lost) = &mut group.quorum.on_lost {
+                        on_lost(healthy);
+                    }
+                    let action = group.quorum.action;
+                    self.events.emit(Event::QuorumLost {
+                        task: TaskId::from(task),
+                        quorum: index,
+                        healthy,
+                    });
+
+                    match action {
+                        QuorumAction::Notify => {}
+                        QuorumAction::Escalate => {
+                            return Err(WatchError::QuorumLost {
+                                quorum: index,
+                                healthy,
+                            });
+                        }
+                        QuorumAction::Shutdown => {
+                            self.begin_shutdown();
+                            // The grace period begins, poll it.
+                            cx.waker().wake_by_ref();
+                            return Ok(());
+                        }
+                    }
+                }
+                (Status::Lost, true) => {
+                    group.status = Status::Met;
+                    // Some task that was not healthy is now.
+                    let task = group
+                        .healthy
+                        .iter()
+                        .copied()
+                        .find(|id| !previous.contains(id))
+                        .unwrap_or_default();
+                    self.events.emit(Event::QuorumRegained {
+                        task: TaskId::from(task),
+                        quorum: index,
+                        healthy,
+                    });
+                }
+                (Status::Pending, true) => group.status = Status::Met,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Tells the health of the watcher to its callback, if it changed.
+    fn check_health(&mut self, cx: &mut Context<'_>) {
+        let monitor = match &mut self.health {
+            Some(monitor) if !self.draining && !self.shutdown.is_triggered() => monitor,
+            _ => return,
+        };
+
+        let now = self.clock.now();
+        let mut storm_over_at = None;
+        let mut retired = false;
+        for slot in &mut self.slots {
+            while matches!(slot.restarts.front(), Some(at) if now - *at >= STORM_WINDOW) {
+                slot.restarts.pop_front();
+            }
+            if slot.restarts.len() >= STORM_RESTARTS {
+                let over_at = slot.restarts[slot.restarts.len() - STORM_RESTARTS] + STORM_WINDOW;
+                storm_over_at = Some(storm_over_at.map_or(over_at, |at: Instant| at.min(over_at)));
+            }
+            retired |= matches!(slot.state, State::Stopped) && slot.instances > 0;
+        }
+        let lost = self
+            .quorums
+            .iter()
+            .any(|group| group.status == Status::Lost);
+
+        let health = if retired || lost || storm_over_at.is_some() {
+            Health::Degraded
+        } else {
+            Health::Healthy
+        };
+        if health != monitor.health {
+            monitor.health = health;
+            (monitor.on_change)(health);
+        }
+
+        monitor.recheck.poll(cx, &*self.clock, storm_over_at);
+    }
+
+    /// Tells the alerter which tasks are down, and runs its hook.
+    fn check_alerts(&mut self, cx: &mut Context<'_>) {
+        let alerter = match &mut self.alerter {
+            Some(alerter) => alerter,
+            None => return,
+        };
+
+        let watching = !self.draining && !self.shutdown.is_triggered();
+        if watching {
+            let now = self.clock.now();
+            for (id, slot) in self.slots.iter().enumerate() {
+                let down = match &slot.state {
+                    State::Running { .. } => !slot.healthy(),
+                    State::Delayed { .. } | State::Queued => true,
+                    State::Stopped | State::Paused | State::Removed => false,
+                };
+                let down_since = if down {
+                    Some(slot.last_exited_at.unwrap_or(now))
+                } else {
+                    None
+                };
+                alerter.observe(id, slot.metadata.name.as_deref(), down_since, now);
+            }
+        }
+        alerter.poll(cx, &*self.clock, watching);
+    }
+
+    /// Returns the tasks added to the builder that never came up, leaving out
+    /// removed ones.
+    fn down(&self) -> Vec<usize> {
+        self.slots[..self.initial]
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| !slot.came_up && !matches!(slot.state, State::Removed))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Updates the [`crate::TaskInfo`] of every task shared with the handles.
+    fn publish(&self) {
+        let now = self.clock.now();
+        self.snapshot.update(|tasks| {
+            for (slot, info) in self.slots.iter().zip(tasks) {
+                // Nothing changes about removed tasks.
+                if matches!(slot.state, State::Removed) && info.state == TaskState::Removed {
+                    continue;
+                }
+                let (state, started_at, next_restart_at) = match &slot.state {
+                    State::Running { current, .. } if current.ready => {
+                        (TaskState::Running, Some(current.since), None)
+                    }
+                    State::Running { current, .. } => {
+                        (TaskState::Starting, Some(current.since), None)
+                    }
+                    State::Delayed { until, .. } => (TaskState::Delayed, None, *until),
+                    State::Queued => (TaskState::Queued, None, None),
+                    State::Stopped => (TaskState::Stopped, None, None),
+                    State::Paused => (TaskState::Paused, None, None),
+                    State::Removed => (TaskState::Removed, None, None),
+                };
+
+                info.state = state;
+                info.instances = slot.instances;
+                info.last_exit = slot.last_exit.clone();
+                info.exits = slot.exits;
+                info.started_at = started_at;
+                info.last_exited_at = slot.last_exited_at;
+                info.next_restart_at = next_restart_at;
+                info.failure_rate = slot.failures.at(now, self.failure_window);
+            }
+        });
+    }
+
+    fn stop(&mut self, error: Option<WatchError>) -> Poll<Result<Summary, WatchError>> {
+        self.drop_running();
+        self.publish();
+        self.delayed = FuturesUnordered::new();
+        self.readiness = FuturesUnordered::new();
+        self.commands.close();
+        self.shutdown.trigger();
+        self.events.close();
+
+        let shutdown = self.shutting_down.take().map(|(since, running)| {
+            let (aborted, exited) = running
+                .into_iter()
+                .map(TaskId::from)
+                .partition(|id| self.aborted.contains(&id.index()));
+            ShutdownReport::new(exited, aborted, self.clock.now() - since)
+        });
+        Poll::Ready(match error {
+            Some(error) => Err(error),
+            None => Ok(Summary::new(
+                self.slots
+                    .iter()
+                    .map(|slot| TaskSummary::new(slot.instances, slot.last_exit.clone()))
+                    .collect(),
+                shutdown,
+            )),
+        })
+    }
+}
+
+impl Future for Watch {
+    type Output = Result<Summary, WatchError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        if let Some(result) = this.result.take() {
+            return Poll::Ready(result);
+        }
+
+        if this.slots.is_empty() && !this.open && this.incoming.is_none() {
+            return Poll::Ready(Err(WatchError::EmptySet));
+        }
+        this.spawns = 0;
+
+        // Whether anything happened during this poll, to tell idle polls.
+        #[cfg(feature = "metrics")]
+        let mut moved = !this.started;
+        if !this.started {
+            this.started = true;
+            for id in 0..this.slots.len() {
+                if let Err(error) = this.check_context(id) {
+                    return this.stop(Some(error));
+                }
+            }
+            #[cfg(all(unix, feature = "signals"))]
+            this.signals.listen();
+            if !this.held {
+                this.begin();
+            }
+        }
+
+        // Hooks send their commands, applied right away.
+        #[cfg(all(unix, feature = "signals"))]
+        this.signals.poll(cx);
+
+        // Tasks yielded are added through commands too, applied right away.
+        while let Some((tasks, handle)) = &mut this.incoming {
+            match tasks.poll_next_unpin(cx) {
+                Poll::Ready(Some(task)) => {
+                    handle.add(task);
+                }
+                Poll::Ready(None) => this.incoming = None,
+                Poll::Pending => break,
+            }
+        }
+
+        while let Poll::Ready(Some(command)) = this.commands.poll_next_unpin(cx) {
+            #[cfg(feature = "metrics")]
+            {
+                moved = true;
+            }
+            if let Err(error) = this.command(command) {
+                return this.stop(Some(error));
+            }
+        }
+        for command in this.commands.dropped() {
+            if let Some((task, command)) = command.droppable() {
+                this.events.emit(Event::CommandDropped { task, command });
+            }
+        }
+        if mem::take(&mut this.incoming_added) {
+            cx.waker().wake_by_ref();
+        }
+
+        if let Some(deadline) = &mut this.deadline {
+            if deadline.poll_unpin(cx).is_ready() {
+                return this.stop(None);
+            }
+        }
+
+        // Every completion found during a wake is applied before looking
+        // for more, and only the futures that were woken are polled again, so
+        // a burst of exits costs one poll per instance.
+        loop {
+            let mut progress = false;
+
+            while let Poll::Ready(Some((id, delay))) = this.delayed.poll_next_unpin(cx) {
+                progress = true;
+                // An aborted delay was taken care of by whoever aborted it.
+                if delay.is_ok() {
+                    this.respawn(id);
+                }
+            }
+
+            while let Poll::Ready(Some((id, instance, reason))) = this.running.poll_next_unpin(cx) {
+                progress = true;
+                this.settle(id);
+                // An aborted instance was taken care of by whoever aborted it.
+                if let Ok(reason) = reason {
+                    if let Err(error) = this.exited(id, instance, reason) {
+                        return this.stop(Some(error));
+                    }
+                }
+            }
+
+            #[cfg(feature = "join-set")]
+            while let Some(Poll::Ready(Some(joined))) = this
+                .join_set
+                .as_mut()
+                .map(|join_set| join_set.poll_join_next(cx))
+            {
+                progress = true;
+                if let Ok((id, _, _)) = &joined {
+                    this.settle(*id);
+                }
+                match joined {
+                    Ok((id, instance, Ok(reason))) => {
+                        if let Err(error) = this.exited(id, instance, reason) {
+                            return this.stop(Some(error));
+                        }
+                    }
+                    // Instances that let panics propagate, see
+                    // [`PanicBehavior::Propagate`].
+                    Err(error) if error.is_panic() => std::panic::resume_unwind(error.into_panic()),
+                    // Instances dropped by the runtime, as if cancelled.
+                    Err(_) => {
+                        while let Some((id, instance)) = this.unjoined.next() {
+                            this.settle(id);
+                            if let Err(error) = this.exited(id, instance, ExitReason::Cancelled) {
+                                return this.stop(Some(error));
+                            }
+                        }
+                    }
+                    Ok((_, _, Err(Aborted))) => {}
+                }
+            }
+
+            // Polled after the instances, which may have just signaled.
+            while let Poll::Ready(Some((id, instance, signaled))) =
+                this.readiness.poll_next_unpin(cx)
+            {
+                progress = true;
+                match signaled {
+                    Signaled::Ready => this.ready(id, instance),
+                    Signaled::Dropped => {}
+                    Signaled::Overdue => {
+                        if let Err(error) = this.overdue(id, instance) {
+                            this.settle(id);
+                            return this.stop(Some(error));
+                        }
+                    }
+                }
+                this.settle(id);
+            }
+
+            progress |= this.advance();
+            progress |= this.dequeue();
+
+            if !progress {
+                break;
+            }
+            #[cfg(feature = "metrics")]
+            {
+                moved = true;
+            }
+        }
+        #[cfg(feature = "metrics")]
+        this.registry.polled(!moved);
+
+        if let Some(mut startup) = this.startup.take() {
+            let down = this.down();
+            if !down.is_empty() {
+                if startup.poll_unpin(cx).is_ready() {
+                    return this.stop(Some(WatchError::StartupTimeout {
+                        tasks: down.into_iter().map(TaskId::from).collect(),
+                    }));
+                }
+                this.startup = Some(startup);
+            }
+        }
+
+        if let Err(error) = this.check_quorums(cx) {
+            return this.stop(Some(error));
+        }
+        this.check_health(cx);
+        this.check_alerts(cx);
+
+        if this.restarts_left == Some(0) {
+            this.restarts_left = None;
+            this.begin_shutdown();
+            // Polled again for the grace period to be over.
+            cx.waker().wake_by_ref();
+        }
+
+        // Tasks left queued once the pacing budget ran out are spawned on the
+        // next poll, see [`RespawnPacing::YieldEvery`].
+        if this.pacing.exhausted(this.spawns) && !this.queue.is_empty() {
+            cx.waker().wake_by_ref();
+            this.publish();
+            return Poll::Pending;
+        }
+
+        // Paused, deferred, gated and parked tasks keep the watcher going, as
+        // they wait to be resumed, triggered, let through or for their turn,
+        // and so does being open or having tasks yet to be yielded, waiting
+        // for tasks to be added, or waiting to be started.
+        let stopping = this.draining || this.shutdown.is_triggered();
+        let waiting = this.slots.iter().any(|slot| match slot.state {
+            State::Paused => true,
+            State::Stopped => slot.held && !stopping,
+            _ => false,
+        }) || !this.gated.is_empty()
+            || !this.parked.is_empty()
+            || this.incoming.is_some()
+            || ((this.open || this.held) && !stopping);
+        if this.idle() && this.delayed.is_empty() && !waiting {
+            return this.stop(None);
+        }
+
+        this.publish();
+        Poll::Pending
+    }
+}
+
+impl futures::Stream for Watch {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.events.buffer();
+
+        loop {
+            if let Some(event) = this.events.take() {
+                return Poll::Ready(Some(event));
+            }
+            if this.result.is_some() {
+                return Poll::Ready(None);
+            }
+            match Pin::new(&mut *this).poll(cx) {
+                Poll::Ready(result) => this.result = Some(result),
+                Poll::Pending => {
+                    return match this.events.take() {
+                        Some(event) => Poll::Ready(Some(event)),
+                        None => Poll::Pending,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        self.events.close();
+    }
+}
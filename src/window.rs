@@ -0,0 +1,57 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A window of time, every day, during which tasks may be restarted by their
+/// policy, see [`crate::Builder::restart_window`]. The window is in UTC, as
+/// told by [`crate::Clock::system_time`].
+///
+/// ```
+/// use std::time::Duration;
+/// use watch::RestartWindow;
+///
+/// let hour = Duration::from_secs(60 * 60);
+/// // From 02:00 to 04:00.
+/// let nightly = RestartWindow::daily(2 * hour, 4 * hour);
+/// // From 22:00 to 06:00 the next day.
+/// let overnight = RestartWindow::daily(22 * hour, 6 * hour);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RestartWindow {
+    start: Duration,
+    end: Duration,
+}
+
+impl RestartWindow {
+    /// Creates a [`RestartWindow`] open from `start` to `end` after midnight,
+    /// wrapped to a day. A window ending before it starts spans midnight, and
+    /// one ending as it starts is always open.
+    pub fn daily(start: Duration, end: Duration) -> Self {
+        Self {
+            start: modulo_day(start),
+            end: modulo_day(end),
+        }
+    }
+
+    /// Returns how long after `time` the window opens, zero if it is open.
+    pub(crate) fn opens_in(&self, time: SystemTime) -> Duration {
+        let now = modulo_day(time.duration_since(UNIX_EPOCH).unwrap_or_default());
+        let open = match self.start.cmp(&self.end) {
+            std::cmp::Ordering::Equal => true,
+            std::cmp::Ordering::Less => self.start <= now && now < self.end,
+            std::cmp::Ordering::Greater => self.start <= now || now < self.end,
+        };
+        if open {
+            Duration::ZERO
+        } else if now < self.start {
+            self.start - now
+        } else {
+            DAY - now + self.start
+        }
+    }
+}
+
+fn modulo_day(duration: Duration) -> Duration {
+    let nanos = duration.as_nanos() % DAY.as_nanos();
+    Duration::from_nanos(nanos as u64)
+}
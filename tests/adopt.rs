@@ -0,0 +1,94 @@
+use futures::channel::oneshot;
+use futures::future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use watch::testing::MockClock;
+use watch::{Builder, RestartDecision, Task, TaskId, TaskState};
+
+/// A factory counting its calls.
+fn counted() -> (
+    Arc<AtomicU32>,
+    impl Fn() -> future::Pending<()> + Send + Sync,
+) {
+    let calls = Arc::new(AtomicU32::new(0));
+    let factory = {
+        let calls = Arc::clone(&calls);
+        move || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            future::pending::<()>()
+        }
+    };
+    (calls, factory)
+}
+
+#[test]
+fn adopted_futures_are_respawned_through_the_factory() {
+    let (calls, factory) = counted();
+    let (mut watch, handle) = Builder::new()
+        .task(future::pending::<()>)
+        .task(Task::new(factory).name("feed").deferred())
+        .policy(|_: &_| RestartDecision::RestartAfter(Duration::ZERO))
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+    assert_eq!(handle.task_info(1).unwrap().state(), TaskState::Stopped);
+
+    let (done, running) = oneshot::channel::<()>();
+    assert_eq!(handle.adopt("feed", running), Some(TaskId::from(1)));
+    watch.tick();
+    let info = handle.task_info(1).unwrap();
+    assert_eq!(info.state(), TaskState::Running);
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+    done.send(()).unwrap();
+    watch.tick();
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert_eq!(handle.task_info(1).unwrap().instances(), 2);
+}
+
+#[test]
+fn adopted_futures_replace_the_running_instance() {
+    let (calls, factory) = counted();
+    let (mut watch, handle) = Builder::new().task(factory).clock(MockClock::new()).build();
+    watch.tick();
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    assert_eq!(
+        handle.adopt(0, future::pending::<()>()),
+        Some(TaskId::from(0))
+    );
+    watch.tick();
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert_eq!(handle.task_info(0).unwrap().instances(), 2);
+
+    // Adopted futures are supervised like any other instance.
+    let (adopted, aborted) = oneshot::channel::<()>();
+    handle.adopt(0, async move {
+        let _adopted = adopted;
+        future::pending::<()>().await
+    });
+    watch.tick();
+    handle.remove(0);
+    watch.tick();
+    assert!(futures::executor::block_on(aborted).is_err());
+}
+
+#[test]
+fn only_watched_tasks_adopt_futures() {
+    let (mut watch, handle) = Builder::new()
+        .task(Task::new(future::pending::<()>).name("feed"))
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    assert_eq!(handle.adopt("poll", future::ready(())), None);
+    assert_eq!(handle.adopt(1, future::ready(())), None);
+    assert_eq!(
+        handle.adopt(0, future::pending::<()>()),
+        Some(TaskId::from(0))
+    );
+    handle.remove(0);
+    watch.tick();
+    assert_eq!(handle.adopt("feed", future::pending::<()>()), None);
+}
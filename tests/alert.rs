@@ -0,0 +1,105 @@
+use futures::future;
+use futures::task::{self, ArcWake};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::Context;
+use std::time::Duration;
+use watch::testing::{FailAfter, MockClock, NeverComplete};
+use watch::{Alert, AlertKind, Builder, RestartDecision, Thresholds};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+fn recorder() -> (
+    Arc<Mutex<Vec<Alert>>>,
+    impl Fn(Alert) -> future::Ready<()> + Send + Sync + 'static,
+) {
+    let alerts = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&alerts);
+    (alerts, move |alert| {
+        recorded.lock().unwrap().push(alert);
+        future::ready(())
+    })
+}
+
+fn kinds(alerts: &Mutex<Vec<Alert>>) -> Vec<AlertKind> {
+    alerts.lock().unwrap().iter().map(Alert::kind).collect()
+}
+
+#[test]
+fn tasks_down_for_too_long_alert_once() {
+    let clock = MockClock::new();
+    let (alerts, hook) = recorder();
+    let mut watch = Builder::new()
+        .task(FailAfter(0))
+        .policy(|_: &_| RestartDecision::RestartAfter(60 * SECOND))
+        .on_alert(Thresholds::new().downtime(30 * SECOND), hook)
+        .clock(clock.clone())
+        .run();
+
+    watch.tick();
+    clock.advance(29 * SECOND);
+    watch.tick();
+    assert!(kinds(&alerts).is_empty());
+
+    clock.advance(SECOND);
+    watch.tick();
+    clock.advance(20 * SECOND);
+    watch.tick();
+    assert_eq!(kinds(&alerts), [AlertKind::Downtime(30 * SECOND)]);
+}
+
+#[test]
+fn consecutive_failures_alert() {
+    let clock = MockClock::new();
+    let (alerts, hook) = recorder();
+    let mut watch = Builder::new()
+        .task(FailAfter(0))
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .on_alert(Thresholds::new().consecutive_failures(2), hook)
+        .clock(clock.clone())
+        .run();
+
+    watch.tick();
+    assert!(kinds(&alerts).is_empty());
+    clock.advance(SECOND);
+    watch.tick();
+    assert_eq!(kinds(&alerts), [AlertKind::ConsecutiveFailures(2)]);
+}
+
+struct Wakes(AtomicUsize);
+
+impl ArcWake for Wakes {
+    fn wake_by_ref(wakes: &Arc<Self>) {
+        wakes.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn draining_watchers_do_not_wake_themselves_for_alerts() {
+    let clock = MockClock::new();
+    let (alerts, hook) = recorder();
+    let (mut watch, handle) = Builder::new()
+        .task(NeverComplete)
+        .task(FailAfter(0))
+        .policy(|_: &_| RestartDecision::RestartAfter(60 * SECOND))
+        .on_alert(Thresholds::new().downtime(30 * SECOND), hook)
+        .clock(clock.clone())
+        .build();
+    watch.tick();
+    handle.drain();
+
+    let wakes = Arc::new(Wakes(AtomicUsize::new(0)));
+    let waker = task::waker(Arc::clone(&wakes));
+    let mut cx = Context::from_waker(&waker);
+    for _ in 0..3 {
+        assert!(Pin::new(&mut watch).poll(&mut cx).is_pending());
+    }
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 0);
+
+    clock.advance(30 * SECOND);
+    assert!(Pin::new(&mut watch).poll(&mut cx).is_pending());
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 0);
+    assert!(kinds(&alerts).is_empty());
+}
@@ -0,0 +1,126 @@
+use futures::future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use watch::{ExitReason, RestartContext, RestartDecision, RestartTrigger, WatchArray, WatchError};
+
+#[tokio::test(start_paused = true)]
+async fn tasks_restart_until_retired() {
+    let watch: WatchArray<_, _, _, 3> = WatchArray::new(
+        |index| tokio::time::sleep(Duration::from_secs(index as u64 + 1)),
+        |context: &RestartContext| {
+            if context.instance() < 2 {
+                RestartDecision::RestartAfter(Duration::from_secs(1))
+            } else {
+                RestartDecision::Retire
+            }
+        },
+    );
+    let start = tokio::time::Instant::now();
+    assert_eq!(watch.await.unwrap(), [2; 3]);
+    // The slowest task runs twice for 3s, with a 1s delay in between.
+    assert_eq!(start.elapsed(), Duration::from_secs(7));
+}
+
+#[tokio::test]
+async fn immediate_restarts_do_not_hold_other_tasks_back() {
+    let spawned = Arc::new(AtomicUsize::new(0));
+    let counted = Arc::clone(&spawned);
+    let watch: WatchArray<_, _, _, 2> = WatchArray::new(
+        move |index| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if index == 1 {
+                    tokio::task::yield_now().await;
+                }
+            }
+        },
+        |context: &RestartContext| {
+            if context.instance() < 100 {
+                RestartDecision::RestartAfter(Duration::ZERO)
+            } else {
+                RestartDecision::Retire
+            }
+        },
+    );
+    assert_eq!(watch.await.unwrap(), [100; 2]);
+    assert_eq!(spawned.load(Ordering::SeqCst), 200);
+}
+
+#[tokio::test]
+async fn escalations_stop_the_watcher() {
+    let watch: WatchArray<_, _, _, 2> = WatchArray::new(
+        |index| async move {
+            if index == 0 {
+                future::pending::<()>().await;
+            }
+        },
+        |_: &RestartContext| RestartDecision::Escalate,
+    );
+    match watch.await {
+        Err(WatchError::Escalated(escalation)) => assert_eq!(escalation.task().index(), 1),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn triggers_restart_waiting_tasks() {
+    let (fire, fired) = oneshot::channel::<()>();
+    let trigger = RestartTrigger::new(fired);
+    let watch: WatchArray<_, _, _, 1> = WatchArray::new(
+        |_| future::ready(()),
+        move |context: &RestartContext| {
+            if context.instance() == 1 {
+                RestartDecision::AwaitTrigger(trigger.clone())
+            } else {
+                RestartDecision::Retire
+            }
+        },
+    );
+    let watch = tokio::spawn(watch);
+    tokio::task::yield_now().await;
+    assert!(!watch.is_finished());
+
+    fire.send(()).unwrap();
+    assert_eq!(watch.await.unwrap().unwrap(), [2]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn panics_are_reported_without_stopping_other_tasks() {
+    let watch: WatchArray<_, _, _, 2> = WatchArray::new(
+        |index| async move {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            if index == 0 {
+                panic!("corrupted state");
+            }
+        },
+        |context: &RestartContext| match context.reason() {
+            ExitReason::Panicked(panic) if panic.message() == "corrupted state" => {
+                RestartDecision::Retire
+            }
+            ExitReason::Completed if context.instance() < 3 => {
+                RestartDecision::RestartAfter(Duration::ZERO)
+            }
+            _ => RestartDecision::Retire,
+        },
+    );
+    assert_eq!(watch.await.unwrap(), [1, 3]);
+}
+
+#[tokio::test]
+async fn factory_panics_go_through_the_policy() {
+    let watch: WatchArray<_, _, _, 1> = WatchArray::new(
+        |_| -> future::Ready<()> { panic!("no connection") },
+        |context: &RestartContext| match context.reason() {
+            ExitReason::Panicked(_) => RestartDecision::Escalate,
+            _ => RestartDecision::Retire,
+        },
+    );
+    match watch.await {
+        Err(WatchError::Escalated(escalation)) => {
+            assert!(matches!(escalation.reason(), ExitReason::Panicked(_)))
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
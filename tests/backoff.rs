@@ -0,0 +1,125 @@
+use std::time::Duration;
+use watch::testing::{CompleteOnCommand, Controller, MockClock};
+use watch::{Backoff, Builder, FailureKind, RestartDecision, Watch};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+fn watch(backoff: Backoff) -> (Watch, Controller, MockClock) {
+    let (task, controller) = CompleteOnCommand::new();
+    let clock = MockClock::new();
+    let watch = Builder::new()
+        .task(task)
+        .backoff(backoff)
+        .clock(clock.clone())
+        .run();
+    (watch, controller, clock)
+}
+
+/// Makes the running instance fail, returning the decision made about it.
+fn fail(watch: &mut Watch, controller: &Controller) -> RestartDecision {
+    controller.fail(FailureKind::Transient);
+    match watch.tick().decisions() {
+        [(task, decision)] if *task == 0 => decision.clone(),
+        decisions => panic!("unexpected decisions {:?}", decisions),
+    }
+}
+
+#[test]
+fn delays_grow_until_the_task_is_retired() {
+    let (mut watch, controller, clock) = watch(Backoff::new(SECOND, 3 * SECOND).max_attempts(3));
+    watch.tick();
+
+    for delay in [SECOND, 2 * SECOND, 3 * SECOND] {
+        assert_eq!(
+            fail(&mut watch, &controller),
+            RestartDecision::RestartAfter(delay)
+        );
+        clock.advance(delay);
+        watch.tick();
+    }
+    assert_eq!(fail(&mut watch, &controller), RestartDecision::Retire);
+}
+
+#[test]
+fn healthy_tasks_start_over() {
+    let (mut watch, controller, clock) =
+        watch(Backoff::new(SECOND, 60 * SECOND).reset_after(30 * SECOND));
+    watch.tick();
+
+    assert_eq!(
+        fail(&mut watch, &controller),
+        RestartDecision::RestartAfter(SECOND)
+    );
+    clock.advance(SECOND);
+    watch.tick();
+    assert_eq!(
+        fail(&mut watch, &controller),
+        RestartDecision::RestartAfter(2 * SECOND)
+    );
+    clock.advance(2 * SECOND);
+    watch.tick();
+
+    clock.advance(30 * SECOND);
+    assert_eq!(
+        fail(&mut watch, &controller),
+        RestartDecision::RestartAfter(SECOND)
+    );
+}
+
+#[test]
+fn max_attempts_applies_to_the_default_policy() {
+    let (task, controller) = CompleteOnCommand::new();
+    let clock = MockClock::new();
+    let mut watch = Builder::new()
+        .task(task)
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .with_max_attempts(1)
+        .clock(clock.clone())
+        .run();
+    watch.tick();
+
+    assert_eq!(
+        fail(&mut watch, &controller),
+        RestartDecision::RestartAfter(SECOND)
+    );
+    clock.advance(SECOND);
+    watch.tick();
+    assert_eq!(fail(&mut watch, &controller), RestartDecision::Retire);
+}
+
+#[test]
+fn max_attempts_apply_whatever_the_order() {
+    let (task, controller) = CompleteOnCommand::new();
+    let clock = MockClock::new();
+    let mut watch = Builder::new()
+        .task(task)
+        .with_max_attempts(1)
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .clock(clock.clone())
+        .run();
+    watch.tick();
+
+    assert_eq!(
+        fail(&mut watch, &controller),
+        RestartDecision::RestartAfter(SECOND)
+    );
+    clock.advance(SECOND);
+    watch.tick();
+    assert_eq!(fail(&mut watch, &controller), RestartDecision::Retire);
+}
+
+#[test]
+fn max_attempts_back_off_without_a_default_policy() {
+    let (task, controller) = CompleteOnCommand::new();
+    let mut watch = Builder::new()
+        .task(task)
+        .with_max_attempts(1)
+        .clock(MockClock::new())
+        .run();
+    watch.tick();
+
+    assert_eq!(
+        fail(&mut watch, &controller),
+        RestartDecision::RestartAfter(Duration::from_millis(100))
+    );
+}
@@ -0,0 +1,69 @@
+use futures::future;
+use futures::task::{noop_waker, Context};
+use futures::SinkExt;
+use watch::testing::{EventRecorder, MockClock};
+use watch::{Backpressure, Builder, Event, TaskId, TaskState, WatchCommand};
+
+#[test]
+fn oldest_commands_about_a_task_are_dropped_when_full() {
+    let (mut watch, handle) = Builder::new()
+        .task(future::pending::<()>)
+        .task(future::pending::<()>)
+        .backpressure(Backpressure::DropOldest(1))
+        .clock(MockClock::new())
+        .build();
+    let mut recorder = EventRecorder::new(&handle);
+    watch.tick();
+
+    handle.pause(0);
+    handle.pause(1);
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().state(), TaskState::Running);
+    assert_eq!(handle.task_info(1).unwrap().state(), TaskState::Paused);
+    let dropped: Vec<_> = recorder
+        .events()
+        .iter()
+        .filter_map(|event| match event {
+            Event::CommandDropped { task, command } => Some((*task, *command)),
+            _ => None,
+        })
+        .collect();
+    let task = TaskId::from(0);
+    assert_eq!(dropped, [(task, WatchCommand::Pause(task))]);
+}
+
+#[test]
+fn shutdowns_are_never_dropped() {
+    let (mut watch, handle) = Builder::new()
+        .task(future::pending::<()>)
+        .backpressure(Backpressure::DropOldest(1))
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    handle.shutdown();
+    handle.pause(0);
+    assert!(watch.tick().into_result().unwrap().is_ok());
+}
+
+#[test]
+fn sinks_wait_for_room_when_full() {
+    let (mut watch, mut handle) = Builder::new()
+        .task(future::pending::<()>)
+        .backpressure(Backpressure::Block(1))
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    assert!(handle.poll_ready_unpin(&mut cx).is_ready());
+    handle
+        .start_send_unpin(WatchCommand::Pause(TaskId::from(0)))
+        .unwrap();
+    assert!(handle.poll_ready_unpin(&mut cx).is_pending());
+
+    watch.tick();
+    assert!(handle.poll_ready_unpin(&mut cx).is_ready());
+    assert_eq!(handle.task_info(0).unwrap().state(), TaskState::Paused);
+}
@@ -0,0 +1,17 @@
+use std::time::Duration;
+use watch::testing::FailAfter;
+use watch::{Builder, RestartDecision};
+
+#[tokio::test(start_paused = true)]
+async fn bounded_runs_count_every_restart() {
+    // Subscribers this far behind would miss most of them.
+    let summary = Builder::new()
+        .tasks((0..8).map(|_| FailAfter(0)))
+        .policy(|_: &_| RestartDecision::RestartAfter(Duration::from_secs(1)))
+        .event_capacity(1)
+        .run_restarts(12)
+        .await
+        .unwrap();
+    assert_eq!(summary.restarts(), 12);
+    assert!(summary.shutdown().is_some());
+}
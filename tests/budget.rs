@@ -0,0 +1,86 @@
+use std::time::Duration;
+use watch::testing::{FailAfter, MockClock};
+use watch::{Builder, RestartBudget, RestartDecision, Task, TaskId, TaskState, WatchError};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+fn worker() -> Task {
+    Task::from(FailAfter(0)).label("role", "worker")
+}
+
+#[test]
+fn spent_budgets_escalate_by_default() {
+    let clock = MockClock::new();
+    let mut watch = Builder::new()
+        .task(worker())
+        .task(worker())
+        .task(Task::from(FailAfter(0)))
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .budget("role=worker", RestartBudget::new(3, 60 * SECOND))
+        .clock(clock.clone())
+        .run();
+
+    let tick = watch.tick();
+    assert_eq!(tick.decisions().len(), 3);
+    assert!(tick.result().is_none());
+
+    clock.advance(SECOND);
+    let tick = watch.tick();
+    let escalated = tick
+        .decisions()
+        .iter()
+        .filter(|(_, decision)| *decision == RestartDecision::Escalate)
+        .count();
+    assert_eq!(escalated, 1);
+    assert!(matches!(
+        tick.result(),
+        Some(Err(WatchError::BudgetExhausted { task })) if task.index() < 2
+    ));
+}
+
+#[test]
+fn budgets_refill_as_restarts_leave_the_window() {
+    let clock = MockClock::new();
+    let mut watch = Builder::new()
+        .task(worker())
+        .policy(|_: &_| RestartDecision::RestartAfter(10 * SECOND))
+        .budget("role=worker", RestartBudget::new(1, 5 * SECOND))
+        .clock(clock.clone())
+        .run();
+
+    watch.tick();
+    clock.advance(10 * SECOND);
+    let tick = watch.tick();
+    assert_eq!(
+        tick.decisions(),
+        &[(TaskId::from(0), RestartDecision::RestartAfter(10 * SECOND))]
+    );
+}
+
+#[test]
+fn tasks_added_at_runtime_spend_the_budgets_matching_their_labels() {
+    let clock = MockClock::new();
+    let (mut watch, map) = Builder::new()
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .budget(
+            "role=worker",
+            RestartBudget::new(1, 60 * SECOND).exhausted(RestartDecision::Retire),
+        )
+        .clock(clock.clone())
+        .build_map();
+    map.insert("worker", worker());
+
+    let tick = watch.tick();
+    assert_eq!(
+        tick.decisions(),
+        &[(TaskId::from(0), RestartDecision::RestartAfter(SECOND))]
+    );
+
+    clock.advance(SECOND);
+    let tick = watch.tick();
+    assert_eq!(
+        tick.decisions(),
+        &[(TaskId::from(0), RestartDecision::Retire)]
+    );
+    assert_eq!(map.task_info("worker").unwrap().state(), TaskState::Stopped);
+}
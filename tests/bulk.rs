@@ -0,0 +1,53 @@
+mod common;
+
+use common::states;
+use watch::testing::{MockClock, NeverComplete};
+use watch::{Builder, Task, TaskState};
+
+fn worker(group: &str) -> Task {
+    Task::from(NeverComplete).label("group", group)
+}
+
+#[test]
+fn tasks_pause_and_resume_by_selector() {
+    let (mut watch, handle) = Builder::new()
+        .task(worker("db"))
+        .task(worker("web"))
+        .task(worker("db"))
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    assert_eq!(handle.pause_matching("group=db"), 2);
+    watch.tick();
+    assert_eq!(
+        states(&handle),
+        [TaskState::Paused, TaskState::Running, TaskState::Paused]
+    );
+
+    assert_eq!(handle.resume_matching("group=db"), 2);
+    watch.tick();
+    assert_eq!(states(&handle), [TaskState::Running; 3]);
+    assert_eq!(handle.task_info(0).unwrap().instances(), 2);
+    assert_eq!(handle.task_info(1).unwrap().instances(), 1);
+}
+
+#[test]
+fn tasks_restart_and_are_removed_by_selector() {
+    let (mut watch, handle) = Builder::new()
+        .task(worker("db"))
+        .task(worker("web"))
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    assert_eq!(handle.restart_matching("group=web"), 1);
+    watch.tick();
+    let instances: Vec<u64> = handle.tasks().iter().map(|task| task.instances()).collect();
+    assert_eq!(instances, [1, 2]);
+
+    assert_eq!(handle.remove_matching("group=db"), 1);
+    watch.tick();
+    assert_eq!(states(&handle), [TaskState::Removed, TaskState::Running]);
+    assert_eq!(handle.restart_matching("group=unknown"), 0);
+}
@@ -0,0 +1,59 @@
+use futures::future::{self, FutureExt};
+use std::time::Duration;
+use watch::testing::MockClock;
+use watch::{Builder, Call, CallError, Mailbox, Task};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+#[test]
+fn calls_wait_for_a_response() {
+    let (mut watch, handle) = Builder::new()
+        .task(Task::with_mailbox(
+            |calls: Mailbox<Call<u32, u32>>| async move {
+                while let Some(call) = calls.recv().await {
+                    let doubled = call.request() * 2;
+                    let _ = call.reply(doubled);
+                }
+            },
+        ))
+        .clock(MockClock::new())
+        .build();
+
+    let mut call = Box::pin(handle.call::<u32, u32>(0, 21, SECOND));
+    assert!((&mut call).now_or_never().is_none());
+    watch.tick();
+    assert_eq!(call.now_or_never(), Some(Ok(42)));
+}
+
+#[test]
+fn calls_fail_without_a_response() {
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(Task::with_mailbox(|_: Mailbox<Call<u32, u32>>| {
+            future::pending::<()>()
+        }))
+        .task(Task::with_mailbox(
+            |calls: Mailbox<Call<u32, u32>>| async move {
+                // Drops every call.
+                while calls.recv().await.is_some() {}
+            },
+        ))
+        .clock(clock.clone())
+        .build();
+
+    let unanswered = handle.call::<u32, u32>(0, 1, SECOND);
+    let dropped = handle.call::<u32, u32>(1, 1, SECOND);
+    let mistyped = handle.call::<u32, String>(1, 1, SECOND);
+    let mut calls = Box::pin(future::join3(unanswered, dropped, mistyped));
+    assert!((&mut calls).now_or_never().is_none());
+    watch.tick();
+    clock.advance(SECOND);
+    assert_eq!(
+        calls.now_or_never(),
+        Some((
+            Err(CallError::TimedOut),
+            Err(CallError::Dropped),
+            Err(CallError::NoMailbox)
+        ))
+    );
+}
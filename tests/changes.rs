@@ -0,0 +1,70 @@
+use futures::{FutureExt, StreamExt};
+use std::time::Duration;
+use watch::testing::{FailAfter, MockClock};
+use watch::{Builder, Change, RestartDecision, TaskId, TaskState};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+#[test]
+fn changes_coalesce_what_happens_within_the_interval() {
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(FailAfter(0))
+        .task(futures::future::pending::<()>)
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .clock(clock.clone())
+        .build();
+    let mut changes = handle.changes(10 * SECOND).instances_above(5);
+
+    watch.tick();
+    assert!(changes.next().now_or_never().is_none());
+    clock.advance(10 * SECOND);
+    assert_eq!(
+        changes.next().now_or_never(),
+        Some(Some(vec![
+            Change::State {
+                task: TaskId::from(0),
+                from: TaskState::Stopped,
+                to: TaskState::Delayed,
+            },
+            Change::State {
+                task: TaskId::from(1),
+                from: TaskState::Stopped,
+                to: TaskState::Running,
+            },
+        ]))
+    );
+
+    for _ in 0..10 {
+        watch.tick();
+        clock.advance(SECOND);
+    }
+    watch.tick();
+    assert_eq!(
+        changes.next().now_or_never(),
+        Some(Some(vec![Change::InstancesAbove {
+            task: TaskId::from(0),
+            instances: 12,
+        }]))
+    );
+
+    handle.shutdown();
+    watch.tick();
+    clock.advance(10 * SECOND);
+    assert_eq!(
+        changes.next().now_or_never(),
+        Some(Some(vec![
+            Change::State {
+                task: TaskId::from(0),
+                from: TaskState::Delayed,
+                to: TaskState::Stopped,
+            },
+            Change::State {
+                task: TaskId::from(1),
+                from: TaskState::Running,
+                to: TaskState::Stopped,
+            },
+        ]))
+    );
+    assert_eq!(changes.next().now_or_never(), Some(None));
+}
@@ -0,0 +1,34 @@
+use futures::future;
+use watch::testing::MockClock;
+use watch::{Builder, TaskState};
+
+#[test]
+fn children_are_started_from_the_template() {
+    let (mut watch, handle) = Builder::new()
+        .template(|_tenant: String| future::pending::<()>())
+        .clock(MockClock::new())
+        .build();
+
+    let acme = handle.start_child("acme".to_string()).unwrap();
+    assert!(handle.start_child(7u32).is_none());
+    watch.tick();
+    assert_eq!(handle.task_info(acme).unwrap().state(), TaskState::Running);
+    assert_eq!(handle.tasks().len(), 1);
+}
+
+#[test]
+fn removed_children_free_their_identifiers() {
+    let (mut watch, handle) = Builder::new()
+        .template(|_tenant: String| future::pending::<()>())
+        .clock(MockClock::new())
+        .build();
+
+    for tenant in 0..10 {
+        let id = handle.start_child(tenant.to_string()).unwrap();
+        watch.tick();
+        assert_eq!(handle.task_info(id).unwrap().state(), TaskState::Running);
+        handle.remove(id);
+        watch.tick();
+    }
+    assert_eq!(handle.tasks().len(), 1);
+}
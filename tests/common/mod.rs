@@ -0,0 +1,31 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use futures::channel::mpsc::{self, UnboundedSender};
+use futures::future;
+use futures::lock::Mutex;
+use futures::stream::StreamExt;
+use std::sync::Arc;
+use watch::{Task, TaskContext, TaskState, WatchHandle};
+
+/// Returns a task whose instances signal readiness, one per message sent
+/// through the returned sender, then run forever.
+pub fn gated() -> (Task, UnboundedSender<()>) {
+    let (gate, opened) = mpsc::unbounded();
+    let opened = Arc::new(Mutex::new(opened));
+    let task = Task::with_context(move |context: TaskContext| {
+        let opened = Arc::clone(&opened);
+        async move {
+            opened.lock().await.next().await;
+            context.ready();
+            future::pending::<()>().await;
+        }
+    })
+    .signals_readiness();
+    (task, gate)
+}
+
+/// Returns the state of every task, in order.
+pub fn states(handle: &WatchHandle) -> Vec<TaskState> {
+    handle.tasks().iter().map(|task| task.state()).collect()
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use common::{gated, states};
+use watch::testing::MockClock;
+use watch::{Builder, RestartDecision, Task, TaskState};
+
+#[test]
+fn at_most_max_concurrent_starts_instances_are_starting() {
+    let (first, first_gate) = gated();
+    let (second, second_gate) = gated();
+    let (third, _third_gate) = gated();
+    let (mut watch, handle) = Builder::new()
+        .task(first)
+        .task(second)
+        .task(third)
+        .max_concurrent_starts(2)
+        .clock(MockClock::new())
+        .build();
+
+    watch.tick();
+    assert_eq!(
+        states(&handle),
+        [TaskState::Starting, TaskState::Starting, TaskState::Queued]
+    );
+
+    second_gate.unbounded_send(()).unwrap();
+    watch.tick();
+    assert_eq!(
+        states(&handle),
+        [TaskState::Starting, TaskState::Running, TaskState::Starting]
+    );
+
+    first_gate.unbounded_send(()).unwrap();
+    watch.tick();
+    assert_eq!(
+        states(&handle),
+        [TaskState::Running, TaskState::Running, TaskState::Starting]
+    );
+}
+
+#[test]
+fn at_most_max_running_tasks_are_running() {
+    let (first, first_gate) = gated();
+    let (mut watch, handle) = Builder::new()
+        .task(first)
+        .task(futures::future::pending::<()>)
+        .task(Task::new(futures::future::pending::<()>).priority(1))
+        .task(futures::future::pending::<()>)
+        .max_running(2)
+        .policy(|_: &_| RestartDecision::Retire)
+        .clock(MockClock::new())
+        .build();
+
+    watch.tick();
+    assert_eq!(
+        states(&handle),
+        [
+            TaskState::Starting,
+            TaskState::Running,
+            TaskState::Queued,
+            TaskState::Queued
+        ]
+    );
+
+    // Ready instances still count.
+    first_gate.unbounded_send(()).unwrap();
+    watch.tick();
+    assert_eq!(states(&handle)[2], TaskState::Queued);
+
+    handle.cancel_current(0);
+    watch.tick();
+    assert_eq!(
+        states(&handle),
+        [
+            TaskState::Stopped,
+            TaskState::Running,
+            TaskState::Running,
+            TaskState::Queued
+        ]
+    );
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+use watch::testing::{FailAfter, MockClock, NeverComplete};
+use watch::{Builder, RestartBudget, RestartDecision, Task, TaskId, TaskState, WatchError};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+#[test]
+fn best_effort_tasks_are_retired_instead_of_escalating() {
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(NeverComplete)
+        .task(
+            Task::from(FailAfter(0))
+                .label("role", "cache")
+                .best_effort(),
+        )
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .budget("role=cache", RestartBudget::new(1, 60 * SECOND))
+        .fail_fast()
+        .clock(clock.clone())
+        .build();
+
+    watch.tick();
+    clock.advance(SECOND);
+    let tick = watch.tick();
+    assert_eq!(
+        tick.decisions(),
+        &[(TaskId::from(1), RestartDecision::Retire)]
+    );
+    assert!(tick.result().is_none());
+    assert_eq!(handle.task_info(1).unwrap().state(), TaskState::Stopped);
+}
+
+#[test]
+fn critical_tasks_escalate_when_retired_after_a_failure() {
+    let mut watch = Builder::new()
+        .task(NeverComplete)
+        .task(Task::from(FailAfter(0)).critical())
+        .task(FailAfter(0))
+        .policy(|_: &_| RestartDecision::Retire)
+        .clock(MockClock::new())
+        .run();
+
+    let tick = watch.tick();
+    assert!(tick
+        .decisions()
+        .contains(&(TaskId::from(1), RestartDecision::Escalate)));
+    assert!(!tick
+        .decisions()
+        .contains(&(TaskId::from(2), RestartDecision::Escalate)));
+    assert!(matches!(
+        tick.result(),
+        Some(Err(WatchError::Escalated(escalation))) if escalation.task() == 1
+    ));
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+use watch::testing::{EventRecorder, MockClock};
+use watch::{Builder, Debounce, Event, EventKind, RestartDecision, TaskId};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+#[test]
+fn flapping_tasks_cool_down_once() {
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(|| async {})
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .debounce(Debounce::new(3, SECOND, 60 * SECOND))
+        .clock(clock.clone())
+        .build();
+    let mut recorder = EventRecorder::new(&handle);
+
+    let mut delays = Vec::new();
+    for _ in 0..5 {
+        let tick = watch.tick();
+        delays.extend(
+            tick.decisions()
+                .iter()
+                .map(|(_, decision)| decision.clone()),
+        );
+        clock.advance(60 * SECOND);
+    }
+    assert_eq!(
+        delays,
+        [
+            RestartDecision::RestartAfter(SECOND),
+            RestartDecision::RestartAfter(SECOND),
+            RestartDecision::RestartAfter(60 * SECOND),
+            RestartDecision::RestartAfter(60 * SECOND),
+            RestartDecision::RestartAfter(60 * SECOND),
+        ]
+    );
+    let flapping: Vec<_> = recorder
+        .events()
+        .iter()
+        .filter(|event| event.kind() == EventKind::Flapping)
+        .cloned()
+        .collect();
+    assert_eq!(
+        flapping,
+        [Event::Flapping {
+            task: TaskId::from(0),
+            instance: 3,
+            cool_down: 60 * SECOND,
+        }]
+    );
+}
+
+#[test]
+fn long_runs_end_the_flapping() {
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(futures::future::pending::<()>)
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .debounce(Debounce::new(1, SECOND, 60 * SECOND))
+        .clock(clock.clone())
+        .build();
+
+    watch.tick();
+    handle.cancel_current(0);
+    assert_eq!(
+        watch.tick().decisions(),
+        &[(TaskId::from(0), RestartDecision::RestartAfter(60 * SECOND))]
+    );
+    clock.advance(60 * SECOND);
+    watch.tick();
+    clock.advance(2 * SECOND);
+    handle.cancel_current(0);
+    assert_eq!(
+        watch.tick().decisions(),
+        &[(TaskId::from(0), RestartDecision::RestartAfter(SECOND))]
+    );
+}
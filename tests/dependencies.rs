@@ -0,0 +1,83 @@
+mod common;
+
+use common::{gated, states};
+use watch::testing::{MockClock, NeverComplete};
+use watch::{Builder, RestartDecision, Task, TaskState};
+
+#[test]
+fn dependents_restart_once_their_dependency_is_ready_again() {
+    let (provider, gate) = gated();
+    let (mut watch, handle) = Builder::new()
+        .task(provider)
+        .task(Task::from(NeverComplete).depends_on(0))
+        .task(Task::from(NeverComplete).depends_on(1))
+        .task(NeverComplete)
+        .clock(MockClock::new())
+        .build();
+    let instances = || -> Vec<u64> { handle.tasks().iter().map(|task| task.instances()).collect() };
+
+    gate.unbounded_send(()).unwrap();
+    watch.tick();
+    assert_eq!(instances(), [1, 1, 1, 1]);
+
+    handle.restart(0);
+    watch.tick();
+    assert_eq!(instances(), [2, 1, 1, 1]);
+
+    gate.unbounded_send(()).unwrap();
+    watch.tick();
+    assert_eq!(instances(), [2, 2, 2, 1]);
+}
+
+#[test]
+fn cycles_restart_every_task_once() {
+    let (mut watch, handle) = Builder::new()
+        .task(Task::from(NeverComplete).depends_on(1))
+        .task(Task::from(NeverComplete).depends_on(0))
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    handle.restart(0);
+    watch.tick();
+    let instances: Vec<_> = handle.tasks().iter().map(|task| task.instances()).collect();
+    assert_eq!(instances, [2, 2]);
+}
+
+#[test]
+fn deferred_tasks_start_once_a_dependency_is_ready() {
+    let (provider, gate) = gated();
+    let (mut watch, handle) = Builder::new()
+        .task(provider)
+        .task(Task::from(NeverComplete).depends_on(0).deferred())
+        .clock(MockClock::new())
+        .build();
+
+    watch.tick();
+    assert_eq!(states(&handle), [TaskState::Starting, TaskState::Stopped]);
+
+    gate.unbounded_send(()).unwrap();
+    watch.tick();
+    assert_eq!(states(&handle), [TaskState::Running, TaskState::Running]);
+
+    // Supervised like any other task from then on.
+    handle.restart(0);
+    gate.unbounded_send(()).unwrap();
+    watch.tick();
+    let instances: Vec<_> = handle.tasks().iter().map(|task| task.instances()).collect();
+    assert_eq!(instances, [2, 2]);
+}
+
+#[test]
+fn deferred_tasks_keep_the_watcher_going_until_triggered() {
+    let (mut watch, handle) = Builder::new()
+        .task(Task::new(|| async {}).deferred())
+        .policy(|_: &_| RestartDecision::Retire)
+        .clock(MockClock::new())
+        .build();
+
+    assert!(watch.tick().result().is_none());
+    handle.trigger(0);
+    let summary = watch.tick().into_result().unwrap().unwrap();
+    assert_eq!(summary.spawned(), 1);
+}
@@ -0,0 +1,40 @@
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use std::sync::{Arc, Mutex};
+use watch::testing::{MockClock, NeverComplete};
+use watch::{Builder, TaskId, WatchObserver};
+
+struct Starts(Arc<Mutex<Vec<TaskId>>>);
+
+impl WatchObserver for Starts {
+    fn on_start(&mut self, task: TaskId, _instance: u64) {
+        self.0.lock().unwrap().push(task);
+    }
+}
+
+#[test]
+fn observers_are_notified_on_the_executor() {
+    let spawned: Arc<Mutex<Vec<BoxFuture<'static, ()>>>> = Arc::default();
+    let executor = Arc::clone(&spawned);
+    let starts = Arc::new(Mutex::new(Vec::new()));
+    let (mut watch, handle) = Builder::new()
+        .task(NeverComplete)
+        .task(NeverComplete)
+        .observer(Starts(Arc::clone(&starts)))
+        .observer_executor(move |future| executor.lock().unwrap().push(future))
+        .clock(MockClock::new())
+        .build();
+
+    watch.tick();
+    assert_eq!(spawned.lock().unwrap().len(), 1);
+    assert!(starts.lock().unwrap().is_empty());
+
+    let mut dispatch = spawned.lock().unwrap().pop().unwrap();
+    assert!((&mut dispatch).now_or_never().is_none());
+    assert_eq!(*starts.lock().unwrap(), [TaskId::from(0), TaskId::from(1)]);
+
+    // Dispatching ends along with the watcher.
+    handle.shutdown();
+    assert!(watch.tick().into_result().is_some());
+    assert!(dispatch.now_or_never().is_some());
+}
@@ -0,0 +1,69 @@
+use futures::FutureExt;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use watch::testing::{CompleteOnCommand, MockClock, NeverComplete};
+use watch::{Builder, ExitReason, FailureKind, RestartDecision, Task};
+
+#[test]
+fn exits_are_counted_by_reason() {
+    let calls = Arc::new(AtomicU32::new(0));
+    let task = Task::fallible(
+        move || {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match call % 3 {
+                    0 => Ok(()),
+                    1 => Err("reset"),
+                    _ => panic!("corrupted"),
+                }
+            }
+        },
+        |_| FailureKind::Transient,
+    );
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(task)
+        .policy(|_: &_| RestartDecision::RestartAfter(Duration::from_secs(1)))
+        .clock(clock.clone())
+        .build();
+
+    for _ in 0..7 {
+        watch.tick();
+        clock.advance(Duration::from_secs(1));
+    }
+    let exits = handle.task_info(0).unwrap().exits();
+    assert_eq!(
+        (exits.completed(), exits.failed(), exits.panicked()),
+        (3, 2, 2)
+    );
+    assert_eq!((exits.cancelled(), exits.timed_out()), (0, 0));
+    assert_eq!(exits.total(), 7);
+}
+
+#[test]
+fn next_exits_resolve_with_the_next_exit_only() {
+    let (task, controller) = CompleteOnCommand::new();
+    let (mut watch, handle) = Builder::new()
+        .task(task)
+        .task(NeverComplete)
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    let mut next = handle.next_exit(0);
+    let removed = handle.next_exit(1);
+    watch.tick();
+    assert_eq!((&mut next).now_or_never(), None);
+
+    controller.fail(FailureKind::Transient);
+    controller.complete();
+    watch.tick();
+    let reason = ExitReason::Failed(FailureKind::Transient);
+    assert_eq!(next.now_or_never(), Some(Some(reason)));
+
+    // Removed tasks drop their running instance first.
+    handle.remove(1);
+    watch.tick();
+    assert_eq!(removed.now_or_never(), Some(Some(ExitReason::Cancelled)));
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+use watch::testing::{FailAfter, MockClock};
+use watch::{Builder, Clock, RestartDecision, TaskState};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+#[test]
+fn expedited_tasks_skip_what_is_left_of_their_delay() {
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(FailAfter(0))
+        .policy(|_: &_| RestartDecision::RestartAfter(10 * SECOND))
+        .clock(clock.clone())
+        .build();
+    watch.tick();
+
+    clock.advance(4 * SECOND);
+    watch.tick();
+    assert_eq!(handle.restart_delay(0), Some(6 * SECOND));
+    let info = handle.task_info(0).unwrap();
+    assert_eq!(info.next_restart_at(), Some(clock.now() + 6 * SECOND));
+
+    handle.expedite(0);
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().instances(), 2);
+    assert_eq!(handle.restart_delay(0), Some(10 * SECOND));
+}
+
+#[test]
+fn expedited_restarts_still_wait_for_freezes() {
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(FailAfter(0))
+        .policy(|_: &_| RestartDecision::RestartAfter(10 * SECOND))
+        .clock(clock.clone())
+        .build();
+    watch.tick();
+
+    handle.freeze(60 * SECOND);
+    handle.expedite(0);
+    watch.tick();
+    let info = handle.task_info(0).unwrap();
+    assert_eq!((info.instances(), info.state()), (1, TaskState::Delayed));
+    assert_eq!(handle.restart_delay(0), Some(60 * SECOND));
+}
+
+#[test]
+fn running_tasks_have_no_restart_delay() {
+    let (mut watch, handle) = Builder::new()
+        .task(futures::future::pending::<()>)
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    handle.expedite(0);
+    watch.tick();
+    assert_eq!(handle.restart_delay(0), None);
+    assert_eq!(handle.task_info(0).unwrap().instances(), 1);
+}
+
+#[test]
+fn tasks_may_wait_forever_until_expedited() {
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(FailAfter(0))
+        .policy(|_: &_| RestartDecision::RestartAfter(Duration::MAX))
+        .clock(clock.clone())
+        .build();
+    watch.tick();
+
+    clock.advance(365 * 24 * 60 * 60 * SECOND);
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().state(), TaskState::Delayed);
+
+    handle.expedite(0);
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().instances(), 2);
+}
@@ -0,0 +1,98 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use watch::testing::{EventRecorder, MockClock};
+use watch::{
+    ArcFactory, Builder, Event, EventKind, ExitReason, PanicBehavior, RestartDecision, Task, TaskId,
+};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+/// Returns a task whose factory panics the first `panics` times it is called.
+fn flaky(panics: u32) -> Task {
+    let calls = Arc::new(AtomicU32::new(0));
+    Task::new(move || {
+        if calls.fetch_add(1, Ordering::SeqCst) < panics {
+            panic!("no connection");
+        }
+        futures::future::pending::<()>()
+    })
+}
+
+#[test]
+fn factory_panics_go_through_the_policy() {
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(flaky(1))
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .clock(clock.clone())
+        .build();
+    let mut recorder = EventRecorder::new(&handle);
+
+    let tick = watch.tick();
+    assert_eq!(
+        tick.decisions(),
+        &[(TaskId::from(0), RestartDecision::RestartAfter(SECOND))]
+    );
+    let events = recorder.events();
+    assert!(matches!(
+        &events[0],
+        Event::FactoryPanicked { task, instance: 1, panic }
+            if *task == 0 && panic.message() == "no connection"
+    ));
+    assert!(matches!(
+        events
+            .iter()
+            .find(|event| event.kind() == EventKind::Exited),
+        Some(Event::Exited {
+            reason: ExitReason::Panicked(_),
+            ..
+        })
+    ));
+
+    clock.advance(SECOND);
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().instances(), 2);
+    assert!(watch.tick().decisions().is_empty());
+}
+
+#[test]
+fn factory_panics_follow_the_panic_behavior() {
+    let mut watch = Builder::new()
+        .task(flaky(1).panic_behavior(PanicBehavior::Retire))
+        .task(flaky(0))
+        .clock(MockClock::new())
+        .run();
+
+    let tick = watch.tick();
+    assert_eq!(
+        tick.decisions(),
+        &[(TaskId::from(0), RestartDecision::Retire)]
+    );
+}
+
+#[test]
+fn arc_factories_are_shared_by_watchers() {
+    let calls = Arc::new(AtomicU32::new(0));
+    let counted = {
+        let calls = Arc::clone(&calls);
+        ArcFactory::new(move || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            futures::future::pending::<()>()
+        })
+    };
+    let factories = vec![counted, ArcFactory::from(|| async { "done" })];
+
+    for _ in 0..2 {
+        let mut watch = Builder::new()
+            .tasks(factories.clone())
+            .policy(|_: &_| RestartDecision::Retire)
+            .clock(MockClock::new())
+            .run();
+        assert_eq!(
+            watch.tick().decisions(),
+            &[(TaskId::from(1), RestartDecision::Retire)]
+        );
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,51 @@
+use futures::FutureExt;
+use watch::testing::{CompleteOnCommand, MockClock};
+use watch::{Builder, TaskState};
+
+#[test]
+fn held_gates_block_restarts_until_released() {
+    let (task, controller) = CompleteOnCommand::new();
+    let (mut watch, handle) = Builder::new().task(task).clock(MockClock::new()).build();
+    watch.tick();
+
+    let gate = handle.restart_gate();
+    let mut acquire = Box::pin(gate.acquire());
+    assert!((&mut acquire).now_or_never().is_none());
+    watch.tick();
+    let hold = acquire.now_or_never().unwrap();
+    // A second hold keeps the gate held once the first is released.
+    let mut other = Box::pin(gate.acquire());
+    assert!((&mut other).now_or_never().is_none());
+    watch.tick();
+    let other = other.now_or_never().unwrap();
+
+    controller.complete();
+    watch.tick();
+    let info = handle.task_info(0).unwrap();
+    assert_eq!((info.instances(), info.state()), (1, TaskState::Queued));
+
+    hold.release();
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().instances(), 1);
+
+    drop(other);
+    watch.tick();
+    let info = handle.task_info(0).unwrap();
+    assert_eq!((info.instances(), info.state()), (2, TaskState::Running));
+}
+
+#[test]
+fn dropped_acquisitions_release_the_gate() {
+    let (task, controller) = CompleteOnCommand::new();
+    let (mut watch, handle) = Builder::new().task(task).clock(MockClock::new()).build();
+    watch.tick();
+
+    let gate = handle.restart_gate();
+    let mut acquire = Box::pin(gate.acquire());
+    assert!((&mut acquire).now_or_never().is_none());
+    drop(acquire);
+
+    controller.complete();
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().instances(), 2);
+}
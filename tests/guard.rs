@@ -0,0 +1,80 @@
+use futures::future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use watch::testing::MockClock;
+use watch::{Builder, TaskState};
+
+#[test]
+fn dropped_guards_remove_their_task() {
+    let (mut watch, handle) = Builder::new()
+        .template(|_: ()| future::pending::<()>())
+        .clock(MockClock::new())
+        .build();
+
+    let guard = handle.start_guarded(future::pending::<()>).unwrap();
+    let id = guard.id();
+    watch.tick();
+    assert_eq!(handle.task_info(id).unwrap().state(), TaskState::Running);
+
+    drop(guard);
+    watch.tick();
+    assert_eq!(handle.task_info(id).unwrap().state(), TaskState::Removed);
+}
+
+#[test]
+fn stale_guards_leave_identifiers_handed_out_again_alone() {
+    let (mut watch, handle) = Builder::new()
+        .template(|_: ()| future::pending::<()>())
+        .clock(MockClock::new())
+        .build();
+
+    let guard = handle.start_guarded(future::pending::<()>).unwrap();
+    handle.remove(guard.id());
+    watch.tick();
+    let child = handle.start_child(()).unwrap();
+    assert_eq!(child, guard.id());
+
+    drop(guard);
+    watch.tick();
+    assert_eq!(handle.task_info(child).unwrap().state(), TaskState::Running);
+}
+
+#[test]
+fn dropped_watch_guards_shut_the_watcher_down() {
+    let (mut watch, handle) = Builder::new()
+        .task(future::pending::<()>)
+        .clock(MockClock::new())
+        .build();
+    let guard = handle.guard();
+    watch.tick();
+    assert!(watch.tick().result().is_none());
+
+    drop(guard);
+    assert!(matches!(watch.tick().into_result(), Some(Ok(_))));
+}
+
+#[tokio::test(start_paused = true)]
+async fn closed_watch_guards_wait_for_instances_to_return() {
+    let returned = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&returned);
+    let builder = Builder::new().grace_period(Duration::from_secs(5));
+    let shutdown = builder.shutdown_signal();
+    let (watch, handle) = builder
+        .task(move || {
+            let (shutdown, flag) = (shutdown.clone(), Arc::clone(&flag));
+            async move {
+                shutdown.await;
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                flag.store(true, Ordering::SeqCst);
+            }
+        })
+        .build();
+    let guard = handle.guard();
+    let watch = tokio::spawn(watch);
+    tokio::task::yield_now().await;
+
+    guard.close().await;
+    assert!(returned.load(Ordering::SeqCst));
+    assert!(watch.await.unwrap().unwrap().shutdown().unwrap().is_clean());
+}
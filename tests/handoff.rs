@@ -0,0 +1,52 @@
+use futures::future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use watch::testing::MockClock;
+use watch::{Builder, RestartDecision, Task};
+
+#[test]
+fn replacements_take_what_the_previous_instance_deposited() {
+    let taken = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&taken);
+    let (mut watch, handle) = Builder::new()
+        .task(Task::with_handoff(move |handoff| {
+            let seen = Arc::clone(&seen);
+            async move {
+                let previous = handoff.take();
+                seen.lock().unwrap().push(previous);
+                handoff.deposit(previous.unwrap_or(0) + 1);
+                future::pending::<()>().await;
+            }
+        }))
+        .policy(|_: &_| RestartDecision::RestartAfter(Duration::ZERO))
+        .clock(MockClock::new())
+        .build();
+
+    watch.tick();
+    handle.restart(0);
+    watch.tick();
+    handle.cancel_current(0);
+    watch.tick();
+    assert_eq!(*taken.lock().unwrap(), [None, Some(1), Some(2)]);
+}
+
+#[test]
+fn values_left_untaken_are_handed_back() {
+    let handoff = Arc::new(Mutex::new(None));
+    let kept = Arc::clone(&handoff);
+    let mut watch = Builder::new()
+        .task(Task::with_handoff(move |slot| {
+            *kept.lock().unwrap() = Some(slot);
+            future::pending::<()>()
+        }))
+        .clock(MockClock::new())
+        .run();
+    watch.tick();
+
+    let slot = handoff.lock().unwrap().take().unwrap();
+    assert_eq!(slot.deposit("a"), None);
+    assert_eq!(slot.deposit("b"), Some("a"));
+    assert!(slot.is_deposited());
+    assert_eq!(slot.take(), Some("b"));
+    assert!(!slot.is_deposited());
+}
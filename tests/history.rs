@@ -0,0 +1,127 @@
+#![cfg(feature = "history")]
+
+use futures::future;
+use futures::task::{noop_waker, Context};
+use std::future::Future;
+use std::io;
+use std::sync::{Arc, Mutex};
+use watch::testing::{EventRecorder, MockClock};
+use watch::{BatchedHistory, Builder, HistorySink, Record};
+
+/// Creates a history exporting with `export`, along with the batches given to
+/// it so far.
+fn history<F>(
+    max_batch: usize,
+    mut export: F,
+) -> (
+    BatchedHistory,
+    impl Future<Output = ()>,
+    Arc<Mutex<Vec<Vec<Record>>>>,
+)
+where
+    F: FnMut() -> io::Result<()> + Send + 'static,
+{
+    let batches = Arc::new(Mutex::new(Vec::new()));
+    let exported = Arc::clone(&batches);
+    let (history, export) = BatchedHistory::new(max_batch, move |batch| {
+        exported.lock().unwrap().push(batch);
+        future::ready(export())
+    });
+    (history, export, batches)
+}
+
+#[test]
+fn records_are_exported_in_batches() {
+    let (history, export, batches) = history(2, || Ok(()));
+    let (mut watch, handle) = Builder::new()
+        .tasks((0..3).map(|_| future::pending::<()>))
+        .history(history)
+        .clock(MockClock::new())
+        .build();
+    let mut recorder = EventRecorder::new(&handle);
+    watch.tick();
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    futures::pin_mut!(export);
+    assert!(export.as_mut().poll(&mut cx).is_pending());
+    let events = recorder.events();
+    {
+        let batches = batches.lock().unwrap();
+        assert!(batches.len() > 1);
+        assert!(batches.iter().all(|batch| batch.len() <= 2));
+        let exported: Vec<_> = batches.iter().flatten().map(Record::event).collect();
+        assert_eq!(exported, events);
+    }
+
+    // The exporter stops once the watcher is gone.
+    drop(watch);
+    assert!(export.as_mut().poll(&mut cx).is_ready());
+}
+
+#[test]
+fn exporting_goes_on_after_errors() {
+    let (history, export, batches) = history(1, || Err(io::ErrorKind::Other.into()));
+    let (mut watch, _handle) = Builder::new()
+        .tasks((0..2).map(|_| future::pending::<()>))
+        .history(history)
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+    drop(watch);
+
+    futures::executor::block_on(export);
+    assert!(batches.lock().unwrap().len() > 1);
+}
+
+#[test]
+fn sinks_failing_are_reported() {
+    struct Full;
+
+    impl HistorySink for Full {
+        fn append(&mut self, _: &Record) -> io::Result<()> {
+            Err(io::ErrorKind::StorageFull.into())
+        }
+    }
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let failed = Arc::clone(&errors);
+    let (mut watch, handle) = Builder::new()
+        .task(future::pending::<()>)
+        .history(Full)
+        .on_history_error(move |error| failed.lock().unwrap().push(error.kind()))
+        .clock(MockClock::new())
+        .build();
+    let mut recorder = EventRecorder::new(&handle);
+    watch.tick();
+
+    let events = recorder.events().len();
+    assert!(events > 0);
+    assert_eq!(
+        *errors.lock().unwrap(),
+        vec![io::ErrorKind::StorageFull; events]
+    );
+}
+
+#[test]
+fn exports_failing_are_reported_by_the_next_append() {
+    let (history, export, _) = history(1, || Err(io::ErrorKind::Other.into()));
+    let errors = Arc::new(Mutex::new(0));
+    let failed = Arc::clone(&errors);
+    let (mut watch, handle) = Builder::new()
+        .task(future::pending::<()>)
+        .history(history)
+        .on_history_error(move |_| *failed.lock().unwrap() += 1)
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    futures::pin_mut!(export);
+    assert!(export.as_mut().poll(&mut cx).is_pending());
+    assert_eq!(*errors.lock().unwrap(), 0);
+    handle.restart(0);
+    watch.tick();
+    assert!(*errors.lock().unwrap() > 0);
+}
@@ -0,0 +1,118 @@
+use futures::future::{self, BoxFuture, FutureExt};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use watch::testing::{EventRecorder, MockClock};
+use watch::{Builder, Event, RestartContext, RestartDecision, Task, TaskId, TaskState, WatchError};
+
+/// Returns a factory whose instances return right away, but for the third
+/// one which keeps running.
+fn third_stays_up() -> impl Fn() -> BoxFuture<'static, ()> + Send + Sync {
+    let instances = Arc::new(AtomicU64::new(0));
+    move || match instances.fetch_add(1, Ordering::SeqCst) {
+        2 => future::pending().boxed(),
+        _ => future::ready(()).boxed(),
+    }
+}
+
+/// Returns the immediate exits reported, along with the count they made.
+fn immediate_exits(recorder: &mut EventRecorder) -> Vec<(u64, u32)> {
+    recorder
+        .events()
+        .iter()
+        .filter_map(|event| match event {
+            Event::StartupImmediateExit {
+                instance, exits, ..
+            } => Some((*instance, *exits)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn immediate_exits_are_reported_until_an_instance_stays_up() {
+    let (mut watch, handle) = Builder::new()
+        .task(third_stays_up())
+        .policy(|context: &RestartContext| {
+            if context.instance() < 4 {
+                RestartDecision::RestartAfter(Default::default())
+            } else {
+                RestartDecision::Retire
+            }
+        })
+        .max_immediate_exits(3)
+        .clock(MockClock::new())
+        .build();
+    let mut recorder = EventRecorder::new(&handle);
+    assert!(watch.tick().result().is_none());
+    assert_eq!(immediate_exits(&mut recorder), [(1, 1), (2, 2)]);
+
+    // Once up, instances returning right away are not reported anymore.
+    handle.restart(0);
+    assert!(watch.tick().into_result().unwrap().is_ok());
+    assert_eq!(immediate_exits(&mut recorder), [(1, 1), (2, 2)]);
+    assert_eq!(handle.task_info(0).unwrap().instances(), 4);
+}
+
+#[test]
+fn startup_fails_after_too_many_immediate_exits() {
+    let (mut watch, handle) = Builder::new()
+        .task(|| future::ready(()))
+        .max_immediate_exits(2)
+        .clock(MockClock::new())
+        .build();
+    let tick = watch.tick();
+    let task = TaskId::from(0);
+    assert_eq!(
+        tick.decisions(),
+        [
+            (task, RestartDecision::RestartAfter(Default::default())),
+            (task, RestartDecision::Escalate)
+        ]
+    );
+    assert_eq!(
+        tick.into_result().unwrap().unwrap_err(),
+        WatchError::StartupFailed { task, exits: 2 }
+    );
+    assert_eq!(handle.task_info(0).unwrap().instances(), 2);
+}
+
+#[test]
+fn best_effort_tasks_are_retired_instead() {
+    let (mut watch, handle) = Builder::new()
+        .task(Task::new(|| future::ready(())).best_effort())
+        .task(future::pending::<()>)
+        .max_immediate_exits(1)
+        .clock(MockClock::new())
+        .build();
+    assert!(watch.tick().result().is_none());
+    assert_eq!(handle.task_info(0).unwrap().state(), TaskState::Stopped);
+    assert_eq!(handle.task_info(1).unwrap().state(), TaskState::Running);
+}
+
+#[test]
+fn tasks_spawned_later_are_not_counted() {
+    let (mut watch, handle) = Builder::new()
+        .task(future::pending::<()>)
+        .task(Task::new(|| future::ready(())).deferred())
+        .policy(|context: &RestartContext| {
+            if context.instance() < 3 {
+                RestartDecision::RestartAfter(Default::default())
+            } else {
+                RestartDecision::Retire
+            }
+        })
+        .max_immediate_exits(1)
+        .clock(MockClock::new())
+        .build();
+    let mut recorder = EventRecorder::new(&handle);
+    assert!(watch.tick().result().is_none());
+
+    // Neither triggered nor added tasks exiting on their first poll count.
+    handle.trigger(1);
+    handle.merge(Builder::new().task(|| future::ready(())));
+    let tick = watch.tick();
+    assert!(tick.result().is_none());
+    assert_eq!(immediate_exits(&mut recorder), []);
+    assert_eq!(handle.task_info(1).unwrap().instances(), 3);
+    assert_eq!(handle.task_info(2).unwrap().instances(), 3);
+}
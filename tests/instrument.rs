@@ -0,0 +1,38 @@
+#![cfg(feature = "instrument")]
+
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+use watch::testing::{CompleteOnCommand, FailAfter, MockClock};
+use watch::{Builder, ExitReason, FailureKind, RestartDecision, TaskId};
+
+#[test]
+fn polls_of_every_instance_are_reported() {
+    let (task, controller) = CompleteOnCommand::new();
+    let polls = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&polls);
+    let (mut watch, _) = Builder::new()
+        .task(task)
+        .task(FailAfter(0))
+        .policy(|_: &_| RestartDecision::Retire)
+        .on_poll(move |task, _, poll| {
+            recorded
+                .lock()
+                .unwrap()
+                .push((task, poll.map(Clone::clone)));
+        })
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+    controller.complete();
+    watch.tick();
+
+    let failed = ExitReason::Failed(FailureKind::Transient);
+    assert_eq!(
+        *polls.lock().unwrap(),
+        [
+            (TaskId::from(0), Poll::Pending),
+            (TaskId::from(1), Poll::Ready(failed)),
+            (TaskId::from(0), Poll::Ready(ExitReason::Completed)),
+        ]
+    );
+}
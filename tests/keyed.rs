@@ -0,0 +1,93 @@
+use futures::future;
+use std::sync::{Arc, Mutex};
+use watch::testing::MockClock;
+use watch::{Builder, Shard, TaskState, Watch, WatchHandle};
+
+/// Builds a watcher over `count` keyed shards, along with the shards given to
+/// their instances so far.
+fn sharded(count: usize) -> (Watch, WatchHandle, Arc<Mutex<Vec<Shard>>>) {
+    let shards = Arc::new(Mutex::new(Vec::new()));
+    let given = Arc::clone(&shards);
+    let (watch, handle) = Builder::new()
+        .keyed_shards(count, move |shard: Shard| {
+            given.lock().unwrap().push(shard);
+            future::pending::<()>()
+        })
+        .clock(MockClock::new())
+        .build();
+    (watch, handle, shards)
+}
+
+/// Returns the index of the shard owning each key in `0..1000`.
+fn owners(shards: &[Shard]) -> Vec<usize> {
+    (0..1000_u32)
+        .map(|key| {
+            let owners: Vec<_> = shards.iter().filter(|shard| shard.owns(&key)).collect();
+            assert_eq!(owners.len(), 1);
+            owners[0].index()
+        })
+        .collect()
+}
+
+#[test]
+fn growing_only_starts_new_shards() {
+    let (mut watch, handle, shards) = sharded(4);
+    watch.tick();
+    let before = owners(&shards.lock().unwrap());
+
+    assert!(handle.reshard(6));
+    watch.tick();
+    let names: Vec<_> = handle
+        .tasks()
+        .iter()
+        .map(|task| (task.name().unwrap().to_owned(), task.instances()))
+        .collect();
+    assert_eq!(
+        names,
+        (0..6)
+            .map(|index| (format!("shard-{}", index), 1))
+            .collect::<Vec<_>>()
+    );
+    let shards = shards.lock().unwrap();
+    assert_eq!(shards.len(), 6);
+    let after = owners(&shards);
+    // Keys only move to the new shards.
+    assert!(before
+        .iter()
+        .zip(&after)
+        .all(|(before, after)| before == after || *after >= 4));
+    assert!(after.iter().any(|&owner| owner >= 4));
+}
+
+#[test]
+fn shrinking_hands_keys_over_to_the_remaining_shards() {
+    let (mut watch, handle, shards) = sharded(4);
+    watch.tick();
+    let before = owners(&shards.lock().unwrap());
+
+    assert!(handle.reshard(2));
+    watch.tick();
+    let states: Vec<_> = handle.tasks().iter().map(|task| task.state()).collect();
+    assert_eq!(
+        states,
+        [
+            TaskState::Running,
+            TaskState::Running,
+            TaskState::Removed,
+            TaskState::Removed
+        ]
+    );
+    let shards = shards.lock().unwrap();
+    let after = owners(&shards[..2]);
+    assert!(before
+        .iter()
+        .zip(&after)
+        .all(|(before, after)| before == after || *before >= 2));
+    assert!(shards.iter().all(|shard| shard.count() == 2));
+}
+
+#[test]
+fn watchers_without_keyed_shards_do_not_reshard() {
+    let (_, handle) = Builder::new().task(future::pending::<()>).build();
+    assert!(!handle.reshard(4));
+}
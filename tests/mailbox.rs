@@ -0,0 +1,57 @@
+use futures::future;
+use std::sync::{Arc, Mutex};
+use watch::testing::MockClock;
+use watch::{Builder, Mailbox, Task};
+
+/// Returns a task whose instances receive one message each, then run
+/// forever, along with the messages received so far.
+fn receiver() -> (Task, Arc<Mutex<Vec<u32>>>) {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&received);
+    let task = Task::with_mailbox(move |mailbox: Mailbox<u32>| {
+        let seen = Arc::clone(&seen);
+        async move {
+            if let Some(message) = mailbox.recv().await {
+                seen.lock().unwrap().push(message);
+            }
+            future::pending::<()>().await;
+        }
+    });
+    (task, received)
+}
+
+#[test]
+fn messages_are_kept_across_restarts() {
+    let (task, received) = receiver();
+    let (mut watch, handle) = Builder::new().task(task).clock(MockClock::new()).build();
+
+    handle.send(0, 1u32).unwrap();
+    handle.send(0, 2u32).unwrap();
+    watch.tick();
+    assert_eq!(*received.lock().unwrap(), [1]);
+
+    handle.restart(0);
+    watch.tick();
+    assert_eq!(*received.lock().unwrap(), [1, 2]);
+}
+
+#[test]
+fn only_tasks_with_a_mailbox_of_the_type_receive() {
+    let (task, received) = receiver();
+    let (mut watch, shards) = Builder::new().clock(MockClock::new()).build_map();
+    shards.insert("plain", Task::new(future::pending::<()>));
+    shards.insert("mailbox", task);
+    let handle = shards.handle().clone();
+    let plain = shards.task_info("plain").unwrap().id();
+    let mailbox = shards.task_info("mailbox").unwrap().id();
+
+    assert_eq!(handle.send(plain, 1u32), Err(1));
+    assert_eq!(handle.send(mailbox, "one"), Err("one"));
+    assert_eq!(handle.send(mailbox, 1u32), Ok(()));
+    watch.tick();
+    assert_eq!(*received.lock().unwrap(), [1]);
+
+    shards.remove("mailbox");
+    watch.tick();
+    assert_eq!(handle.send(mailbox, 2u32), Err(2));
+}
@@ -0,0 +1,60 @@
+use watch::testing::{MockClock, NeverComplete};
+use watch::{Builder, Event, EventFilter, Task, TaskState};
+
+#[test]
+fn inserting_a_key_again_replaces_its_task() {
+    let (mut watch, shards) = Builder::new().clock(MockClock::new()).build_map();
+    assert!(!shards.insert(1, NeverComplete));
+    watch.tick();
+    let first = shards.task_info(&1).unwrap().id();
+
+    assert!(shards.insert(1, NeverComplete));
+    watch.tick();
+    let second = shards.task_info(&1).unwrap();
+    assert_ne!(second.id(), first);
+    assert_eq!(second.state(), TaskState::Running);
+    assert_eq!(shards.len(), 1);
+
+    assert!(shards.remove(&1));
+    assert!(!shards.remove(&1));
+    watch.tick();
+    assert!(shards.is_empty());
+    assert!(shards
+        .handle()
+        .tasks()
+        .iter()
+        .all(|task| task.state() == TaskState::Removed));
+}
+
+#[test]
+fn subscribers_see_the_events_of_named_entries() {
+    let (mut watch, shards) = Builder::new().clock(MockClock::new()).build_map();
+    let mut events = shards
+        .handle()
+        .subscribe(EventFilter::new().name("billing"));
+    shards.insert("search", Task::from(NeverComplete).name("search"));
+    shards.insert("billing", Task::from(NeverComplete).name("billing"));
+    watch.tick();
+
+    let billing = shards.task_info("billing").unwrap().id();
+    assert!(matches!(
+        events.try_next(),
+        Some(Event::Started { task, .. }) if task == billing
+    ));
+    assert!(matches!(
+        events.try_next(),
+        Some(Event::Ready { task, .. }) if task == billing
+    ));
+    assert!(events.try_next().is_none());
+
+    shards.remove("billing");
+    watch.tick();
+    assert!(matches!(
+        events.try_next(),
+        Some(Event::Exited { task, .. }) if task == billing
+    ));
+    assert!(matches!(
+        events.try_next(),
+        Some(Event::Removed { task }) if task == billing
+    ));
+}
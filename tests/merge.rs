@@ -0,0 +1,134 @@
+use std::time::Duration;
+use watch::testing::{FailAfter, MockClock, NeverComplete};
+use watch::{Builder, Event, RestartDecision, Task, TaskId};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+#[test]
+fn merged_tasks_keep_the_policy_of_their_set() {
+    let library = Builder::new()
+        .task(FailAfter(0))
+        .policy(|_: &_| RestartDecision::RestartAfter(5 * SECOND));
+    let mut watch = Builder::new()
+        .task(FailAfter(0))
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .merge(library)
+        .clock(MockClock::new())
+        .run();
+
+    let mut decisions = watch.tick().decisions().to_vec();
+    decisions.sort_by_key(|(task, _)| *task);
+    assert_eq!(
+        decisions,
+        [
+            (TaskId::from(0), RestartDecision::RestartAfter(SECOND)),
+            (TaskId::from(1), RestartDecision::RestartAfter(5 * SECOND)),
+        ]
+    );
+}
+
+#[test]
+fn merged_dependencies_follow_their_tasks() {
+    let library = Builder::new()
+        .task(NeverComplete)
+        .task(Task::from(NeverComplete).depends_on(0));
+    let (mut watch, handle) = Builder::new()
+        .task(NeverComplete)
+        .merge(library)
+        .clock(MockClock::new())
+        .build();
+    let mut events = handle.events();
+    watch.tick();
+
+    handle.restart(1);
+    watch.tick();
+    let started: Vec<_> = std::iter::from_fn(|| events.try_next())
+        .filter_map(|event| match event {
+            Event::Started { task, instance: 2 } => Some(task),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(started, [1, 2]);
+}
+
+#[test]
+fn merged_streams_keep_yielding_tasks() {
+    let (tasks, incoming) = futures::channel::mpsc::unbounded::<Task>();
+    let library = Builder::new()
+        .task_stream(incoming)
+        .policy(|_: &_| RestartDecision::RestartAfter(5 * SECOND));
+    let (mut watch, handle) = Builder::new()
+        .task(NeverComplete)
+        .merge(library)
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    tasks.unbounded_send(FailAfter(0).into()).unwrap();
+    let tick = watch.tick();
+    assert_eq!(
+        tick.decisions(),
+        [(TaskId::from(1), RestartDecision::RestartAfter(5 * SECOND))]
+    );
+    assert_eq!(handle.tasks().len(), 2);
+}
+
+#[test]
+fn sets_may_be_merged_once_started() {
+    let (tasks, incoming) = futures::channel::mpsc::unbounded::<Task>();
+    let (mut watch, handle) = Builder::new()
+        .task(NeverComplete)
+        .clock(MockClock::new())
+        .build();
+    let mut events = handle.events();
+    watch.tick();
+
+    let library = Builder::new()
+        .task(NeverComplete)
+        .task(Task::from(NeverComplete).depends_on(0))
+        .task(FailAfter(0))
+        .task_stream(incoming)
+        .policy(|_: &_| RestartDecision::RestartAfter(5 * SECOND));
+    let ids = handle.merge(library);
+    assert_eq!(ids, [TaskId::from(1), TaskId::from(2), TaskId::from(3)]);
+    let tick = watch.tick();
+    assert_eq!(
+        tick.decisions(),
+        [(TaskId::from(3), RestartDecision::RestartAfter(5 * SECOND))]
+    );
+
+    // Dependencies point to the identifiers handed out.
+    handle.restart(1);
+    watch.tick();
+    let started: Vec<_> = std::iter::from_fn(|| events.try_next())
+        .filter_map(|event| match event {
+            Event::Started { task, instance: 2 } => Some(task),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(started, [1, 2]);
+
+    // So do the tasks yielded by its stream, with its policy.
+    tasks.unbounded_send(FailAfter(0).into()).unwrap();
+    let tick = watch.tick();
+    assert_eq!(
+        tick.decisions(),
+        [(TaskId::from(4), RestartDecision::RestartAfter(5 * SECOND))]
+    );
+}
+
+#[test]
+fn nothing_is_merged_while_draining() {
+    let (tasks, incoming) = futures::channel::mpsc::unbounded::<Task>();
+    let (mut watch, handle) = Builder::new()
+        .task(NeverComplete)
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    handle.drain();
+    handle.merge(Builder::new().task_stream(incoming));
+    watch.tick();
+    assert!(tasks.is_closed());
+    assert_eq!(handle.tasks().len(), 1);
+}
@@ -0,0 +1,56 @@
+use futures::future;
+use std::time::Duration;
+use watch::testing::MockClock;
+use watch::{Builder, RespawnPacing, RestartDecision, TaskId};
+
+#[test]
+fn watchers_yield_once_their_budget_is_spent() {
+    let (mut watch, handle) = Builder::new()
+        .task(|| async {})
+        .respawn_pacing(RespawnPacing::YieldEvery(1))
+        .clock(MockClock::new())
+        .build();
+
+    // Instances returning right away no longer keep the watcher busy.
+    for instances in 1..=3 {
+        assert!(watch.tick().result().is_none());
+        assert_eq!(handle.task_info(0).unwrap().instances(), instances);
+    }
+}
+
+#[test]
+fn immediate_restarts_wait_for_the_pacing_delay() {
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(|| async {})
+        .task(future::pending::<()>)
+        .respawn_pacing(RespawnPacing::Delay(Duration::from_millis(10)))
+        .clock(clock.clone())
+        .build();
+
+    let tick = watch.tick();
+    assert_eq!(
+        tick.decisions(),
+        [(
+            TaskId::from(0),
+            RestartDecision::RestartAfter(Duration::ZERO)
+        )]
+    );
+    assert_eq!(handle.restart_delay(0), Some(Duration::from_millis(10)));
+
+    clock.advance(Duration::from_millis(10));
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().instances(), 2);
+}
+
+#[tokio::test]
+async fn paced_watchers_keep_going_on_a_runtime() {
+    let summary = Builder::new()
+        .tasks((0..8).map(|_| || async {}))
+        .policy(|_: &_| RestartDecision::Retire)
+        .respawn_pacing(RespawnPacing::YieldEvery(3))
+        .run()
+        .await
+        .unwrap();
+    assert_eq!(summary.spawned(), 8);
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{gated, states};
+use watch::testing::MockClock;
+use watch::{Builder, TaskState};
+
+#[test]
+fn queued_tasks_start_by_priority_then_in_order() {
+    let (first, first_gate) = gated();
+    let (low, _low_gate) = gated();
+    let (high, high_gate) = gated();
+    let (other_high, _other_high_gate) = gated();
+    let (mut watch, handle) = Builder::new()
+        .task(first)
+        .task(low.priority(-1))
+        .task(high.priority(10))
+        .task(other_high.priority(10))
+        .max_concurrent_starts(1)
+        .clock(MockClock::new())
+        .build();
+
+    watch.tick();
+    assert_eq!(
+        states(&handle),
+        [
+            TaskState::Starting,
+            TaskState::Queued,
+            TaskState::Queued,
+            TaskState::Queued,
+        ]
+    );
+
+    first_gate.unbounded_send(()).unwrap();
+    watch.tick();
+    assert_eq!(
+        states(&handle),
+        [
+            TaskState::Running,
+            TaskState::Queued,
+            TaskState::Starting,
+            TaskState::Queued,
+        ]
+    );
+
+    high_gate.unbounded_send(()).unwrap();
+    watch.tick();
+    assert_eq!(
+        states(&handle),
+        [
+            TaskState::Running,
+            TaskState::Queued,
+            TaskState::Running,
+            TaskState::Starting,
+        ]
+    );
+}
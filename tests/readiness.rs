@@ -0,0 +1,102 @@
+mod common;
+
+use common::{gated, states};
+use futures::future;
+use std::time::Duration;
+use watch::testing::{EventRecorder, MockClock};
+use watch::{Builder, Event, ExitReason, RestartDecision, Task, TaskId, TaskState};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+#[test]
+fn instances_are_starting_until_they_signal_readiness() {
+    let (task, gate) = gated();
+    let (mut watch, handle) = Builder::new().task(task).clock(MockClock::new()).build();
+
+    watch.tick();
+    assert_eq!(states(&handle), [TaskState::Starting]);
+
+    gate.unbounded_send(()).unwrap();
+    watch.tick();
+    assert_eq!(states(&handle), [TaskState::Running]);
+}
+
+#[test]
+fn rolling_restarts_keep_the_previous_instance_until_the_new_one_is_ready() {
+    let (task, gate) = gated();
+    let (mut watch, handle) = Builder::new()
+        .task(task.rolling_restart())
+        .clock(MockClock::new())
+        .build();
+    let mut recorder = EventRecorder::new(&handle);
+
+    gate.unbounded_send(()).unwrap();
+    watch.tick();
+    handle.restart(0);
+    watch.tick();
+    let replaced = Event::Exited {
+        task: TaskId::from(0),
+        instance: 1,
+        reason: ExitReason::Cancelled,
+    };
+    assert!(recorder.events().contains(&Event::Started {
+        task: TaskId::from(0),
+        instance: 2,
+    }));
+    assert!(!recorder.events().contains(&replaced));
+    assert_eq!(handle.task_info(0).unwrap().instances(), 2);
+
+    gate.unbounded_send(()).unwrap();
+    watch.tick();
+    assert!(recorder.events().contains(&Event::Ready {
+        task: TaskId::from(0),
+        instance: 2,
+    }));
+    assert!(recorder.events().contains(&replaced));
+    assert_eq!(states(&handle), [TaskState::Running]);
+}
+
+#[test]
+fn instances_not_ready_in_time_fail_to_start() {
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(Task::new(future::pending::<()>).ready_within(5 * SECOND))
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .clock(clock.clone())
+        .build();
+    watch.tick();
+
+    clock.advance(4 * SECOND);
+    assert!(watch.tick().decisions().is_empty());
+    clock.advance(SECOND);
+    let tick = watch.tick();
+    assert_eq!(
+        tick.decisions(),
+        [(TaskId::from(0), RestartDecision::RestartAfter(SECOND))]
+    );
+    let info = handle.task_info(0).unwrap();
+    assert_eq!(info.last_exit(), Some(ExitReason::TimedOut));
+    assert_eq!(info.exits().timed_out(), 1);
+
+    clock.advance(SECOND);
+    watch.tick();
+    let info = handle.task_info(0).unwrap();
+    assert_eq!((info.instances(), info.state()), (2, TaskState::Starting));
+}
+
+#[test]
+fn ready_instances_outlive_their_deadline() {
+    let (task, gate) = gated();
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(task.ready_within(5 * SECOND))
+        .clock(clock.clone())
+        .build();
+
+    gate.unbounded_send(()).unwrap();
+    watch.tick();
+    clock.advance(10 * SECOND);
+    watch.tick();
+    let info = handle.task_info(0).unwrap();
+    assert_eq!((info.instances(), info.state()), (1, TaskState::Running));
+}
@@ -0,0 +1,48 @@
+use std::time::Duration;
+use watch::testing::{FailAfter, MockClock};
+use watch::{Builder, RestartDecision};
+
+#[test]
+fn tasks_are_configured_along_with_their_item() {
+    let second = Duration::from_secs(1);
+    let (mut watch, handle) = Builder::new()
+        .tasks_with(
+            [FailAfter(0), FailAfter(1)],
+            |FailAfter(completions), task| {
+                let task = task.name(format!("fails-after-{}", completions));
+                match completions {
+                    0 => task.policy(|_: &_| RestartDecision::Retire),
+                    _ => task,
+                }
+            },
+        )
+        .policy(move |_: &_| RestartDecision::RestartAfter(second))
+        .clock(MockClock::new())
+        .build();
+
+    let tick = watch.tick();
+    let decisions: Vec<_> = tick
+        .decisions()
+        .iter()
+        .map(|(_, decision)| decision.clone())
+        .collect();
+    assert_eq!(
+        decisions,
+        [
+            RestartDecision::Retire,
+            RestartDecision::RestartAfter(second)
+        ]
+    );
+    let names: Vec<_> = handle
+        .tasks()
+        .iter()
+        .map(|task| task.name().map(str::to_owned))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            Some("fails-after-0".to_owned()),
+            Some("fails-after-1".to_owned())
+        ]
+    );
+}
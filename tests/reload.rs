@@ -0,0 +1,115 @@
+use futures::future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use watch::testing::{CompleteOnCommand, MockClock};
+use watch::{Builder, RestartDecision, Task, TaskId, TaskState};
+
+fn named(name: &str) -> Task {
+    Task::new(future::pending::<()>).name(name)
+}
+
+#[test]
+fn reloads_keep_unchanged_tasks_and_start_new_ones() {
+    let (mut watch, handle) = Builder::new()
+        .task(named("api"))
+        .task(named("cron"))
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    let ids = handle.reload([
+        named("worker"),
+        named("api"),
+        Task::new(future::pending::<()>),
+    ]);
+    watch.tick();
+    assert_eq!(ids, [TaskId::from(2), TaskId::from(0), TaskId::from(3)]);
+    let tasks: Vec<_> = handle
+        .tasks()
+        .iter()
+        .map(|task| (task.instances(), task.state()))
+        .collect();
+    assert_eq!(
+        tasks,
+        [
+            (1, TaskState::Running),
+            // Removed once its instance returns.
+            (1, TaskState::Running),
+            (1, TaskState::Running),
+            (1, TaskState::Running),
+        ]
+    );
+}
+
+#[test]
+fn tasks_left_out_are_removed_once_their_instance_returned() {
+    let (task, controller) = CompleteOnCommand::new();
+    let (mut watch, handle) = Builder::new()
+        .task(named("api"))
+        .task(Task::from(task).name("batch"))
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    handle.reload([named("api")]);
+    watch.tick();
+    assert_eq!(handle.task_info(1).unwrap().state(), TaskState::Running);
+
+    controller.complete();
+    watch.tick();
+    let info = handle.task_info(1).unwrap();
+    assert_eq!((info.instances(), info.state()), (1, TaskState::Removed));
+}
+
+#[test]
+fn kept_tasks_are_replaced_at_their_next_restart() {
+    let made = Arc::new(AtomicUsize::new(0));
+    let replacement = {
+        let made = Arc::clone(&made);
+        Task::new(move || {
+            made.fetch_add(1, Ordering::SeqCst);
+            future::pending::<()>()
+        })
+        .name("api")
+    };
+    let (mut watch, handle) = Builder::new()
+        .task(named("api"))
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    // The running instance is left alone.
+    assert_eq!(handle.reload([replacement]), [TaskId::from(0)]);
+    watch.tick();
+    let info = handle.task_info(0).unwrap();
+    assert_eq!((info.instances(), info.state()), (1, TaskState::Running));
+    assert_eq!(made.load(Ordering::SeqCst), 0);
+
+    handle.restart(0);
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().instances(), 2);
+    assert_eq!(made.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn replacements_bring_their_policy() {
+    let (mut watch, handle) = Builder::new()
+        .task(named("api"))
+        .policy(|_: &_| RestartDecision::RestartAfter(Default::default()))
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    handle.reload([named("api").policy(|_: &_| RestartDecision::Retire)]);
+    handle.cancel_current(0);
+    let tick = watch.tick();
+    let restarted = RestartDecision::RestartAfter(Default::default());
+    assert_eq!(tick.decisions(), [(TaskId::from(0), restarted)]);
+
+    handle.cancel_current(0);
+    let tick = watch.tick();
+    assert_eq!(
+        tick.decisions(),
+        [(TaskId::from(0), RestartDecision::Retire)]
+    );
+}
@@ -0,0 +1,44 @@
+use futures::future;
+use futures::task::{noop_waker, Context};
+use futures::StreamExt;
+use std::task::Poll;
+use std::time::Duration;
+
+#[tokio::test]
+async fn yields_the_output_of_every_instance() {
+    let mut next = 0;
+    let outputs: Vec<_> = watch::respawn(|| {
+        next += 1;
+        future::ready(next)
+    })
+    .take(3)
+    .collect()
+    .await;
+    assert_eq!(outputs, [1, 2, 3]);
+}
+
+#[test]
+fn instances_are_made_once_polled() {
+    let mut respawner = watch::respawn(future::pending::<()>);
+    assert_eq!(respawner.instances(), 0);
+    assert!(!respawner.is_running());
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    for _ in 0..2 {
+        assert_eq!(respawner.poll_next_unpin(&mut cx), Poll::Pending);
+    }
+    assert_eq!(respawner.instances(), 1);
+    assert!(respawner.is_running());
+}
+
+#[tokio::test(start_paused = true)]
+async fn pinned_instances_are_polled_in_place() {
+    let respawner = watch::respawn(|| tokio::time::sleep(Duration::from_secs(1)));
+    futures::pin_mut!(respawner);
+    let start = tokio::time::Instant::now();
+    respawner.as_mut().take(2).for_each(|()| async {}).await;
+    assert_eq!(start.elapsed(), Duration::from_secs(2));
+    assert_eq!(respawner.instances(), 2);
+    assert!(!respawner.is_running());
+}
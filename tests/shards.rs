@@ -0,0 +1,26 @@
+use std::time::Duration;
+use watch::testing::MockClock;
+use watch::{Builder, RestartDecision, TaskState};
+
+#[test]
+fn sharded_instances_are_all_watched() {
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .tasks((0..100).map(|_| || async {}))
+        .task(futures::future::pending::<()>)
+        .policy(|_: &_| RestartDecision::RestartAfter(Duration::from_secs(1)))
+        .shards(8)
+        .clock(clock.clone())
+        .build();
+
+    assert_eq!(watch.tick().decisions().len(), 100);
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(watch.tick().decisions().len(), 100);
+    assert!(handle.tasks()[..100]
+        .iter()
+        .all(|task| task.state() == TaskState::Delayed && task.instances() == 2));
+    assert_eq!(handle.task_info(100).unwrap().state(), TaskState::Running);
+
+    handle.shutdown();
+    assert!(matches!(watch.tick().result(), Some(Ok(_))));
+}
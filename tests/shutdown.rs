@@ -0,0 +1,49 @@
+use std::time::Duration;
+use watch::testing::{MockClock, NeverComplete};
+use watch::{Builder, TaskId};
+
+#[test]
+fn instances_returning_within_the_grace_period_shut_down_cleanly() {
+    let builder = Builder::new().grace_period(Duration::from_secs(5));
+    let shutdown = builder.shutdown_signal();
+    let (mut watch, handle) = builder
+        .task(move || shutdown.clone())
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    handle.shutdown();
+    let summary = watch.tick().into_result().unwrap().unwrap();
+    let report = summary.shutdown().unwrap();
+    assert_eq!(report.exited(), &[TaskId::from(0)]);
+    assert!(report.is_clean());
+    assert_eq!(report.took(), Duration::ZERO);
+}
+
+#[test]
+fn instances_are_aborted_without_a_grace_period() {
+    let (mut watch, handle) = Builder::new()
+        .task(NeverComplete)
+        .task(NeverComplete)
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    handle.shutdown();
+    let summary = watch.tick().into_result().unwrap().unwrap();
+    let report = summary.shutdown().unwrap();
+    assert_eq!(report.aborted(), &[TaskId::from(0), TaskId::from(1)]);
+    assert!(report.exited().is_empty());
+}
+
+#[test]
+fn drained_watchers_have_no_shutdown_report() {
+    let (mut watch, handle) = Builder::new()
+        .task(|| async {})
+        .clock(MockClock::new())
+        .build();
+
+    handle.drain();
+    let summary = watch.tick().into_result().unwrap().unwrap();
+    assert_eq!(summary.shutdown(), None);
+}
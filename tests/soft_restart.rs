@@ -0,0 +1,105 @@
+use futures::channel::mpsc::{self, UnboundedSender};
+use futures::lock::Mutex;
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+use watch::testing::MockClock;
+use watch::{Builder, RestartBudget, RestartDecision, Task, TaskContext, TaskId, TaskState};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+/// Returns a task whose instances handle every unit of work sent, until asked
+/// to restart, along with where to send the units.
+fn worker() -> (Task, UnboundedSender<()>) {
+    let (units, receiver) = mpsc::unbounded::<()>();
+    let receiver = Arc::new(Mutex::new(receiver));
+    let task = Task::with_context(move |context: TaskContext| {
+        let receiver = Arc::clone(&receiver);
+        async move {
+            let mut receiver = receiver.lock().await;
+            while receiver.next().await.is_some() {
+                if context.restart_requested() {
+                    break;
+                }
+            }
+        }
+    });
+    (task.label("role", "worker"), units)
+}
+
+#[test]
+fn instances_asked_to_restart_return_at_their_next_safe_point() {
+    let (task, units) = worker();
+    let (mut watch, handle) = Builder::new()
+        .task(task)
+        .policy(|_: &_| RestartDecision::Retire)
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    handle.restart_at_yield(0);
+    watch.tick();
+    let info = handle.task_info(0).unwrap();
+    assert_eq!((info.instances(), info.state()), (1, TaskState::Running));
+
+    units.unbounded_send(()).unwrap();
+    let tick = watch.tick();
+    assert!(tick.decisions().is_empty());
+    let info = handle.task_info(0).unwrap();
+    assert_eq!((info.instances(), info.state()), (2, TaskState::Running));
+}
+
+#[test]
+fn restarts_asked_wait_for_restarts_to_be_allowed() {
+    let (task, units) = worker();
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(task)
+        .policy(|_: &_| RestartDecision::Retire)
+        .clock(clock.clone())
+        .build();
+    watch.tick();
+
+    handle.freeze(5 * SECOND);
+    handle.restart_at_yield(0);
+    watch.tick();
+    units.unbounded_send(()).unwrap();
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().state(), TaskState::Delayed);
+
+    clock.advance(5 * SECOND);
+    watch.tick();
+    let info = handle.task_info(0).unwrap();
+    assert_eq!((info.instances(), info.state()), (2, TaskState::Running));
+}
+
+#[test]
+fn restarts_asked_spend_budgets() {
+    let (task, units) = worker();
+    let (mut watch, handle) = Builder::new()
+        .task(task)
+        .budget(
+            "role=worker",
+            RestartBudget::new(1, 60 * SECOND).exhausted(RestartDecision::Retire),
+        )
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    for instances in 1..=2 {
+        handle.restart_at_yield(0);
+        watch.tick();
+        units.unbounded_send(()).unwrap();
+        let tick = watch.tick();
+        if instances == 1 {
+            assert!(tick.decisions().is_empty());
+        } else {
+            assert_eq!(
+                tick.decisions(),
+                [(TaskId::from(0), RestartDecision::Retire)]
+            );
+        }
+    }
+    let info = handle.task_info(0).unwrap();
+    assert_eq!((info.instances(), info.state()), (2, TaskState::Stopped));
+}
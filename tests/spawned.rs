@@ -0,0 +1,82 @@
+#![cfg(feature = "join-set")]
+
+use futures::future;
+use futures::{FutureExt, StreamExt};
+use std::time::Duration;
+use watch::testing::MockClock;
+use watch::{Builder, ExitReason, RestartDecision, Task, TaskId, TaskState};
+
+#[tokio::test]
+async fn spawned_tasks_are_respawned_through_the_factory() {
+    let first = tokio::spawn(async { panic!("lost the lease") });
+    let (mut watch, handle) = Builder::new()
+        .task(Task::join_handle(first, || {
+            tokio::spawn(future::pending::<()>())
+        }))
+        .policy(|_: &_| RestartDecision::RestartAfter(Duration::ZERO))
+        .clock(MockClock::new())
+        .build();
+    let mut monitor = handle.monitor(0);
+
+    let exit = loop {
+        watch.tick();
+        if let Some(Some(exit)) = monitor.next().now_or_never() {
+            break exit;
+        }
+        tokio::task::yield_now().await;
+    };
+    assert!(matches!(
+        exit.reason(),
+        ExitReason::Panicked(panic) if panic.message() == "lost the lease"
+    ));
+    watch.tick();
+    let info = handle.task_info(0).unwrap();
+    assert_eq!(info.state(), TaskState::Running);
+    assert_eq!(info.instances(), 2);
+}
+
+#[tokio::test]
+async fn stopped_instances_abort_their_task() {
+    let (spawned, aborted) = futures::channel::oneshot::channel::<()>();
+    let (mut watch, handle) = Builder::new()
+        .task(Task::join_handle(
+            tokio::spawn(async move {
+                let _spawned = spawned;
+                future::pending::<()>().await
+            }),
+            || tokio::spawn(future::pending::<()>()),
+        ))
+        .clock(MockClock::new())
+        .build();
+
+    watch.tick();
+    handle.remove(0);
+    watch.tick();
+    assert!(aborted.await.is_err());
+}
+
+#[test]
+fn instances_dropped_by_the_runtime_are_cancelled() {
+    let (mut watch, handle) = Builder::new()
+        .task(future::pending::<()>)
+        .join_set()
+        .policy(|_: &_| RestartDecision::RestartAfter(Duration::ZERO))
+        .clock(MockClock::new())
+        .build();
+    let runtime = || {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+    };
+    runtime().block_on(async {
+        watch.tick();
+    });
+
+    // The instance was dropped along with the first runtime.
+    let tick = runtime().block_on(async { watch.tick() });
+    let restarted = RestartDecision::RestartAfter(Duration::ZERO);
+    assert_eq!(tick.decisions(), [(TaskId::from(0), restarted)]);
+    let info = handle.task_info(0).unwrap();
+    assert_eq!(info.last_exit(), Some(ExitReason::Cancelled));
+    assert_eq!((info.instances(), info.state()), (2, TaskState::Running));
+}
@@ -0,0 +1,66 @@
+use futures::future;
+use std::time::Duration;
+use watch::testing::MockClock;
+use watch::{Builder, Task, TaskState, WatchError};
+
+#[test]
+fn held_watchers_spawn_nothing_until_started() {
+    let (mut watch, handle) = Builder::new()
+        .task(future::pending::<()>)
+        .task(future::pending::<()>)
+        .template(|_: ()| future::pending::<()>())
+        .start_paused()
+        .clock(MockClock::new())
+        .build();
+
+    handle.pause(1);
+    let child = handle.start_child(()).unwrap();
+    handle.restart(0);
+    let tick = watch.tick();
+    assert!(tick.result().is_none());
+    assert_eq!(handle.task_info(0).unwrap().state(), TaskState::Stopped);
+    assert_eq!(handle.task_info(1).unwrap().state(), TaskState::Paused);
+    assert_eq!(handle.task_info(child).unwrap().state(), TaskState::Stopped);
+
+    handle.start();
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().state(), TaskState::Running);
+    assert_eq!(handle.task_info(1).unwrap().state(), TaskState::Paused);
+    assert_eq!(handle.task_info(child).unwrap().state(), TaskState::Running);
+}
+
+#[test]
+fn startup_deadlines_run_from_the_start() {
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(Task::new(future::pending::<()>).signals_readiness())
+        .startup_deadline(Duration::from_secs(10))
+        .start_paused()
+        .clock(clock.clone())
+        .build();
+
+    watch.tick();
+    clock.advance(Duration::from_secs(20));
+    assert!(watch.tick().result().is_none());
+
+    handle.start();
+    watch.tick();
+    clock.advance(Duration::from_secs(10));
+    assert!(matches!(
+        watch.tick().into_result(),
+        Some(Err(WatchError::StartupTimeout { .. }))
+    ));
+}
+
+#[test]
+fn held_watchers_stop_once_drained() {
+    let (mut watch, handle) = Builder::new()
+        .task(future::pending::<()>)
+        .start_paused()
+        .clock(MockClock::new())
+        .build();
+
+    handle.drain();
+    let summary = watch.tick().into_result().unwrap().unwrap();
+    assert_eq!(summary.spawned(), 0);
+}
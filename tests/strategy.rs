@@ -0,0 +1,160 @@
+use futures::channel::oneshot;
+use futures::{future, FutureExt};
+use std::time::Duration;
+use watch::testing::{CompleteOnCommand, MockClock, NeverComplete};
+use watch::{
+    Builder, FailureKind, OneForAll, RestForOne, RestartDecision, RestartOrder, Task, TaskContext,
+    TaskState, Watch,
+};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+/// Ticks the watcher until it settles, returning every decision it made, by
+/// task index.
+fn decisions(watch: &mut Watch) -> Vec<(usize, RestartDecision)> {
+    let mut decisions = Vec::new();
+    for _ in 0..3 {
+        let tick = watch.tick();
+        let tick = tick.decisions().iter();
+        decisions.extend(tick.map(|(task, decision)| (task.index(), decision.clone())));
+    }
+    decisions
+}
+
+#[test]
+fn one_for_all_restarts_every_task_through_its_own_policy() {
+    let (failing, controller) = CompleteOnCommand::new();
+    let mut watch = Builder::new()
+        .task(Task::from(failing).policy(|_: &_| RestartDecision::RestartAfter(SECOND)))
+        .task(NeverComplete)
+        .task(NeverComplete)
+        .policy(|_: &_| RestartDecision::RestartAfter(5 * SECOND))
+        .strategy(OneForAll)
+        .clock(MockClock::new())
+        .run();
+    watch.tick();
+
+    controller.fail(FailureKind::Transient);
+    let decisions = decisions(&mut watch);
+    assert_eq!(decisions.len(), 3);
+    assert!(decisions.contains(&(0, RestartDecision::RestartAfter(SECOND))));
+    assert!(decisions.contains(&(1, RestartDecision::RestartAfter(5 * SECOND))));
+    assert!(decisions.contains(&(2, RestartDecision::RestartAfter(5 * SECOND))));
+}
+
+#[test]
+fn rest_for_one_only_restarts_the_tasks_added_after() {
+    let (failing, controller) = CompleteOnCommand::new();
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(NeverComplete)
+        .task(failing)
+        .task(NeverComplete)
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .strategy(RestForOne)
+        .clock(clock.clone())
+        .build();
+    watch.tick();
+
+    controller.fail(FailureKind::Transient);
+    let mut decisions = decisions(&mut watch);
+    decisions.sort_by_key(|(task, _)| *task);
+    assert_eq!(
+        decisions,
+        [
+            (1, RestartDecision::RestartAfter(SECOND)),
+            (2, RestartDecision::RestartAfter(SECOND)),
+        ]
+    );
+    assert_eq!(handle.task_info(0).unwrap().state(), TaskState::Running);
+    assert_eq!(handle.task_info(2).unwrap().state(), TaskState::Delayed);
+
+    clock.advance(SECOND);
+    watch.tick();
+    assert_eq!(handle.task_info(2).unwrap().state(), TaskState::Running);
+}
+
+#[test]
+fn tasks_restarting_together_start_by_registration_order() {
+    let (failing, controller) = CompleteOnCommand::new();
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(Task::from(NeverComplete).policy(|_: &_| RestartDecision::RestartAfter(SECOND)))
+        .task(failing)
+        .task(NeverComplete)
+        .policy(|_: &_| RestartDecision::RestartAfter(Duration::ZERO))
+        .strategy(OneForAll)
+        .clock(clock.clone())
+        .build();
+    watch.tick();
+
+    controller.fail(FailureKind::Transient);
+    watch.tick();
+    let states: Vec<_> = handle.tasks().iter().map(|task| task.state()).collect();
+    assert_eq!(
+        states,
+        [TaskState::Delayed, TaskState::Queued, TaskState::Queued]
+    );
+
+    clock.advance(SECOND);
+    watch.tick();
+    let states: Vec<_> = handle.tasks().iter().map(|task| task.state()).collect();
+    assert_eq!(states, [TaskState::Running; 3]);
+}
+
+#[test]
+fn ordered_restarts_may_wait_for_readiness() {
+    let (failing, controller) = CompleteOnCommand::new();
+    let (ready, signal) = oneshot::channel::<()>();
+    let signal = signal.shared();
+    let provider = Task::with_context(move |context: TaskContext| {
+        let signal = signal.clone();
+        async move {
+            // Only restarted instances wait to be told to get ready.
+            if context.instance() > 1 {
+                let _ = signal.await;
+            }
+            context.ready();
+            future::pending::<()>().await
+        }
+    })
+    .signals_readiness();
+    let (mut watch, handle) = Builder::new()
+        .task(provider)
+        .task(failing)
+        .policy(|_: &_| RestartDecision::RestartAfter(Duration::ZERO))
+        .strategy(OneForAll)
+        .restart_order(RestartOrder::Ready)
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    controller.fail(FailureKind::Transient);
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().state(), TaskState::Starting);
+    assert_eq!(handle.task_info(1).unwrap().state(), TaskState::Queued);
+
+    ready.send(()).unwrap();
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().state(), TaskState::Running);
+    assert_eq!(handle.task_info(1).unwrap().state(), TaskState::Running);
+}
+
+#[test]
+fn unordered_restarts_start_as_soon_as_policies_let_them() {
+    let (failing, controller) = CompleteOnCommand::new();
+    let (mut watch, handle) = Builder::new()
+        .task(Task::from(NeverComplete).policy(|_: &_| RestartDecision::RestartAfter(SECOND)))
+        .task(failing)
+        .policy(|_: &_| RestartDecision::RestartAfter(Duration::ZERO))
+        .strategy(OneForAll)
+        .restart_order(RestartOrder::Unordered)
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    controller.fail(FailureKind::Transient);
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().state(), TaskState::Delayed);
+    assert_eq!(handle.task_info(1).unwrap().state(), TaskState::Running);
+}
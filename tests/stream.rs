@@ -0,0 +1,53 @@
+use futures::{FutureExt, StreamExt};
+use watch::testing::{FailAfter, NeverComplete};
+use watch::{Builder, Event, RestartDecision};
+
+#[tokio::test]
+async fn watchers_yield_their_events_then_their_result() {
+    let mut watch = Builder::new()
+        .task(FailAfter(0))
+        .policy(|_: &_| RestartDecision::Retire)
+        .run();
+
+    let events: Vec<_> = (&mut watch).collect().await;
+    assert!(events.iter().all(|event| event.task() == 0));
+    assert!(matches!(
+        events.as_slice(),
+        [
+            Event::Started { instance: 1, .. },
+            Event::Ready { instance: 1, .. },
+            Event::Exited { instance: 1, .. },
+            Event::StartupImmediateExit { instance: 1, .. },
+            Event::Retired { instance: 1, .. },
+        ]
+    ));
+    assert!(watch.await.is_ok());
+}
+
+#[test]
+fn events_not_yielded_are_kept_up_to_the_capacity() {
+    let (mut watch, handle) = Builder::new().task(NeverComplete).event_capacity(2).build();
+    assert!(matches!(
+        watch.next().now_or_never(),
+        Some(Some(Event::Started { instance: 1, .. }))
+    ));
+    assert_eq!(watch.missed_events(), 0);
+
+    // Awaited or ticked rather than streamed, the watcher keeps the latest.
+    for _ in 0..3 {
+        handle.restart(0);
+        watch.tick();
+    }
+    let mut kept = Vec::new();
+    while let Some(Some(event)) = watch.next().now_or_never() {
+        kept.push(event);
+    }
+    assert!(matches!(
+        kept.as_slice(),
+        [
+            Event::Started { instance: 4, .. },
+            Event::Ready { instance: 4, .. },
+        ]
+    ));
+    assert!(watch.missed_events() > 0);
+}
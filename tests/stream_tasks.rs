@@ -0,0 +1,58 @@
+use futures::channel::mpsc;
+use futures::stream;
+use watch::testing::{MockClock, NeverComplete};
+use watch::{Builder, RestartDecision, Task};
+
+#[test]
+fn tasks_are_added_as_they_are_yielded() {
+    let (tasks, incoming) = mpsc::unbounded::<Task>();
+    let (mut watch, handle) = Builder::new()
+        .task(NeverComplete)
+        .task_stream(incoming)
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+    assert_eq!(handle.tasks().len(), 1);
+
+    tasks.unbounded_send(NeverComplete.into()).unwrap();
+    tasks.unbounded_send(NeverComplete.into()).unwrap();
+    watch.tick();
+    let instances: Vec<_> = handle.tasks().iter().map(|task| task.instances()).collect();
+    assert_eq!(instances, [1, 1, 1]);
+}
+
+#[test]
+fn watchers_keep_going_while_their_stream_does() {
+    let (tasks, incoming) = mpsc::unbounded::<Task>();
+    // Not `Unpin`, as it holds the future of the closure across polls.
+    let incoming = stream::unfold(incoming, |mut incoming| async move {
+        let task = futures::StreamExt::next(&mut incoming).await?;
+        Some((task, incoming))
+    });
+    let (mut watch, handle) = Builder::new()
+        .task_stream(incoming)
+        .policy(|_: &_| RestartDecision::Retire)
+        .clock(MockClock::new())
+        .build();
+    assert!(watch.tick().result().is_none());
+
+    tasks.unbounded_send(Task::new(|| async {})).unwrap();
+    assert!(watch.tick().result().is_none());
+    assert_eq!(handle.task_info(0).unwrap().instances(), 1);
+
+    drop(tasks);
+    let summary = watch.tick().into_result().unwrap().unwrap();
+    assert_eq!(summary.spawned(), 1);
+}
+
+#[test]
+fn drained_watchers_stop_polling_their_stream() {
+    let (mut watch, handle) = Builder::new()
+        .task_stream(stream::pending::<Task>())
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    handle.drain();
+    assert!(watch.tick().into_result().unwrap().is_ok());
+}
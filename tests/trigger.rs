@@ -0,0 +1,59 @@
+use futures::channel::oneshot;
+use futures::future;
+use watch::testing::{EventRecorder, FailAfter, MockClock};
+use watch::{Builder, Event, RespawnPacing, RestartDecision, RestartTrigger, TaskId, TaskState};
+
+#[test]
+fn restarts_wait_for_their_trigger() {
+    let (fire, fired) = oneshot::channel::<()>();
+    let trigger = RestartTrigger::new(fired);
+    let decision = RestartDecision::AwaitTrigger(trigger.clone());
+    let (mut watch, handle) = Builder::new()
+        .task(FailAfter(0))
+        .policy(move |_: &_| RestartDecision::AwaitTrigger(trigger.clone()))
+        .respawn_pacing(RespawnPacing::YieldEvery(1))
+        .clock(MockClock::new())
+        .build();
+    let mut recorder = EventRecorder::new(&handle);
+
+    let tick = watch.tick();
+    assert_eq!(tick.decisions(), [(TaskId::from(0), decision)]);
+    let info = handle.task_info(0).unwrap();
+    assert_eq!(
+        (info.state(), info.next_restart_at()),
+        (TaskState::Delayed, None)
+    );
+    assert!(recorder.events().contains(&Event::RestartAwaiting {
+        task: TaskId::from(0),
+        instance: 1,
+    }));
+    assert_eq!(watch.tick().decisions(), []);
+
+    fire.send(()).unwrap();
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().instances(), 2);
+    // The trigger stays fired, so that later instances restart right away.
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().instances(), 3);
+}
+
+#[test]
+fn expedited_tasks_skip_their_trigger() {
+    let (mut watch, handle) = Builder::new()
+        .task(FailAfter(0))
+        .policy(|_: &_| RestartDecision::AwaitTrigger(RestartTrigger::new(future::pending::<()>())))
+        .clock(MockClock::new())
+        .build();
+    watch.tick();
+
+    handle.expedite(0);
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().instances(), 2);
+}
+
+#[test]
+fn triggers_only_equal_their_clones() {
+    let trigger = RestartTrigger::new(future::ready(()));
+    assert_eq!(trigger, trigger.clone());
+    assert_ne!(trigger, RestartTrigger::new(future::ready(())));
+}
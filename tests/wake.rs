@@ -0,0 +1,54 @@
+use futures::channel::oneshot;
+use futures::future::FutureExt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use watch::testing::MockClock;
+use watch::{Builder, RestartDecision, Task};
+
+/// Counts how many times the future it wraps is polled.
+struct Counted<F> {
+    future: F,
+    polls: Arc<AtomicUsize>,
+}
+
+impl<F: Future + Unpin> Future for Counted<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        self.polls.fetch_add(1, Ordering::SeqCst);
+        self.future.poll_unpin(cx)
+    }
+}
+
+#[test]
+fn bursts_of_exits_poll_every_instance_once() {
+    const TASKS: usize = 100;
+    let (fire, fired) = oneshot::channel::<()>();
+    let fired = fired.shared();
+    let polls = Arc::new(AtomicUsize::new(0));
+
+    let mut builder = Builder::new()
+        .policy(|_: &_| RestartDecision::Retire)
+        .clock(MockClock::new());
+    for _ in 0..TASKS {
+        let fired = fired.clone();
+        let polls = Arc::clone(&polls);
+        builder = builder.task(Task::new(move || Counted {
+            future: fired.clone(),
+            polls: Arc::clone(&polls),
+        }));
+    }
+    let mut watch = builder.run();
+
+    watch.tick();
+    assert_eq!(polls.load(Ordering::SeqCst), TASKS);
+
+    fire.send(()).unwrap();
+    let tick = watch.tick();
+    assert_eq!(tick.decisions().len(), TASKS);
+    assert_eq!(polls.load(Ordering::SeqCst), 2 * TASKS);
+    assert!(tick.result().is_some());
+}
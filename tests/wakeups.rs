@@ -0,0 +1,34 @@
+#![cfg(feature = "metrics")]
+
+use futures::future;
+use std::task::Poll;
+use watch::testing::MockClock;
+use watch::Builder;
+
+#[test]
+fn instances_woken_to_no_avail_are_counted() {
+    let (mut watch, handle) = Builder::new()
+        .task(|| {
+            future::poll_fn(|cx| {
+                // Wakes itself without ever making progress.
+                cx.waker().wake_by_ref();
+                Poll::<()>::Pending
+            })
+        })
+        .task(future::pending::<()>)
+        .clock(MockClock::new())
+        .build();
+
+    watch.tick();
+    let metrics = handle.metrics();
+    assert_eq!((metrics.polls(), metrics.idle_polls()), (1, 0));
+
+    watch.tick();
+    watch.tick();
+    let metrics = handle.metrics();
+    assert_eq!((metrics.polls(), metrics.idle_polls()), (3, 2));
+    let tasks = metrics.tasks();
+    assert!(tasks[0].pending_polls() >= 3);
+    assert_eq!(tasks[0].pending_polls(), tasks[0].poll_durations().count());
+    assert_eq!(tasks[1].pending_polls(), 1);
+}
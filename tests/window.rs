@@ -0,0 +1,79 @@
+use std::time::{Duration, UNIX_EPOCH};
+use watch::testing::{FailAfter, MockClock};
+use watch::{Builder, RestartDecision, RestartWindow, Task, TaskState};
+
+const SECOND: Duration = Duration::from_secs(1);
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+#[test]
+fn restarts_are_deferred_until_the_window_opens() {
+    // Midnight, two hours before the window opens.
+    let clock = MockClock::at(UNIX_EPOCH + 24 * HOUR);
+    let (mut watch, handle) = Builder::new()
+        .task(FailAfter(1))
+        .task(Task::from(FailAfter(1)).critical())
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .restart_window(RestartWindow::daily(2 * HOUR, 4 * HOUR))
+        .clock(clock.clone())
+        .build();
+    let instances = || -> Vec<u64> { handle.tasks().iter().map(|task| task.instances()).collect() };
+
+    watch.tick();
+    clock.advance(SECOND);
+    watch.tick();
+    assert_eq!(instances(), [1, 2]);
+    assert_eq!(handle.task_info(0).unwrap().state(), TaskState::Delayed);
+
+    clock.advance(2 * HOUR - 2 * SECOND);
+    watch.tick();
+    assert_eq!(instances()[0], 1);
+
+    clock.advance(SECOND);
+    watch.tick();
+    assert_eq!(instances()[0], 2);
+}
+
+#[test]
+fn freezes_defer_restarts_until_thawed() {
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(FailAfter(1))
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .clock(clock.clone())
+        .build();
+    let instances = || handle.task_info(0).unwrap().instances();
+
+    handle.freeze(30 * 60 * SECOND);
+    watch.tick();
+    clock.advance(SECOND);
+    watch.tick();
+    assert_eq!(instances(), 1);
+
+    clock.advance(60 * SECOND);
+    watch.tick();
+    assert_eq!(instances(), 1);
+
+    handle.thaw();
+    watch.tick();
+    assert_eq!(instances(), 2);
+}
+
+#[test]
+fn freezes_may_last_forever() {
+    let clock = MockClock::new();
+    let (mut watch, handle) = Builder::new()
+        .task(FailAfter(1))
+        .policy(|_: &_| RestartDecision::RestartAfter(SECOND))
+        .clock(clock.clone())
+        .build();
+
+    handle.freeze(Duration::MAX);
+    watch.tick();
+    clock.advance(365 * 24 * HOUR);
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().instances(), 1);
+
+    handle.thaw();
+    watch.tick();
+    assert_eq!(handle.task_info(0).unwrap().instances(), 2);
+}